@@ -39,6 +39,8 @@ mod nvme;
 mod nvmf;
 mod uring;
 
+pub(crate) use loopback::resize as loopback_resize;
+
 impl Uri {
     pub fn parse(
         uri: &str,
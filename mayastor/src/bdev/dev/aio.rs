@@ -130,6 +130,7 @@ impl CreateDestroy for Aio {
                     .await
                     .context(nexus_uri::CancelBdev {
                         name: self.get_name(),
+                        operation: "bdev_aio_delete",
                     })?
                     .context(nexus_uri::DestroyBdev {
                         name: self.get_name(),
@@ -145,6 +145,7 @@ impl CreateDestroy for Iscsi {
             .await
             .context(nexus_uri::CancelBdev {
                 name: self.get_name(),
+                operation: "create_iscsi_disk",
             })?
             .context(nexus_uri::CreateBdev {
                 name: self.get_name(),
@@ -180,6 +181,7 @@ impl CreateDestroy for Iscsi {
                     .await
                     .context(nexus_uri::CancelBdev {
                         name: self.get_name(),
+                        operation: "delete_iscsi_disk",
                     })?
                     .context(nexus_uri::DestroyBdev {
                         name: self.get_name(),
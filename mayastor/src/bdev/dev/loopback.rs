@@ -21,6 +21,8 @@ pub(super) struct Loopback {
     name: String,
     alias: String,
     uuid: Option<uuid::Uuid>,
+    /// succeed even if the bdev does not exist (yet)
+    create_if_missing: bool,
 }
 
 impl TryFrom<&Url> for Loopback {
@@ -45,16 +47,65 @@ impl TryFrom<&Url> for Loopback {
             },
         )?;
 
+        let create_if_missing = match parameters.remove("create_if_missing") {
+            Some(value) => uri::boolean(&value, true).context(
+                nexus_uri::BoolParamParseError {
+                    uri: url.to_string(),
+                    parameter: String::from("create_if_missing"),
+                },
+            )?,
+            None => false,
+        };
+
         reject_unknown_parameters(url, parameters)?;
 
         Ok(Loopback {
             name: segments.join("/"),
             alias: url.to_string(),
             uuid,
+            create_if_missing,
         })
     }
 }
 
+impl Loopback {
+    unsafe fn resize(&self, num_blocks: u64) -> Result<(), NexusBdevError> {
+        match Bdev::lookup_by_name(&self.name) {
+            Some(bdev) => {
+                bdev.resize(num_blocks).context(nexus_uri::ResizeBdev {
+                    name: self.get_name(),
+                })
+            }
+            None => Err(NexusBdevError::BdevNotFound {
+                name: self.get_name(),
+            }),
+        }
+    }
+}
+
+/// Change the number of blocks of the bdev the loopback uri refers to. A
+/// nexus with a child on the bdev picks up the new geometry from the resize
+/// event.
+///
+/// # Safety
+///
+/// The storage behind the bdev must hold the new number of blocks, see
+/// [`Bdev::resize`].
+pub(crate) unsafe fn resize(
+    uri: &str,
+    num_blocks: u64,
+) -> Result<(), NexusBdevError> {
+    let url = Url::parse(uri).context(nexus_uri::UrlParseError {
+        uri: uri.to_string(),
+    })?;
+    match url.scheme() {
+        "bdev" | "loopback" => Loopback::try_from(&url)?.resize(num_blocks),
+        scheme => Err(NexusBdevError::UriSchemeUnsupported {
+            scheme: scheme.to_string(),
+        }),
+    }
+}
+
 impl GetName for Loopback {
     fn get_name(&self) -> String {
         self.name.clone()
@@ -65,19 +116,23 @@ impl GetName for Loopback {
 impl CreateDestroy for Loopback {
     type Error = NexusBdevError;
 
+    /// The bdev must already exist, unless create_if_missing is set, in which
+    /// case the name is returned regardless.
     async fn create(&self) -> Result<String, Self::Error> {
-        if let Some(mut bdev) = Bdev::lookup_by_name(&self.name) {
-            if let Some(uuid) = self.uuid {
-                bdev.set_uuid(Some(uuid.to_string()));
+        match Bdev::lookup_by_name(&self.name) {
+            Some(mut bdev) => {
+                if let Some(uuid) = self.uuid {
+                    bdev.set_uuid(Some(uuid.to_string()));
+                }
+                bdev.add_alias_checked(&self.alias)?;
             }
-            if !bdev.add_alias(&self.alias) {
-                error!(
-                    "Failed to add alias {} to device {}",
-                    self.alias,
-                    self.get_name()
-                );
+            None if !self.create_if_missing => {
+                return Err(NexusBdevError::BdevNotFound {
+                    name: self.get_name(),
+                });
             }
-        };
+            None => {}
+        }
         Ok(self.get_name())
     }
 
@@ -3,6 +3,9 @@
 //! that the backing memory is allocated from huge pages and not from the
 //! heap. IOW, you must ensure you do not run out of huge pages while using
 //! this.
+//!
+//! The whole disk is allocated and zeroed when the bdev is created, so a lack
+//! of huge pages fails the creation with ENOMEM rather than any later IO.
 use crate::{
     bdev::{dev::reject_unknown_parameters, util::uri},
     nexus_uri::{
@@ -14,7 +17,7 @@ use async_trait::async_trait;
 use std::{collections::HashMap, convert::TryFrom};
 use url::Url;
 use uuid::Uuid;
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Malloc {
     /// the name of the bdev we created, this is equal to the URI path minus
     /// the leading '/'
@@ -155,18 +158,26 @@ impl CreateDestroy for Malloc {
                 name: self.name.clone(),
             })
         } else {
-            self.uuid.map(|u| {
-                Bdev::lookup_by_name(&self.name).map(|mut b| {
-                    b.set_uuid(Some(u.to_string()));
-                    if !b.add_alias(&self.alias) {
-                        error!(
-                            "Failed to add alias {} to device {}",
-                            self.alias,
-                            self.get_name()
-                        );
+            info!(
+                "Created malloc bdev {}, reserved {} bytes",
+                self.name,
+                self.num_blocks * self.blk_size as u64
+            );
+            if let Some(u) = self.uuid {
+                if let Some(mut bdev) = Bdev::lookup_by_name(&self.name) {
+                    bdev.set_uuid(Some(u.to_string()));
+                    if let Err(error) = bdev.add_alias_checked(&self.alias) {
+                        // the bdev cannot be found by its URI, do not leak it
+                        if let Err(e) = Box::new(self.clone()).destroy().await {
+                            error!(
+                                "Failed to destroy bdev {}: {}",
+                                self.name, e
+                            );
+                        }
+                        return Err(error);
                     }
-                })
-            });
+                }
+            }
             Ok(self.name.clone())
         }
     }
@@ -185,6 +196,7 @@ impl CreateDestroy for Malloc {
             r.await
                 .context(nexus_uri::CancelBdev {
                     name: self.name.clone(),
+                    operation: "delete_malloc_disk",
                 })?
                 .context(nexus_uri::DestroyBdev {
                     name: self.name,
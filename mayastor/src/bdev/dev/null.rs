@@ -1,6 +1,9 @@
 //! As the name implies, this is a dummy driver that discards all writes and
 //! returns undefined data for reads. It's useful for benchmarking the I/O stack
 //! with minimal overhead and should *NEVER* be used with *real* data.
+//!
+//! Reads return a repeated byte instead when the bdev is created with a
+//! pattern, e.g. null:///disk?size_mb=64&pattern=0xAB
 use crate::{
     bdev::{dev::reject_unknown_parameters, util::uri},
     nexus_uri::{
@@ -9,11 +12,13 @@ use crate::{
     },
 };
 use async_trait::async_trait;
-use std::{collections::HashMap, convert::TryFrom};
+use once_cell::sync::{Lazy, OnceCell};
+use spdk_sys::{spdk_bdev_fn_table, spdk_bdev_io, spdk_io_channel};
+use std::{collections::HashMap, convert::TryFrom, sync::Mutex};
 use url::Url;
 use uuid::Uuid;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Null {
     /// the name of the bdev we created, this is equal to the URI path minus
     /// the leading '/'
@@ -24,12 +29,19 @@ pub struct Null {
     num_blocks: u64,
     /// the size of a single block if no blk_size is given we default to 512
     blk_size: u32,
+    /// the size of the metadata of each block, none by default
+    md_size: u32,
+    /// whether the metadata is interleaved with the data of each block
+    /// rather than held in a separate buffer
+    md_interleave: bool,
     /// uuid of the spdk bdev
     uuid: Option<uuid::Uuid>,
+    /// byte which reads return, undefined data if none
+    pattern: Option<u8>,
 }
 use crate::{
     bdev::{CreateDestroy, GetName},
-    core::Bdev,
+    core::{Bdev, Bio, IoType},
     ffihelper::{cb_arg, done_errno_cb, ErrnoResult, IntoCString},
 };
 use futures::channel::oneshot;
@@ -96,6 +108,38 @@ impl TryFrom<&Url> for Null {
             });
         }
 
+        let md_size: u32 = if let Some(value) = parameters.remove("md_size") {
+            value.parse().context(nexus_uri::IntParamParseError {
+                uri: uri.to_string(),
+                parameter: String::from("md_size"),
+            })?
+        } else {
+            0
+        };
+
+        let md_interleave = match parameters.remove("md_interleave") {
+            Some(value) => uri::boolean(&value, true).context(
+                nexus_uri::BoolParamParseError {
+                    uri: uri.to_string(),
+                    parameter: String::from("md_interleave"),
+                },
+            )?,
+            None => false,
+        };
+
+        let pattern = match parameters.remove("pattern") {
+            Some(value) => Some(parse_pattern(&value).ok_or_else(|| {
+                NexusBdevError::UriInvalid {
+                    uri: uri.to_string(),
+                    message: format!(
+                        "invalid pattern {}, must be a single byte in hex or decimal",
+                        value
+                    ),
+                }
+            })?),
+            None => None,
+        };
+
         let uuid = uri::uuid(parameters.remove("uuid")).context(
             nexus_uri::UuidParamParseError {
                 uri: uri.to_string(),
@@ -113,11 +157,96 @@ impl TryFrom<&Url> for Null {
                 (size << 20) / blk_size
             } as u64,
             blk_size,
+            md_size,
+            md_interleave,
             uuid: uuid.or_else(|| Some(Uuid::new_v4())),
+            pattern,
         })
     }
 }
 
+/// Parse a single byte given in hex, with a 0x prefix, or in decimal
+fn parse_pattern(value: &str) -> Option<u8> {
+    match value.get(.. 2) {
+        Some("0x") | Some("0X") => u8::from_str_radix(&value[2 ..], 16).ok(),
+        _ => value.parse().ok(),
+    }
+}
+
+/// Patterns of the null bdevs created with one, by bdev
+static PATTERNS: Lazy<Mutex<HashMap<usize, u8>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static PATTERN_FN_TBL: OnceCell<PatternFnTable> = OnceCell::new();
+
+/// The function table of the null bdevs created with a pattern. It is the
+/// table of the SPDK null bdev, with the submit function filling the buffers
+/// of reads with the pattern before the IO is submitted to the null bdev,
+/// which leaves them untouched.
+struct PatternFnTable {
+    f_tbl: spdk_bdev_fn_table,
+    null_tbl: spdk_bdev_fn_table,
+}
+
+unsafe impl Sync for PatternFnTable {}
+
+unsafe impl Send for PatternFnTable {}
+
+impl PatternFnTable {
+    /// make the reads of the given null bdev return the pattern
+    fn set_pattern(bdev: &Bdev, pattern: u8) {
+        let ptr = bdev.as_ptr();
+        let table = PATTERN_FN_TBL.get_or_init(|| {
+            let null_tbl = unsafe { *(*ptr).fn_table };
+            Self {
+                f_tbl: spdk_bdev_fn_table {
+                    submit_request: Some(Self::io_submit),
+                    ..null_tbl
+                },
+                null_tbl,
+            }
+        });
+
+        PATTERNS.lock().unwrap().insert(ptr as usize, pattern);
+        unsafe { (*ptr).fn_table = &table.f_tbl };
+    }
+
+    fn remove_pattern(bdev: &Bdev) {
+        PATTERNS.lock().unwrap().remove(&(bdev.as_ptr() as usize));
+    }
+
+    extern "C" fn io_submit(ch: *mut spdk_io_channel, io: *mut spdk_bdev_io) {
+        let bio = Bio::from(io);
+        if bio.io_type() == IoType::Read {
+            let pattern = PATTERNS
+                .lock()
+                .unwrap()
+                .get(&(bio.bdev().as_ptr() as usize))
+                .copied();
+            if let Some(pattern) = pattern {
+                let iovs = unsafe {
+                    std::slice::from_raw_parts(
+                        bio.iovs(),
+                        bio.iov_count() as usize,
+                    )
+                };
+                for iov in iovs.iter().filter(|iov| !iov.iov_base.is_null()) {
+                    unsafe {
+                        std::ptr::write_bytes(
+                            iov.iov_base as *mut u8,
+                            pattern,
+                            iov.iov_len as usize,
+                        )
+                    };
+                }
+            }
+        }
+
+        let table = PATTERN_FN_TBL.get().unwrap();
+        unsafe { table.null_tbl.submit_request.unwrap()(ch, io) };
+    }
+}
+
 impl GetName for Null {
     fn get_name(&self) -> String {
         self.name.clone()
@@ -142,8 +271,8 @@ impl CreateDestroy for Null {
             uuid: std::ptr::null(),
             num_blocks: self.num_blocks,
             block_size: self.blk_size,
-            md_size: 0,
-            md_interleave: false,
+            md_size: self.md_size,
+            md_interleave: self.md_interleave,
             dif_type: spdk_sys::SPDK_DIF_DISABLE,
             dif_is_head_of_md: false,
         };
@@ -159,24 +288,33 @@ impl CreateDestroy for Null {
                 name: self.name.clone(),
             })
         } else {
-            self.uuid.map(|u| {
-                Bdev::lookup_by_name(&self.name).map(|mut b| {
-                    b.set_uuid(Some(u.to_string()));
-                    if !b.add_alias(&self.alias) {
-                        error!(
-                            "Failed to add alias {} to device {}",
-                            self.alias,
-                            self.get_name()
-                        );
+            if let Some(pattern) = self.pattern {
+                if let Some(bdev) = Bdev::lookup_by_name(&self.name) {
+                    PatternFnTable::set_pattern(&bdev, pattern);
+                }
+            }
+            if let Some(u) = self.uuid {
+                if let Some(mut bdev) = Bdev::lookup_by_name(&self.name) {
+                    bdev.set_uuid(Some(u.to_string()));
+                    if let Err(error) = bdev.add_alias_checked(&self.alias) {
+                        // the bdev cannot be found by its URI, do not leak it
+                        if let Err(e) = Box::new(self.clone()).destroy().await {
+                            error!(
+                                "Failed to destroy bdev {}: {}",
+                                self.name, e
+                            );
+                        }
+                        return Err(error);
                     }
-                })
-            });
+                }
+            }
             Ok(self.name.clone())
         }
     }
 
     async fn destroy(self: Box<Self>) -> Result<(), Self::Error> {
         if let Some(bdev) = Bdev::lookup_by_name(&self.name) {
+            PatternFnTable::remove_pattern(&bdev);
             let (s, r) = oneshot::channel::<ErrnoResult<()>>();
             unsafe {
                 spdk_sys::bdev_null_delete(
@@ -189,6 +327,7 @@ impl CreateDestroy for Null {
             r.await
                 .context(nexus_uri::CancelBdev {
                     name: self.name.clone(),
+                    operation: "bdev_null_delete",
                 })?
                 .context(nexus_uri::DestroyBdev {
                     name: self.name,
@@ -103,6 +103,7 @@ impl CreateDestroy for NVMe {
             .await
             .context(nexus_uri::CancelBdev {
                 name: self.name.clone(),
+                operation: "bdev_nvme_create",
             })?
             .context(nexus_uri::CreateBdev {
                 name: self.name.clone(),
@@ -184,6 +184,7 @@ impl CreateDestroy for Nvmf {
             .await
             .context(nexus_uri::CancelBdev {
                 name: self.name.clone(),
+                operation: "bdev_nvme_create",
             })?
             .context(nexus_uri::CreateBdev {
                 name: self.name.clone(),
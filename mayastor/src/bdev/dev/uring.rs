@@ -127,6 +127,7 @@ impl CreateDestroy for Uring {
                     .await
                     .context(nexus_uri::CancelBdev {
                         name: self.get_name(),
+                        operation: "delete_uring_bdev",
                     })?
                     .context(nexus_uri::DestroyBdev {
                         name: self.get_name(),
@@ -1,17 +1,48 @@
 use async_trait::async_trait;
 
+use crate::core::{Bdev, NvmeTransportId};
+
 pub use nexus::{
     nexus_bdev::{
         nexus_create,
         nexus_lookup,
+        nexus_lookup_by_bdev_name,
+        nexus_lookup_by_child_uri,
+        Error,
         Nexus,
+        NexusDetachedState,
         NexusState,
         NexusStatus,
+        ReadPreference,
         VerboseError,
     },
-    nexus_child::{lookup_child_from_bdev, ChildState, Reason},
+    nexus_child::{
+        lookup_child_from_bdev,
+        ChildState,
+        ChildStatus,
+        NexusChild,
+        Reason,
+    },
     nexus_child_status_config,
-    nexus_label::{GptEntry, GptHeader},
+    nexus_label::{
+        GptEntry,
+        GptGuid,
+        GptHeader,
+        GptName,
+        LabelError,
+        LabelSummary,
+        NexusLabel,
+        NexusLabelStatus,
+        PartitionSummary,
+        Pmbr,
+        ProbeError,
+    },
+    nexus_metadata::{
+        MetaDataError,
+        MetaDataHeader,
+        MetaDataIndexEntry,
+        NexusMetaData,
+    },
     nexus_metadata_content::{
         NexusConfig,
         NexusConfigVersion1,
@@ -41,6 +72,12 @@ pub trait GetName {
 
 pub struct Uri;
 
+/// Transport ID of the NVMe controller behind the named device, if it is an
+/// NVMe device
+pub fn device_get_transport_id(name: &str) -> Option<NvmeTransportId> {
+    Bdev::lookup_by_name(name)?.nvme_transport_id()
+}
+
 pub(crate) mod dev;
 pub(crate) mod nexus;
 pub mod util;
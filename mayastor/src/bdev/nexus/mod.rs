@@ -6,6 +6,7 @@ use crate::{
     bdev::nexus::{nexus_bdev::Nexus, nexus_fn_table::NexusFnTable},
     core::{Bdev, Share},
     jsonrpc::{jsonrpc_register, Code, JsonRpcError, Result},
+    rebuild::{ClientOperations, RebuildJob},
 };
 use futures::{future::Future, FutureExt};
 use std::pin::Pin;
@@ -48,6 +49,77 @@ struct NexusShareReply {
     uri: String,
 }
 
+#[derive(Serialize)]
+struct ChildStateDump {
+    uri: String,
+    state: String,
+    /// progress in % of the rebuild of this child, if any
+    rebuild_progress: Option<u64>,
+    /// number of rebuild IOs currently in flight to this child
+    rebuild_tasks_active: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct NexusStateDump {
+    name: String,
+    uuid: String,
+    state: String,
+    status: String,
+    num_read_ops: u64,
+    num_write_ops: u64,
+    children: Vec<ChildStateDump>,
+}
+
+#[derive(Serialize)]
+struct StateDump {
+    nexus: Vec<NexusStateDump>,
+}
+
+/// Take a snapshot of the state of all nexus instances and their children.
+/// The nexus list is not held across the await points; the IO statistics are
+/// collected afterwards by looking up the nexus bdevs by name.
+async fn dump_state() -> StateDump {
+    let mut nexus = instances()
+        .iter()
+        .map(|n| NexusStateDump {
+            name: n.name.clone(),
+            uuid: n.bdev.uuid_as_string(),
+            state: n.state.lock().unwrap().to_string(),
+            status: n.status().to_string(),
+            num_read_ops: 0,
+            num_write_ops: 0,
+            children: n
+                .children
+                .iter()
+                .map(|c| {
+                    let stats = RebuildJob::lookup(&c.name)
+                        .ok()
+                        .map(|job| job.as_client().stats());
+                    ChildStateDump {
+                        uri: c.name.clone(),
+                        state: c.state().to_string(),
+                        rebuild_progress: stats.as_ref().map(|s| s.progress),
+                        rebuild_tasks_active: stats.map(|s| s.tasks_active),
+                    }
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    for n in &mut nexus {
+        if let Some(bdev) = Bdev::lookup_by_name(&n.name) {
+            if let Ok(stats) = bdev.stats().await {
+                n.num_read_ops = stats.num_read_ops;
+                n.num_write_ops = stats.num_write_ops;
+            }
+        }
+    }
+
+    StateDump {
+        nexus,
+    }
+}
+
 /// public function which simply calls register module
 pub fn register_module() {
     nexus_module::register_module();
@@ -108,6 +180,13 @@ pub fn register_module() {
             Box::pin(f.boxed_local())
         },
     );
+
+    jsonrpc_register(
+        "mayastor_dump_state",
+        |_args: ()| -> Pin<Box<dyn Future<Output = Result<StateDump>>>> {
+            async { Ok(dump_state().await) }.boxed_local()
+        },
+    );
 }
 
 /// get a reference to the module
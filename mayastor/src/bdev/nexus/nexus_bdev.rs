@@ -9,11 +9,14 @@ use std::{
     fmt::{Display, Formatter},
     os::raw::c_void,
     ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicU64},
+    time::{Duration, Instant},
 };
 
 use futures::{channel::oneshot, future::join_all};
+use futures_timer::Delay;
 use nix::errno::Errno;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use tonic::{Code, Status};
 
@@ -34,14 +37,23 @@ use crate::{
             nexus_channel::{DrEvent, NexusChannel, ReconfigureCtx},
             nexus_child::{ChildError, ChildState, NexusChild},
             nexus_label::LabelError,
+            nexus_metadata::MetaDataError,
             nexus_nbd::{NbdDisk, NbdError},
         },
     },
-    core::{Bdev, CoreError, IoType, Protocol, Reactor, Share},
+    core::{
+        Bdev,
+        CoreError,
+        IoType,
+        MayastorEnvironment,
+        Protocol,
+        Reactor,
+        Share,
+    },
     ffihelper::errno_result_from_i32,
     nexus_uri::{bdev_destroy, NexusBdevError},
-    rebuild::RebuildError,
-    subsys::{NvmfError, NvmfSubsystem},
+    rebuild::{RebuildError, RebuildJob},
+    subsys::{Config, NvmfError, NvmfSubsystem},
 };
 
 /// Obtain the full error chain
@@ -126,13 +138,48 @@ pub enum Error {
     #[snafu(display("Children of nexus {} have mixed block sizes", name))]
     MixedBlockSizes { name: String },
     #[snafu(display(
-        "Child {} of nexus {} has incompatible size or block size",
+        "Child {} of nexus {} has incompatible size or block size: \
+        {} blocks of {} bytes, at least {} blocks of {} bytes required",
         child,
-        name
+        name,
+        child_num_blocks,
+        child_block_len,
+        num_blocks,
+        block_len
     ))]
-    ChildGeometry { child: String, name: String },
+    ChildGeometry {
+        child: String,
+        name: String,
+        child_block_len: u32,
+        child_num_blocks: u64,
+        block_len: u32,
+        num_blocks: u64,
+    },
     #[snafu(display("Child {} of nexus {} cannot be found", child, name))]
     ChildMissing { child: String, name: String },
+    #[snafu(display("Child {} of nexus {} already exists", child, name))]
+    ChildExists { child: String, name: String },
+    #[snafu(display(
+        "Candidate child {} of nexus {} is in use by nexus {}",
+        child,
+        name,
+        nexus
+    ))]
+    ChildInUse {
+        child: String,
+        name: String,
+        nexus: String,
+    },
+    #[snafu(display(
+        "Failed to open candidate child {} of nexus {}",
+        child,
+        name
+    ))]
+    CandidateChild {
+        source: CoreError,
+        child: String,
+        name: String,
+    },
     #[snafu(display("Child {} of nexus {} has no error store", child, name))]
     ChildMissingErrStore { child: String, name: String },
     #[snafu(display("Failed to open child {} of nexus {}", child, name))]
@@ -215,14 +262,47 @@ pub enum Error {
         name: String,
         source: RebuildError,
     },
+    #[snafu(display(
+        "Failed to checkpoint the rebuild of child {} of nexus {}",
+        child,
+        name,
+    ))]
+    RebuildCheckpoint {
+        child: String,
+        name: String,
+        source: MetaDataError,
+    },
     #[snafu(display("Invalid ShareProtocol value {}", sp_value))]
     InvalidShareProtocol { sp_value: i32 },
     #[snafu(display("Invalid NvmeAnaState value {}", ana_value))]
     InvalidNvmeAnaState { ana_value: i32 },
+    #[snafu(display("Invalid ReadPreference value {}", pref_value))]
+    InvalidReadPreference { pref_value: i32 },
+    #[snafu(display(
+        "Invalid EWMA weight {} for nexus {}, must be within (0, 1]",
+        alpha,
+        name
+    ))]
+    InvalidEwmaAlpha { alpha: f64, name: String },
+    #[snafu(display(
+        "Timed out waiting for {} active IOs of nexus {} to complete",
+        active_ios,
+        name
+    ))]
+    IoFenceTimeout { active_ios: u64, name: String },
     #[snafu(display("Failed to create nexus {}", name))]
     NexusCreate { name: String },
+    #[snafu(display("Nexus {} already exists", name))]
+    NexusExists { name: String },
     #[snafu(display("Failed to destroy nexus {}", name))]
     NexusDestroy { name: String },
+    #[snafu(display("Cannot rename nexus {} while it is shared", name))]
+    RenameShared { name: String },
+    #[snafu(display("Failed to rename nexus {} in the config file", name))]
+    RenameConfig {
+        source: std::io::Error,
+        name: String,
+    },
     #[snafu(display(
         "Child {} of nexus {} is not degraded but {}",
         child,
@@ -234,6 +314,55 @@ pub enum Error {
         name: String,
         state: String,
     },
+    #[snafu(display(
+        "Child {} of nexus {} cannot be the rebuild source: {}",
+        child,
+        name,
+        reason
+    ))]
+    InvalidRebuildSource {
+        child: String,
+        name: String,
+        reason: String,
+    },
+    #[snafu(display(
+        "Failed to replace child {} of nexus {}, rebuild of {} ended in state {}",
+        child,
+        name,
+        replacement,
+        state
+    ))]
+    ReplaceChild {
+        child: String,
+        replacement: String,
+        name: String,
+        state: String,
+    },
+    #[snafu(display(
+        "Rebuild job of child {} of nexus {} ended without reporting a state",
+        child,
+        name
+    ))]
+    RebuildJobLost { child: String, name: String },
+    #[snafu(display(
+        "Range of {} blocks at block {} is beyond the end of nexus {}",
+        num_blocks,
+        offset_blocks,
+        name
+    ))]
+    InvalidRange {
+        offset_blocks: u64,
+        num_blocks: u64,
+        name: String,
+    },
+    #[snafu(display("Failed to read child {} of nexus {}", child, name))]
+    ChildReadFailed {
+        source: CoreError,
+        child: String,
+        name: String,
+    },
+    #[snafu(display("Nexus {} has no open child", name))]
+    NoOpenChild { name: String },
     #[snafu(display("Failed to get BdevHandle for snapshot operation"))]
     FailedGetHandle,
     #[snafu(display("Failed to create snapshot on nexus {}", name))]
@@ -280,6 +409,12 @@ impl From<Error> for tonic::Status {
             Error::ChildGeometry {
                 ..
             } => Status::invalid_argument(e.to_string()),
+            Error::ChildExists {
+                ..
+            } => Status::already_exists(e.to_string()),
+            Error::ChildInUse {
+                ..
+            } => Status::failed_precondition(e.to_string()),
             Error::OpenChild {
                 ..
             } => Status::invalid_argument(e.to_string()),
@@ -289,6 +424,27 @@ impl From<Error> for tonic::Status {
             Error::ChildNotFound {
                 ..
             } => Status::not_found(e.to_string()),
+            Error::InvalidReadPreference {
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            Error::InvalidEwmaAlpha {
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            Error::NexusExists {
+                ..
+            } => Status::already_exists(e.to_string()),
+            Error::RenameShared {
+                ..
+            } => Status::failed_precondition(e.to_string()),
+            Error::InvalidRange {
+                ..
+            } => Status::out_of_range(e.to_string()),
+            Error::NoOpenChild {
+                ..
+            } => Status::failed_precondition(e.to_string()),
+            Error::InvalidRebuildSource {
+                ..
+            } => Status::failed_precondition(e.to_string()),
             e => Status::new(Code::Internal, e.to_string()),
         }
     }
@@ -296,6 +452,16 @@ impl From<Error> for tonic::Status {
 
 pub(crate) static NEXUS_PRODUCT_ID: &str = "Nexus CAS Driver v0.0.1";
 
+/// maximum time to wait for the rebuild jobs of a nexus to be cancelled
+pub(crate) const REBUILD_CANCELLATION_TIMEOUT: Duration =
+    Duration::from_secs(30);
+
+/// maximum time to wait for the active IOs of the children to complete
+pub(crate) const IO_FENCE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// interval at which the active IOs are checked while waiting on a fence
+const IO_FENCE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 #[derive(Debug)]
 pub enum NexusTarget {
     NbdDisk(NbdDisk),
@@ -327,6 +493,51 @@ pub struct Nexus {
     pub(crate) share_handle: Option<String>,
     /// enum containing the protocol-specific target used to publish the nexus
     pub nexus_target: Option<NexusTarget>,
+    /// children that are being rebuilt as the replacement of another child,
+    /// these do not degrade the nexus
+    pub(crate) replacing: Vec<String>,
+    /// policy used to select the child that serves a read
+    pub(crate) read_preference: ReadPreference,
+    /// maximum time to wait for the rebuild jobs to be cancelled on destroy
+    pub(crate) rebuild_cancellation_timeout: Duration,
+    /// maximum time to wait for the active IOs to complete on a fence
+    pub(crate) io_fence_timeout: Duration,
+    /// number of healthy children below which the nexus is only serving IO
+    /// by quorum
+    pub(crate) min_healthy_children: usize,
+    /// smallest number of blocks of the children, the child geometry does
+    /// not change once opened so it is only updated when children change
+    pub(crate) min_num_blocks_cache: AtomicU64,
+    /// number of IOs modifying data submitted to the nexus while a child is
+    /// offline, which tells whether the offline child has missed any write
+    pub(crate) data_writes: AtomicU64,
+    /// set while any child is offline, the writes are only counted then
+    pub(crate) count_data_writes: AtomicBool,
+    /// set once the nexus has received an IO modifying data
+    pub(crate) data_written: AtomicBool,
+    /// dropping the sender stops the health check of the children
+    pub(crate) health_check: Option<oneshot::Sender<()>>,
+    /// set once the nexus is being destroyed or detached, the rebuilds
+    /// stopped from then on keep their checkpoint as no more writes go to
+    /// any child
+    pub(crate) shutdown: bool,
+    /// names of the bdev before the nexus was renamed, which are only freed
+    /// with the nexus as other threads may still be reading them
+    retired_names: Vec<std::ffi::CString>,
+}
+
+/// Configuration of a detached nexus, from which it can be attached again.
+/// It can be serialized so that it survives a restart of the process.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NexusDetachedState {
+    name: String,
+    size: u64,
+    uuid: String,
+    children: Vec<String>,
+    read_preference: ReadPreference,
+    rebuild_cancellation_timeout: Duration,
+    io_fence_timeout: Duration,
+    min_healthy_children: usize,
 }
 
 unsafe impl core::marker::Sync for Nexus {}
@@ -336,12 +547,40 @@ unsafe impl core::marker::Send for Nexus {}
 pub enum NexusStatus {
     /// The nexus cannot perform any IO operation
     Faulted,
+    /// Fewer children than min_healthy_children are healthy, IO is served by
+    /// the remaining children
+    Quorum,
     /// Degraded, one or more child is missing but IO can still flow
     Degraded,
+    /// All the original children are healthy and one or more new children
+    /// are being rebuilt
+    Rebuilding,
     /// Online
     Online,
 }
 
+/// Policy used to select which child a read IO is submitted to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ReadPreference {
+    /// rotate between all children that can serve reads
+    RoundRobin,
+    /// read from the child with the lowest exponentially weighted moving
+    /// average of its read latency, ewma_alpha being the weight given to
+    /// the latest sample
+    LatencyBased { ewma_alpha: f64 },
+    /// rotate between the children that are local to the nexus, falling
+    /// back to all children when none of them is local
+    PreferLocal,
+    /// keep reading from the same child for as long as it can serve reads
+    Sticky,
+}
+
+impl Default for ReadPreference {
+    fn default() -> Self {
+        ReadPreference::RoundRobin
+    }
+}
+
 #[derive(Debug, Serialize, Clone, Copy, PartialEq, PartialOrd)]
 pub enum NexusState {
     /// nexus created but no children attached
@@ -370,6 +609,8 @@ impl ToString for NexusStatus {
             NexusStatus::Degraded => "degraded",
             NexusStatus::Online => "online",
             NexusStatus::Faulted => "faulted",
+            NexusStatus::Rebuilding => "rebuilding",
+            NexusStatus::Quorum => "quorum",
         }
         .parse()
         .unwrap()
@@ -416,6 +657,18 @@ impl Nexus {
             share_handle: None,
             size,
             nexus_target: None,
+            replacing: Vec::new(),
+            read_preference: ReadPreference::default(),
+            rebuild_cancellation_timeout: REBUILD_CANCELLATION_TIMEOUT,
+            io_fence_timeout: IO_FENCE_TIMEOUT,
+            min_healthy_children: 1,
+            min_num_blocks_cache: AtomicU64::new(u64::MAX),
+            data_writes: AtomicU64::new(0),
+            count_data_writes: AtomicBool::new(false),
+            data_written: AtomicBool::new(false),
+            health_check: None,
+            shutdown: false,
+            retired_names: Vec::new(),
         });
 
         n.bdev.set_uuid(uuid.map(String::from));
@@ -475,6 +728,8 @@ impl Nexus {
 
         self.try_open_children().await?;
         self.sync_labels().await?;
+        self.check_child_metadata().await;
+        self.discard_stale_rebuild_checkpoints().await;
         self.register().await
     }
 
@@ -513,6 +768,7 @@ impl Nexus {
         }
 
         trace!("{}: closing, from state: {:?} ", self.name, self.state);
+        self.health_check.take();
 
         let nexus_name = self.name.clone();
         Reactor::block_on(async move {
@@ -542,7 +798,119 @@ impl Nexus {
     /// Destroy the nexus
     pub async fn destroy(&mut self) -> Result<(), Error> {
         info!("Destroying nexus {}", self.name);
-        // used to synchronize the destroy call
+        self.unregister(false).await?;
+
+        // Update the child states to remove them from the config file.
+        NexusChild::save_state_change_async().await.ok();
+        Ok(())
+    }
+
+    /// Detach the nexus, closing its children and unregistering its bdev.
+    /// Unlike destroy, the bdevs of the children are kept and their
+    /// configuration is returned so that the nexus can be attached again
+    /// (see attach).
+    pub async fn detach(&mut self) -> NexusDetachedState {
+        info!("Detaching nexus {}", self.name);
+        let state = NexusDetachedState {
+            name: self.name.clone(),
+            size: self.size,
+            uuid: self.bdev.uuid_as_string(),
+            children: self.children.iter().map(|c| c.name.clone()).collect(),
+            read_preference: self.read_preference,
+            rebuild_cancellation_timeout: self.rebuild_cancellation_timeout,
+            io_fence_timeout: self.io_fence_timeout,
+            min_healthy_children: self.min_healthy_children,
+        };
+
+        if let Err(e) = self.unregister(true).await {
+            error!("Failed to detach nexus {}: {}", state.name, e.verbose());
+        }
+        state
+    }
+
+    /// Attach a nexus that was previously detached, registering its bdev and
+    /// reopening its children
+    pub async fn attach(state: NexusDetachedState) -> Result<(), Error> {
+        if nexus_lookup(&state.name).is_some() {
+            return Err(Error::NexusExists {
+                name: state.name,
+            });
+        }
+
+        nexus_create_internal(
+            &state.name,
+            state.size,
+            Some(&state.uuid),
+            &state.children,
+            true,
+        )
+        .await?;
+
+        let nexus =
+            nexus_lookup(&state.name).ok_or_else(|| Error::NexusNotFound {
+                name: state.name.clone(),
+            })?;
+        nexus.read_preference = state.read_preference;
+        nexus.rebuild_cancellation_timeout = state.rebuild_cancellation_timeout;
+        nexus.io_fence_timeout = state.io_fence_timeout;
+        nexus.min_healthy_children = state.min_healthy_children;
+        Ok(())
+    }
+
+    /// Rename the nexus and its bdev without closing it, so that IO to the
+    /// nexus is not interrupted. A shared nexus cannot be renamed as its
+    /// target is looked up by the name of the bdev.
+    pub async fn rename(&mut self, new_name: &str) -> Result<(), Error> {
+        if nexus_lookup(new_name).is_some()
+            || Bdev::lookup_by_name(new_name).is_some()
+        {
+            return Err(Error::NexusExists {
+                name: new_name.to_string(),
+            });
+        }
+
+        if self.bdev.shared().is_some() {
+            return Err(Error::RenameShared {
+                name: self.name.clone(),
+            });
+        }
+
+        info!("Renaming nexus {} to {}", self.name, new_name);
+        // a nexus from the config file would be created again under its old
+        // name on a restart, so the config file is updated first
+        Config::rename_nexus(&self.name, new_name).context(RenameConfig {
+            name: self.name.clone(),
+        })?;
+
+        // The name of the bdev is swapped in place. This relies on SPDK
+        // 21.01 looking up a bdev by walking the list of bdevs and comparing
+        // their names, later versions keep the names in a tree which would
+        // not be updated. Any thread may be reading the old name meanwhile,
+        // so it is kept until the nexus is dropped.
+        unsafe {
+            let old =
+                std::mem::replace(&mut (*self.bdev_raw).name, c_str!(new_name));
+            self.retired_names.push(std::ffi::CString::from_raw(old));
+        }
+
+        for child in self.children.iter_mut() {
+            if let Ok(job) = RebuildJob::lookup(&child.name) {
+                job.nexus = new_name.to_string();
+            }
+            child.parent = new_name.to_string();
+        }
+
+        // nexus instances are looked up by their name, so the nexus is known
+        // under the new name only from here on, without awaiting in between
+        self.name = new_name.to_string();
+        Ok(())
+    }
+
+    /// Unregister the nexus bdev. The bdevs of the children are destroyed
+    /// unless keep_children is set, in which case only their descriptors are
+    /// closed.
+    async fn unregister(&mut self, keep_children: bool) -> Result<(), Error> {
+        // used to synchronize the unregister call
         extern "C" fn nexus_destroy_cb(arg: *mut c_void, rc: i32) {
             let s = unsafe { Box::from_raw(arg as *mut oneshot::Sender<bool>) };
 
@@ -563,19 +931,28 @@ impl Nexus {
 
         // wait for all rebuild jobs to be cancelled before proceeding with the
         // destruction of the nexus
-        for child in self.children.iter() {
-            self.cancel_child_rebuild_jobs(&child.name).await;
-        }
-
-        for child in self.children.iter_mut() {
-            info!("Destroying child bdev {}", child.name);
-            if let Err(e) = child.close().await {
-                // TODO: should an error be returned here?
-                error!(
-                    "Failed to close child {} with error {}",
-                    child.name,
-                    e.verbose()
-                );
+        self.shutdown = true;
+        self.cancel_all_rebuilds().await;
+
+        if keep_children {
+            // take the children out of the I/O path before their descriptors
+            // are dropped
+            self.children
+                .iter()
+                .for_each(|c| c.set_state(ChildState::Closed));
+            self.reconfigure(DrEvent::ChildRemove).await;
+            self.children.iter_mut().for_each(|c| c.release());
+        } else {
+            for child in self.children.iter_mut() {
+                info!("Destroying child bdev {}", child.name);
+                if let Err(e) = child.close().await {
+                    // TODO: should an error be returned here?
+                    error!(
+                        "Failed to close child {} with error {}",
+                        child.name,
+                        e.verbose()
+                    );
+                }
             }
         }
 
@@ -591,8 +968,6 @@ impl Nexus {
         }
 
         if r.await.unwrap() {
-            // Update the child states to remove them from the config file.
-            NexusChild::save_state_change();
             Ok(())
         } else {
             Err(Error::NexusDestroy {
@@ -624,6 +999,37 @@ impl Nexus {
         Ok(())
     }
 
+    /// returns the number of IOs submitted to the children that have not
+    /// completed yet
+    pub fn active_ios(&self) -> u64 {
+        self.children.iter().map(|c| c.active_io()).sum()
+    }
+
+    /// wait for all IOs that are active on the children to complete. Unlike
+    /// pause, new IO is not blocked so the fence only orders the caller after
+    /// the IO that is in flight, or submitted, while waiting. Fails when the
+    /// IOs have not completed within `io_fence_timeout`.
+    pub async fn io_fence(&self) -> Result<(), Error> {
+        let start = Instant::now();
+        loop {
+            let active_ios = self.active_ios();
+            if active_ios == 0 {
+                return Ok(());
+            }
+
+            if start.elapsed() >= self.io_fence_timeout {
+                return Err(Error::IoFenceTimeout {
+                    active_ios,
+                    name: self.name.clone(),
+                });
+            }
+
+            // the reactor does not run a tokio runtime so its timer cannot
+            // be used
+            Delay::new(IO_FENCE_POLL_INTERVAL).await;
+        }
+    }
+
     /// get ANA state of the NVMe subsystem
     pub async fn get_ana_state(&self) -> Result<NvmeAnaState, Error> {
         if let Some(Protocol::Nvmf) = self.shared() {
@@ -661,6 +1067,37 @@ impl Nexus {
         })
     }
 
+    /// set the policy used to select the child that serves a read, the IO
+    /// channels are refreshed so that the new policy takes effect on all cores
+    pub async fn set_read_preference(
+        &mut self,
+        pref: ReadPreference,
+    ) -> Result<(), Error> {
+        if let ReadPreference::LatencyBased {
+            ewma_alpha,
+        } = pref
+        {
+            if !(ewma_alpha > 0.0 && ewma_alpha <= 1.0) {
+                return Err(Error::InvalidEwmaAlpha {
+                    alpha: ewma_alpha,
+                    name: self.name.clone(),
+                });
+            }
+        }
+
+        info!("{}: setting read preference to {:?}", self.name, pref);
+        self.read_preference = pref;
+        self.reconfigure(DrEvent::ReadPreference).await;
+        Ok(())
+    }
+
+    /// set the number of healthy children below which the status of the
+    /// nexus is Quorum rather than Degraded
+    pub fn set_min_healthy_children(&mut self, count: usize) {
+        info!("{}: setting min healthy children to {}", self.name, count);
+        self.min_healthy_children = count;
+    }
+
     /// register the bdev with SPDK and set the callbacks for io channel
     /// creation. Once this function is called, the device is visible and can
     /// be used for IO.
@@ -741,30 +1178,47 @@ impl Nexus {
     /// At least one child must be online
     ///
     /// Faulted
-    /// No child is online so the nexus is faulted
+    /// No child is online, or only read only children are, so the nexus is
+    /// faulted
     /// This may be made more configurable in the future
     pub fn status(&self) -> NexusStatus {
         match *self.state.lock().unwrap() {
             NexusState::Init => NexusStatus::Degraded,
             NexusState::Closed => NexusStatus::Faulted,
             NexusState::Open => {
-                if self
+                let healthy = self
                     .children
                     .iter()
-                    // All children are online, so the Nexus is also online
-                    .all(|c| c.state() == ChildState::Open)
+                    .filter(|c| {
+                        c.state() == ChildState::Open
+                            || self.replacing.contains(&c.name)
+                    })
+                    .count();
+
+                if !self.children.is_empty()
+                    && !self.children.iter().any(|c| {
+                        c.state() == ChildState::Open && !c.is_read_only()
+                    })
                 {
+                    // no child is online which can be written to, read only
+                    // children cannot serve the IO of the nexus
+                    NexusStatus::Faulted
+                } else if healthy == self.children.len() {
+                    // All children are online, so the Nexus is also online
                     NexusStatus::Online
+                } else if healthy < self.min_healthy_children {
+                    // too few children are healthy, whether or not the
+                    // others are being rebuilt
+                    NexusStatus::Quorum
                 } else if self
                     .children
                     .iter()
-                    // at least one child online, so the Nexus is also online
-                    .any(|c| c.state() == ChildState::Open)
+                    .all(|c| c.state() == ChildState::Open || c.rebuilding())
                 {
-                    NexusStatus::Degraded
+                    // the children which are not online are being rebuilt
+                    NexusStatus::Rebuilding
                 } else {
-                    // nexus has no children or at least no child is online
-                    NexusStatus::Faulted
+                    NexusStatus::Degraded
                 }
             }
         }
@@ -783,6 +1237,19 @@ pub async fn nexus_create(
     size: u64,
     uuid: Option<&str>,
     children: &[String],
+) -> Result<(), Error> {
+    nexus_create_internal(name, size, uuid, children, false).await
+}
+
+/// Create a new nexus, either creating the bdevs of its children or, when
+/// attaching a detached nexus, opening the bdevs that were kept. The bdevs of
+/// existing children are left in place if the nexus fails to come online.
+async fn nexus_create_internal(
+    name: &str,
+    size: u64,
+    uuid: Option<&str>,
+    children: &[String],
+    existing: bool,
 ) -> Result<(), Error> {
     // global variable defined in the nexus module
     let nexus_list = instances();
@@ -793,12 +1260,18 @@ pub async fn nexus_create(
         return Ok(());
     }
 
+    // Unless one is given, the UUID of the nexus is derived from the id of
+    // this node so that it is unique across nodes.
+    let uuid = uuid
+        .map(String::from)
+        .unwrap_or_else(|| MayastorEnvironment::node_scoped_uuid().to_string());
+
     // Create a new Nexus object, and immediately add it to the global list.
     // This is necessary to ensure proper cleanup, as the code responsible for
     // closing a child assumes that the nexus to which it belongs will appear
     // in the global list of nexus instances. We must also ensure that the
     // nexus instance gets removed from the global list if an error occurs.
-    nexus_list.push(Nexus::new(name, size, uuid, None));
+    nexus_list.push(Nexus::new(name, size, Some(&uuid), None));
 
     // Obtain a reference to the newly created Nexus object.
     let ni =
@@ -810,12 +1283,21 @@ pub async fn nexus_create(
             })?;
 
     for child in children {
-        if let Err(error) = ni.create_and_register(child).await {
+        let registered = if existing {
+            ni.register_existing(child)
+        } else {
+            ni.create_and_register(child).await
+        };
+        if let Err(error) = registered {
             error!(
                 "failed to create nexus {}: failed to create child {}: {}",
                 name, child, error
             );
-            ni.close_children().await;
+            if existing {
+                ni.children.iter_mut().for_each(|c| c.release());
+            } else {
+                ni.close_children().await;
+            }
             nexus_list.retain(|n| n.name != name);
             return Err(Error::CreateChild {
                 source: error,
@@ -832,7 +1314,11 @@ pub async fn nexus_create(
             // although this currently only works for config files.
             // We need to explicitly clean up child bdevs if we get this error.
             error!("failed to open nexus {}: missing children", name);
-            destroy_child_bdevs(name, children).await;
+            if existing {
+                ni.children.iter_mut().for_each(|c| c.release());
+            } else {
+                destroy_child_bdevs(name, children).await;
+            }
             nexus_list.retain(|n| n.name != name);
             Err(Error::NexusCreate {
                 name: String::from(name),
@@ -841,12 +1327,20 @@ pub async fn nexus_create(
 
         Err(error) => {
             error!("failed to open nexus {}: {}", name, error);
-            ni.close_children().await;
+            if existing {
+                ni.children.iter_mut().for_each(|c| c.release());
+            } else {
+                ni.close_children().await;
+            }
             nexus_list.retain(|n| n.name != name);
             Err(error)
         }
 
-        Ok(_) => Ok(()),
+        Ok(_) => {
+            ni.start_slow_child_monitor();
+            ni.start_health_check();
+            Ok(())
+        }
     }
 }
 
@@ -867,6 +1361,27 @@ pub fn nexus_lookup(name: &str) -> Option<&mut Nexus> {
         .map(AsMut::as_mut)
 }
 
+/// Lookup the nexus that owns the child backed by the bdev with the given
+/// name.
+pub fn nexus_lookup_by_bdev_name(bdev_name: &str) -> Option<&mut Nexus> {
+    instances()
+        .iter_mut()
+        .find(|n| {
+            n.children.iter().any(|c| {
+                c.bdev.as_ref().map_or(false, |b| b.name() == bdev_name)
+            })
+        })
+        .map(AsMut::as_mut)
+}
+
+/// Lookup the nexus that owns the child with the given URI.
+pub fn nexus_lookup_by_child_uri(uri: &str) -> Option<&mut Nexus> {
+    instances()
+        .iter_mut()
+        .find(|n| n.children.iter().any(|c| c.name == uri))
+        .map(AsMut::as_mut)
+}
+
 impl Display for Nexus {
     fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
         let _ = writeln!(
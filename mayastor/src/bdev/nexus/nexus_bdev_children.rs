@@ -20,16 +20,33 @@
 //! child requires rebuild first. If the rebuild flag is set then the rebuild
 //! is also started otherwise it has to be started through `start_rebuild`.
 //!
+//! `atomic_child_set_replace` will add a new child as the replacement of an
+//! existing child, and remove the existing child once the new child has been
+//! rebuilt. The nexus does not become degraded during the replacement.
+//!
 //! When reconfiguring the nexus, we traverse all our children, create new IO
 //! channels for all children that are in the open state.
 
-use futures::future::join_all;
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{self, join_all, Either},
+};
+use futures_timer::Delay;
 use snafu::ResultExt;
 
 use crate::{
     bdev::{
         nexus::{
+            instances,
             nexus_bdev::{
+                nexus_lookup_by_bdev_name,
+                CandidateChild,
+                ChildReadFailed,
                 CreateChild,
                 Error,
                 Nexus,
@@ -38,16 +55,24 @@ use crate::{
                 OpenChild,
             },
             nexus_channel::DrEvent,
-            nexus_child::{ChildState, NexusChild},
+            nexus_child::{ChildState, ChildStatus, NexusChild},
             nexus_child_status_config::ChildStatusConfig,
+            nexus_label::{LabelError, NexusLabel, ProbeError},
         },
         Reason,
         VerboseError,
     },
-    core::Bdev,
-    nexus_uri::{bdev_create, bdev_destroy, NexusBdevError},
+    core::{Bdev, BdevHandle, CoreError, Reactors},
+    nexus_uri::{bdev_create, bdev_destroy, bdev_get_name, NexusBdevError},
+    rebuild::RebuildState,
+    subsys::Config,
+    utils::all_equal,
 };
 
+/// number of consecutive sampling windows a child has to exceed the slow
+/// child threshold for before it is excluded from the read path
+const SLOW_CHILD_WINDOWS: u32 = 3;
+
 impl Nexus {
     /// register children with the nexus, only allowed during the nexus init
     /// phase
@@ -85,6 +110,29 @@ impl Nexus {
         Ok(())
     }
 
+    /// register a child whose bdev already exists, such as one that was kept
+    /// when the nexus was detached
+    pub(crate) fn register_existing(
+        &mut self,
+        uri: &str,
+    ) -> Result<(), NexusBdevError> {
+        assert_eq!(*self.state.lock().unwrap(), NexusState::Init);
+        let name = bdev_get_name(uri)?;
+        let bdev = Bdev::lookup_by_name(&name).ok_or_else(|| {
+            NexusBdevError::BdevNotFound {
+                name,
+            }
+        })?;
+        self.children.push(NexusChild::new(
+            uri.to_string(),
+            self.name.clone(),
+            Some(bdev),
+        ));
+
+        self.child_count += 1;
+        Ok(())
+    }
+
     /// add a new child to an existing nexus. note that the child is added and
     /// opened but not taking part of any new IO's that are submitted to the
     /// nexus.
@@ -99,10 +147,10 @@ impl Nexus {
         uri: &str,
         norebuild: bool,
     ) -> Result<NexusStatus, Error> {
-        let status = self.add_child_only(uri).await?;
+        let status = self.add_child_only(uri, false).await?;
 
         if !norebuild {
-            if let Err(e) = self.start_rebuild(&uri).await {
+            if let Err(e) = self.start_rebuild(&uri, false).await {
                 // todo: CAS-253 retry starting the rebuild again when ready
                 error!(
                     "Child added but rebuild failed to start: {}",
@@ -121,20 +169,220 @@ impl Nexus {
         Ok(status)
     }
 
+    /// Replace the child given by old_uri with a new child given by new_uri.
+    /// The new child is added and receives all writes while it is being
+    /// rebuilt, the old child is only removed once the rebuild has completed
+    /// and the new child has become part of the IO path. Hence the number of
+    /// healthy children never drops during the replacement.
+    /// If the rebuild does not complete, the new child is removed again and
+    /// the old child is retained.
+    pub async fn atomic_child_set_replace(
+        &mut self,
+        old_uri: &str,
+        new_uri: &str,
+    ) -> Result<NexusStatus, Error> {
+        trace!(
+            "{}: replace child request for {} with {}",
+            self.name,
+            old_uri,
+            new_uri
+        );
+
+        if !self.children.iter().any(|c| c.name == old_uri) {
+            return Err(Error::ChildNotFound {
+                child: old_uri.to_owned(),
+                name: self.name.clone(),
+            });
+        }
+
+        self.add_child_only(new_uri, false).await?;
+        self.replacing.push(new_uri.to_owned());
+
+        let result = match self.start_rebuild(new_uri, false).await {
+            Ok(complete) => match complete.await {
+                Ok(RebuildState::Completed) => Ok(()),
+                Ok(state) => Err(Error::ReplaceChild {
+                    child: old_uri.to_owned(),
+                    replacement: new_uri.to_owned(),
+                    name: self.name.clone(),
+                    state: state.to_string(),
+                }),
+                // the job went away without sending its final state, which
+                // is not a cancellation requested by anyone
+                Err(_) => Err(Error::RebuildJobLost {
+                    child: new_uri.to_owned(),
+                    name: self.name.clone(),
+                }),
+            },
+            Err(e) => Err(e),
+        };
+
+        self.replacing.retain(|c| c != new_uri);
+
+        if let Err(error) = result {
+            error!(
+                "{}: failed to rebuild replacement child {}: {}",
+                self.name,
+                new_uri,
+                error.verbose()
+            );
+            if let Err(e) = self.remove_child(new_uri).await {
+                error!(
+                    "{}: failed to remove replacement child {}: {}",
+                    self.name,
+                    new_uri,
+                    e.verbose()
+                );
+            }
+            return Err(error);
+        }
+
+        // the completion of the rebuild is processed asynchronously, so make
+        // sure the new child is part of the IO path before the old child is
+        // removed
+        let child = self.get_child_by_name(new_uri)?;
+        if child.state() != ChildState::Open {
+            child.set_state(ChildState::Open);
+            NexusChild::save_state_change_async().await.ok();
+            self.reconfigure(DrEvent::ChildRebuild).await;
+        }
+
+        self.remove_child(old_uri).await?;
+        Ok(self.status())
+    }
+
+    /// Check that the bdev of a new child can hold the data of the nexus,
+    /// and that its block size matches the block size of the nexus
+    fn check_child_geometry(&self, name: &str) -> Result<Bdev, Error> {
+        let child =
+            Bdev::lookup_by_name(name).ok_or_else(|| Error::ChildMissing {
+                child: name.to_owned(),
+                name: self.name.clone(),
+            })?;
+
+        if child.block_len() != self.bdev.block_len()
+            || self.min_num_blocks() > child.num_blocks()
+        {
+            for (uri, block_len, num_blocks) in self.children_geometry() {
+                info!(
+                    "{}: child {} has {} blocks of {} bytes",
+                    self.name, uri, num_blocks, block_len
+                );
+            }
+
+            return Err(Error::ChildGeometry {
+                child: name.to_owned(),
+                name: self.name.clone(),
+                child_block_len: child.block_len(),
+                child_num_blocks: child.num_blocks(),
+                block_len: self.bdev.block_len(),
+                num_blocks: self.min_num_blocks(),
+            });
+        }
+
+        Ok(child)
+    }
+
+    /// Check whether the device given by the uri could be added as a child,
+    /// without adding it. Unless its bdev already exists, the device is
+    /// created to check its geometry and its label, if it has any, and
+    /// destroyed again. A device without a label is a valid candidate as the
+    /// label is written when it is added. A bdev that is already the child of
+    /// a nexus is not a valid candidate.
+    pub async fn validate_candidate_child(
+        &self,
+        uri: &str,
+    ) -> Result<(), Error> {
+        if self.children.iter().any(|c| c.name == uri) {
+            return Err(Error::ChildExists {
+                child: uri.to_owned(),
+                name: self.name.clone(),
+            });
+        }
+
+        let name = bdev_get_name(uri).context(CreateChild {
+            name: self.name.clone(),
+        })?;
+
+        if let Some(nexus) = nexus_lookup_by_bdev_name(&name) {
+            return Err(Error::ChildInUse {
+                child: uri.to_owned(),
+                name: self.name.clone(),
+                nexus: nexus.name.clone(),
+            });
+        }
+
+        // only destroy the bdev again if it was created here
+        let created = Bdev::lookup_by_name(&name).is_none();
+        let name = if created {
+            bdev_create(&uri).await.context(CreateChild {
+                name: self.name.clone(),
+            })?
+        } else {
+            name
+        };
+
+        let result = self.validate_candidate_bdev(&name).await;
+
+        if created {
+            if let Err(err) = bdev_destroy(uri).await {
+                error!("Failed to destroy candidate child {}: {}", uri, err);
+            }
+        }
+        result
+    }
+
+    async fn validate_candidate_bdev(&self, name: &str) -> Result<(), Error> {
+        self.check_child_geometry(name)?;
+
+        let handle =
+            BdevHandle::open(name, false, false).context(CandidateChild {
+                child: name.to_owned(),
+                name: self.name.clone(),
+            })?;
+        match NexusLabel::probe(&handle).await {
+            Err(LabelError::InvalidLabel {
+                source: ProbeError::MbrSignature {},
+            }) => Ok(()),
+            Err(source) => Err(Error::ReadLabel {
+                source,
+                name: self.name.clone(),
+            }),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    /// add a new child to an existing nexus which is only read from, such as
+    /// a snapshot of the nexus that is used as a source of data. The child is
+    /// opened write protected and put online, but it does not take part in
+    /// the IO path of the nexus, as it would serve stale data once the nexus
+    /// is written to. It is only used as the source of a rebuild when asked
+    /// for explicitly, see start_rebuild_from.
+    pub async fn add_read_only_child(
+        &mut self,
+        uri: &str,
+    ) -> Result<NexusStatus, Error> {
+        self.add_child_only(uri, true).await
+    }
+
     /// The child may require a rebuild first, so the nexus will
     /// transition to degraded mode when the addition has been successful.
+    /// A read only child is not rebuilt and is put online right away.
     async fn add_child_only(
         &mut self,
         uri: &str,
+        read_only: bool,
     ) -> Result<NexusStatus, Error> {
         let name = bdev_create(&uri).await.context(CreateChild {
             name: self.name.clone(),
         })?;
 
-        let child_bdev = match Bdev::lookup_by_name(&name) {
-            Some(child) => {
-                if child.block_len() != self.bdev.block_len()
-                    || self.min_num_blocks() > child.num_blocks()
+        let child_bdev = match self.check_child_geometry(&name) {
+            Ok(child_bdev) => child_bdev,
+            Err(e) => {
+                if let Error::ChildGeometry {
+                    ..
+                } = e
                 {
                     if let Err(err) = bdev_destroy(uri).await {
                         error!(
@@ -142,20 +390,8 @@ impl Nexus {
                             err
                         );
                     }
-
-                    return Err(Error::ChildGeometry {
-                        child: name,
-                        name: self.name.clone(),
-                    });
-                } else {
-                    child
                 }
-            }
-            None => {
-                return Err(Error::ChildMissing {
-                    child: name,
-                    name: self.name.clone(),
-                })
+                return Err(e);
             }
         };
 
@@ -164,7 +400,7 @@ impl Nexus {
             self.name.clone(),
             Some(child_bdev),
         );
-        match child.open(self.size) {
+        match child.open(self.size, read_only) {
             Ok(name) => {
                 // we have created the bdev, and created a nexusChild struct. To
                 // make use of the device itself the
@@ -175,19 +411,26 @@ impl Nexus {
 
                 // it can never take part in the IO path
                 // of the nexus until it's rebuilt from a healthy child.
-                child.fault(Reason::OutOfSync).await;
+                if !read_only {
+                    child.fault(Reason::OutOfSync).await;
+                }
                 if ChildStatusConfig::add(&child).is_err() {
                     error!("Failed to add child status information");
                 }
 
                 self.children.push(child);
                 self.child_count += 1;
+                self.invalidate_min_num_blocks_cache();
 
                 if let Err(e) = self.sync_labels().await {
                     error!("Failed to sync labels {:?}", e);
                     // todo: how to signal this?
                 }
 
+                if read_only {
+                    self.reconfigure(DrEvent::ChildReadOnly).await;
+                }
+
                 Ok(self.status())
             }
             Err(e) => {
@@ -217,6 +460,7 @@ impl Nexus {
 
         let cancelled_rebuilding_children =
             self.cancel_child_rebuild_jobs(uri).await;
+        self.discard_rebuild_checkpoint(uri).await;
 
         let idx = match self.children.iter().position(|c| c.name == uri) {
             None => return Ok(()),
@@ -233,9 +477,14 @@ impl Nexus {
 
         self.children.remove(idx);
         self.child_count -= 1;
+        self.invalidate_min_num_blocks_cache();
+        self.update_count_data_writes();
+        // the channels index the children by their position, which has
+        // changed for the children after the removed one
+        self.reconfigure(DrEvent::ChildRemove).await;
 
         // Update child status to remove this child
-        NexusChild::save_state_change();
+        NexusChild::save_state_change_async().await.ok();
 
         self.start_rebuild_jobs(cancelled_rebuilding_children).await;
         Ok(())
@@ -250,17 +499,31 @@ impl Nexus {
 
         let cancelled_rebuilding_children =
             self.cancel_child_rebuild_jobs(name).await;
+        self.discard_rebuild_checkpoint(name).await;
 
-        if let Some(child) = self.children.iter_mut().find(|c| c.name == name) {
-            child.offline().await;
-        } else {
+        if !self.children.iter().any(|c| c.name == name) {
             return Err(Error::ChildNotFound {
                 name: self.name.clone(),
                 child: name.to_owned(),
             });
         }
 
+        // count the writes only once those in flight have completed, a write
+        // which fails on the child before then faults it
+        self.pause().await?;
+        let drained = self.io_fence().await;
+        let writes = self.data_writes.load(Ordering::Relaxed);
+        if let Some(child) = self.children.iter_mut().find(|c| c.name == name) {
+            if drained.is_err() || child.state() != ChildState::Open {
+                child.set_dirty(true);
+            }
+            child.offline_since = Some((Instant::now(), writes));
+            child.offline().await;
+        }
+        self.update_count_data_writes();
+
         self.reconfigure(DrEvent::ChildOffline).await;
+        self.resume().await?;
         self.start_rebuild_jobs(cancelled_rebuilding_children).await;
 
         Ok(self.status())
@@ -297,6 +560,7 @@ impl Nexus {
 
         let cancelled_rebuilding_children =
             self.cancel_child_rebuild_jobs(name).await;
+        self.discard_rebuild_checkpoint(name).await;
 
         let result = match self.children.iter_mut().find(|c| c.name == name) {
             Some(child) => {
@@ -304,7 +568,7 @@ impl Nexus {
                     ChildState::Faulted(_) => {}
                     _ => {
                         child.fault(reason).await;
-                        NexusChild::save_state_change();
+                        NexusChild::save_state_change_async().await.ok();
                         self.reconfigure(DrEvent::ChildFault).await;
                     }
                 }
@@ -332,11 +596,13 @@ impl Nexus {
         trace!("{} Online child request", self.name);
 
         if let Some(child) = self.children.iter_mut().find(|c| c.name == name) {
-            child.online(self.size).await.context(OpenChild {
+            let result = child.online(self.size).await;
+            self.update_count_data_writes();
+            result.context(OpenChild {
                 child: name.to_owned(),
                 name: self.name.clone(),
             })?;
-            self.start_rebuild(name).await.map(|_| {})?;
+            self.start_rebuild(name, false).await.map(|_| {})?;
             Ok(self.status())
         } else {
             Err(Error::ChildNotFound {
@@ -346,8 +612,56 @@ impl Nexus {
         }
     }
 
+    /// reconnect a previously offlined child, see NexusChild::reconnect. The
+    /// child rejoins the IO path right away if it has not missed any write,
+    /// otherwise it is rebuilt as when it is onlined.
+    pub async fn reconnect_child(
+        &mut self,
+        name: &str,
+        max_dirty_seconds: u64,
+    ) -> Result<NexusStatus, Error> {
+        trace!("{}: Reconnect child request for {}", self.name, name);
+
+        let child = match self.children.iter_mut().find(|c| c.name == name) {
+            Some(child) => child,
+            None => {
+                return Err(Error::ChildNotFound {
+                    name: self.name.clone(),
+                    child: name.to_owned(),
+                })
+            }
+        };
+        let writes = child.offline_since.map(|(_, writes)| writes);
+        let result = child.reconnect(max_dirty_seconds).await;
+        let state = result.context(OpenChild {
+            child: name.to_owned(),
+            name: self.name.clone(),
+        })?;
+
+        if state == ChildState::Open {
+            self.reconfigure(DrEvent::ChildReconnect).await;
+            // a write that was submitted while the channels were being
+            // reconfigured may not have reached the child
+            let missed_writes =
+                Some(self.data_writes.load(Ordering::Relaxed)) != writes;
+            self.update_count_data_writes();
+            if !missed_writes {
+                return Ok(self.status());
+            }
+            if let Some(child) = self.child_lookup(name) {
+                child.set_state(ChildState::Faulted(Reason::OutOfSync));
+            }
+            self.reconfigure(DrEvent::ChildFault).await;
+        }
+        self.update_count_data_writes();
+
+        self.start_rebuild(name, false).await.map(|_| {})?;
+        Ok(self.status())
+    }
+
     /// Close each child that belongs to this nexus.
     pub(crate) async fn close_children(&mut self) {
+        self.health_check.take();
         let futures = self.children.iter_mut().map(|c| c.close());
         let results = join_all(futures).await;
         if results.iter().any(|c| c.is_err()) {
@@ -380,17 +694,18 @@ impl Nexus {
             });
         }
 
-        let blk_size = self.children[0].bdev.as_ref().unwrap().block_len();
-
-        if self
-            .children
-            .iter()
-            .any(|b| b.bdev.as_ref().unwrap().block_len() != blk_size)
-        {
-            return Err(Error::MixedBlockSizes {
-                name: self.name.clone(),
-            });
-        }
+        let blk_size = match all_equal(
+            self.children
+                .iter()
+                .map(|c| c.bdev.as_ref().unwrap().block_len()),
+        ) {
+            Some(blk_size) => blk_size,
+            None => {
+                return Err(Error::MixedBlockSizes {
+                    name: self.name.clone(),
+                });
+            }
+        };
 
         self.bdev.set_block_len(blk_size);
 
@@ -399,7 +714,7 @@ impl Nexus {
         let (open, error): (Vec<_>, Vec<_>) = self
             .children
             .iter_mut()
-            .map(|c| c.open(size))
+            .map(|c| c.open(size, false))
             .partition(Result::is_ok);
 
         // depending on IO consistency policies, we might be able to go online
@@ -429,6 +744,8 @@ impl Nexus {
             });
         }
 
+        self.invalidate_min_num_blocks_cache();
+
         self.children
             .iter()
             .map(|c| c.bdev.as_ref().unwrap().alignment())
@@ -450,23 +767,72 @@ impl Nexus {
     }
 
     /// The nexus is allowed to be smaller then the underlying child devices
-    /// this function returns the smallest blockcnt of all online children as
+    /// this function returns the smallest blockcnt of all children as
     /// they MAY vary in size.
-    pub(crate) fn min_num_blocks(&self) -> u64 {
-        let mut blockcnt = std::u64::MAX;
+    #[inline]
+    pub fn min_num_blocks(&self) -> u64 {
+        self.min_num_blocks_cache.load(Ordering::Relaxed)
+    }
+
+    /// Return the largest blockcnt of all children, or 0 when none of
+    /// them has a bdev
+    pub fn max_num_blocks(&self) -> u64 {
         self.children
             .iter()
-            .filter(|c| c.state() == ChildState::Open)
-            .map(|c| c.bdev.as_ref().unwrap().num_blocks())
-            .collect::<Vec<_>>()
+            .filter_map(|c| c.bdev.as_ref().map(|b| b.num_blocks()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Return the uri, block size and blockcnt of every child which has a
+    /// bdev, to explain why a child does not fit the nexus
+    pub fn children_geometry(&self) -> Vec<(String, u32, u64)> {
+        self.children
             .iter()
-            .map(|s| {
-                if *s < blockcnt {
-                    blockcnt = *s;
-                }
+            .filter_map(|c| {
+                c.bdev
+                    .as_ref()
+                    .map(|b| (c.name.clone(), b.block_len(), b.num_blocks()))
             })
-            .for_each(drop);
-        blockcnt
+            .collect()
+    }
+
+    /// Recompute the smallest blockcnt of the children, to be called
+    /// whenever children are opened, added or removed.
+    pub(crate) fn invalidate_min_num_blocks_cache(&mut self) {
+        let blockcnt = self
+            .children
+            .iter()
+            .filter_map(|c| c.bdev.as_ref().map(|b| b.num_blocks()))
+            .min()
+            .unwrap_or(std::u64::MAX);
+        self.min_num_blocks_cache.store(blockcnt, Ordering::Relaxed);
+    }
+
+    /// account an IO modifying data submitted to the nexus. The writes are
+    /// only counted while a child is offline, so that no counter shared by
+    /// all cores is updated on the IO path otherwise.
+    #[inline]
+    pub(crate) fn data_write_submitted(&self) {
+        if !self.data_written.load(Ordering::Relaxed) {
+            self.data_written.store(true, Ordering::Relaxed);
+        }
+        if self.count_data_writes.load(Ordering::Relaxed) {
+            self.data_writes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count the writes for as long as any child is offline, to be called
+    /// whenever a child is set offline or leaves the offline state.
+    pub(crate) fn update_count_data_writes(&self) {
+        let offline = self.children.iter().any(|c| c.offline_since.is_some());
+        self.count_data_writes.store(offline, Ordering::Relaxed);
+    }
+
+    /// Return the status of all children in a single pass over the child
+    /// list, so that the result reflects one consistent view of the nexus
+    pub async fn get_children_status(&self) -> Vec<ChildStatus> {
+        self.children.iter().map(|c| c.status()).collect()
     }
 
     /// lookup a child by its name
@@ -489,4 +855,235 @@ impl Nexus {
             }),
         }
     }
+
+    /// Read the same region, in blocks of the nexus, from all healthy
+    /// children concurrently, bypassing the read path of the nexus. Returns
+    /// the data read from each child together with its name.
+    pub async fn split_io_read(
+        &self,
+        offset_blocks: u64,
+        num_blocks: u64,
+    ) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let invalid_range = || Error::InvalidRange {
+            offset_blocks,
+            num_blocks,
+            name: self.name.clone(),
+        };
+
+        let end = offset_blocks
+            .checked_add(num_blocks)
+            .ok_or_else(invalid_range)?;
+        if end > self.bdev.num_blocks() {
+            return Err(invalid_range());
+        }
+
+        // the range is within the nexus, but the offset on the children and
+        // the length in bytes may still not be representable
+        let block_len = u64::from(self.bdev.block_len());
+        let offset = self
+            .data_ent_offset
+            .checked_add(offset_blocks)
+            .and_then(|o| o.checked_mul(block_len))
+            .ok_or_else(invalid_range)?;
+        let len = num_blocks
+            .checked_mul(block_len)
+            .ok_or_else(invalid_range)?;
+
+        let reads = self
+            .children
+            .iter()
+            .filter(|c| c.state() == ChildState::Open)
+            .map(|child| async move {
+                let read = async {
+                    let hdl = child.handle()?;
+                    let mut buf = hdl.dma_malloc(len).map_err(|source| {
+                        CoreError::DmaAllocation {
+                            source,
+                        }
+                    })?;
+                    hdl.read_at(offset, &mut buf).await?;
+                    Ok::<_, CoreError>(buf.as_slice().to_vec())
+                };
+                let data = read.await.context(ChildReadFailed {
+                    child: child.name.clone(),
+                    name: self.name.clone(),
+                })?;
+                Ok((child.name.clone(), data))
+            });
+
+        join_all(reads).await.into_iter().collect()
+    }
+
+    /// Read the same region from all healthy children concurrently, see
+    /// split_io_read, and compare the data of each child with the data of
+    /// the first one. Returns whether the data of each child matches, the
+    /// first child always matching itself.
+    pub async fn split_io_compare(
+        &self,
+        offset_blocks: u64,
+        num_blocks: u64,
+    ) -> Result<Vec<(String, bool)>, Error> {
+        let reads = self.split_io_read(offset_blocks, num_blocks).await?;
+        let first = match reads.first() {
+            Some((_, data)) => data.clone(),
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(reads
+            .into_iter()
+            .map(|(name, data)| (name, data == first))
+            .collect())
+    }
+
+    /// Sample the IO latency of the children once every slow child window,
+    /// for as long as the nexus exists. Nothing is sampled when the slow
+    /// child threshold is 0.
+    pub(crate) fn start_slow_child_monitor(&self) {
+        let opts = &Config::get().nvme_bdev_opts;
+        if opts.slow_child_threshold_us == 0 {
+            return;
+        }
+
+        let window = Duration::from_millis(opts.slow_child_window_ms);
+        // the nexus is identified by its uuid as it can be renamed
+        let uuid = self.bdev.uuid_as_string();
+        Reactors::master().send_future(async move {
+            loop {
+                Delay::new(window).await;
+                match instances()
+                    .iter_mut()
+                    .find(|n| n.bdev.uuid_as_string() == uuid)
+                {
+                    Some(nexus) => nexus.check_slow_children().await,
+                    None => break,
+                }
+            }
+        });
+    }
+
+    /// Evaluate the latency of each child over the last sampling window. A
+    /// child whose latency exceeded the slow child threshold for
+    /// SLOW_CHILD_WINDOWS windows in a row is excluded from the read path,
+    /// writes still go to all children, until its latency drops below half
+    /// of the threshold.
+    pub(crate) async fn check_slow_children(&mut self) {
+        let threshold = Config::get().nvme_bdev_opts.slow_child_threshold_us;
+        let mut events = Vec::new();
+
+        for child in self.children.iter_mut() {
+            let latency_us = match child.take_window_latency_us() {
+                Some(latency_us) => latency_us,
+                None => continue,
+            };
+
+            if child.slow {
+                if latency_us < threshold / 2 {
+                    info!(
+                        "{}: child {} recovered, latency {}us",
+                        self.name, child.name, latency_us
+                    );
+                    child.slow = false;
+                    child.slow_windows = 0;
+                    events.push(DrEvent::ChildRecovered {
+                        child_name: child.name.clone(),
+                    });
+                }
+            } else if latency_us > threshold {
+                child.slow_windows += 1;
+                if child.slow_windows >= SLOW_CHILD_WINDOWS {
+                    warn!(
+                        "{}: child {} is slow, latency {}us",
+                        self.name, child.name, latency_us
+                    );
+                    child.slow = true;
+                    events.push(DrEvent::ChildSlow {
+                        child_name: child.name.clone(),
+                        latency_us,
+                    });
+                }
+            } else {
+                child.slow_windows = 0;
+            }
+        }
+
+        for event in events {
+            self.reconfigure(event).await;
+        }
+    }
+
+    /// Probe the open children once every health check interval, until the
+    /// children are closed. Nothing is probed when the interval is 0.
+    pub(crate) fn start_health_check(&mut self) {
+        let interval = Config::get().nvme_bdev_opts.health_check_interval_ms;
+        if interval == 0 {
+            return;
+        }
+
+        let (sender, mut receiver) = oneshot::channel::<()>();
+        self.health_check = Some(sender);
+        let interval = Duration::from_millis(interval);
+        let uuid = self.bdev.uuid_as_string();
+        Reactors::master().send_future(async move {
+            loop {
+                let delay = Delay::new(interval);
+                if let Either::Right(_) =
+                    future::select(delay, &mut receiver).await
+                {
+                    break;
+                }
+                match instances()
+                    .iter_mut()
+                    .find(|n| n.bdev.uuid_as_string() == uuid)
+                {
+                    Some(nexus) => nexus.check_child_health().await,
+                    None => break,
+                }
+            }
+        });
+    }
+
+    /// Probe each open child with a read of a single block. A child that
+    /// failed health_check_max_failures probes in a row is faulted, unless
+    /// it is the last healthy child of the nexus.
+    pub(crate) async fn check_child_health(&mut self) {
+        let max_failures =
+            Config::get().nvme_bdev_opts.health_check_max_failures;
+        let mut failed = Vec::new();
+
+        for child in self
+            .children
+            .iter_mut()
+            .filter(|c| c.state() == ChildState::Open)
+        {
+            match child.probe().await {
+                Ok(()) => child.probe_failures = 0,
+                Err(e) => {
+                    child.probe_failures += 1;
+                    warn!(
+                        "{}: child {} failed health check {} of {}: {}",
+                        self.name,
+                        child.name,
+                        child.probe_failures,
+                        max_failures,
+                        e.verbose()
+                    );
+                    if child.probe_failures >= max_failures {
+                        child.probe_failures = 0;
+                        failed.push(child.name.clone());
+                    }
+                }
+            }
+        }
+
+        for name in failed {
+            if let Err(e) = self.fault_child(&name, Reason::IoError).await {
+                error!(
+                    "{}: failed to fault unhealthy child {}: {}",
+                    self.name,
+                    name,
+                    e.verbose()
+                );
+            }
+        }
+    }
 }
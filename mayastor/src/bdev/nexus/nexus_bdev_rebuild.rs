@@ -1,4 +1,10 @@
-use futures::channel::oneshot::Receiver;
+use std::sync::atomic::Ordering;
+
+use futures::{
+    channel::oneshot::Receiver,
+    future::{join_all, select, Either},
+};
+use futures_timer::Delay;
 use snafu::ResultExt;
 
 use rpc::mayastor::{
@@ -15,19 +21,21 @@ use crate::{
                 CreateRebuildError,
                 Error,
                 Nexus,
+                RebuildCheckpoint,
                 RebuildJobNotFound,
                 RebuildOperationError,
                 RemoveRebuildJob,
             },
             nexus_channel::DrEvent,
             nexus_child::{ChildState, NexusChild, Reason},
+            nexus_metadata,
         },
         VerboseError,
     },
     core::Reactors,
     rebuild::{
         ClientOperations,
-        RebuildError,
+        RebuildFailureReason,
         RebuildJob,
         RebuildState,
         RebuildStats,
@@ -36,24 +44,77 @@ use crate::{
 
 impl Nexus {
     /// Starts a rebuild job and returns a receiver channel
-    /// which can be used to await the rebuild completion
+    /// which can be used to await the rebuild completion.
+    /// With verify set, the rebuilt data is compared with the source once
+    /// copied and the rebuild fails should they differ.
     pub async fn start_rebuild(
         &mut self,
         name: &str,
+        verify: bool,
     ) -> Result<Receiver<RebuildState>, Error> {
         trace!("{}: start rebuild request for {}", self.name, name);
 
-        let src_child_name = match self
-            .children
-            .iter()
-            .find(|c| c.state() == ChildState::Open && c.name != name)
-        {
+        // read only children do not receive the writes of the nexus
+        let src_child_name = match self.children.iter().find(|c| {
+            c.state() == ChildState::Open && !c.is_read_only() && c.name != name
+        }) {
             Some(child) => Ok(child.name.clone()),
             None => Err(Error::NoRebuildSource {
                 name: self.name.clone(),
             }),
         }?;
 
+        self.start_rebuild_job(name, src_child_name, verify).await
+    }
+
+    /// Starts a rebuild job of child `destination` which copies the data from
+    /// child `source` rather than from a child picked by the nexus, and
+    /// returns a receiver channel which can be used to await the rebuild
+    /// completion. The source has to be an open child other than the
+    /// destination.
+    pub async fn start_rebuild_from(
+        &mut self,
+        destination: &str,
+        source: &str,
+    ) -> Result<Receiver<RebuildState>, Error> {
+        trace!(
+            "{}: start rebuild request for {} from {}",
+            self.name,
+            destination,
+            source
+        );
+
+        let invalid_source = |reason: String| Error::InvalidRebuildSource {
+            child: source.to_owned(),
+            name: self.name.clone(),
+            reason,
+        };
+        match self.children.iter().find(|c| c.name == source) {
+            Some(_) if source == destination => {
+                Err(invalid_source("it is the rebuild destination".into()))
+            }
+            Some(c) if c.state() != ChildState::Open => {
+                Err(invalid_source(c.state().to_string()))
+            }
+            Some(_) => Ok(()),
+            None => Err(Error::ChildNotFound {
+                child: source.to_owned(),
+                name: self.name.clone(),
+            }),
+        }?;
+
+        self.start_rebuild_job(destination, source.to_owned(), false)
+            .await
+    }
+
+    /// Creates and starts the rebuild job of child `name` from child
+    /// `src_child_name`
+    async fn start_rebuild_job(
+        &mut self,
+        name: &str,
+        src_child_name: String,
+        verify: bool,
+    ) -> Result<Receiver<RebuildState>, Error> {
         let dst_child_name =
             match self.children.iter_mut().find(|c| c.name == name) {
                 Some(c)
@@ -91,6 +152,64 @@ impl Nexus {
             name: self.name.clone(),
         })?;
 
+        job.set_verify(verify);
+        job.set_checkpoint_fn(|nexus, job| {
+            Reactors::current().send_future(async move {
+                Nexus::checkpoint_rebuild_async(nexus, job).await;
+            });
+        });
+
+        // resume from where a previous rebuild of the child left off, unless
+        // the data is now copied from another child or this nexus has
+        // already written data the child, which is not part of the IO path
+        // yet, has missed
+        let checkpoint = self
+            .get_child_by_name(&dst_child_name)?
+            .read_rebuild_checkpoint()
+            .await;
+        let missed_writes = self.data_written.load(Ordering::Relaxed);
+        match checkpoint {
+            Ok(Some(checkpoint))
+                if checkpoint.source_uri == src_child_name
+                    && checkpoint.destination_uri == dst_child_name
+                    && checkpoint.total_blocks == self.bdev.num_blocks()
+                    && !missed_writes =>
+            {
+                info!(
+                    "{}: resuming the rebuild of {} from block {}",
+                    self.name, dst_child_name, checkpoint.next_lba
+                );
+                if let Err(e) = job.resume_from(checkpoint.next_lba) {
+                    warn!(
+                        "{}: failed to resume the rebuild of {}: {}",
+                        self.name,
+                        dst_child_name,
+                        e.verbose()
+                    );
+                }
+            }
+            Ok(Some(_)) => {
+                info!(
+                    "{}: discarding the rebuild checkpoint of {}",
+                    self.name, dst_child_name
+                );
+                self.get_child_by_name(&dst_child_name)?
+                    .clear_rebuild_checkpoint()
+                    .await
+                    .ok();
+            }
+            // no checkpoint, or no metadata partition to hold one
+            Ok(None) | Err(_) => {}
+        }
+
+        job.set_progress_fn(Nexus::on_rebuild_progress);
+        self.get_child_by_name(&dst_child_name)?.set_state(
+            ChildState::Rebuilding {
+                source: src_child_name.clone(),
+                progress_pct: job.progress_pct(),
+            },
+        );
+
         // We're now rebuilding the `dst_child` which means it HAS to become an
         // active participant in the frontend nexus bdev for Writes.
         // This is because the rebuild job copies from src to target child
@@ -101,10 +220,73 @@ impl Nexus {
         // rebuilt ranges in sync with the other children.
         self.reconfigure(DrEvent::ChildRebuild).await;
 
-        job.as_client().start().context(RebuildOperationError {
+        let complete = job.as_client().start().context(RebuildOperationError {
             job: name.to_owned(),
             name: self.name.clone(),
-        })
+        });
+        if complete.is_err() {
+            self.get_child_by_name(&dst_child_name)?
+                .set_state(ChildState::Faulted(Reason::OutOfSync));
+        }
+        complete
+    }
+
+    /// Persists the progress of the rebuild of child `name` on the child,
+    /// allowing a rebuild to resume from it should the nexus be recreated
+    pub async fn checkpoint_rebuild(
+        &mut self,
+        name: &str,
+    ) -> Result<(), Error> {
+        let job = self.get_rebuild_job(name)?;
+        // the checkpoint of a finished job has been discarded already
+        if job.state().done() {
+            return Ok(());
+        }
+        let checkpoint = nexus_metadata::RebuildCheckpoint::new(
+            &job.source,
+            &job.destination,
+            job.checkpoint_lba(),
+            self.bdev.num_blocks(),
+        );
+
+        self.get_child_by_name(name)?
+            .write_rebuild_checkpoint(&checkpoint)
+            .await
+            .context(RebuildCheckpoint {
+                child: name.to_owned(),
+                name: self.name.clone(),
+            })
+    }
+
+    /// Discards the rebuild checkpoint of child `name`, which must happen
+    /// before the child stops receiving the writes to the nexus as it could
+    /// otherwise resume past data it has missed
+    pub(crate) async fn discard_rebuild_checkpoint(&self, name: &str) {
+        if let Some(child) = self.children.iter().find(|c| c.name == name) {
+            child.clear_rebuild_checkpoint().await.ok();
+        }
+    }
+
+    /// Discards the rebuild checkpoints of the children which were not
+    /// written by a rebuild from another child of this nexus
+    pub(crate) async fn discard_stale_rebuild_checkpoints(&self) {
+        for child in &self.children {
+            if let Ok(Some(checkpoint)) = child.read_rebuild_checkpoint().await
+            {
+                if checkpoint.destination_uri != child.name
+                    || !self
+                        .children
+                        .iter()
+                        .any(|c| c.name == checkpoint.source_uri)
+                {
+                    info!(
+                        "{}: discarding the stale rebuild checkpoint of {}",
+                        self.name, child.name
+                    );
+                    child.clear_rebuild_checkpoint().await.ok();
+                }
+            }
+        }
     }
 
     /// Terminates a rebuild in the background
@@ -162,11 +344,37 @@ impl Nexus {
         name: &str,
     ) -> Result<RebuildStateReply, Error> {
         let rj = self.get_rebuild_job(name)?;
+        let (blocks_total, blocks_transferred) = rj.blocks();
         Ok(RebuildStateReply {
             state: rj.state().to_string(),
+            reason: rj
+                .failure_reason()
+                .map(|r| r.to_string())
+                .unwrap_or_default(),
+            throughput: rj.throughput(),
+            blocks_total,
+            blocks_transferred,
+            progress_percent: rj.progress_pct(),
+            eta_seconds: rj.eta().map(|eta| eta.as_secs()).unwrap_or_default(),
         })
     }
 
+    /// Limit the rate at which the rebuild job of child `name` copies data,
+    /// in bytes per second, 0 removes the limit
+    pub fn set_rebuild_rate_limit(
+        &self,
+        name: &str,
+        bps: u64,
+    ) -> Result<(), Error> {
+        let rj = self.get_rebuild_job(name)?;
+        info!(
+            "{}: limiting the rebuild of {} to {} bytes per second",
+            self.name, name, bps
+        );
+        rj.set_rate_limit(bps);
+        Ok(())
+    }
+
     /// Return the stats of a rebuild job
     pub async fn get_rebuild_stats(
         &mut self,
@@ -213,11 +421,44 @@ impl Nexus {
         rebuilding_children
     }
 
+    /// Cancels the rebuild jobs of all children, waiting at most
+    /// `rebuild_cancellation_timeout` for them to terminate. Jobs which have
+    /// terminated are removed as they hold a descriptor to the nexus.
+    pub async fn cancel_all_rebuilds(&mut self) {
+        let names = self
+            .children
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+
+        // the reactor does not run a tokio runtime so its timer cannot be used
+        let cancel = join_all(
+            names
+                .iter()
+                .map(|name| self.cancel_child_rebuild_jobs(name)),
+        );
+        let timeout = Delay::new(self.rebuild_cancellation_timeout);
+        if let Either::Right(_) = select(cancel, timeout).await {
+            error!(
+                "{}: timed out after {:?} waiting for the rebuild jobs to be cancelled",
+                self.name, self.rebuild_cancellation_timeout
+            );
+        }
+
+        for name in &names {
+            if let Ok(job) = RebuildJob::lookup(name) {
+                if job.state().done() {
+                    let _ = RebuildJob::remove(name);
+                }
+            }
+        }
+    }
+
     /// Start a rebuild for each of the children
     /// todo: how to proceed if no healthy child is found?
     pub async fn start_rebuild_jobs(&mut self, child_names: Vec<String>) {
         for name in child_names {
-            if let Err(e) = self.start_rebuild(&name).await {
+            if let Err(e) = self.start_rebuild(&name, false).await {
                 error!("Failed to start rebuild: {}", e.verbose());
             }
         }
@@ -256,38 +497,53 @@ impl Nexus {
         &mut self,
         job: &RebuildJob,
     ) -> Result<(), Error> {
+        // a finished or stopped rebuild is not resumed, unless it was
+        // stopped by the nexus going away, after which no child is written
+        let keep = job.state() == RebuildState::Stopped && self.shutdown;
         let recovering_child = self.get_child_by_name(&job.destination)?;
+        if job.state().done() && !keep {
+            recovering_child.clear_rebuild_checkpoint().await.ok();
+        }
 
         match job.state() {
             RebuildState::Completed => {
                 recovering_child.set_state(ChildState::Open);
-                NexusChild::save_state_change();
+                recovering_child.set_dirty(false);
+                NexusChild::save_state_change_async().await.ok();
                 info!(
                     "Child {} has been rebuilt successfully",
                     recovering_child.name
                 );
             }
             RebuildState::Stopped => {
+                // the child has to be rebuilt again, unless it is being
+                // removed
+                if recovering_child.rebuilding() {
+                    recovering_child
+                        .set_state(ChildState::Faulted(Reason::OutOfSync));
+                }
                 info!(
                     "Rebuild job for child {} of nexus {} stopped",
                     &job.destination, &self.name,
                 );
             }
-            RebuildState::Failed => {
+            RebuildState::Failed {
+                reason,
+            } => {
                 // rebuild has failed so we need to set the child as faulted
                 // allowing the control plane to replace it with another
-                if let Some(RebuildError::ReadIoError {
+                if let RebuildFailureReason::SourceIoError {
                     ..
-                }) = job.error
+                } = reason
                 {
                     // todo: retry rebuild using another child as source?
                 }
                 recovering_child.fault(Reason::RebuildFailed).await;
-                error!(
-                    "Rebuild job for child {} of nexus {} failed, error: {}",
+                warn!(
+                    "Rebuild job for child {} of nexus {} failed, reason: {}",
                     &job.destination,
                     &self.name,
-                    job.error_desc(),
+                    reason.verbose(),
                 );
             }
             _ => {
@@ -327,6 +583,26 @@ impl Nexus {
         complete_err.and(remove_err)
     }
 
+    /// Rebuild checkpoint callback of a rebuild job
+    async fn checkpoint_rebuild_async(nexus: String, job: String) {
+        if let Some(nexus) = nexus_lookup(&nexus) {
+            if let Err(e) = nexus.checkpoint_rebuild(&job).await {
+                warn!("Failed to checkpoint the rebuild: {}", e.verbose());
+            }
+        } else {
+            error!("Failed to find nexus {} for rebuild job {}", nexus, job);
+        }
+    }
+
+    /// Rebuild progress callback of a rebuild job
+    fn on_rebuild_progress(nexus: String, job: String, progress: f64) {
+        if let Some(nexus) = nexus_lookup(&nexus) {
+            if let Ok(child) = nexus.get_child_by_name(&job) {
+                child.set_rebuild_progress(progress);
+            }
+        }
+    }
+
     /// Rebuild updated callback when a rebuild job state updates
     async fn notify_rebuild(nexus: String, job: String) {
         info!("nexus {} received notify_rebuild from job {}", nexus, job);
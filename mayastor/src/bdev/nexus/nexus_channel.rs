@@ -1,10 +1,11 @@
 //!
 //! IO is driven by means of so called channels.
-use std::{ffi::c_void, ptr::NonNull};
+use std::{collections::HashMap, ffi::c_void, ptr::NonNull};
 
 use futures::channel::oneshot;
 
 use spdk_sys::{
+    spdk_bdev,
     spdk_for_each_channel,
     spdk_for_each_channel_continue,
     spdk_io_channel,
@@ -15,7 +16,11 @@ use spdk_sys::{
 };
 
 use crate::{
-    bdev::{nexus::nexus_child::ChildState, Nexus, Reason},
+    bdev::{
+        nexus::{nexus_bdev::ReadPreference, nexus_child::ChildState},
+        Nexus,
+        Reason,
+    },
     core::{BdevHandle, Mthread},
 };
 
@@ -32,6 +37,19 @@ pub(crate) struct NexusChannelInner {
     pub(crate) writers: Vec<BdevHandle>,
     pub(crate) readers: Vec<BdevHandle>,
     pub(crate) previous: usize,
+    /// read latency estimate, in ticks, of each reader; None until the
+    /// reader has completed a read
+    pub(crate) read_latency: Vec<Option<f64>>,
+    /// whether each reader is local to the nexus, see NexusChild::is_local()
+    pub(crate) read_local: Vec<bool>,
+    pub(crate) read_preference: ReadPreference,
+    /// granularity, in blocks, at which unmaps are sent to the writers. It
+    /// is a multiple of the discard granularity of each writer, such that
+    /// all of them discard the same range.
+    pub(crate) unmap_granularity: u64,
+    /// position of each child in the children of the nexus by its bdev, so
+    /// that child IO is accounted to its child without searching for it
+    pub(crate) child_index: HashMap<*mut spdk_bdev, usize>,
     device: *mut c_void,
 }
 
@@ -69,6 +87,17 @@ pub enum DrEvent {
     ChildRebuild,
     /// Child status information is being applied
     ChildStatusSync,
+    /// The read preference of the nexus has changed
+    ReadPreference,
+    /// The latency of the child stayed above the slow child threshold, it
+    /// is excluded from the read path
+    ChildSlow { child_name: String, latency_us: u64 },
+    /// The latency of a slow child has recovered, it serves reads again
+    ChildRecovered { child_name: String },
+    /// An offline child reconnected without missing any write
+    ChildReconnect,
+    /// A read only child was added, it serves reads right away
+    ChildReadOnly,
 }
 
 impl NexusChannelInner {
@@ -81,6 +110,23 @@ impl NexusChannelInner {
         if self.readers.is_empty() {
             None
         } else {
+            match self.read_preference {
+                ReadPreference::LatencyBased {
+                    ..
+                } => {
+                    if let Some(i) = self.lowest_read_latency() {
+                        return Some(i);
+                    }
+                }
+                ReadPreference::PreferLocal => {
+                    if let Some(i) = self.next_local_reader() {
+                        return Some(i);
+                    }
+                }
+                ReadPreference::Sticky => return Some(self.previous),
+                ReadPreference::RoundRobin => {}
+            }
+
             if self.previous < self.readers.len() - 1 {
                 self.previous += 1;
             } else {
@@ -90,6 +136,85 @@ impl NexusChannelInner {
         }
     }
 
+    /// rotate to the next reader which is local to the nexus, None if none
+    /// of the readers is local
+    fn next_local_reader(&mut self) -> Option<usize> {
+        let count = self.readers.len();
+        let next = (1 ..= count)
+            .map(|i| (self.previous + i) % count)
+            .find(|i| self.read_local[*i])?;
+        self.previous = next;
+        Some(next)
+    }
+
+    /// index of the reader with the lowest read latency estimate, None as
+    /// long as any of the readers has no estimate yet
+    fn lowest_read_latency(&self) -> Option<usize> {
+        let mut lowest: Option<(usize, f64)> = None;
+        for (i, latency) in self.read_latency.iter().enumerate() {
+            let latency = (*latency)?;
+            if lowest.map_or(true, |(_, l)| latency < l) {
+                lowest = Some((i, latency));
+            }
+        }
+        lowest.map(|(i, _)| i)
+    }
+
+    /// fold the latency of a completed read into the estimate of the reader
+    /// it was submitted to
+    pub(crate) fn read_completed(&mut self, reader: usize, ticks: u64) {
+        if let ReadPreference::LatencyBased {
+            ewma_alpha,
+        } = self.read_preference
+        {
+            if let Some(latency) = self.read_latency.get_mut(reader) {
+                let sample = ticks as f64;
+                *latency = Some(match *latency {
+                    Some(l) => ewma_alpha * sample + (1.0 - ewma_alpha) * l,
+                    None => sample,
+                });
+            }
+        }
+    }
+
+    /// least common multiple of the discard granularity, in blocks, of all
+    /// the writers
+    fn update_unmap_granularity(&mut self) {
+        fn gcd(a: u64, b: u64) -> u64 {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+
+        self.unmap_granularity = self.writers.iter().fold(1, |lcm, h| {
+            let bdev = h.get_bdev();
+            let granularity = (bdev.discard_granularity()
+                / u64::from(bdev.block_len()))
+            .max(1);
+            lcm / gcd(lcm, granularity) * granularity
+        });
+    }
+
+    /// index the children of the nexus by their bdev
+    fn update_child_index(&mut self, nexus: &Nexus) {
+        self.child_index = nexus
+            .children
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.bdev.as_ref().map(|b| (b.as_ptr(), i)))
+            .collect();
+    }
+
+    /// slow children are excluded from the read path, unless none but slow
+    /// children are left to read from
+    fn exclude_slow_readers(nexus: &Nexus) -> bool {
+        nexus.children.iter().any(|c| {
+            c.state() == ChildState::Open && !c.slow && !c.is_read_only()
+        })
+    }
+
     /// refreshing our channels simply means that we either have a child going
     /// online or offline. We don't know which child has gone, or was added, so
     /// we simply put back all the channels, and reopen the bdevs that are in
@@ -115,7 +240,10 @@ impl NexusChannelInner {
         // channel
         self.writers.clear();
         self.readers.clear();
+        self.read_local.clear();
         self.previous = 0;
+        self.read_preference = nexus.read_preference;
+        let exclude_slow = Self::exclude_slow_readers(nexus);
 
         // iterate over all our children which are in the open state
         nexus
@@ -124,8 +252,16 @@ impl NexusChannelInner {
             .filter(|c| c.state() == ChildState::Open)
             .for_each(|c| match (c.handle(), c.handle()) {
                 (Ok(w), Ok(r)) => {
+                    // read only children do not receive the writes of the
+                    // nexus, so they cannot serve its reads either
+                    if w.is_write_protected() {
+                        return;
+                    }
                     self.writers.push(w);
-                    self.readers.push(r);
+                    if !(exclude_slow && c.slow) {
+                        self.readers.push(r);
+                        self.read_local.push(c.is_local().unwrap_or(false));
+                    }
                 }
                 _ => {
                     c.set_state(ChildState::Faulted(Reason::CantOpen));
@@ -149,6 +285,10 @@ impl NexusChannelInner {
                 });
         }
 
+        self.read_latency = vec![None; self.readers.len()];
+        self.update_unmap_granularity();
+        self.update_child_index(nexus);
+
         trace!(
             "{}: New number of IO channels write:{} read:{} out of {} children",
             nexus.name,
@@ -175,8 +315,14 @@ impl NexusChannel {
             writers: Vec::new(),
             readers: Vec::new(),
             previous: 0,
+            read_latency: Vec::new(),
+            read_local: Vec::new(),
+            read_preference: nexus.read_preference,
+            unmap_granularity: 1,
+            child_index: HashMap::new(),
             device,
         });
+        let exclude_slow = NexusChannelInner::exclude_slow_readers(nexus);
 
         nexus
             .children
@@ -184,14 +330,23 @@ impl NexusChannel {
             .filter(|c| c.state() == ChildState::Open)
             .for_each(|c| match (c.handle(), c.handle()) {
                 (Ok(w), Ok(r)) => {
+                    if w.is_write_protected() {
+                        return;
+                    }
                     channels.writers.push(w);
-                    channels.readers.push(r);
+                    if !(exclude_slow && c.slow) {
+                        channels.readers.push(r);
+                        channels.read_local.push(c.is_local().unwrap_or(false));
+                    }
                 }
                 _ => {
                     c.set_state(ChildState::Faulted(Reason::CantOpen));
                     error!("Failed to get handle for {}, skipping bdev", c)
                 }
             });
+        channels.read_latency = vec![None; channels.readers.len()];
+        channels.update_unmap_granularity();
+        channels.update_child_index(nexus);
         ch.inner = Box::into_raw(channels);
         0
     }
@@ -203,6 +358,8 @@ impl NexusChannel {
         let inner = NexusChannel::from_raw(ctx).inner_mut();
         inner.writers.clear();
         inner.readers.clear();
+        inner.read_local.clear();
+        inner.child_index.clear();
     }
 
     /// function called when we receive a Dynamic Reconfigure event (DR)
@@ -216,7 +373,16 @@ impl NexusChannel {
             | DrEvent::ChildRemove
             | DrEvent::ChildFault
             | DrEvent::ChildRebuild
-            | DrEvent::ChildStatusSync => unsafe {
+            | DrEvent::ChildStatusSync
+            | DrEvent::ReadPreference
+            | DrEvent::ChildReconnect
+            | DrEvent::ChildReadOnly
+            | DrEvent::ChildSlow {
+                ..
+            }
+            | DrEvent::ChildRecovered {
+                ..
+            } => unsafe {
                 spdk_for_each_channel(
                     device,
                     Some(NexusChannel::refresh_io_channels),
@@ -1,13 +1,20 @@
 use std::{
     convert::TryFrom,
     fmt::{Display, Formatter},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use nix::errno::Errno;
 use serde::Serialize;
 use snafu::{ResultExt, Snafu};
 
+use spdk_sys::spdk_get_ticks_hz;
+
 use crate::{
     bdev::{
         nexus::{
@@ -19,11 +26,20 @@ use crate::{
         nexus_lookup,
         VerboseError,
     },
-    core::{Bdev, BdevHandle, CoreError, Descriptor, Reactor, Reactors},
+    core::{
+        Bdev,
+        BdevHandle,
+        CoreError,
+        Descriptor,
+        IoSizeHistogram,
+        IoStats,
+        NvmeTransportId,
+        Reactor,
+        Reactors,
+    },
     nexus_uri::{bdev_create, bdev_destroy, NexusBdevError},
     rebuild::{ClientOperations, RebuildJob},
 };
-use crossbeam::atomic::AtomicCell;
 use futures::{channel::mpsc, SinkExt, StreamExt};
 
 #[derive(Debug, Snafu)]
@@ -95,7 +111,7 @@ impl Display for Reason {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ChildState {
     /// child has not been opened, but we are in the process of opening it
     Init,
@@ -109,6 +125,8 @@ pub enum ChildState {
     Closed,
     /// the child is faulted
     Faulted(Reason),
+    /// the child is being rebuilt from the source child
+    Rebuilding { source: String, progress_pct: f64 },
 }
 
 impl Display for ChildState {
@@ -120,10 +138,31 @@ impl Display for ChildState {
             Self::Open => write!(f, "Child is open"),
             Self::Destroying => write!(f, "Child is being destroyed"),
             Self::Closed => write!(f, "Closed"),
+            Self::Rebuilding {
+                source,
+                progress_pct,
+            } => write!(f, "Rebuilding from {} ({:.0}%)", source, progress_pct),
         }
     }
 }
 
+/// Point in time status of a single child as reported to the control plane
+#[derive(Debug, Clone, Serialize)]
+pub struct ChildStatus {
+    /// name of the underlying bdev, empty if the child has no bdev
+    pub name: String,
+    /// URI used to create the child
+    pub uri: String,
+    /// current state of the child
+    pub state: ChildState,
+    /// the child is open and can serve IO
+    pub healthy: bool,
+    /// fraction of blocks rebuilt so far, if the child is being rebuilt
+    pub rebuild_progress: Option<f64>,
+    /// path to the child, if it is an NVMe device
+    pub transport_id: Option<NvmeTransportId>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct NexusChild {
     /// name of the parent this child belongs too
@@ -138,12 +177,49 @@ pub struct NexusChild {
     pub(crate) desc: Option<Arc<Descriptor>>,
     /// current state of the child
     #[serde(skip_serializing)]
-    pub state: AtomicCell<ChildState>,
+    pub state: Mutex<ChildState>,
     /// previous state of the child
     #[serde(skip_serializing)]
-    pub prev_state: AtomicCell<ChildState>,
+    pub prev_state: Mutex<ChildState>,
     #[serde(skip_serializing)]
     remove_channel: (mpsc::Sender<()>, mpsc::Receiver<()>),
+    /// IO completed by the child on behalf of the nexus
+    #[serde(skip_serializing)]
+    io_stats: IoStats,
+    /// sizes of the IOs submitted to the child on behalf of the nexus
+    #[serde(skip_serializing)]
+    io_sizes: IoSizeHistogram,
+    /// handles to the child are write protected
+    #[serde(skip_serializing)]
+    read_only: bool,
+    /// IOs submitted to the child by the nexus that have not completed yet
+    #[serde(skip_serializing)]
+    active_io: AtomicU64,
+    /// total latency, in ticks, of the IOs completed in the current sampling
+    /// window
+    #[serde(skip_serializing)]
+    latency_ticks: AtomicU64,
+    /// number of IOs completed in the current sampling window
+    #[serde(skip_serializing)]
+    latency_count: AtomicU64,
+    /// number of consecutive sampling windows with a latency above the
+    /// slow child threshold
+    #[serde(skip_serializing)]
+    pub(crate) slow_windows: u32,
+    /// the child is persistently slow and excluded from the read path
+    #[serde(skip_serializing)]
+    pub(crate) slow: bool,
+    /// number of consecutive health check probes the child failed
+    #[serde(skip_serializing)]
+    pub(crate) probe_failures: u32,
+    /// time the child was set offline together with the number of writes
+    /// the nexus had counted by then, see Nexus::data_write_submitted
+    #[serde(skip_serializing)]
+    pub(crate) offline_since: Option<(Instant, u64)>,
+    /// a write to the child has failed since it was set offline, or it was
+    /// not in sync when it was set offline, so it has to be rebuilt
+    #[serde(skip_serializing)]
+    dirty: AtomicBool,
 }
 
 impl Display for NexusChild {
@@ -166,8 +242,10 @@ impl Display for NexusChild {
 
 impl NexusChild {
     pub(crate) fn set_state(&self, state: ChildState) {
-        let prev_state = self.state.swap(state);
-        self.prev_state.store(prev_state);
+        let prev_state = std::mem::replace(
+            &mut *self.state.lock().unwrap(),
+            state.clone(),
+        );
         trace!(
             "{}: child {}: state change from {} to {}",
             self.parent,
@@ -175,11 +253,40 @@ impl NexusChild {
             prev_state.to_string(),
             state.to_string(),
         );
+        *self.prev_state.lock().unwrap() = prev_state;
+    }
+
+    /// Set the state to `new` if it is `current`, returning the state the
+    /// child was in
+    pub(crate) fn compare_and_set_state(
+        &self,
+        current: ChildState,
+        new: ChildState,
+    ) -> ChildState {
+        let mut state = self.state.lock().unwrap();
+        let prev_state = state.clone();
+        if prev_state == current {
+            *state = new;
+        }
+        prev_state
+    }
+
+    /// Update the progress of the rebuild of the child, if it is being
+    /// rebuilt
+    pub(crate) fn set_rebuild_progress(&self, progress: f64) {
+        if let ChildState::Rebuilding {
+            progress_pct,
+            ..
+        } = &mut *self.state.lock().unwrap()
+        {
+            *progress_pct = progress;
+        }
     }
 
     /// Open the child in RW mode and claim the device to be ours. If the child
     /// is already opened by someone else (i.e one of the targets) it will
-    /// error out.
+    /// error out. When read_only is set, all handles to the child are write
+    /// protected such that it can only serve as a source of data.
     ///
     /// only devices in the closed or Init state can be opened.
     ///
@@ -190,6 +297,7 @@ impl NexusChild {
     pub(crate) fn open(
         &mut self,
         parent_size: u64,
+        read_only: bool,
     ) -> Result<String, ChildError> {
         trace!("{}: Opening child device {}", self.parent, self.name);
 
@@ -245,6 +353,7 @@ impl NexusChild {
         )?);
 
         self.desc = Some(desc);
+        self.read_only = read_only;
 
         self.set_state(ChildState::Open);
 
@@ -272,7 +381,7 @@ impl NexusChild {
                 self.set_state(ChildState::Faulted(reason));
             }
         }
-        NexusChild::save_state_change();
+        NexusChild::save_state_change_async().await.ok();
     }
 
     /// Set the child as temporarily offline
@@ -285,7 +394,7 @@ impl NexusChild {
                 e.verbose()
             );
         }
-        NexusChild::save_state_change();
+        NexusChild::save_state_change_async().await.ok();
     }
 
     /// Online a previously offlined child.
@@ -297,7 +406,7 @@ impl NexusChild {
     ) -> Result<String, ChildError> {
         // Only online a child if it was previously set offline. Check for a
         // "Closed" state as that is what offlining a child will set it to.
-        match self.state.load() {
+        match self.state() {
             ChildState::Closed => {
                 // Re-create the bdev as it will have been previously destroyed.
                 let name =
@@ -309,29 +418,98 @@ impl NexusChild {
             _ => return Err(ChildError::ChildNotClosed {}),
         }
 
-        let result = self.open(parent_size);
+        let result = self.open(parent_size, self.read_only);
         self.set_state(ChildState::Faulted(Reason::OutOfSync));
-        NexusChild::save_state_change();
+        self.offline_since = None;
+        NexusChild::save_state_change_async().await.ok();
         result
     }
 
+    /// returns how long ago the child was set offline, if it is offline
+    pub fn time_since_offline(&self) -> Option<Duration> {
+        self.offline_since.map(|(since, _)| since.elapsed())
+    }
+
+    /// Reconnect a previously offlined child, which skips the rebuild when
+    /// the child cannot have missed any write: it was offline for at most
+    /// max_dirty_seconds, the nexus has not received a write in the meantime,
+    /// no write to the child failed and the label of the child is still
+    /// valid. Otherwise the child is set out-of-sync, as when it is onlined,
+    /// so that it will be rebuilt. When the child cannot be opened it stays
+    /// offline. Returns the state of the child.
+    pub async fn reconnect(
+        &mut self,
+        max_dirty_seconds: u64,
+    ) -> Result<ChildState, ChildError> {
+        let (since, writes) = match (self.state(), self.offline_since) {
+            (ChildState::Closed, Some(offline_since)) => offline_since,
+            _ => return Err(ChildError::ChildNotOffline {}),
+        };
+        let parent_size = match nexus_lookup(&self.parent) {
+            Some(nexus) => nexus.size,
+            None => return Err(ChildError::ChildInvalid {}),
+        };
+
+        // Re-create the bdev as it will have been previously destroyed.
+        let name = bdev_create(&self.name).await.context(ChildBdevCreate {
+            child: self.name.clone(),
+        })?;
+        self.bdev = Bdev::lookup_by_name(&name);
+
+        if let Err(error) = self.open(parent_size, self.read_only) {
+            // stay offline, so that reconnecting can be retried
+            self.close().await.ok();
+            self.set_state(ChildState::Closed);
+            return Err(error);
+        }
+        // keep the child out of the IO path until it is known to be in sync
+        self.set_state(ChildState::Faulted(Reason::OutOfSync));
+        self.offline_since = None;
+
+        let in_sync = since.elapsed() <= Duration::from_secs(max_dirty_seconds)
+            && !self.dirty.load(Ordering::SeqCst)
+            && self.probe_label().await.is_ok()
+            && nexus_lookup(&self.parent).map_or(false, |nexus| {
+                nexus.data_writes.load(Ordering::Relaxed) == writes
+            });
+        // either the child is in sync or it is going to be rebuilt
+        self.set_dirty(false);
+
+        if in_sync {
+            info!(
+                "{}: child {} reconnected without missing any write",
+                self.parent, self.name
+            );
+            self.set_state(ChildState::Open);
+        }
+        NexusChild::save_state_change_async().await.ok();
+        Ok(self.state())
+    }
+
     /// Save the state of the children to the config file
-    pub(crate) fn save_state_change() {
+    #[deprecated(note = "use save_state_change_async instead")]
+    pub fn save_state_change() {
         if ChildStatusConfig::save().is_err() {
             error!("Failed to save child status information");
         }
     }
 
+    /// Save the state of the children to the config file without blocking
+    /// the reactor
+    pub async fn save_state_change_async() -> Result<(), std::io::Error> {
+        ChildStatusConfig::save_async().await.map_err(|e| {
+            error!("Failed to save child status information: {}", e);
+            e
+        })
+    }
+
     /// returns the state of the child
     pub fn state(&self) -> ChildState {
-        self.state.load()
+        self.state.lock().unwrap().clone()
     }
 
     pub(crate) fn rebuilding(&self) -> bool {
-        match RebuildJob::lookup(&self.name) {
-            Ok(_) => self.state() == ChildState::Faulted(Reason::OutOfSync),
-            Err(_) => false,
-        }
+        matches!(self.state(), ChildState::Rebuilding { .. })
     }
 
     /// return a descriptor to this child
@@ -363,8 +541,8 @@ impl NexusChild {
         // Only wait for bdev removal if the child has been initialised.
         // An uninitialized child won't have an underlying bdev.
         // Also check previous state as remove event may not have occurred
-        if self.state.load() != ChildState::Init
-            && self.prev_state.load() != ChildState::Init
+        if self.state() != ChildState::Init
+            && *self.prev_state.lock().unwrap() != ChildState::Init
         {
             self.remove_channel.1.next().await;
         }
@@ -373,6 +551,17 @@ impl NexusChild {
         destroyed
     }
 
+    /// Close the descriptor of the child but keep its bdev, so that the data
+    /// on it survives and it can be opened again by another nexus. The child
+    /// must already have been taken out of the I/O path.
+    pub(crate) fn release(&mut self) {
+        info!("Releasing child {}", self.name);
+        if let Some(desc) = self.desc.take() {
+            desc.unclaim();
+        }
+        self.set_state(ChildState::Closed);
+    }
+
     /// Called in response to a SPDK_BDEV_EVENT_REMOVE event.
     /// All the necessary teardown should be performed here before the bdev is
     /// removed.
@@ -391,10 +580,14 @@ impl NexusChild {
             self.bdev = None;
             destroying = true;
 
-            state = self.prev_state.load();
+            state = self.prev_state.lock().unwrap().clone();
         }
         match state {
-            ChildState::Open | Faulted(Reason::OutOfSync) => {
+            ChildState::Open
+            | Faulted(Reason::OutOfSync)
+            | ChildState::Rebuilding {
+                ..
+            } => {
                 // Change the state of the child to ensure it is taken out of
                 // the I/O path when the nexus is reconfigured.
                 self.set_state(ChildState::Closed)
@@ -407,7 +600,7 @@ impl NexusChild {
                         "Restoring previous child state {}",
                         state.to_string()
                     );
-                    self.set_state(state);
+                    self.set_state(state.clone());
                 }
             }
         }
@@ -456,12 +649,89 @@ impl NexusChild {
             bdev,
             parent,
             desc: None,
-            state: AtomicCell::new(ChildState::Init),
-            prev_state: AtomicCell::new(ChildState::Init),
+            state: Mutex::new(ChildState::Init),
+            prev_state: Mutex::new(ChildState::Init),
             remove_channel: mpsc::channel(0),
+            io_stats: IoStats::default(),
+            io_sizes: IoSizeHistogram::default(),
+            read_only: false,
+            active_io: AtomicU64::new(0),
+            latency_ticks: AtomicU64::new(0),
+            latency_count: AtomicU64::new(0),
+            slow_windows: 0,
+            slow: false,
+            probe_failures: 0,
+            offline_since: None,
+            dirty: AtomicBool::new(false),
         }
     }
 
+    /// returns the number of IOs submitted to the child by the nexus that
+    /// have not completed yet
+    pub fn active_io(&self) -> u64 {
+        self.active_io.load(Ordering::Relaxed)
+    }
+
+    /// account an IO that has been submitted to the child
+    pub(crate) fn io_submitted(&self) {
+        self.active_io.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// account an IO of the child that has completed
+    pub(crate) fn io_completed(&self) {
+        self.active_io.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// record whether the child may have missed a write, which prevents it
+    /// from being reconnected without a rebuild, until it is rebuilt
+    pub(crate) fn set_dirty(&self, dirty: bool) {
+        self.dirty.store(dirty, Ordering::SeqCst);
+    }
+
+    /// add the latency of a completed IO to the current sampling window
+    pub(crate) fn io_latency(&self, ticks: u64) {
+        self.latency_ticks.fetch_add(ticks, Ordering::Relaxed);
+        self.latency_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// average latency, in microseconds, of the IOs completed in the current
+    /// sampling window, which is then restarted. None if no IO completed.
+    pub(crate) fn take_window_latency_us(&self) -> Option<u64> {
+        let count = self.latency_count.swap(0, Ordering::Relaxed);
+        let ticks = self.latency_ticks.swap(0, Ordering::Relaxed);
+        if count == 0 {
+            return None;
+        }
+        Some(ticks / count * 1_000_000 / unsafe { spdk_get_ticks_hz() })
+    }
+
+    /// returns true if the child is excluded from the read path because it
+    /// is persistently slow
+    pub fn is_slow(&self) -> bool {
+        self.slow
+    }
+
+    /// returns true when the child was opened read only
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// IO stats of the child since they were last reset
+    pub fn io_stats(&self) -> &IoStats {
+        &self.io_stats
+    }
+
+    /// histogram of the sizes of the reads and writes submitted to the child
+    /// since it was last reset
+    pub fn iops_histogram(&self) -> &IoSizeHistogram {
+        &self.io_sizes
+    }
+
+    /// clear the histogram of the sizes of the IOs submitted to the child
+    pub fn reset_iops_histogram(&mut self) {
+        self.io_sizes.reset();
+    }
+
     /// destroy the child bdev
     pub(crate) async fn destroy(&self) -> Result<(), NexusBdevError> {
         trace!("destroying child {:?}", self);
@@ -481,8 +751,14 @@ impl NexusChild {
     ///    label and metadata to be updated
     /// 2. It needs to be rebuilt
     fn is_accessible(&self) -> bool {
-        self.state() == ChildState::Open
-            || self.state() == ChildState::Faulted(Reason::OutOfSync)
+        match self.state() {
+            ChildState::Open
+            | ChildState::Faulted(Reason::OutOfSync)
+            | ChildState::Rebuilding {
+                ..
+            } => true,
+            _ => false,
+        }
     }
 
     /// return reference to child's bdev and a new BdevHandle
@@ -515,6 +791,26 @@ impl NexusChild {
             .unwrap_or_else(|| -1)
     }
 
+    /// Return the status of this child, including the rebuild progress if a
+    /// rebuild job is running for it
+    pub fn status(&self) -> ChildStatus {
+        let state = self.state();
+        ChildStatus {
+            name: self.bdev.as_ref().map(|b| b.name()).unwrap_or_default(),
+            uri: self.name.clone(),
+            healthy: state == ChildState::Open,
+            state,
+            rebuild_progress: self.get_rebuild_job().map(|j| {
+                let stats = j.stats();
+                stats.blocks_recovered as f64 / stats.blocks_total as f64
+            }),
+            transport_id: self
+                .bdev
+                .as_ref()
+                .and_then(|b| b.nvme_transport_id()),
+        }
+    }
+
     /// Determines if a child is local to the nexus (i.e. on the same node)
     pub fn is_local(&self) -> Option<bool> {
         match &self.bdev {
@@ -527,9 +823,23 @@ impl NexusChild {
         }
     }
 
+    /// Read the first block of the child to check that it still serves IO
+    pub(crate) async fn probe(&self) -> Result<(), CoreError> {
+        let hdl = self.handle()?;
+        let mut buf = hdl
+            .dma_malloc(hdl.get_bdev().block_len() as u64)
+            .map_err(|source| CoreError::DmaAllocation {
+                source,
+            })?;
+        hdl.read_at(0, &mut buf).await?;
+        Ok(())
+    }
+
     pub fn handle(&self) -> Result<BdevHandle, CoreError> {
         if let Some(desc) = self.desc.as_ref() {
-            BdevHandle::try_from(Arc::clone(desc))
+            let hdl = BdevHandle::try_from(Arc::clone(desc))?;
+            hdl.set_write_protect(self.read_only)?;
+            Ok(hdl)
         } else {
             error!("BUG: Child {} does not have valid descriptor", self.name);
             Err(CoreError::InvalidDescriptor {
@@ -1,30 +1,52 @@
 //! The purpose of this module is to persist child status information across
 //! Mayastor restarts.
 //!
-//! The load function should only be called when Mayastor is initialising. This
-//! will read in the child status information from the config file and use it to
-//! initialise the in-memory ChildStatusConfig structure. The apply function can
-//! then be called which will set the status of all children to match the
+//! The load_async function should only be called when Mayastor is initialising.
+//! This will read in the child status information from the config file, from a
+//! separate thread so the reactor is not blocked, and use it to initialise the
+//! in-memory ChildStatusConfig structure. The apply function can then be
+//! called which will set the status of all children to match the
 //! configuration.
 //!
 //! The save function should be called whenever a child's status is updated.
 //! This will update the configuration file but WILL NOT update the in-memory
 //! ChildStatusConfig structure as this is only required on startup and not
-//! during runtime.
+//! during runtime. From an async context save_async should be used instead,
+//! which writes the file from a separate thread so the reactor is not blocked.
 
-use crate::bdev::nexus::{
-    instances,
-    nexus_channel::DrEvent,
-    nexus_child::{ChildState, NexusChild},
+use crate::{
+    bdev::nexus::{
+        instances,
+        nexus_channel::DrEvent,
+        nexus_child::{ChildState, NexusChild, Reason},
+    },
+    core::Mthread,
+    utils::write_file_atomic,
 };
-use once_cell::sync::OnceCell;
+use futures::channel::oneshot;
+use once_cell::sync::{Lazy, OnceCell};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, fs::File, io::Write, sync::Once};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+        Once,
+    },
+};
 
 type ChildName = String;
 static mut CONFIG_FILE: Option<String> = None;
 static INIT: Once = Once::new();
 pub static STATUS_CONFIG: OnceCell<ChildStatusConfig> = OnceCell::new();
+/// sequence number of the next snapshot of the child status, snapshots are
+/// taken on the reactor so their order is the order of the status changes
+static NEXT_SNAPSHOT: AtomicU64 = AtomicU64::new(1);
+/// sequence number of the last snapshot written, which also serialises
+/// writers of the configuration file as they share the temporary file
+static WRITTEN_SNAPSHOT: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(0));
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ChildStatusConfig {
@@ -55,27 +77,45 @@ impl ChildStatusConfig {
     }
 
     /// Load the configuration file if it exists otherwise use default values.
-    pub(crate) fn load(
+    /// The file is read and parsed from a separate thread so the reactor is
+    /// not blocked.
+    pub async fn load_async(
         cfg_file_path: &Option<String>,
     ) -> Result<ChildStatusConfig, ()> {
-        if let Some(cfg_location) = cfg_file_path {
-            ChildStatusConfig::init_config_location(cfg_location);
-
-            debug!("Loading configuration file from {}", cfg_location);
-            let cfg = fs::read(cfg_location).unwrap_or_default();
-            if cfg.is_empty() {
-                Ok(ChildStatusConfig::default())
-            } else {
-                match serde_yaml::from_slice(&cfg) {
-                    Ok(config) => Ok(config),
-                    Err(e) => {
-                        error!("{}", e);
-                        Err(())
-                    }
+        let cfg_location = match cfg_file_path {
+            Some(cfg_location) => cfg_location.clone(),
+            None => return Ok(ChildStatusConfig::default()),
+        };
+        ChildStatusConfig::init_config_location(&cfg_location);
+
+        // the file may be loaded before the cores are known, so the thread
+        // cannot be unaffinitized from them
+        let (s, r) = oneshot::channel();
+        std::thread::spawn(move || {
+            s.send(ChildStatusConfig::read(&cfg_location)).ok();
+        });
+
+        r.await.unwrap_or_else(|_| {
+            error!("child status config reader terminated");
+            Err(())
+        })
+    }
+
+    /// Read and parse the configuration file, a missing or empty file
+    /// results in default values.
+    fn read(cfg_location: &str) -> Result<ChildStatusConfig, ()> {
+        debug!("Loading configuration file from {}", cfg_location);
+        let cfg = fs::read(cfg_location).unwrap_or_default();
+        if cfg.is_empty() {
+            Ok(ChildStatusConfig::default())
+        } else {
+            match serde_yaml::from_slice(&cfg) {
+                Ok(config) => Ok(config),
+                Err(e) => {
+                    error!("{}", e);
+                    Err(())
                 }
             }
-        } else {
-            Ok(ChildStatusConfig::default())
         }
     }
 
@@ -90,7 +130,14 @@ impl ChildStatusConfig {
                         "Apply state to child {}, reasons {:?}",
                         child.name, status
                     );
-                    child.set_state(*status);
+                    child.set_state(match status {
+                        // the rebuild does not survive a restart so the child
+                        // has to be rebuilt again
+                        ChildState::Rebuilding {
+                            ..
+                        } => ChildState::Faulted(Reason::OutOfSync),
+                        status => status.clone(),
+                    });
                 }
             });
             nexus.reconfigure(DrEvent::ChildStatusSync).await;
@@ -102,8 +149,46 @@ impl ChildStatusConfig {
         ChildStatusConfig::do_save(None)
     }
 
+    /// Save the status of all children to the configuration file without
+    /// blocking the reactor. The file is written from a separate thread.
+    pub(crate) async fn save_async() -> Result<(), std::io::Error> {
+        let (cfg_file, contents, seq) =
+            match ChildStatusConfig::serialize(None)? {
+                Some(cfg) => cfg,
+                None => return Ok(()),
+            };
+
+        let (s, r) = oneshot::channel();
+        Mthread::spawn_unaffinitized(move || {
+            s.send(ChildStatusConfig::write(&cfg_file, &contents, seq))
+                .ok();
+        });
+
+        r.await.unwrap_or_else(|_| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "child status config writer terminated",
+            ))
+        })
+    }
+
     /// Save the status of all children to the configuration file.
     fn do_save(cfg: Option<ChildStatusConfig>) -> Result<(), std::io::Error> {
+        match ChildStatusConfig::serialize(cfg)? {
+            Some((cfg_file, contents, seq)) => {
+                ChildStatusConfig::write(&cfg_file, &contents, seq)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Serialize the status of all children, merged into cfg if given.
+    /// Returns the location of the configuration file together with its new
+    /// contents and the sequence number of the snapshot, or None if no
+    /// configuration file was specified.
+    fn serialize(
+        cfg: Option<ChildStatusConfig>,
+    ) -> Result<Option<(String, String, u64)>, std::io::Error> {
         let cfg_file;
         unsafe {
             match CONFIG_FILE.clone() {
@@ -111,7 +196,7 @@ impl ChildStatusConfig {
                 None => {
                     // If a configuration file wasn't specified, nothing has to
                     // be done.
-                    return Ok(());
+                    return Ok(None);
                 }
             }
         }
@@ -131,10 +216,11 @@ impl ChildStatusConfig {
         });
 
         match serde_yaml::to_string(&status_cfg) {
-            Ok(s) => {
-                let mut cfg_file = File::create(cfg_file)?;
-                cfg_file.write_all(s.as_bytes())
-            }
+            Ok(s) => Ok(Some((
+                cfg_file,
+                s,
+                NEXT_SNAPSHOT.fetch_add(1, Ordering::Relaxed),
+            ))),
             Err(_) => Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "failed to serialize status config",
@@ -142,6 +228,25 @@ impl ChildStatusConfig {
         }
     }
 
+    /// Atomically replace the configuration file by writing the contents to a
+    /// temporary file first and renaming it afterwards. A snapshot older than
+    /// the one last written is skipped, as the writer threads may run in any
+    /// order, and the newer status must not be overwritten.
+    fn write(
+        cfg_file: &str,
+        contents: &str,
+        seq: u64,
+    ) -> Result<(), std::io::Error> {
+        let mut written = WRITTEN_SNAPSHOT.lock().unwrap();
+        if seq < *written {
+            debug!("Skipping child status snapshot {}", seq);
+            return Ok(());
+        }
+        write_file_atomic(Path::new(cfg_file), contents.as_bytes())?;
+        *written = seq;
+        Ok(())
+    }
+
     /// Add the child to the configuration and then save it.
     /// The configuration is updated on a status change and expects the child to
     /// already be listed as a nexus child. However, when a child is added,
@@ -14,6 +14,7 @@ use spdk_sys::{
     spdk_bdev_unmap_blocks,
     spdk_bdev_write_zeroes_blocks,
     spdk_bdev_writev_blocks,
+    spdk_get_ticks,
     spdk_io_channel,
 };
 
@@ -26,6 +27,7 @@ use crate::{
         nexus_lookup,
         ChildState,
         Nexus,
+        NexusChild,
         NexusStatus,
         Reason,
     },
@@ -95,6 +97,10 @@ pub struct NioCtx {
     status: IoStatus,
     channel: NonNull<spdk_io_channel>,
     core: u32,
+    /// index of the reader a read was submitted to
+    reader: usize,
+    /// tick count at the time the IO was submitted
+    submitted: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -114,6 +120,9 @@ pub(crate) fn nexus_submit_io(mut io: NexusBio) {
         IoType::Read => io.readv(),
         // these IOs are submitted to all the underlying children
         IoType::Write | IoType::WriteZeros | IoType::Reset | IoType::Unmap => {
+            if io.cmd() != IoType::Reset {
+                io.nexus_as_ref().data_write_submitted();
+            }
             io.submit_all()
         }
         IoType::Flush => {
@@ -150,6 +159,7 @@ impl NexusBio {
         ctx.status = IoStatus::Pending;
         ctx.in_flight = 0;
         ctx.num_ok = 0;
+        ctx.submitted = spdk_get_ticks();
         bio
     }
 
@@ -234,6 +244,14 @@ impl NexusBio {
         // decrement the counter of in flight IO
         self.ctx_as_mut().in_flight -= 1;
 
+        let ticks = unsafe { spdk_get_ticks() } - self.ctx().submitted;
+        if success && self.cmd() == IoType::Read {
+            self.inner_channel()
+                .read_completed(self.ctx().reader, ticks);
+        }
+
+        self.account_child_io(&child_io, success, ticks);
+
         // record the state of at least one of the IO's.
         if !success {
             self.ctx_as_mut().status = IoStatus::Failed;
@@ -299,6 +317,57 @@ impl NexusBio {
         child_io.free();
     }
 
+    /// lookup the child of the nexus which wraps the given bdev, through the
+    /// index of the children kept by the channel. The index is stale from
+    /// the moment a child is removed until the channel is refreshed, so the
+    /// child found is checked and the children searched when it differs.
+    fn child_for_bdev(&self, bdev: &Bdev) -> Option<&NexusChild> {
+        let wraps = |c: &&NexusChild| {
+            c.bdev
+                .as_ref()
+                .map_or(false, |b| b.as_ptr() == bdev.as_ptr())
+        };
+        let children = &self.nexus_as_ref().children;
+
+        self.inner_channel()
+            .child_index
+            .get(&bdev.as_ptr())
+            .and_then(|i| children.get(*i))
+            .filter(wraps)
+            .or_else(|| children.iter().find(wraps))
+    }
+
+    /// account a child IO that has been submitted to the given handle
+    fn child_io_submitted(&self, hdl: &BdevHandle) {
+        if let Some(child) = self.child_for_bdev(&hdl.get_bdev()) {
+            child.io_submitted();
+            child
+                .iops_histogram()
+                .record(self.io_type(), self.num_blocks());
+        }
+    }
+
+    /// account a completed child IO to the child it was submitted to, IO
+    /// that completed successfully is added to the IO stats and the latency
+    /// samples of the child
+    fn account_child_io(&self, child_io: &Bio, success: bool, ticks: u64) {
+        if let Some(child) = self.child_for_bdev(&child_io.bdev()) {
+            child.io_completed();
+            if !success && self.cmd() != IoType::Read {
+                // the child may be on its way out of the IO path, in which
+                // case it is not retired but still has to be rebuilt
+                child.set_dirty(true);
+            }
+            if success {
+                child.io_latency(ticks);
+                child.io_stats().record(
+                    child_io.io_type(),
+                    child_io.num_blocks() * child_io.block_len(),
+                );
+            }
+        }
+    }
+
     /// reference to the inner channels. The inner channel contains the specific
     /// per-core data structures.
     #[allow(clippy::mut_from_ref)]
@@ -340,10 +409,12 @@ impl NexusBio {
     /// submit read IO to some child
     fn readv(&mut self) -> Result<(), Errno> {
         if let Some(i) = self.inner_channel().child_select() {
+            self.ctx_as_mut().reader = i;
             let hdl = self.read_channel_at_index(i);
-            self.submit_read(hdl).map(|_| {
-                self.ctx_as_mut().in_flight += 1;
-            })
+            self.submit_read(hdl)?;
+            self.child_io_submitted(hdl);
+            self.ctx_as_mut().in_flight += 1;
+            Ok(())
         } else {
             self.fail();
             Err(Errno::ENODEV)
@@ -368,15 +439,39 @@ impl NexusBio {
         .to_result(Errno::from_i32)
     }
 
+    /// The range, as offset and number of blocks, to unmap on the children
+    /// aligned to the unmap granularity of the channel. The range is shrunk
+    /// rather than grown, as unmapping blocks outside of the request would
+    /// discard data. Returns None when the aligned range is empty.
+    fn unmap_range(&self) -> Option<(u64, u64)> {
+        let granularity = self.inner_channel().unmap_granularity;
+        let start = self.offset() + self.data_ent_offset();
+        let end = start + self.num_blocks();
+        let aligned_start =
+            (start + granularity - 1) / granularity * granularity;
+        let aligned_end = end / granularity * granularity;
+
+        if aligned_end <= aligned_start {
+            None
+        } else {
+            Some((aligned_start, aligned_end - aligned_start))
+        }
+    }
+
     #[inline(always)]
-    fn submit_unmap(&self, hdl: &BdevHandle) -> Result<(), Errno> {
+    fn submit_unmap(
+        &self,
+        hdl: &BdevHandle,
+        offset: u64,
+        num_blocks: u64,
+    ) -> Result<(), Errno> {
         let (desc, chan) = hdl.io_tuple();
         unsafe {
             spdk_bdev_unmap_blocks(
                 desc,
                 chan,
-                self.offset() + self.data_ent_offset(),
-                self.num_blocks(),
+                offset,
+                num_blocks,
                 Some(Self::child_completion),
                 self.as_ptr().cast(),
             )
@@ -419,6 +514,15 @@ impl NexusBio {
     /// avoid double frees. This function handles IO for a subset that must
     /// be submitted to all the underlying children.
     fn submit_all(&mut self) -> Result<(), Errno> {
+        // without writers the IO would complete without being written
+        // anywhere, only an unmap too small to align has nothing to do
+        if self.inner_channel().writers.is_empty()
+            && !(self.cmd() == IoType::Unmap && self.unmap_range().is_none())
+        {
+            self.fail();
+            return Err(Errno::ENODEV);
+        }
+
         let mut inflight = 0;
         let mut status = IoStatus::Pending;
 
@@ -426,20 +530,26 @@ impl NexusBio {
             IoType::Write => {
                 self.inner_channel().writers.iter().try_for_each(|h| {
                     self.submit_write(h).map(|_| {
+                        self.child_io_submitted(h);
                         inflight += 1;
                     })
                 })
             }
-            IoType::Unmap => {
-                self.inner_channel().writers.iter().try_for_each(|h| {
-                    self.submit_unmap(h).map(|_| {
-                        inflight += 1;
+            IoType::Unmap => match self.unmap_range() {
+                Some((offset, num_blocks)) => {
+                    self.inner_channel().writers.iter().try_for_each(|h| {
+                        self.submit_unmap(h, offset, num_blocks).map(|_| {
+                            self.child_io_submitted(h);
+                            inflight += 1;
+                        })
                     })
-                })
-            }
+                }
+                None => Ok(()),
+            },
             IoType::WriteZeros => {
                 self.inner_channel().writers.iter().try_for_each(|h| {
                     self.submit_write_zeroes(h).map(|_| {
+                        self.child_io_submitted(h);
                         inflight += 1;
                     })
                 })
@@ -447,6 +557,7 @@ impl NexusBio {
             IoType::Reset => {
                 self.inner_channel().writers.iter().try_for_each(|h| {
                     self.submit_reset(h).map(|_| {
+                        self.child_io_submitted(h);
                         inflight += 1;
                     })
                 })
@@ -470,8 +581,12 @@ impl NexusBio {
             self.ctx_as_mut().in_flight = inflight;
             self.ctx_as_mut().status = status;
         } else {
-            // if no IO was submitted at all, we can fail the IO now.
-            if matches!(result, Err(Errno::ENOMEM)) {
+            // if no IO was submitted at all, we can complete the IO now. This
+            // happens for unmaps smaller than the unmap granularity of the
+            // channel, otherwise the submission failed.
+            if result.is_ok() {
+                self.ok();
+            } else if matches!(result, Err(Errno::ENOMEM)) {
                 self.no_mem();
             } else {
                 // right now this could only be EINVAL, make sure to verify this
@@ -500,7 +615,7 @@ impl NexusBio {
         match nexus_lookup(&nexus) {
             Some(nexus) => {
                 if let Some(child) = nexus.child_lookup(&child.name()) {
-                    let current_state = child.state.compare_and_swap(
+                    let current_state = child.compare_and_set_state(
                         ChildState::Open,
                         ChildState::Faulted(Reason::IoError),
                     );
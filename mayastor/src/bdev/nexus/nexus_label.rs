@@ -55,22 +55,29 @@
 use bincode::{deserialize_from, serialize, serialize_into, Error};
 use crc::{crc32, Hasher32};
 use serde::{
-    de::{Deserialize, Deserializer, SeqAccess, Unexpected, Visitor},
+    de::{Deserialize, Deserializer, SeqAccess, Visitor},
     ser::{Serialize, SerializeTuple, Serializer},
 };
 use snafu::{ResultExt, Snafu};
 use std::{
-    cmp::min,
+    cmp::{max, min},
     convert::From,
     fmt::{self, Display},
     io::{Cursor, Seek, SeekFrom},
     str::FromStr,
+    time::Duration,
 };
+
+use futures_timer::Delay;
 use uuid::{self, parser, Uuid};
 
 use crate::{
-    bdev::nexus::{nexus_bdev::Nexus, nexus_child::NexusChild},
-    core::{CoreError, DmaBuf, DmaError},
+    bdev::nexus::{
+        nexus_bdev::{Error as NexusError, Nexus, ReadLabel},
+        nexus_child::{ChildState, NexusChild},
+    },
+    core::{BdevHandle, CoreError, DmaBuf, DmaError},
+    subsys::Config,
 };
 
 #[derive(Debug, Snafu)]
@@ -108,9 +115,27 @@ pub enum LabelError {
     DeviceTooSmall { blocks: u64 },
     #[snafu(display("The written label could not be read from disk, likely the child {} is a null device", name))]
     ReReadError { name: String },
+    #[snafu(display(
+        "Child {} has no valid GPT header to repair the label from",
+        name
+    ))]
+    NoLabelToRepair { name: String },
+    #[snafu(display(
+        "GPT partition name {} is {} UTF-16 code units long, the maximum is {}",
+        name,
+        len,
+        GptName::MAX_LEN
+    ))]
+    NameTooLong { name: String, len: usize },
+    #[snafu(display(
+        "Timed out waiting for {} active IOs to complete",
+        active_ios
+    ))]
+    IoFenceTimeout { active_ios: u64 },
 }
 
 #[derive(Debug, Snafu)]
+#[non_exhaustive]
 pub enum ProbeError {
     #[snafu(display("Deserialization error: {}", source))]
     DeserializeError { source: Error },
@@ -120,6 +145,12 @@ pub enum ProbeError {
     MbrSize {},
     #[snafu(display("Incorrect GPT header signature"))]
     GptSignature {},
+    #[snafu(display(
+        "Unsupported GPT header revision: expected {}, got {}",
+        GptHeader::describe_revision(*expected),
+        GptHeader::describe_revision(*actual)
+    ))]
+    GptRevision { actual: [u8; 4], expected: [u8; 4] },
     #[snafu(display(
         "Incorrect GPT header size: actual={} expected={}",
         actual_size,
@@ -194,6 +225,26 @@ impl Nexus {
     pub const METADATA_PARTITION_TYPE_ID: &'static str =
         "27663382-e5e6-11e9-81b4-ca5ca5ca5ca5";
     pub const METADATA_PARTITION_SIZE: u64 = 4 * 1024 * 1024;
+    /// Default boundary, in bytes, the "MayaData" partition is aligned to.
+    pub const DATA_PARTITION_ALIGNMENT: u64 = 1024 * 1024;
+
+    /// Return the first block of the "MayaData" partition. It follows the
+    /// metadata partition, rounded up to a multiple of the alignment. An
+    /// alignment smaller than the block size aligns to the block size.
+    pub(crate) fn data_start_lba(
+        lba_start: u64,
+        block_size: u32,
+        data_alignment_bytes: u64,
+    ) -> u64 {
+        let block_size = u64::from(block_size);
+        let alignment = max(data_alignment_bytes, block_size);
+        let metadata_size =
+            Aligned::get_blocks(Nexus::METADATA_PARTITION_SIZE, block_size);
+
+        let offset = (lba_start + metadata_size) * block_size;
+        let aligned = Aligned::get_blocks(offset, alignment) * alignment;
+        Aligned::get_blocks(aligned, block_size)
+    }
 
     /// Generate a new nexus label based on the nexus configuration.
     pub(crate) fn generate_label(
@@ -216,6 +267,7 @@ impl Nexus {
             &header,
             block_size,
             data_blocks,
+            Nexus::DATA_PARTITION_ALIGNMENT,
         )?;
 
         header.table_crc = GptEntry::checksum(&partitions, header.num_entries);
@@ -235,21 +287,24 @@ impl Nexus {
 
     /// Create partition table entries for the MayaMeta and
     /// MayaData partitions based on the nexus configuration.
+    /// The MayaData partition starts on a multiple of data_alignment_bytes.
     #[allow(clippy::vec_init_then_push)]
     fn create_maya_partitions(
         config: &LabelConfig,
         header: &GptHeader,
         block_size: u32,
         data_blocks: u64,
+        data_alignment_bytes: u64,
     ) -> Result<Vec<GptEntry>, LabelError> {
-        let metadata_size = Aligned::get_blocks(
-            Nexus::METADATA_PARTITION_SIZE,
-            u64::from(block_size),
+        let data = Nexus::data_start_lba(
+            header.lba_start,
+            block_size,
+            data_alignment_bytes,
         );
-        let data = header.lba_start + metadata_size;
 
         if data > header.lba_end {
             // Device is too small to accomodate Metadata partition
+            // and the alignment of the data partition
             return Err(LabelError::DeviceTooSmall {
                 blocks: header.lba_alt + 1,
             });
@@ -264,7 +319,7 @@ impl Nexus {
             ent_start: header.lba_start,
             ent_end: data - 1,
             ent_attr: 0,
-            ent_name: "MayaMeta".into(),
+            ent_name: GptName::new("MayaMeta")?,
         });
 
         partitions.push(GptEntry {
@@ -274,7 +329,7 @@ impl Nexus {
             ent_start: data,
             ent_end: min(data + data_blocks - 1, header.lba_end),
             ent_attr: 0,
-            ent_name: "MayaData".into(),
+            ent_name: GptName::new("MayaData")?,
         });
 
         Ok(partitions)
@@ -329,9 +384,44 @@ impl std::fmt::Display for GptGuid {
 }
 
 impl GptGuid {
-    pub(crate) fn new_random() -> Self {
+    pub fn new_random() -> Self {
         GptGuid::from(Uuid::new_v4())
     }
+
+    /// the GUID in the mixed endian byte order used by GPT, where the first
+    /// three fields are little endian, which is how it is stored on disk
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0 .. 4].copy_from_slice(&self.time_low.to_le_bytes());
+        bytes[4 .. 6].copy_from_slice(&self.time_mid.to_le_bytes());
+        bytes[6 .. 8].copy_from_slice(&self.time_high.to_le_bytes());
+        bytes[8 .. 16].copy_from_slice(&self.node);
+        bytes
+    }
+
+    /// create a GUID from bytes in the mixed endian byte order used by GPT
+    pub fn from_bytes(bytes: &[u8; 16]) -> GptGuid {
+        let mut node = [0; 8];
+        node.copy_from_slice(&bytes[8 .. 16]);
+        GptGuid {
+            time_low: u32::from_le_bytes([
+                bytes[0], bytes[1], bytes[2], bytes[3],
+            ]),
+            time_mid: u16::from_le_bytes([bytes[4], bytes[5]]),
+            time_high: u16::from_le_bytes([bytes[6], bytes[7]]),
+            node,
+        }
+    }
+
+    /// the GUID in the big endian byte order of RFC 4122
+    pub fn to_uuid_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0 .. 4].copy_from_slice(&self.time_low.to_be_bytes());
+        bytes[4 .. 6].copy_from_slice(&self.time_mid.to_be_bytes());
+        bytes[6 .. 8].copy_from_slice(&self.time_high.to_be_bytes());
+        bytes[8 .. 16].copy_from_slice(&self.node);
+        bytes
+    }
 }
 
 #[derive(Debug, Deserialize, PartialEq, Default, Serialize, Copy, Clone)]
@@ -367,6 +457,37 @@ pub struct GptHeader {
 
 impl GptHeader {
     pub const PARTITION_TABLE_SIZE: u64 = 128 * 128;
+    /// the primary partition table directly follows the primary header,
+    /// whatever the block size of the device
+    pub const PRIMARY_TABLE_LBA: u64 = 2;
+    /// the first usable block is aligned to 1MiB
+    pub const DATA_ALIGNMENT: u64 = 1 << 20;
+    /// GPT header revision written by us (version 1.0)
+    pub const HEADER_REVISION: [u8; 4] = [0x00, 0x00, 0x01, 0x00];
+    /// revisions we know about, along with a human readable description
+    pub const KNOWN_REVISIONS: &'static [([u8; 4], &'static str)] =
+        &[(GptHeader::HEADER_REVISION, "version 1.0")];
+
+    /// returns true if we are able to read a header with the given revision
+    pub fn is_supported_revision(revision: [u8; 4]) -> bool {
+        revision == GptHeader::HEADER_REVISION
+    }
+
+    /// describe a revision in human readable form, the revision is stored
+    /// as a little endian u32 with the major version in the upper 16 bits
+    pub fn describe_revision(revision: [u8; 4]) -> String {
+        match GptHeader::KNOWN_REVISIONS
+            .iter()
+            .find(|(known, _)| *known == revision)
+        {
+            Some((_, description)) => description.to_string(),
+            None => format!(
+                "version {}.{}",
+                u16::from_le_bytes([revision[2], revision[3]]),
+                u16::from_le_bytes([revision[0], revision[1]])
+            ),
+        }
+    }
 
     /// converts a slice into a gpt header and verifies the validity of the data
     pub fn from_slice(slice: &[u8]) -> Result<GptHeader, ProbeError> {
@@ -381,12 +502,17 @@ impl GptHeader {
             });
         }
 
-        if gpt.signature != [0x45, 0x46, 0x49, 0x20, 0x50, 0x41, 0x52, 0x54]
-            || gpt.revision != [0x00, 0x00, 0x01, 0x00]
-        {
+        if gpt.signature != [0x45, 0x46, 0x49, 0x20, 0x50, 0x41, 0x52, 0x54] {
             return Err(ProbeError::GptSignature {});
         }
 
+        if !GptHeader::is_supported_revision(gpt.revision) {
+            return Err(ProbeError::GptRevision {
+                actual: gpt.revision,
+                expected: GptHeader::HEADER_REVISION,
+            });
+        }
+
         let checksum = gpt.self_checksum;
 
         if gpt.checksum() != checksum {
@@ -403,6 +529,20 @@ impl GptHeader {
         self.self_checksum
     }
 
+    /// Return the first usable block for the given block size. The partition
+    /// table takes 32 blocks of 512 bytes but only 4 blocks of 4096 bytes,
+    /// either way it has to fit in front of the first usable block.
+    pub fn first_usable_lba(block_size: u32) -> u64 {
+        let block_size = u64::from(block_size);
+        let partition_size =
+            Aligned::get_blocks(GptHeader::PARTITION_TABLE_SIZE, block_size);
+
+        max(
+            Aligned::get_blocks(GptHeader::DATA_ALIGNMENT, block_size),
+            GptHeader::PRIMARY_TABLE_LBA + partition_size,
+        )
+    }
+
     // Create a new GPT header for a device with specified size
     pub fn new(block_size: u32, num_blocks: u64, guid: GptGuid) -> Self {
         let partition_size = Aligned::get_blocks(
@@ -410,11 +550,11 @@ impl GptHeader {
             u64::from(block_size),
         );
 
-        let start = u64::from((1 << 20) / block_size);
+        let start = GptHeader::first_usable_lba(block_size);
 
         GptHeader {
             signature: [0x45, 0x46, 0x49, 0x20, 0x50, 0x41, 0x52, 0x54],
-            revision: [0x00, 0x00, 0x01, 0x00],
+            revision: GptHeader::HEADER_REVISION,
             header_size: 92,
             self_checksum: 0,
             reserved: [0; 4],
@@ -423,7 +563,7 @@ impl GptHeader {
             lba_start: start,
             lba_end: num_blocks - partition_size - 2,
             guid,
-            lba_table: 2,
+            lba_table: GptHeader::PRIMARY_TABLE_LBA,
             num_entries: 2,
             entry_size: 128,
             table_crc: 0,
@@ -432,24 +572,26 @@ impl GptHeader {
 
     // Create a reference GPT header for a device of sufficient
     // size to have the requisite number of data blocks
-    pub fn reference(block_size: u32, data_blocks: u64, guid: GptGuid) -> Self {
+    pub fn reference(
+        block_size: u32,
+        data_blocks: u64,
+        guid: GptGuid,
+        data_alignment_bytes: u64,
+    ) -> Self {
         let partition_size = Aligned::get_blocks(
             GptHeader::PARTITION_TABLE_SIZE,
             u64::from(block_size),
         );
 
-        let metadata_size = Aligned::get_blocks(
-            Nexus::METADATA_PARTITION_SIZE,
-            u64::from(block_size),
-        );
-
-        let start = u64::from((1 << 20) / block_size);
-        let table = start + metadata_size + data_blocks;
+        let start = GptHeader::first_usable_lba(block_size);
+        let data =
+            Nexus::data_start_lba(start, block_size, data_alignment_bytes);
+        let table = data + data_blocks;
         let last = table + partition_size;
 
         GptHeader {
             signature: [0x45, 0x46, 0x49, 0x20, 0x50, 0x41, 0x52, 0x54],
-            revision: [0x00, 0x00, 0x01, 0x00],
+            revision: GptHeader::HEADER_REVISION,
             header_size: 92,
             self_checksum: 0,
             reserved: [0; 4],
@@ -458,7 +600,7 @@ impl GptHeader {
             lba_start: start,
             lba_end: table - 1,
             guid,
-            lba_table: 2,
+            lba_table: GptHeader::PRIMARY_TABLE_LBA,
             num_entries: 2,
             entry_size: 128,
             table_crc: 0,
@@ -478,7 +620,7 @@ impl GptHeader {
         let mut primary = *self;
         primary.lba_self = self.lba_alt;
         primary.lba_alt = self.lba_self;
-        primary.lba_table = self.lba_alt + 1;
+        primary.lba_table = GptHeader::PRIMARY_TABLE_LBA;
         primary.checksum();
         primary
     }
@@ -565,6 +707,32 @@ pub struct NexusLabel {
     pub secondary: GptHeader,
 }
 
+/// Summary of a child label, describing the partition geometry without
+/// exposing the GPT structures themselves.
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct LabelSummary {
+    /// GUID of the disk
+    pub disk_guid: String,
+    /// block size of the child in bytes
+    pub block_size: u64,
+    /// which halves of the label are valid on disk
+    pub status: NexusLabelStatus,
+    /// the partitions in the order of the partition table
+    pub partitions: Vec<PartitionSummary>,
+}
+
+/// Geometry of a single partition of a child label.
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct PartitionSummary {
+    pub name: String,
+    pub type_guid: String,
+    /// first block of the partition
+    pub start_lba: u64,
+    /// last block of the partition, inclusive
+    pub end_lba: u64,
+    pub size_bytes: u64,
+}
+
 impl NexusLabel {
     /// update label with new disk guid
     fn set_guid(&mut self, guid: GptGuid) {
@@ -624,6 +792,62 @@ impl NexusLabel {
         }
     }
 
+    /// returns true if the "MayaMeta" and "MayaData" partitions of the label
+    /// are laid out as in the reference partition table entries
+    pub fn is_maya_formatted(
+        &self,
+        reference: &[GptEntry],
+        block_size: u32,
+    ) -> bool {
+        self.maya_partition_errors(reference, block_size).is_empty()
+    }
+
+    /// returns the reasons why the "MayaMeta" and "MayaData" partitions of
+    /// the label are not laid out as in the reference partition table
+    /// entries, one for each violation
+    pub fn maya_partition_errors(
+        &self,
+        reference: &[GptEntry],
+        block_size: u32,
+    ) -> Vec<&'static str> {
+        let mut errors = Vec::new();
+
+        match self.get_partition("MayaMeta") {
+            Some(entry) => {
+                if entry.ent_type != reference[0].ent_type {
+                    errors.push("MayaMeta partition has the wrong type GUID");
+                }
+                if entry.ent_start != reference[0].ent_start {
+                    errors.push("MayaMeta partition has the wrong start LBA");
+                }
+                if entry.ent_end != reference[0].ent_end {
+                    errors.push("MayaMeta partition has the wrong end LBA");
+                }
+                if (entry.ent_end + 1).saturating_sub(entry.ent_start)
+                    * u64::from(block_size)
+                    < Nexus::METADATA_PARTITION_SIZE
+                {
+                    errors.push("MayaMeta partition is too small");
+                }
+            }
+            None => errors.push("MayaMeta partition is missing"),
+        }
+
+        match self.get_partition("MayaData") {
+            Some(entry) => {
+                if entry.ent_type != reference[1].ent_type {
+                    errors.push("MayaData partition has the wrong type GUID");
+                }
+                if entry.ent_start != reference[1].ent_start {
+                    errors.push("MayaData partition has the wrong start LBA");
+                }
+            }
+            None => errors.push("MayaData partition is missing"),
+        }
+
+        errors
+    }
+
     /// get current label config
     pub fn get_label_config(&self) -> Option<LabelConfig> {
         if let Some(meta) = self.get_partition("MayaMeta") {
@@ -696,6 +920,23 @@ pub struct GptName {
     pub name: String,
 }
 
+impl GptName {
+    /// maximum length of a name in UTF-16 code units
+    pub const MAX_LEN: usize = 36;
+
+    /// create a name, which must fit in the partition entry
+    pub fn new(name: &str) -> Result<GptName, LabelError> {
+        let len = name.encode_utf16().count();
+        if len > GptName::MAX_LEN {
+            return Err(LabelError::NameTooLong {
+                name: name.to_string(),
+                len,
+            });
+        }
+        Ok(GptName::from(name))
+    }
+}
+
 struct GpEntryNameVisitor;
 
 impl<'a> Deserialize<'a> for GptName {
@@ -703,7 +944,11 @@ impl<'a> Deserialize<'a> for GptName {
     where
         D: Deserializer<'a>,
     {
-        deserializer.deserialize_tuple_struct("GptName", 36, GpEntryNameVisitor)
+        deserializer.deserialize_tuple_struct(
+            "GptName",
+            GptName::MAX_LEN,
+            GpEntryNameVisitor,
+        )
     }
 }
 
@@ -716,8 +961,8 @@ impl Serialize for GptName {
         S: Serializer,
     {
         // we can't use serialize_type_struct here as we want exactly 72 bytes
-        let mut s = serializer.serialize_tuple(36)?;
-        let mut out: Vec<u16> = vec![0; 36];
+        let mut s = serializer.serialize_tuple(GptName::MAX_LEN)?;
+        let mut out: Vec<u16> = vec![0; GptName::MAX_LEN];
         for (i, o) in self.name.encode_utf16().zip(out.iter_mut()) {
             *o = i;
         }
@@ -737,23 +982,23 @@ impl<'a> Visitor<'a> for GpEntryNameVisitor {
     where
         A: SeqAccess<'a>,
     {
-        let mut out = Vec::new();
-        let mut end = false;
-        loop {
-            match seq.next_element()? {
-                Some(0) => {
-                    end = true;
-                }
-                Some(e) if !end => out.push(e),
-                _ => break,
-            }
+        // collect the whole region before decoding it, so that a surrogate
+        // pair occupying the last two code units is decoded as one character
+        let mut out: Vec<u16> = Vec::with_capacity(GptName::MAX_LEN);
+        while let Some(e) = seq.next_element()? {
+            out.push(e);
         }
 
-        if end {
-            Ok(GptName::from(String::from_utf16_lossy(&out)))
-        } else {
-            Err(serde::de::Error::invalid_value(Unexpected::Seq, &self))
+        if out.len() != GptName::MAX_LEN {
+            return Err(serde::de::Error::invalid_length(out.len(), &self));
         }
+
+        // the name ends at the first zero code unit, if there is one
+        if let Some(end) = out.iter().position(|&e| e == 0) {
+            out.truncate(end);
+        }
+
+        Ok(GptName::from(String::from_utf16_lossy(&out)))
     }
 }
 
@@ -859,6 +1104,134 @@ impl Default for Pmbr {
 }
 
 impl NexusLabel {
+    /// read and validate the label of the device the handle refers to
+    pub(crate) async fn probe(
+        handle: &BdevHandle,
+    ) -> Result<NexusLabel, LabelError> {
+        let bdev = handle.get_bdev();
+        let block_size = u64::from(bdev.block_len());
+        let num_blocks = bdev.num_blocks();
+
+        // Protective MBR
+        let mut buf = handle.dma_malloc(block_size).context(ReadAlloc {
+            name: String::from("header"),
+        })?;
+        handle.read_at(0, &mut buf).await.context(ReadError {
+            name: String::from("MBR"),
+        })?;
+        let mbr = NexusLabel::read_mbr(&buf).context(InvalidLabel {})?;
+
+        // GPT headers
+
+        let status: NexusLabelStatus;
+        let primary: GptHeader;
+        let secondary: GptHeader;
+        let active: &GptHeader;
+
+        // Get primary GPT header.
+        handle
+            .read_at(block_size, &mut buf)
+            .await
+            .context(ReadError {
+                name: String::from("primary GPT header"),
+            })?;
+        match NexusLabel::read_primary_header(&buf, block_size, num_blocks) {
+            Ok(header) => {
+                primary = header;
+                active = &primary;
+                // Get secondary GPT header.
+                let offset = (num_blocks - 1) * block_size;
+                handle.read_at(offset, &mut buf).await.context(ReadError {
+                    name: String::from("secondary GPT header"),
+                })?;
+                match NexusLabel::read_secondary_header(
+                    &buf, block_size, num_blocks,
+                ) {
+                    Ok(header) => {
+                        NexusLabel::consistency_check(&primary, &header)
+                            .context(InvalidLabel {})?;
+                        // All good - primary and secondary GTP headers
+                        // are valid and consistent with each other.
+                        secondary = header;
+                        status = NexusLabelStatus::Both;
+                    }
+                    Err(_) => {
+                        // Secondary GPT header is either not present
+                        // or invalid. Construct new secondary
+                        // GPT header from primary.
+                        secondary = primary.to_backup();
+                        status = NexusLabelStatus::Primary;
+                    }
+                }
+            }
+            Err(error) => {
+                // Primary GPT header is either not present or invalid.
+                // See if we can obtain a valid secondary GPT header.
+                let offset = (num_blocks - 1) * block_size;
+                handle.read_at(offset, &mut buf).await.context(ReadError {
+                    name: String::from("secondary GPT header"),
+                })?;
+                match NexusLabel::read_secondary_header(
+                    &buf, block_size, num_blocks,
+                ) {
+                    Ok(header) => {
+                        secondary = header;
+                        active = &secondary;
+                        // Construct new primary GPT header from secondary.
+                        primary = secondary.to_primary();
+                        status = NexusLabelStatus::Secondary;
+                    }
+                    Err(_) => {
+                        // Neither primary or secondary GPT header
+                        // is present or valid.
+                        return Err(LabelError::InvalidLabel {
+                            source: error,
+                        });
+                    }
+                }
+            }
+        }
+
+        // The disk size recorded in protective MBR
+        // must be consistent with GPT header.
+        if mbr.entries[0].num_sectors != 0xffff_ffff
+            && u64::from(mbr.entries[0].num_sectors) != primary.lba_alt
+        {
+            return Err(LabelError::InvalidLabel {
+                source: ProbeError::MbrSize {},
+            });
+        }
+
+        // Partition table
+        let blocks = Aligned::get_blocks(
+            u64::from(active.entry_size * active.num_entries),
+            block_size,
+        );
+        let mut buf =
+            handle.dma_malloc(blocks * block_size).context(ReadAlloc {
+                name: String::from("partition table"),
+            })?;
+        let offset = active.lba_table * block_size;
+        handle.read_at(offset, &mut buf).await.context(ReadError {
+            name: String::from("partition table"),
+        })?;
+        let mut partitions = NexusLabel::read_partitions(&buf, active)
+            .context(InvalidLabel {})?;
+
+        // There can be up to 128 partition entries stored on disk,
+        // even though most are not used. Retain only those entries
+        // that actually define partitions.
+        partitions.retain(|entry| entry.ent_start > 0 && entry.ent_end > 0);
+
+        Ok(NexusLabel {
+            status,
+            mbr,
+            primary,
+            partitions,
+            secondary,
+        })
+    }
+
     /// construct a Pmbr from raw data
     fn read_mbr(buf: &DmaBuf) -> Result<Pmbr, ProbeError> {
         Pmbr::from_slice(&buf.as_slice()[440 .. 512])
@@ -917,7 +1290,7 @@ impl NexusLabel {
         if primary.lba_end >= primary.lba_alt {
             return Err(ProbeError::LastUsableBlock {});
         }
-        if primary.lba_table != primary.lba_self + 1 {
+        if primary.lba_table != GptHeader::PRIMARY_TABLE_LBA {
             return Err(ProbeError::PartitionTableLocation {});
         }
         if (primary.num_entries * primary.entry_size) as u64
@@ -1025,168 +1398,47 @@ impl NexusLabel {
 }
 
 impl NexusChild {
-    /// read and validate this child's label
-    pub async fn probe_label(&self) -> Result<NexusLabel, LabelError> {
-        let handle = self.handle().context(HandleError {
-            name: self.name.clone(),
-        })?;
-
-        let bdev = handle.get_bdev();
-        let block_size = u64::from(bdev.block_len());
-        let num_blocks = bdev.num_blocks();
-
-        // Protective MBR
-        let mut buf = handle.dma_malloc(block_size).context(ReadAlloc {
-            name: String::from("header"),
-        })?;
-        handle.read_at(0, &mut buf).await.context(ReadError {
-            name: String::from("MBR"),
-        })?;
-        let mbr = NexusLabel::read_mbr(&buf).context(InvalidLabel {})?;
-
-        // GPT headers
-
-        let status: NexusLabelStatus;
-        let primary: GptHeader;
-        let secondary: GptHeader;
-        let active: &GptHeader;
-
-        // Get primary GPT header.
-        handle
-            .read_at(block_size, &mut buf)
-            .await
-            .context(ReadError {
-                name: String::from("primary GPT header"),
-            })?;
-        match NexusLabel::read_primary_header(&buf, block_size, num_blocks) {
-            Ok(header) => {
-                primary = header;
-                active = &primary;
-                // Get secondary GPT header.
-                let offset = (num_blocks - 1) * block_size;
-                handle.read_at(offset, &mut buf).await.context(ReadError {
-                    name: String::from("secondary GPT header"),
-                })?;
-                match NexusLabel::read_secondary_header(
-                    &buf, block_size, num_blocks,
-                ) {
-                    Ok(header) => {
-                        NexusLabel::consistency_check(&primary, &header)
-                            .context(InvalidLabel {})?;
-                        // All good - primary and secondary GTP headers
-                        // are valid and consistent with each other.
-                        secondary = header;
-                        status = NexusLabelStatus::Both;
-                    }
-                    Err(_) => {
-                        // Secondary GPT header is either not present
-                        // or invalid. Construct new secondary
-                        // GPT header from primary.
-                        secondary = primary.to_backup();
-                        status = NexusLabelStatus::Primary;
-                    }
-                }
-            }
-            Err(error) => {
-                // Primary GPT header is either not present or invalid.
-                // See if we can obtain a valid secondary GPT header.
-                let offset = (num_blocks - 1) * block_size;
-                handle.read_at(offset, &mut buf).await.context(ReadError {
-                    name: String::from("secondary GPT header"),
-                })?;
-                match NexusLabel::read_secondary_header(
-                    &buf, block_size, num_blocks,
-                ) {
-                    Ok(header) => {
-                        secondary = header;
-                        active = &secondary;
-                        // Construct new primary GPT header from secondary.
-                        primary = secondary.to_primary();
-                        status = NexusLabelStatus::Secondary;
-                    }
-                    Err(_) => {
-                        // Neither primary or secondary GPT header
-                        // is present or valid.
-                        return Err(LabelError::InvalidLabel {
-                            source: error,
-                        });
-                    }
-                }
-            }
-        }
-
-        // The disk size recorded in protective MBR
-        // must be consistent with GPT header.
-        if mbr.entries[0].num_sectors != 0xffff_ffff
-            && u64::from(mbr.entries[0].num_sectors) != primary.lba_alt
-        {
-            return Err(LabelError::InvalidLabel {
-                source: ProbeError::MbrSize {},
+    /// Get a handle to probe the label with. A remote child may briefly
+    /// have no handle while its controller reconnects, so retry a few times
+    /// before giving up. Only open children are retried, as the handle of
+    /// any other child does not come back by waiting.
+    async fn probe_handle(&self) -> Result<BdevHandle, LabelError> {
+        if self.state() != ChildState::Open {
+            return self.handle().context(HandleError {
+                name: self.name.clone(),
             });
         }
 
-        // Partition table
-        let blocks = Aligned::get_blocks(
-            u64::from(active.entry_size * active.num_entries),
-            block_size,
-        );
-        let mut buf =
-            handle.dma_malloc(blocks * block_size).context(ReadAlloc {
-                name: String::from("partition table"),
-            })?;
-        let offset = active.lba_table * block_size;
-        handle.read_at(offset, &mut buf).await.context(ReadError {
-            name: String::from("partition table"),
-        })?;
-        let mut partitions = NexusLabel::read_partitions(&buf, active)
-            .context(InvalidLabel {})?;
-
-        // There can be up to 128 partition entries stored on disk,
-        // even though most are not used. Retain only those entries
-        // that actually define partitions.
-        partitions.retain(|entry| entry.ent_start > 0 && entry.ent_end > 0);
-
-        Ok(NexusLabel {
-            status,
-            mbr,
-            primary,
-            partitions,
-            secondary,
-        })
-    }
-
-    // Check for the presence of "MayaMeta" and "MayaData" partitions
-    fn check_maya_partitions(
-        reference: &[GptEntry],
-        label: &NexusLabel,
-        block_size: u32,
-    ) -> bool {
-        match label.get_partition("MayaMeta") {
-            Some(entry) => {
-                if entry.ent_start != reference[0].ent_start {
-                    return false;
-                }
-                if entry.ent_end != reference[0].ent_end {
-                    return false;
+        let opts = &Config::get().nvme_bdev_opts;
+        let mut retries = 0;
+        loop {
+            match self.handle() {
+                Ok(handle) => return Ok(handle),
+                Err(error) if retries < opts.probe_max_retries => {
+                    retries += 1;
+                    debug!(
+                        "{}: failed to get handle to probe label ({}), retry {} of {}",
+                        self.name, error, retries, opts.probe_max_retries
+                    );
+                    Delay::new(Duration::from_millis(
+                        opts.probe_retry_delay_ms,
+                    ))
+                    .await;
                 }
-                if (entry.ent_end - entry.ent_start + 1) * u64::from(block_size)
-                    < Nexus::METADATA_PARTITION_SIZE
-                {
-                    return false;
+                Err(source) => {
+                    return Err(LabelError::HandleError {
+                        source,
+                        name: self.name.clone(),
+                    })
                 }
             }
-            None => {
-                return false;
-            }
-        }
-
-        if let Some(entry) = label.get_partition("MayaData") {
-            if entry.ent_start == reference[1].ent_start {
-                return true;
-            }
         }
+    }
 
-        false
+    /// read and validate this child's label
+    pub async fn probe_label(&self) -> Result<NexusLabel, LabelError> {
+        let handle = self.probe_handle().await?;
+        NexusLabel::probe(&handle).await
     }
 
     /// Create a new label on this child
@@ -1218,11 +1470,7 @@ impl NexusChild {
         total_blocks: u64,
     ) -> Result<NexusLabel, LabelError> {
         match self.probe_label().await {
-            Ok(mut label)
-                if NexusChild::check_maya_partitions(
-                    reference, &label, block_size,
-                ) =>
-            {
+            Ok(mut label) if label.is_maya_formatted(reference, block_size) => {
                 // Use existing label
                 if label.primary.guid != config.disk_guid {
                     info!("updating existing label for child {}: setting guid to {}", self.name, config.disk_guid);
@@ -1255,7 +1503,7 @@ impl NexusChild {
     ) -> Result<NexusLabel, LabelError> {
         let label = self.probe_label().await?;
 
-        if !NexusChild::check_maya_partitions(reference, &label, block_size) {
+        if !label.is_maya_formatted(reference, block_size) {
             return Err(LabelError::InvalidLabel {
                 source: ProbeError::IncorrectPartitions {},
             });
@@ -1273,9 +1521,7 @@ impl NexusChild {
 
 impl Nexus {
     /// Validate label on each child device
-    pub(crate) async fn validate_child_labels(
-        &mut self,
-    ) -> Result<(), LabelError> {
+    pub async fn validate_child_labels(&mut self) -> Result<(), LabelError> {
         let guid = GptGuid::from(Uuid::from_bytes(self.bdev.uuid().as_bytes()));
         let config = LabelConfig::new(guid);
 
@@ -1284,15 +1530,25 @@ impl Nexus {
         let mut min_blocks = nexus_blocks;
 
         // Generate "reference" partition table entries
-        let header = GptHeader::reference(block_size, nexus_blocks, guid);
+        let header = GptHeader::reference(
+            block_size,
+            nexus_blocks,
+            guid,
+            Nexus::DATA_PARTITION_ALIGNMENT,
+        );
         let reference = Nexus::create_maya_partitions(
             &config,
             &header,
             block_size,
             nexus_blocks,
+            Nexus::DATA_PARTITION_ALIGNMENT,
         )?;
         let data_offset = reference[1].ent_start;
 
+        // a label may be (re)written below, make sure it is ordered after any
+        // IO that is still active on the children
+        self.label_io_fence().await?;
+
         for child in self.children.iter_mut() {
             let handle = child.handle().context(HandleError {
                 name: child.name.clone(),
@@ -1317,6 +1573,40 @@ impl Nexus {
         Ok(())
     }
 
+    /// wait for the active IOs of the children to complete before a label is
+    /// written
+    async fn label_io_fence(&self) -> Result<(), LabelError> {
+        match self.io_fence().await {
+            Err(NexusError::IoFenceTimeout {
+                active_ios,
+                ..
+            }) => Err(LabelError::IoFenceTimeout {
+                active_ios,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Summarise the label of the first open child, the labels of all open
+    /// children of a nexus are kept in sync
+    pub async fn label_summary(
+        &self,
+    ) -> Result<(String, LabelSummary), NexusError> {
+        let child = self
+            .children
+            .iter()
+            .find(|c| c.state() == ChildState::Open)
+            .ok_or_else(|| NexusError::NoOpenChild {
+                name: self.name.clone(),
+            })?;
+
+        let summary = child.label_summary().await.context(ReadLabel {
+            name: self.name.clone(),
+        })?;
+
+        Ok((child.name.clone(), summary))
+    }
+
     // Get configuration from first valid label with specified disk guid
     async fn find_label_config(
         &self,
@@ -1361,15 +1651,22 @@ impl Nexus {
         let nexus_blocks = self.size / u64::from(block_size);
 
         // Generate "reference" partition table entries
-        let header = GptHeader::reference(block_size, nexus_blocks, guid);
+        let header = GptHeader::reference(
+            block_size,
+            nexus_blocks,
+            guid,
+            Nexus::DATA_PARTITION_ALIGNMENT,
+        );
         let reference = Nexus::create_maya_partitions(
             &config,
             &header,
             block_size,
             nexus_blocks,
+            Nexus::DATA_PARTITION_ALIGNMENT,
         )?;
 
-        for child in self.children.iter_mut() {
+        // the label of a read only child cannot be written
+        for child in self.children.iter_mut().filter(|c| !c.is_read_only()) {
             let handle = child.handle().context(HandleError {
                 name: child.name.clone(),
             })?;
@@ -1402,15 +1699,23 @@ impl Nexus {
         let mut min_blocks = nexus_blocks;
 
         // Generate "reference" partition table entries
-        let header = GptHeader::reference(block_size, nexus_blocks, guid);
+        let header = GptHeader::reference(
+            block_size,
+            nexus_blocks,
+            guid,
+            Nexus::DATA_PARTITION_ALIGNMENT,
+        );
         let reference = Nexus::create_maya_partitions(
             &config,
             &header,
             block_size,
             nexus_blocks,
+            Nexus::DATA_PARTITION_ALIGNMENT,
         )?;
         let data_offset = reference[1].ent_start;
 
+        self.label_io_fence().await?;
+
         for child in self.children.iter_mut() {
             let handle = child.handle().context(HandleError {
                 name: child.name.clone(),
@@ -1584,6 +1889,68 @@ impl NexusChild {
 
         Ok(())
     }
+
+    /// read this child's label and summarise its partition geometry
+    pub async fn label_summary(&self) -> Result<LabelSummary, LabelError> {
+        let label = self.probe_label().await?;
+        let handle = self.handle().context(HandleError {
+            name: self.name.clone(),
+        })?;
+        let block_size = u64::from(handle.get_bdev().block_len());
+
+        Ok(LabelSummary {
+            disk_guid: label.primary.guid.to_string(),
+            block_size,
+            status: label.status,
+            partitions: label
+                .partitions
+                .iter()
+                .map(|entry| PartitionSummary {
+                    name: entry.ent_name.name.clone(),
+                    type_guid: entry.ent_type.to_string(),
+                    start_lba: entry.ent_start,
+                    end_lba: entry.ent_end,
+                    size_bytes: (entry.ent_end + 1 - entry.ent_start)
+                        * block_size,
+                })
+                .collect(),
+        })
+    }
+
+    /// Repair a label that was probed with only one valid GPT header by
+    /// writing back the missing half, which is then read back to confirm
+    /// the repair. Unlike a full relabel, the valid half is left untouched.
+    /// Repairing a label with both halves valid does nothing.
+    pub async fn repair_label(
+        &self,
+        label: &NexusLabel,
+    ) -> Result<NexusLabelStatus, LabelError> {
+        match label.status {
+            NexusLabelStatus::Both => return Ok(NexusLabelStatus::Both),
+            NexusLabelStatus::Neither => {
+                return Err(LabelError::NoLabelToRepair {
+                    name: self.name.clone(),
+                })
+            }
+            NexusLabelStatus::Primary | NexusLabelStatus::Secondary => {}
+        }
+
+        info!(
+            "{}: repairing label of child {}, valid half: {:?}",
+            self.parent, self.name, label.status
+        );
+        self.write_label(label).await?;
+
+        // a null device accepts the write, but reads back zeroes
+        match self.probe_label().await {
+            Ok(label) if label.status == NexusLabelStatus::Both => {
+                Ok(NexusLabelStatus::Both)
+            }
+            _ => Err(LabelError::ReReadError {
+                name: self.name.clone(),
+            }),
+        }
+    }
 }
 
 pub trait Aligned {
@@ -4,14 +4,37 @@
 //! present.
 //!
 //! The data layout is as follows:
-//!  - The first block of the partition is not used.
-//!  - The second block contains a MetaDataHeader (currently 72 bytes) while the
+//!  - The first block of the partition holds the RebuildCheckpoint of the
+//!    rebuild that has this child as its destination, if any. The block is
+//!    all zeros when there is no checkpoint.
+//!  - The second block contains a MetaDataHeader (currently 76 bytes) while the
 //!    remainder of the block is padded with zeros.
 //!  - The "index" starts at the third block and contains a fixed number of
 //!    MetaDataIndexEntry entries, each of which contains the address of an
 //!    object that has been written to the partition.
 //!  - The first usable "data" block is the first block following the index
 //!    (whose size is aligned to the blocksize of the disk).
+//!  - The last blocks of the partition are reserved for a journal, which is
+//!    used to make updates of the header + index atomic. The journal is a
+//!    circular buffer of JOURNAL_SLOTS slots. Each slot consists of a single
+//!    block holding a MetaDataJournalCommit record, followed by the blocks
+//!    holding a MetaDataJournalRecord.
+//!
+//! Before the header + index is written, the current on-disk contents are
+//! saved to the journal as an "uncommitted" record. Once the write has
+//! completed the record is marked as committed. Any record that is found to
+//! be uncommitted when the nexus is opened (see check_or_initialise_index) is
+//! used to roll back the header + index to the state prior to the failed
+//! update.
+//!
+//! The format_version field of the header identifies the layout of the
+//! metadata. Metadata written in an older format is migrated to the current
+//! format (see NexusMetaData::migrate_to_current) when it is read, and is
+//! written back in the current format when the nexus is opened. The
+//! following versions exist:
+//!  - 0: 72 byte header without the format_version field, no journal.
+//!  - 1: the format_version field is added and space is reserved for the
+//!    journal.
 //!
 //! ## Example
 //! Sample code to create a new index and add a config object:
@@ -29,8 +52,10 @@
 //!    let metadata = child.get_metadata().await?;
 //!    let config = child.get_latest_config_object(&metadata).await?;
 use std::{
+    env,
     io::{Cursor, Seek, SeekFrom},
     str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
     time::{SystemTime, SystemTimeError, UNIX_EPOCH},
 };
 
@@ -48,7 +73,7 @@ use snafu::{ResultExt, Snafu};
 use crate::{
     bdev::nexus::{
         nexus_bdev::Nexus,
-        nexus_child::{ChildError, NexusChild},
+        nexus_child::{ChildError, ChildState, NexusChild},
         nexus_label::{Aligned, GptEntry, GptGuid, LabelError},
         nexus_metadata_content::NexusConfig,
     },
@@ -98,6 +123,8 @@ pub enum MetaDataError {
     IndexChecksum {},
     #[snafu(display("Incorrect MetaData configuration object checksum"))]
     ObjectChecksum {},
+    #[snafu(display("Incorrect rebuild checkpoint checksum"))]
+    CheckpointChecksum {},
     #[snafu(display("MetaData index is inconsistent"))]
     IndexInconsistent {},
     #[snafu(display("MetaData index ({}) out of range ({})", selected, used))]
@@ -114,8 +141,36 @@ pub enum MetaDataError {
     MissingPartition {},
     #[snafu(display("Error calculating timestamp: {}", source))]
     TimeStampError { source: SystemTimeError },
+    #[snafu(display("Incorrect MetaData journal record checksum"))]
+    JournalChecksum {},
+    #[snafu(display(
+        "Incorrect MetaData journal record size: actual={} expected={}",
+        actual_size,
+        expected_size
+    ))]
+    JournalRecordSize {
+        actual_size: u64,
+        expected_size: u64,
+    },
+    #[snafu(display(
+        "MetaData format version {} is too old, minimum supported version is {}",
+        found,
+        minimum
+    ))]
+    FormatTooOld { found: u32, minimum: u32 },
+    #[snafu(display(
+        "MetaData format version {} is newer than current version {}",
+        found,
+        current
+    ))]
+    FormatTooNew { found: u32, current: u32 },
+    #[snafu(display("MetaData format version {} has no journal", version))]
+    NoJournal { version: u32 },
 }
 
+/// Sequence number of the next journal record to be written.
+static JOURNAL_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Debug, Deserialize, PartialEq, Default, Serialize, Copy, Clone)]
 pub struct MetaDataHeader {
     /// Signature identifying this as a MetaDataHeader object
@@ -142,12 +197,100 @@ pub struct MetaDataHeader {
     pub data_start: u64,
     /// Offset of last usable data block relative to self_lba
     pub data_end: u64,
+    /// Version of the layout of the metadata
+    pub format_version: u32,
+}
+
+/// Header as written by format version 0, which lacks the format_version
+/// field. Apart from that, the layout is identical to MetaDataHeader.
+#[derive(Debug, Deserialize, PartialEq, Default, Serialize, Copy, Clone)]
+struct MetaDataHeaderV0 {
+    signature: [u8; 8],
+    header_size: u32,
+    self_checksum: u32,
+    generation: u64,
+    self_lba: u64,
+    index_start: u64,
+    used_entries: u32,
+    max_entries: u32,
+    entry_size: u32,
+    index_checksum: u32,
+    data_start: u64,
+    data_end: u64,
+}
+
+impl MetaDataHeaderV0 {
+    const METADATA_HEADER_SIZE: u32 = 72;
+
+    /// Convert a slice into a MetaDataHeaderV0 and validate
+    fn from_slice(slice: &[u8]) -> Result<MetaDataHeaderV0, MetaDataError> {
+        let mut header: MetaDataHeaderV0 =
+            deserialize_from(&mut Cursor::new(slice))
+                .context(DeserializeError {})?;
+
+        if header.header_size != MetaDataHeaderV0::METADATA_HEADER_SIZE {
+            return Err(MetaDataError::HeaderSize {
+                actual_size: header.header_size,
+                expected_size: MetaDataHeaderV0::METADATA_HEADER_SIZE,
+            });
+        }
+
+        if header.signature != [0x4d, 0x61, 0x79, 0x61, 0x44, 0x61, 0x74, 0x61]
+        {
+            return Err(MetaDataError::HeaderSignature {});
+        }
+
+        let checksum = header.self_checksum;
+        header.self_checksum = 0;
+
+        if crc32::checksum_ieee(&serialize(&header).unwrap()) != checksum {
+            return Err(MetaDataError::HeaderChecksum {});
+        }
+
+        Ok(header)
+    }
+
+    /// Convert to a version 1 header, reserving space for the journal at
+    /// the end of the partition.
+    fn migrate(&self) -> MetaDataHeader {
+        // Each journal slot holds a commit block, followed by the header +
+        // index (which occupy all blocks up to data_start) and the record
+        // overhead, which always fits in a single additional block.
+        let journal_blocks =
+            MetaDataHeader::JOURNAL_SLOTS * (self.data_start + 2);
+
+        let mut header = MetaDataHeader {
+            signature: self.signature,
+            header_size: MetaDataHeader::METADATA_HEADER_SIZE,
+            self_checksum: 0,
+            generation: self.generation,
+            self_lba: self.self_lba,
+            index_start: self.index_start,
+            used_entries: self.used_entries,
+            max_entries: self.max_entries,
+            entry_size: self.entry_size,
+            index_checksum: self.index_checksum,
+            data_start: self.data_start,
+            data_end: self.data_end - journal_blocks,
+            format_version: 1,
+        };
+        header.checksum();
+        header
+    }
 }
 
 impl MetaDataHeader {
-    pub const METADATA_HEADER_SIZE: u32 = 72;
+    pub const METADATA_HEADER_SIZE: u32 = 76;
+    /// Format version of the metadata written by this version of mayastor.
+    /// This must be incremented whenever the layout of the metadata changes.
+    pub const CURRENT_VERSION: u32 = 1;
+    /// Number of versions prior to CURRENT_VERSION that can be migrated
+    pub const MIGRATABLE_VERSIONS: u32 = 2;
     pub const MAX_INDEX_ENTRIES: u32 = 32;
     pub const INDEX_ENTRY_SIZE: u32 = 44;
+    pub const JOURNAL_SLOTS: u64 = 4;
+    /// Size of a serialized MetaDataJournalRecord excluding the payload
+    pub const JOURNAL_RECORD_OVERHEAD: u64 = 20;
 
     /// Convert a slice into a MetaDataHeader and validate
     pub fn from_slice(slice: &[u8]) -> Result<MetaDataHeader, MetaDataError> {
@@ -173,9 +316,55 @@ impl MetaDataHeader {
             return Err(MetaDataError::HeaderChecksum {});
         }
 
+        if header.format_version > MetaDataHeader::CURRENT_VERSION {
+            return Err(MetaDataError::FormatTooNew {
+                found: header.format_version,
+                current: MetaDataHeader::CURRENT_VERSION,
+            });
+        }
+
         Ok(header)
     }
 
+    /// Determine the format version of a raw header, without validating it
+    pub fn probe_version(slice: &[u8]) -> Result<u32, MetaDataError> {
+        let header: MetaDataHeaderV0 =
+            deserialize_from(&mut Cursor::new(slice))
+                .context(DeserializeError {})?;
+
+        if header.header_size == MetaDataHeaderV0::METADATA_HEADER_SIZE {
+            return Ok(0);
+        }
+
+        // format_version immediately follows the version 0 fields
+        deserialize_from(&mut Cursor::new(
+            &slice[MetaDataHeaderV0::METADATA_HEADER_SIZE as usize ..],
+        ))
+        .context(DeserializeError {})
+    }
+
+    /// Check that metadata with the given format version can be read
+    pub fn check_version(version: u32) -> Result<(), MetaDataError> {
+        let minimum = MetaDataHeader::CURRENT_VERSION
+            .saturating_sub(MetaDataHeader::MIGRATABLE_VERSIONS);
+
+        if version < minimum {
+            return Err(MetaDataError::FormatTooOld {
+                found: version,
+                minimum,
+            });
+        }
+
+        if version > MetaDataHeader::CURRENT_VERSION {
+            return Err(MetaDataError::FormatTooNew {
+                found: version,
+                current: MetaDataHeader::CURRENT_VERSION,
+            });
+        }
+
+        Ok(())
+    }
+
     /// Checksum the header with the checksum field itself set to 0
     pub fn checksum(&mut self) -> u32 {
         self.self_checksum = 0;
@@ -198,7 +387,7 @@ impl MetaDataHeader {
                 block_size,
             );
 
-        MetaDataHeader {
+        let mut header = MetaDataHeader {
             signature: [0x4d, 0x61, 0x79, 0x61, 0x44, 0x61, 0x74, 0x61],
             header_size: MetaDataHeader::METADATA_HEADER_SIZE,
             self_checksum: 0,
@@ -212,10 +401,103 @@ impl MetaDataHeader {
             index_checksum: 0,
             data_start: data_start as u64,
             data_end: partition.ent_end - partition.ent_start - 1,
+            format_version: MetaDataHeader::CURRENT_VERSION,
+        };
+
+        // reserve space for the journal at the end of the partition
+        header.data_end -= header.journal_blocks(u64::from(block_size));
+        header
+    }
+
+    /// Number of blocks occupied by the header + index (relative to self_lba)
+    pub fn index_blocks(&self, block_size: u64) -> u64 {
+        self.index_start
+            + Aligned::get_blocks(
+                u64::from(self.max_entries * self.entry_size),
+                block_size,
+            )
+    }
+
+    /// Number of blocks occupied by a single journal slot
+    pub fn journal_slot_blocks(&self, block_size: u64) -> u64 {
+        1 + Aligned::get_blocks(
+            MetaDataHeader::JOURNAL_RECORD_OVERHEAD
+                + self.index_blocks(block_size) * block_size,
+            block_size,
+        )
+    }
+
+    /// Number of blocks reserved for the journal
+    pub fn journal_blocks(&self, block_size: u64) -> u64 {
+        MetaDataHeader::JOURNAL_SLOTS * self.journal_slot_blocks(block_size)
+    }
+
+    /// Returns true if space is reserved for the journal in this layout
+    pub fn has_journal(&self) -> bool {
+        self.format_version >= 1
+    }
+
+    /// Location of the first journal block (relative to self_lba)
+    pub fn journal_start(&self) -> u64 {
+        self.data_end + 1
+    }
+
+    /// Location of the journal slot used for the given sequence number
+    /// (relative to self_lba)
+    pub fn journal_slot(&self, sequence: u64, block_size: u64) -> u64 {
+        self.journal_start()
+            + (sequence % MetaDataHeader::JOURNAL_SLOTS)
+                * self.journal_slot_blocks(block_size)
+    }
+}
+
+/// Journal record containing the contents of the header + index as they were
+/// on disk prior to an update.
+#[derive(Debug, Deserialize, PartialEq, Default, Serialize, Clone)]
+pub struct MetaDataJournalRecord {
+    /// Sequence number of this record
+    pub sequence: u64,
+    /// CRC-32 checksum of the payload
+    pub checksum: u32,
+    /// Raw contents of the header + index blocks
+    pub payload: Vec<u8>,
+}
+
+impl MetaDataJournalRecord {
+    /// Convert a slice into a MetaDataJournalRecord and validate
+    pub fn from_slice(
+        slice: &[u8],
+        expected_size: u64,
+    ) -> Result<MetaDataJournalRecord, MetaDataError> {
+        let record: MetaDataJournalRecord =
+            deserialize_from(&mut Cursor::new(slice))
+                .context(DeserializeError {})?;
+
+        if record.payload.len() as u64 != expected_size {
+            return Err(MetaDataError::JournalRecordSize {
+                actual_size: record.payload.len() as u64,
+                expected_size,
+            });
+        }
+
+        if crc32::checksum_ieee(&record.payload) != record.checksum {
+            return Err(MetaDataError::JournalChecksum {});
         }
+
+        Ok(record)
     }
 }
 
+/// Journal commit record, written once the update that the corresponding
+/// MetaDataJournalRecord protects has completed.
+#[derive(Debug, Deserialize, PartialEq, Default, Serialize, Copy, Clone)]
+pub struct MetaDataJournalCommit {
+    /// Sequence number of the record this applies to
+    pub sequence: u64,
+    /// Set once the update has completed
+    pub committed: bool,
+}
+
 #[derive(Debug, Deserialize, PartialEq, Default, Serialize, Clone)]
 pub struct MetaDataIndexEntry {
     /// Current object revision
@@ -258,6 +540,71 @@ impl MetaDataIndexEntry {
     }
 }
 
+/// Progress of a rebuild, persisted on the destination child so that the
+/// rebuild can be resumed rather than restarted should the nexus be
+/// recreated. The checkpoint is only valid for the same source and
+/// destination pair and for a data partition of the same size.
+#[derive(Debug, Deserialize, PartialEq, Default, Serialize, Clone)]
+pub struct RebuildCheckpoint {
+    /// URI of the child the data is copied from
+    pub source_uri: String,
+    /// URI of the child the data is copied to
+    pub destination_uri: String,
+    /// All blocks below this block have been rebuilt
+    pub next_lba: u64,
+    /// Number of blocks to rebuild in total
+    pub total_blocks: u64,
+    /// CRC-32 checksum of this record, calculated with this field set to 0
+    pub crc32: u32,
+}
+
+impl RebuildCheckpoint {
+    /// Create a new checkpoint and calculate its checksum
+    pub fn new(
+        source_uri: &str,
+        destination_uri: &str,
+        next_lba: u64,
+        total_blocks: u64,
+    ) -> RebuildCheckpoint {
+        let mut checkpoint = RebuildCheckpoint {
+            source_uri: source_uri.to_string(),
+            destination_uri: destination_uri.to_string(),
+            next_lba,
+            total_blocks,
+            crc32: 0,
+        };
+        checkpoint.crc32 = checkpoint.checksum();
+        checkpoint
+    }
+
+    /// Calculate the checksum of the payload
+    pub fn checksum(&self) -> u32 {
+        let mut payload = self.clone();
+        payload.crc32 = 0;
+        crc32::checksum_ieee(&serialize(&payload).unwrap())
+    }
+
+    /// Convert a slice into a RebuildCheckpoint and validate.
+    /// Returns None if the slice does not hold a checkpoint.
+    pub fn from_slice(
+        slice: &[u8],
+    ) -> Result<Option<RebuildCheckpoint>, MetaDataError> {
+        if slice.iter().all(|b| *b == 0) {
+            return Ok(None);
+        }
+
+        let checkpoint: RebuildCheckpoint =
+            deserialize_from(&mut Cursor::new(slice))
+                .context(DeserializeError {})?;
+
+        if checkpoint.checksum() != checkpoint.crc32 {
+            return Err(MetaDataError::CheckpointChecksum {});
+        }
+
+        Ok(Some(checkpoint))
+    }
+}
+
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct NexusMetaData {
     pub header: MetaDataHeader,
@@ -265,6 +612,50 @@ pub struct NexusMetaData {
 }
 
 impl NexusMetaData {
+    /// Return the format version of this metadata
+    pub fn get_version(&self) -> u32 {
+        self.header.format_version
+    }
+
+    /// Convert metadata written in an older format to the current format.
+    /// The data consists of the header, as written by the given version,
+    /// immediately followed by the index entries.
+    pub fn migrate_to_current(
+        old_version: u32,
+        data: &[u8],
+    ) -> Result<NexusMetaData, MetaDataError> {
+        MetaDataHeader::check_version(old_version)?;
+
+        let (header, header_size) = match old_version {
+            0 => (
+                MetaDataHeaderV0::from_slice(data)?.migrate(),
+                MetaDataHeaderV0::METADATA_HEADER_SIZE,
+            ),
+            _ => (
+                MetaDataHeader::from_slice(data)?,
+                MetaDataHeader::METADATA_HEADER_SIZE,
+            ),
+        };
+
+        let index = MetaDataIndexEntry::from_slice(
+            &data[header_size as usize ..],
+            header.used_entries,
+        )?;
+
+        if MetaDataIndexEntry::checksum(&index) != header.index_checksum {
+            return Err(MetaDataError::IndexChecksum {});
+        }
+
+        if index.iter().any(|entry| entry.data_end > header.data_end) {
+            return Err(MetaDataError::PartitionSizeExceeded {});
+        }
+
+        Ok(NexusMetaData {
+            header,
+            index,
+        })
+    }
+
     /// Construct a MetaDataHeader from raw data
     fn read_header(buf: &DmaBuf) -> Result<MetaDataHeader, MetaDataError> {
         MetaDataHeader::from_slice(buf.as_slice())
@@ -336,6 +727,12 @@ impl NexusChild {
             .context(ReadError {
                 name: String::from("header"),
             })?;
+
+        let version = MetaDataHeader::probe_version(buf.as_slice())?;
+        if version < MetaDataHeader::CURRENT_VERSION {
+            return self.probe_legacy_index(version, &buf).await;
+        }
+
         let header = NexusMetaData::read_header(&buf)?;
 
         //
@@ -368,6 +765,52 @@ impl NexusChild {
         })
     }
 
+    /// Read the Metadata header + index, as written by an older format
+    /// version, from disk and migrate it to the current format.
+    async fn probe_legacy_index(
+        &self,
+        version: u32,
+        buf: &DmaBuf,
+    ) -> Result<NexusMetaData, MetaDataError> {
+        MetaDataHeader::check_version(version)?;
+
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+
+        // version 0 is currently the only format predating the current one
+        let header = MetaDataHeaderV0::from_slice(buf.as_slice())?;
+        let size = (header.used_entries * header.entry_size) as u64;
+
+        let mut data =
+            buf.as_slice()[.. header.header_size as usize].to_vec();
+
+        if size > 0 {
+            let blocks = Aligned::get_blocks(size, block_size);
+            let mut buf =
+                hndl.dma_malloc(blocks * block_size).context(ReadAlloc {
+                    name: String::from("index"),
+                })?;
+            hndl.read_at(
+                (header.self_lba + header.index_start) * block_size,
+                &mut buf,
+            )
+            .await
+            .context(ReadError {
+                name: String::from("index"),
+            })?;
+            data.extend_from_slice(&buf.as_slice()[.. size as usize]);
+        }
+
+        info!(
+            "{}: migrating MetaData from format version {} to {}",
+            self.name,
+            version,
+            MetaDataHeader::CURRENT_VERSION
+        );
+
+        NexusMetaData::migrate_to_current(version, &data)
+    }
+
     /// Read the selected config object from disk.
     async fn probe_config_object(
         &self,
@@ -433,20 +876,17 @@ impl NexusChild {
         Ok(list)
     }
 
-    /// Write the Metadata header + index to disk
-    async fn write_index(
+    /// Write the Metadata header + index to disk.
+    /// Note that this does not go through the journal, use sync_metadata()
+    /// for that.
+    pub async fn write_index(
         &self,
         metadata: &NexusMetaData,
     ) -> Result<(), MetaDataError> {
         let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
         let block_size = bdev.block_len() as u64;
 
-        let blocks = metadata.header.index_start
-            + Aligned::get_blocks(
-                (metadata.header.max_entries * metadata.header.entry_size)
-                    as u64,
-                block_size,
-            );
+        let blocks = metadata.header.index_blocks(block_size);
         let mut buf = DmaBuf::new(blocks * block_size, bdev.alignment())
             .context(WriteAlloc {
                 name: String::from("index"),
@@ -679,6 +1119,223 @@ impl NexusChild {
         Ok(())
     }
 
+    /// Save the current on-disk contents of the header + index to the
+    /// journal, ahead of these being updated. The record remains uncommitted
+    /// until journal_commit() is called with the returned sequence number.
+    pub async fn journal_begin(
+        &self,
+        metadata: &NexusMetaData,
+    ) -> Result<u64, MetaDataError> {
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+        let header = &metadata.header;
+
+        let blocks = header.index_blocks(block_size);
+        let mut buf =
+            hndl.dma_malloc(blocks * block_size).context(ReadAlloc {
+                name: String::from("index"),
+            })?;
+        hndl.read_at(header.self_lba * block_size, &mut buf)
+            .await
+            .context(ReadError {
+                name: String::from("index"),
+            })?;
+
+        let sequence = JOURNAL_SEQUENCE.fetch_add(1, Ordering::SeqCst);
+
+        let record = MetaDataJournalRecord {
+            sequence,
+            checksum: crc32::checksum_ieee(buf.as_slice()),
+            payload: buf.as_slice().to_vec(),
+        };
+
+        let blocks = header.journal_slot_blocks(block_size);
+        let mut buf = DmaBuf::new(blocks * block_size, bdev.alignment())
+            .context(WriteAlloc {
+                name: String::from("journal"),
+            })?;
+        let mut writer = Cursor::new(buf.as_mut_slice());
+
+        // invalidate any previous commit record in this slot
+        serialize_into(
+            &mut writer,
+            &MetaDataJournalCommit {
+                sequence,
+                committed: false,
+            },
+        )
+        .context(SerializeError {})?;
+
+        writer.seek(SeekFrom::Start(block_size)).unwrap();
+        serialize_into(&mut writer, &record).context(SerializeError {})?;
+
+        hndl.write_at(
+            (header.self_lba + header.journal_slot(sequence, block_size))
+                * block_size,
+            &buf,
+        )
+        .await
+        .context(WriteError {
+            name: String::from("journal"),
+        })?;
+
+        Ok(sequence)
+    }
+
+    /// Mark the journal record with the given sequence number as committed.
+    pub async fn journal_commit(
+        &self,
+        metadata: &NexusMetaData,
+        sequence: u64,
+    ) -> Result<(), MetaDataError> {
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+        let header = &metadata.header;
+
+        let mut buf =
+            DmaBuf::new(block_size, bdev.alignment()).context(WriteAlloc {
+                name: String::from("journal"),
+            })?;
+        serialize_into(
+            &mut Cursor::new(buf.as_mut_slice()),
+            &MetaDataJournalCommit {
+                sequence,
+                committed: true,
+            },
+        )
+        .context(SerializeError {})?;
+
+        hndl.write_at(
+            (header.self_lba + header.journal_slot(sequence, block_size))
+                * block_size,
+            &buf,
+        )
+        .await
+        .context(WriteError {
+            name: String::from("journal"),
+        })?;
+
+        Ok(())
+    }
+
+    /// Discard all records in the journal.
+    async fn trim_journal(
+        &self,
+        header: &MetaDataHeader,
+    ) -> Result<(), MetaDataError> {
+        // the blocks of the journal hold data in layouts without one
+        if !header.has_journal() {
+            return Err(MetaDataError::NoJournal {
+                version: header.format_version,
+            });
+        }
+
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+
+        let buf = DmaBuf::new(
+            header.journal_blocks(block_size) * block_size,
+            bdev.alignment(),
+        )
+        .context(WriteAlloc {
+            name: String::from("journal"),
+        })?;
+
+        hndl.write_at(
+            (header.self_lba + header.journal_start()) * block_size,
+            &buf,
+        )
+        .await
+        .context(WriteError {
+            name: String::from("journal"),
+        })?;
+
+        Ok(())
+    }
+
+    /// Scan the journal for uncommitted records and use these to roll back
+    /// the header + index to the state prior to the interrupted update.
+    /// The journal is trimmed afterwards.
+    async fn replay_journal(
+        &self,
+        header: &MetaDataHeader,
+    ) -> Result<(), MetaDataError> {
+        if !header.has_journal() {
+            return Err(MetaDataError::NoJournal {
+                version: header.format_version,
+            });
+        }
+
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+        let slot_size = header.journal_slot_blocks(block_size) * block_size;
+        let payload_size = header.index_blocks(block_size) * block_size;
+
+        let mut buf = hndl
+            .dma_malloc(header.journal_blocks(block_size) * block_size)
+            .context(ReadAlloc {
+                name: String::from("journal"),
+            })?;
+        hndl.read_at(
+            (header.self_lba + header.journal_start()) * block_size,
+            &mut buf,
+        )
+        .await
+        .context(ReadError {
+            name: String::from("journal"),
+        })?;
+
+        let mut pending: Vec<MetaDataJournalRecord> = Vec::new();
+        let mut last = 0;
+
+        for slot in buf.as_slice().chunks(slot_size as usize) {
+            // an unreadable commit record is treated as uncommitted
+            let commit: MetaDataJournalCommit =
+                deserialize_from(&mut Cursor::new(slot)).unwrap_or_default();
+
+            // a record that fails validation was never completely written,
+            // in which case the update it protects was not started either
+            let record = match MetaDataJournalRecord::from_slice(
+                &slot[block_size as usize ..],
+                payload_size,
+            ) {
+                Ok(record) => record,
+                Err(_) => continue,
+            };
+
+            last = last.max(record.sequence);
+
+            if !commit.committed || commit.sequence != record.sequence {
+                pending.push(record);
+            }
+        }
+
+        // roll back the most recent update first
+        pending.sort_by(|a, b| b.sequence.cmp(&a.sequence));
+
+        for record in pending {
+            warn!(
+                "{}: rolling back uncommitted MetaData update {}",
+                self.name, record.sequence
+            );
+            let mut buf = DmaBuf::new(payload_size, bdev.alignment()).context(
+                WriteAlloc {
+                    name: String::from("index"),
+                },
+            )?;
+            buf.as_mut_slice().copy_from_slice(&record.payload);
+            hndl.write_at(header.self_lba * block_size, &buf)
+                .await
+                .context(WriteError {
+                    name: String::from("index"),
+                })?;
+        }
+
+        JOURNAL_SEQUENCE.fetch_max(last + 1, Ordering::SeqCst);
+
+        self.trim_journal(header).await
+    }
+
     /// Update checksums and write out MetaData header + index to disk.
     pub async fn sync_metadata(
         &mut self,
@@ -687,7 +1344,9 @@ impl NexusChild {
         metadata.header.index_checksum =
             MetaDataIndexEntry::checksum(&metadata.index);
         metadata.header.checksum();
-        self.write_index(&metadata).await
+        let sequence = self.journal_begin(&metadata).await?;
+        self.write_index(&metadata).await?;
+        self.journal_commit(&metadata, sequence).await
     }
 
     /// Create a new header + index on "MetaData" partition.
@@ -711,6 +1370,7 @@ impl NexusChild {
                     header: MetaDataHeader::new(bdev.block_len(), &partition),
                     index: Vec::new(),
                 };
+                self.trim_journal(&metadata.header).await?;
                 self.sync_metadata(&mut metadata).await?;
                 return Ok(metadata);
             }
@@ -739,6 +1399,177 @@ impl NexusChild {
         Err(MetaDataError::MissingPartition {})
     }
 
+    /// Return the location (LBA) of the "MetaData" partition.
+    async fn metadata_partition_start(&self) -> Result<u64, MetaDataError> {
+        if let Some(partition) = self
+            .probe_label()
+            .await
+            .context(ProbeLabelError {})?
+            .partitions
+            .get(0)
+        {
+            if partition.ent_type
+                == GptGuid::from_str(Nexus::METADATA_PARTITION_TYPE_ID).unwrap()
+                && partition.ent_name.name == "MayaMeta"
+            {
+                return Ok(partition.ent_start);
+            }
+        }
+
+        Err(MetaDataError::MissingPartition {})
+    }
+
+    /// Write the rebuild checkpoint to the first block of the "MetaData"
+    /// partition, replacing any previous checkpoint.
+    pub async fn write_rebuild_checkpoint(
+        &self,
+        checkpoint: &RebuildCheckpoint,
+    ) -> Result<(), MetaDataError> {
+        let start = self.metadata_partition_start().await?;
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+
+        let mut buf =
+            DmaBuf::new(block_size, bdev.alignment()).context(WriteAlloc {
+                name: String::from("rebuild checkpoint"),
+            })?;
+        serialize_into(&mut Cursor::new(buf.as_mut_slice()), checkpoint)
+            .context(SerializeError {})?;
+
+        hndl.write_at(start * block_size, &buf)
+            .await
+            .context(WriteError {
+                name: String::from("rebuild checkpoint"),
+            })?;
+
+        Ok(())
+    }
+
+    /// Read the rebuild checkpoint from the first block of the "MetaData"
+    /// partition. Returns None if no checkpoint is present.
+    pub async fn read_rebuild_checkpoint(
+        &self,
+    ) -> Result<Option<RebuildCheckpoint>, MetaDataError> {
+        let start = self.metadata_partition_start().await?;
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+
+        let mut buf =
+            DmaBuf::new(block_size, bdev.alignment()).context(ReadAlloc {
+                name: String::from("rebuild checkpoint"),
+            })?;
+        hndl.read_at(start * block_size, &mut buf).await.context(
+            ReadError {
+                name: String::from("rebuild checkpoint"),
+            },
+        )?;
+
+        RebuildCheckpoint::from_slice(buf.as_slice())
+    }
+
+    /// Discard the rebuild checkpoint, if any.
+    pub async fn clear_rebuild_checkpoint(&self) -> Result<(), MetaDataError> {
+        let start = self.metadata_partition_start().await?;
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+
+        let buf =
+            DmaBuf::new(block_size, bdev.alignment()).context(WriteAlloc {
+                name: String::from("rebuild checkpoint"),
+            })?;
+
+        hndl.write_at(start * block_size, &buf)
+            .await
+            .context(WriteError {
+                name: String::from("rebuild checkpoint"),
+            })?;
+
+        Ok(())
+    }
+
+    /// Determine the layout of the journal from the header stored on the
+    /// "MetaData" partition. Returns None when the header was written in a
+    /// format without a journal. When there is no valid header, the layout
+    /// of a new header is assumed, as that is the only header whose update
+    /// could have been interrupted before it was ever written.
+    async fn probe_journal_layout(
+        &self,
+        partition: &GptEntry,
+    ) -> Result<Option<MetaDataHeader>, MetaDataError> {
+        let (bdev, hndl) = self.get_dev().context(NexusChildError {})?;
+        let block_size = bdev.block_len() as u64;
+
+        let blocks = Aligned::get_blocks(
+            MetaDataHeader::METADATA_HEADER_SIZE as u64,
+            block_size,
+        );
+        let mut buf =
+            hndl.dma_malloc(blocks * block_size).context(ReadAlloc {
+                name: String::from("header"),
+            })?;
+        hndl.read_at((partition.ent_start + 1) * block_size, &mut buf)
+            .await
+            .context(ReadError {
+                name: String::from("header"),
+            })?;
+
+        if MetaDataHeaderV0::from_slice(buf.as_slice()).is_ok() {
+            return Ok(None);
+        }
+
+        Ok(Some(match MetaDataHeader::from_slice(buf.as_slice()) {
+            Ok(header) => header,
+            Err(_) => MetaDataHeader::new(bdev.block_len(), partition),
+        }))
+    }
+
+    /// Roll back any interrupted update and retrieve header + index from
+    /// "MetaData" partition. A new header + index is created if none is
+    /// present, and a header + index written in an older format is written
+    /// back in the current format. This is called when the nexus is opened.
+    pub async fn check_or_initialise_index(
+        &mut self,
+    ) -> Result<NexusMetaData, MetaDataError> {
+        if let Some(partition) = self
+            .probe_label()
+            .await
+            .context(ProbeLabelError {})?
+            .partitions
+            .get(0)
+        {
+            if partition.ent_type
+                == GptGuid::from_str(Nexus::METADATA_PARTITION_TYPE_ID).unwrap()
+                && partition.ent_name.name == "MayaMeta"
+            {
+                let layout = self.probe_journal_layout(partition).await?;
+                if let Some(header) = &layout {
+                    self.replay_journal(header).await?;
+                }
+
+                return match self.probe_index(partition.ent_start).await {
+                    Err(MetaDataError::HeaderSize {
+                        ..
+                    })
+                    | Err(MetaDataError::HeaderSignature {}) => {
+                        self.create_metadata().await
+                    }
+                    Ok(mut metadata) => {
+                        if layout.is_none() {
+                            // the blocks now reserved for the journal were
+                            // part of the data space of the old format
+                            self.trim_journal(&metadata.header).await?;
+                            self.sync_metadata(&mut metadata).await?;
+                        }
+                        Ok(metadata)
+                    }
+                    result => result,
+                };
+            }
+        }
+
+        Err(MetaDataError::MissingPartition {})
+    }
+
     /// Retrieve selected config object from "MetaData" partition.
     /// The "selected" parameter identifies the appropriate entry in the index
     /// array.
@@ -815,6 +1646,31 @@ impl NexusChild {
     }
 }
 
+impl Nexus {
+    /// Check the metadata of the children that are open, see
+    /// check_or_initialise_index. Failures are only logged as the nexus does
+    /// not need the metadata to serve IO.
+    pub(crate) async fn check_child_metadata(&mut self) {
+        if env::var("NEXUS_DONT_READ_LABELS").is_ok() {
+            return;
+        }
+
+        // the metadata of a read only child cannot be updated
+        for child in self
+            .children
+            .iter_mut()
+            .filter(|c| c.state() == ChildState::Open && !c.is_read_only())
+        {
+            if let Err(error) = child.check_or_initialise_index().await {
+                warn!(
+                    "{}: failed to check the MetaData of child {}: {}",
+                    self.name, child.name, error
+                );
+            }
+        }
+    }
+}
+
 impl NexusConfig {
     /// Convert a slice into a NexusConfig object
     pub fn from_slice(buf: &[u8]) -> Result<NexusConfig, Error> {
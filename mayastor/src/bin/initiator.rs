@@ -42,6 +42,17 @@ static INIT_ARRAY: [unsafe extern "C" fn(); 1] = [run_static_initializers];
 /// errors, which will be printed to stderr.
 struct Error {
     msg: String,
+    /// exit code of the utility
+    rc: i32,
+}
+impl Error {
+    /// Error in the arguments given by the user.
+    fn invalid_argument(msg: String) -> Self {
+        Self {
+            msg,
+            rc: 1,
+        }
+    }
 }
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -52,6 +63,7 @@ impl From<CoreError> for Error {
     fn from(err: CoreError) -> Self {
         Self {
             msg: print_error_chain(&err),
+            rc: -1,
         }
     }
 }
@@ -59,6 +71,7 @@ impl From<DmaError> for Error {
     fn from(err: DmaError) -> Self {
         Self {
             msg: print_error_chain(&err),
+            rc: -1,
         }
     }
 }
@@ -66,6 +79,7 @@ impl From<NexusBdevError> for Error {
     fn from(err: NexusBdevError) -> Self {
         Self {
             msg: print_error_chain(&err),
+            rc: -1,
         }
     }
 }
@@ -73,6 +87,7 @@ impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
         Self {
             msg: err.to_string(),
+            rc: -1,
         }
     }
 }
@@ -90,7 +105,7 @@ async fn create_bdev(uri: &str) -> Result<Bdev> {
 /// Read block of data from bdev at given offset to a file.
 async fn read(uri: &str, offset: u64, file: &str) -> Result<()> {
     let bdev = create_bdev(uri).await?;
-    let desc = Bdev::open(&bdev, false).unwrap().into_handle().unwrap();
+    let desc = bdev.open_read_only().unwrap().into_handle().unwrap();
     let mut buf = desc
         .dma_malloc(desc.get_bdev().block_len() as usize as u64)
         .unwrap();
@@ -104,7 +119,7 @@ async fn read(uri: &str, offset: u64, file: &str) -> Result<()> {
 async fn write(uri: &str, offset: u64, file: &str) -> Result<()> {
     let bdev = create_bdev(uri).await?;
     let bytes = fs::read(file)?;
-    let desc = Bdev::open(&bdev, true).unwrap().into_handle().unwrap();
+    let desc = bdev.open_read_write().unwrap().into_handle().unwrap();
     let mut buf = desc.dma_malloc(desc.get_bdev().block_len() as u64).unwrap();
     let mut n = buf.as_mut_slice().write(&bytes[..]).unwrap();
     if n < buf.len() as usize {
@@ -115,10 +130,43 @@ async fn write(uri: &str, offset: u64, file: &str) -> Result<()> {
     Ok(())
 }
 
+/// Unmap the given range of the bdev, and optionally check that it reads
+/// back as zeroes afterwards.
+async fn trim(uri: &str, offset: u64, length: u64, verify: bool) -> Result<()> {
+    let bdev = create_bdev(uri).await?;
+    let h = bdev.open_read_write().unwrap().into_handle().unwrap();
+    let block_len = u64::from(h.get_bdev().block_len());
+    if offset % block_len != 0 || length % block_len != 0 {
+        return Err(Error::invalid_argument(format!(
+            "Offset {} and length {} must be multiples of the block size {}",
+            offset, length, block_len
+        )));
+    }
+    let num_blocks = length / block_len;
+    h.unmap_blocks(offset / block_len, num_blocks).await?;
+    info!("{} blocks unmapped", num_blocks);
+
+    if verify {
+        let mut buf = h.dma_malloc(block_len).unwrap();
+        for blk in 0 .. num_blocks {
+            let off = offset + blk * block_len;
+            h.read_at(off, &mut buf).await?;
+            if buf.as_slice().iter().any(|b| *b != 0) {
+                return Err(Error {
+                    msg: format!("Trimmed block at offset {} is not zero", off),
+                    rc: -1,
+                });
+            }
+        }
+        info!("{} bytes verified to be zero", length);
+    }
+    Ok(())
+}
+
 /// NVMe Admin. Only works with read commands without a buffer requirement.
 async fn nvme_admin(uri: &str, opcode: u8) -> Result<()> {
     let bdev = create_bdev(uri).await?;
-    let h = Bdev::open(&bdev, true).unwrap().into_handle().unwrap();
+    let h = bdev.open_read_write().unwrap().into_handle().unwrap();
     h.nvme_admin_custom(opcode).await?;
     Ok(())
 }
@@ -126,7 +174,7 @@ async fn nvme_admin(uri: &str, opcode: u8) -> Result<()> {
 /// NVMe Admin identify controller, write output to a file.
 async fn identify_ctrlr(uri: &str, file: &str) -> Result<()> {
     let bdev = create_bdev(uri).await?;
-    let h = Bdev::open(&bdev, true).unwrap().into_handle().unwrap();
+    let h = bdev.open_read_write().unwrap().into_handle().unwrap();
     let mut buf = h.dma_malloc(4096).unwrap();
     h.nvme_identify_ctrlr(&mut buf).await?;
     fs::write(file, buf.as_slice())?;
@@ -136,12 +184,33 @@ async fn identify_ctrlr(uri: &str, file: &str) -> Result<()> {
 /// Create a snapshot.
 async fn create_snapshot(uri: &str) -> Result<()> {
     let bdev = create_bdev(uri).await?;
-    let h = Bdev::open(&bdev, true).unwrap().into_handle().unwrap();
+    let h = bdev.open_read_write().unwrap().into_handle().unwrap();
     let t = h.create_snapshot().await?;
     info!("snapshot taken at {}", t);
     Ok(())
 }
 
+/// Copy the source ranges to the destination LBA on the target itself.
+async fn server_copy(
+    uri: &str,
+    dest_lba: u64,
+    ranges: &[(u64, u32)],
+) -> Result<()> {
+    let bdev = create_bdev(uri).await?;
+    let h = bdev.open_read_write().unwrap().into_handle().unwrap();
+    let n = h.nvme_copy(dest_lba, ranges).await?;
+    info!("{} blocks copied", n);
+    Ok(())
+}
+
+/// Parse a source range given as "lba,len".
+fn parse_range(range: &str) -> Option<(u64, u32)> {
+    let mut parts = range.splitn(2, ',');
+    let lba = parts.next()?.trim().parse().ok()?;
+    let len = parts.next()?.trim().parse().ok()?;
+    Some((lba, len))
+}
+
 /// Connect to the target.
 async fn connect(uri: &str) -> Result<()> {
     let _bdev = create_bdev(uri).await?;
@@ -176,6 +245,22 @@ fn main() {
                 .help("File to read data from that will be written to the replica")
                 .required(true)
                 .index(1)))
+        .subcommand(SubCommand::with_name("trim")
+            .about("Unmap a range of the replica")
+            .arg(Arg::with_name("offset")
+                .long("offset")
+                .value_name("BYTES")
+                .help("Start of the range, a multiple of the block size")
+                .takes_value(true))
+            .arg(Arg::with_name("length")
+                .long("length")
+                .value_name("BYTES")
+                .help("Length of the range, a multiple of the block size")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("verify")
+                .long("verify")
+                .help("Check that the range reads back as zeroes")))
         .subcommand(SubCommand::with_name("nvme-admin")
             .about("Send a custom NVMe Admin command")
             .arg(Arg::with_name("opcode")
@@ -190,6 +275,25 @@ fn main() {
                 .index(1)))
         .subcommand(SubCommand::with_name("create-snapshot")
             .about("Create a snapshot on the replica"))
+        .subcommand(SubCommand::with_name("server-copy")
+            .about("Copy blocks on the replica without transferring the data")
+            .arg(Arg::with_name("src")
+                .long("src")
+                .value_name("LBA,LEN")
+                .help("Source range to copy, may be given more than once")
+                .required(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|v| match parse_range(&v) {
+                    Some(_) => Ok(()),
+                    None => Err(format!("Invalid source range {}", v)),
+                }))
+            .arg(Arg::with_name("dst")
+                .long("dst")
+                .value_name("LBA")
+                .help("Destination LBA of the copy")
+                .required(true)
+                .takes_value(true)))
         .get_matches();
 
     logger::init("INFO");
@@ -216,6 +320,17 @@ fn main() {
             read(&uri, offset, matches.value_of("FILE").unwrap()).await
         } else if let Some(matches) = matches.subcommand_matches("write") {
             write(&uri, offset, matches.value_of("FILE").unwrap()).await
+        } else if let Some(matches) = matches.subcommand_matches("trim") {
+            let offset: u64 = match matches.value_of("offset") {
+                Some(val) => val.parse().expect("Offset must be a number"),
+                None => offset,
+            };
+            let length: u64 = matches
+                .value_of("length")
+                .unwrap()
+                .parse()
+                .expect("Length must be a number");
+            trim(&uri, offset, length, matches.is_present("verify")).await
         } else if let Some(matches) = matches.subcommand_matches("nvme-admin") {
             let opcode: u8 = match matches.value_of("opcode") {
                 Some(val) => val.parse().expect("Opcode must be a number"),
@@ -226,12 +341,25 @@ fn main() {
             identify_ctrlr(&uri, matches.value_of("FILE").unwrap()).await
         } else if matches.subcommand_matches("create-snapshot").is_some() {
             create_snapshot(&uri).await
+        } else if let Some(matches) = matches.subcommand_matches("server-copy")
+        {
+            let dest_lba: u64 = matches
+                .value_of("dst")
+                .unwrap()
+                .parse()
+                .expect("Destination LBA must be a number");
+            let ranges: Vec<(u64, u32)> = matches
+                .values_of("src")
+                .unwrap()
+                .filter_map(parse_range)
+                .collect();
+            server_copy(&uri, dest_lba, &ranges).await
         } else {
             connect(&uri).await
         };
         if let Err(err) = res {
             error!("{}", err);
-            -1
+            err.rc
         } else {
             0
         }
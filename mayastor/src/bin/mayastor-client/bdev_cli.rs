@@ -8,7 +8,13 @@ use crate::{
 };
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::prelude::*;
-use rpc::mayastor::{BdevShareRequest, BdevUri, CreateReply, Null};
+use rpc::mayastor::{
+    BdevFilter,
+    BdevShareRequest,
+    BdevUri,
+    CreateReply,
+    ListBdevsRequest,
+};
 use snafu::ResultExt;
 use tonic::Status;
 
@@ -17,7 +23,7 @@ pub async fn handler(
     matches: &ArgMatches<'_>,
 ) -> crate::Result<()> {
     match matches.subcommand() {
-        ("list", Some(args)) => list(ctx, args).await,
+        ("list", Some(args)) => ctx.watch(|ctx| list(ctx, args)).await,
         ("create", Some(args)) => create(ctx, args).await,
         ("share", Some(args)) => share(ctx, args).await,
         ("destroy", Some(args)) => destroy(ctx, args).await,
@@ -30,7 +36,19 @@ pub async fn handler(
 }
 
 pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
-    let list = SubCommand::with_name("list").about("List all bdevs");
+    let list = SubCommand::with_name("list").about("List all bdevs").arg(
+        Arg::with_name("filter")
+            .long("filter")
+            .value_name("KEY:VALUE")
+            .help("Only list bdevs matching type:, name: (glob) or uuid:")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|v| {
+                parse_filter(&v, &mut BdevFilter::default())
+                    .ok_or_else(|| format!("Invalid filter {}", v))
+            }),
+    );
     let create = SubCommand::with_name("create")
         .about("Create a new bdev by specifying a URI")
         .arg(Arg::with_name("uri").required(true).index(1));
@@ -69,8 +87,36 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .subcommand(destroy)
 }
 
-async fn list(mut ctx: Context, _args: &ArgMatches<'_>) -> crate::Result<()> {
-    let response = ctx.bdev.list(Null {}).await.context(GrpcStatus)?;
+/// Add a filter given as "<key>:<value>" to the bdev filter
+fn parse_filter(arg: &str, filter: &mut BdevFilter) -> Option<()> {
+    let mut parts = arg.splitn(2, ':');
+    let key = parts.next()?;
+    let value = parts.next()?.to_string();
+    match key {
+        "type" => filter.driver = value,
+        "name" => filter.name = value,
+        "uuid" => filter.uuid = value,
+        _ => return None,
+    }
+    Some(())
+}
+
+async fn list(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
+    let filter = args.values_of("filter").map(|values| {
+        let mut filter = BdevFilter::default();
+        values.for_each(|v| {
+            parse_filter(v, &mut filter);
+        });
+        filter
+    });
+
+    let response = ctx
+        .bdev
+        .list(ListBdevsRequest {
+            filter,
+        })
+        .await
+        .context(GrpcStatus)?;
 
     match ctx.output {
         OutputFormat::Json => {
@@ -82,7 +128,7 @@ async fn list(mut ctx: Context, _args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let bdevs = &response.get_ref().bdevs;
             if bdevs.is_empty() {
                 ctx.v1("No bdevs found");
@@ -142,7 +188,7 @@ async fn create(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &response.get_ref().name);
         }
     };
@@ -159,7 +205,7 @@ async fn destroy(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
         .to_owned();
     let bdevs = ctx
         .bdev
-        .list(Null {})
+        .list(ListBdevsRequest::default())
         .await
         .context(GrpcStatus)?
         .into_inner();
@@ -198,7 +244,7 @@ async fn destroy(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", found.name,);
         }
     };
@@ -239,7 +285,7 @@ async fn share(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &response.get_ref().uri,);
         }
     }
@@ -272,7 +318,7 @@ async fn unshare(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", name,);
         }
     }
@@ -4,7 +4,7 @@ use bytes::Bytes;
 use clap::ArgMatches;
 use http::uri::{Authority, PathAndQuery, Scheme, Uri};
 use snafu::{Backtrace, ResultExt, Snafu};
-use std::{cmp::max, str::FromStr};
+use std::{cmp::max, future::Future, str::FromStr, time::Duration};
 use tonic::transport::Endpoint;
 
 #[derive(Debug, Snafu)]
@@ -31,11 +31,15 @@ pub enum Error {
     },
     #[snafu(display("Invalid output format: {}", format))]
     OutputFormatError { format: String },
+    #[snafu(display("Invalid watch interval: {}", interval))]
+    WatchIntervalError { interval: String },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum OutputFormat {
     Json,
+    Yaml,
+    Csv,
     Default,
 }
 
@@ -45,6 +49,8 @@ impl FromStr for OutputFormat {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            "csv" => Ok(Self::Csv),
             "default" => Ok(Self::Default),
             s => Err(Error::OutputFormatError {
                 format: s.to_string(),
@@ -53,6 +59,7 @@ impl FromStr for OutputFormat {
     }
 }
 
+#[derive(Clone)]
 pub struct Context {
     pub(crate) client: MayaClient,
     pub(crate) bdev: BdevClient,
@@ -60,6 +67,7 @@ pub struct Context {
     verbosity: u64,
     units: char,
     pub(crate) output: OutputFormat,
+    watch: Option<Duration>,
 }
 
 impl Context {
@@ -111,6 +119,16 @@ impl Context {
         })?;
         let output = output.parse()?;
 
+        let watch = matches
+            .value_of("watch")
+            .map(|s| match s.parse::<u64>() {
+                Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+                _ => Err(Error::WatchIntervalError {
+                    interval: s.to_string(),
+                }),
+            })
+            .transpose()?;
+
         let client = MayaClient::connect(host.clone()).await.unwrap();
         let bdev = BdevClient::connect(host.clone()).await.unwrap();
         let json = JsonClient::connect(host).await.unwrap();
@@ -122,8 +140,32 @@ impl Context {
             verbosity,
             units,
             output,
+            watch,
         })
     }
+
+    /// Run a list command once or, when watching, every interval until
+    /// interrupted, clearing the terminal before each run. The clients are
+    /// cloned for each run and so share the connection.
+    pub(crate) async fn watch<F, Fut>(self, list: F) -> crate::Result<()>
+    where
+        F: Fn(Context) -> Fut,
+        Fut: Future<Output = crate::Result<()>>,
+    {
+        let interval = match self.watch {
+            Some(interval) => interval,
+            None => return list(self).await,
+        };
+        let tty = atty::is(atty::Stream::Stdout);
+
+        loop {
+            if tty {
+                print!("\x1b[2J\x1b[H");
+            }
+            list(self.clone()).await?;
+            tokio::time::delay_for(interval).await;
+        }
+    }
     pub(crate) fn v1(&self, s: &str) {
         if self.verbosity > 0 {
             println!("{}", s)
@@ -147,8 +189,27 @@ impl Context {
     pub(crate) fn print_list(
         &self,
         headers: Vec<&str>,
-        mut data: Vec<Vec<String>>,
+        data: Vec<Vec<String>>,
     ) {
+        let lines = match self.output {
+            OutputFormat::Yaml => format_yaml(headers, data),
+            OutputFormat::Csv => format_csv(headers, data),
+            OutputFormat::Json | OutputFormat::Default => {
+                self.format_list(headers, data)
+            }
+        };
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+
+    /// Format the data as a table, returning the lines to be printed. The
+    /// header line is only included when the verbosity is above 0.
+    pub(crate) fn format_list(
+        &self,
+        headers: Vec<&str>,
+        mut data: Vec<Vec<String>>,
+    ) -> Vec<String> {
         assert_ne!(data.len(), 0);
         let ncols = data.first().unwrap().len();
         assert_eq!(headers.len(), ncols);
@@ -156,16 +217,7 @@ impl Context {
         let columns = if self.verbosity > 0 {
             data.insert(
                 0,
-                headers
-                    .iter()
-                    .map(|h| {
-                        if let Some(stripped) = h.strip_prefix('>') {
-                            stripped.to_string()
-                        } else {
-                            h.to_string()
-                        }
-                    })
-                    .collect(),
+                headers.iter().map(|h| column_name(h).to_string()).collect(),
             );
 
             data.iter().fold(
@@ -185,16 +237,67 @@ impl Context {
             vec![(false, 0usize); ncols]
         };
 
-        for row in data {
-            let vals = row.iter().enumerate().map(|(idx, s)| {
-                if columns[idx].0 {
-                    format!("{:>1$}", s, columns[idx].1)
-                } else {
-                    format!("{:<1$}", s, columns[idx].1)
-                }
-            });
+        data.iter()
+            .map(|row| {
+                let vals = row.iter().enumerate().map(|(idx, s)| {
+                    if columns[idx].0 {
+                        format!("{:>1$}", s, columns[idx].1)
+                    } else {
+                        format!("{:<1$}", s, columns[idx].1)
+                    }
+                });
 
-            println!("{}", vals.collect::<Vec<String>>().join(" "));
-        }
+                vals.collect::<Vec<String>>().join(" ")
+            })
+            .collect()
     }
 }
+
+/// Name of a column, without the '>' marking it as right aligned.
+fn column_name(header: &str) -> &str {
+    header.strip_prefix('>').unwrap_or(header)
+}
+
+/// Format the data as comma separated values, preceded by a header line.
+/// Fields which contain a comma, a quote or a newline are quoted.
+fn format_csv(headers: Vec<&str>, data: Vec<Vec<String>>) -> Vec<String> {
+    let field = |s: &str| {
+        if s.contains(&[',', '"', '\n'][..]) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+
+    std::iter::once(headers.iter().map(|h| field(column_name(h))).collect())
+        .chain(
+            data.iter()
+                .map(|row| row.iter().map(|s| field(s)).collect::<Vec<_>>()),
+        )
+        .map(|row: Vec<String>| row.join(","))
+        .collect()
+}
+
+/// Format the data as a YAML list with one map per row, keyed by the column
+/// names.
+fn format_yaml(headers: Vec<&str>, data: Vec<Vec<String>>) -> Vec<String> {
+    let rows = data
+        .into_iter()
+        .map(|row| {
+            headers
+                .iter()
+                .map(|h| column_name(h).to_string())
+                .zip(row)
+                .map(|(k, v)| {
+                    (serde_yaml::Value::String(k), serde_yaml::Value::String(v))
+                })
+                .collect::<serde_yaml::Mapping>()
+        })
+        .collect::<Vec<_>>();
+
+    serde_yaml::to_string(&rows)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
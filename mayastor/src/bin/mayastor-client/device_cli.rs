@@ -35,7 +35,9 @@ pub async fn handler(
     matches: &ArgMatches<'_>,
 ) -> crate::Result<()> {
     match matches.subcommand() {
-        ("list", Some(args)) => list_block_devices(ctx, args).await,
+        ("list", Some(args)) => {
+            ctx.watch(|ctx| list_block_devices(ctx, args)).await
+        }
         (cmd, _) => {
             Err(Status::not_found(format!("command {} does not exist", cmd)))
                 .context(GrpcStatus)
@@ -75,7 +77,7 @@ async fn list_block_devices(
                     .unwrap()
             )
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let devices: &Vec<rpc::BlockDevice> = &response.get_ref().devices;
 
             if devices.is_empty() {
@@ -52,8 +52,8 @@ pub async fn json_rpc_call(
         .await
         .context(GrpcStatus)?;
 
-    if ctx.output == OutputFormat::Default {
-        debug!("Default output for jsonrpc calls is JSON.");
+    if ctx.output != OutputFormat::Json {
+        debug!("Output for jsonrpc calls is always JSON.");
     };
 
     println!(
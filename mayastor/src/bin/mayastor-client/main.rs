@@ -16,6 +16,7 @@ mod device_cli;
 mod jsonrpc_cli;
 mod nexus_child_cli;
 mod nexus_cli;
+mod nexus_rebuild_cli;
 mod perf_cli;
 mod pool_cli;
 mod rebuild_cli;
@@ -95,10 +96,17 @@ async fn main() -> crate::Result<()> {
                 .long("output")
                 .value_name("FORMAT")
                 .default_value("default")
-                .possible_values(&["default", "json"])
+                .possible_values(&["default", "json", "yaml", "csv"])
                 .global(true)
                 .help("Output format.")
         )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .value_name("SECONDS")
+                .global(true)
+                .help("Repeat list commands every SECONDS until interrupted.")
+        )
         .subcommand(pool_cli::subcommands())
         .subcommand(nexus_cli::subcommands())
         .subcommand(replica_cli::subcommands())
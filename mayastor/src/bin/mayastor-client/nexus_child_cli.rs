@@ -87,7 +87,7 @@ async fn fault(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", uri);
         }
     };
@@ -1,6 +1,7 @@
 use crate::{
     context::{Context, OutputFormat},
     nexus_child_cli,
+    nexus_rebuild_cli,
     parse_size,
     Error,
     GrpcStatus,
@@ -135,6 +136,15 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help("uuid of nexus"),
         );
 
+    let label = SubCommand::with_name("label")
+        .about("show the GPT label of the nexus")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of nexus"),
+        );
+
     SubCommand::with_name("nexus")
         .settings(&[
             AppSettings::SubcommandRequiredElseHelp,
@@ -151,7 +161,9 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .subcommand(ana_state)
         .subcommand(list)
         .subcommand(children)
+        .subcommand(label)
         .subcommand(nexus_child_cli::subcommands())
+        .subcommand(nexus_rebuild_cli::subcommands())
 }
 
 pub async fn handler(
@@ -161,14 +173,18 @@ pub async fn handler(
     match matches.subcommand() {
         ("create", Some(args)) => nexus_create(ctx, &args).await,
         ("destroy", Some(args)) => nexus_destroy(ctx, &args).await,
-        ("list", Some(args)) => nexus_list(ctx, &args).await,
-        ("children", Some(args)) => nexus_children(ctx, &args).await,
+        ("list", Some(args)) => ctx.watch(|ctx| nexus_list(ctx, args)).await,
+        ("children", Some(args)) => {
+            ctx.watch(|ctx| nexus_children(ctx, args)).await
+        }
+        ("label", Some(args)) => nexus_label(ctx, &args).await,
         ("publish", Some(args)) => nexus_publish(ctx, &args).await,
         ("unpublish", Some(args)) => nexus_unpublish(ctx, &args).await,
         ("ana_state", Some(args)) => nexus_nvme_ana_state(ctx, &args).await,
         ("add", Some(args)) => nexus_add(ctx, &args).await,
         ("remove", Some(args)) => nexus_remove(ctx, &args).await,
         ("child", Some(args)) => nexus_child_cli::handler(ctx, args).await,
+        ("rebuild", Some(args)) => nexus_rebuild_cli::handler(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {} does not exist", cmd)))
                 .context(GrpcStatus)
@@ -217,7 +233,7 @@ async fn nexus_create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &response.get_ref().uuid);
         }
     };
@@ -249,7 +265,7 @@ async fn nexus_destroy(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uuid,);
         }
     };
@@ -277,7 +293,7 @@ async fn nexus_list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let nexus = &response.get_ref().nexus_list;
             if nexus.is_empty() {
                 ctx.v1("No nexus found");
@@ -362,7 +378,7 @@ async fn nexus_children(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let table = nexus
                 .children
                 .iter()
@@ -378,6 +394,58 @@ async fn nexus_children(
     Ok(())
 }
 
+async fn nexus_label(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let uuid = matches
+        .value_of("uuid")
+        .ok_or_else(|| Error::MissingValue {
+            field: "uuid".to_string(),
+        })?
+        .to_string();
+
+    let response = ctx
+        .client
+        .get_nexus_label(rpc::GetNexusLabelRequest {
+            uuid,
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
+            let label = response.get_ref();
+            println!("Child: {}", label.uri);
+            println!("GUID: {}", label.disk_guid);
+            println!("Block size: {}", label.block_size);
+            println!("Status: {}", label.status);
+            for (i, p) in label.partitions.iter().enumerate() {
+                println!("  Partition {}", i);
+                println!("    Name: {}", p.name);
+                println!("    Type GUID: {}", p.type_guid);
+                println!("    LBA start: {}", p.start_lba);
+                println!("    LBA end: {}", p.end_lba);
+                println!(
+                    "    Size: {}",
+                    ctx.units(Byte::from_bytes(p.size_bytes.into()))
+                );
+            }
+        }
+    };
+
+    Ok(())
+}
+
 async fn nexus_publish(
     mut ctx: Context,
     matches: &ArgMatches<'_>,
@@ -422,7 +490,7 @@ async fn nexus_publish(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", response.get_ref().device_uri,)
         }
     };
@@ -459,7 +527,7 @@ async fn nexus_unpublish(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uuid,)
         }
     };
@@ -564,7 +632,7 @@ async fn nexus_add(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uuid,)
         }
     };
@@ -608,7 +676,7 @@ async fn nexus_remove(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uri,)
         }
     };
@@ -633,6 +701,8 @@ fn nexus_state_to_str(idx: i32) -> &'static str {
         rpc::NexusState::NexusOnline => "online",
         rpc::NexusState::NexusDegraded => "degraded",
         rpc::NexusState::NexusFaulted => "faulted",
+        rpc::NexusState::NexusRebuilding => "rebuilding",
+        rpc::NexusState::NexusQuorum => "quorum",
     }
 }
 
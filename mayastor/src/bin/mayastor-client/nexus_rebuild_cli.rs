@@ -0,0 +1,218 @@
+//!
+//! methods to interact with the rebuild of the children of a nexus
+
+use crate::{
+    context::{Context, OutputFormat},
+    rebuild_cli,
+    Error,
+    GrpcStatus,
+};
+use ::rpc::mayastor as rpc;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use colored_json::ToColoredJson;
+use snafu::ResultExt;
+use tonic::{Code, Status};
+
+/// width of the progress bar, in characters
+const PROGRESS_BAR_WIDTH: usize = 40;
+
+pub async fn handler(
+    ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    match matches.subcommand() {
+        ("start", Some(_)) | ("stop", Some(_)) | ("pause", Some(_)) => {
+            rebuild_cli::handler(ctx, matches).await
+        }
+        ("status", Some(args)) => status(ctx, &args).await,
+        (cmd, _) => {
+            Err(Status::not_found(format!("command {} does not exist", cmd)))
+                .context(GrpcStatus)
+        }
+    }
+}
+
+pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
+    let start = SubCommand::with_name("start")
+        .about("starts the rebuild of a child")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of the nexus"),
+        )
+        .arg(
+            Arg::with_name("uri")
+                .required(true)
+                .index(2)
+                .help("uri of child to start rebuilding"),
+        );
+
+    let stop = SubCommand::with_name("stop")
+        .about("stops the rebuild of a child")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of the nexus"),
+        )
+        .arg(
+            Arg::with_name("uri")
+                .required(true)
+                .index(2)
+                .help("uri of child to stop rebuilding"),
+        );
+
+    let pause = SubCommand::with_name("pause")
+        .about("pauses the rebuild of a child")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of the nexus"),
+        )
+        .arg(
+            Arg::with_name("uri")
+                .required(true)
+                .index(2)
+                .help("uri of child to pause rebuilding"),
+        );
+
+    let status = SubCommand::with_name("status")
+        .about("shows the rebuild progress of the children")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of the nexus"),
+        )
+        .arg(
+            Arg::with_name("uri")
+                .required(false)
+                .index(2)
+                .help("uri of the child, all rebuilding children if omitted"),
+        );
+
+    SubCommand::with_name("rebuild")
+        .settings(&[
+            AppSettings::SubcommandRequiredElseHelp,
+            AppSettings::ColoredHelp,
+            AppSettings::ColorAlways,
+        ])
+        .about("Nexus child rebuild management")
+        .subcommand(start)
+        .subcommand(stop)
+        .subcommand(pause)
+        .subcommand(status)
+}
+
+async fn status(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let uuid = matches
+        .value_of("uuid")
+        .ok_or_else(|| Error::MissingValue {
+            field: "uuid".to_string(),
+        })?
+        .to_string();
+
+    let uris = match matches.value_of("uri") {
+        Some(uri) => vec![uri.to_string()],
+        None => {
+            let response = ctx
+                .client
+                .list_nexus(rpc::Null {})
+                .await
+                .context(GrpcStatus)?;
+
+            response
+                .get_ref()
+                .nexus_list
+                .iter()
+                .find(|n| n.uuid == uuid)
+                .ok_or_else(|| {
+                    Status::new(
+                        Code::InvalidArgument,
+                        "Specified nexus not found".to_owned(),
+                    )
+                })
+                .context(GrpcStatus)?
+                .children
+                .iter()
+                .filter(|c| c.rebuild_progress >= 0)
+                .map(|c| c.uri.clone())
+                .collect()
+        }
+    };
+
+    let mut stats = Vec::new();
+    for uri in uris {
+        ctx.v2(&format!(
+            "Getting the rebuild stats of child {} on nexus {}",
+            uri, uuid
+        ));
+        let response = ctx
+            .client
+            .get_rebuild_stats(rpc::RebuildStatsRequest {
+                uuid: uuid.clone(),
+                uri: uri.clone(),
+            })
+            .await
+            .context(GrpcStatus)?;
+        stats.push((uri, response.into_inner()));
+    }
+
+    match ctx.output {
+        OutputFormat::Json => {
+            let stats = stats
+                .iter()
+                .map(|(uri, s)| {
+                    serde_json::json!({
+                        "uri": uri,
+                        "stats": s,
+                    })
+                })
+                .collect::<Vec<_>>();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&stats)
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
+            let tty = atty::is(atty::Stream::Stdout);
+            for (uri, s) in &stats {
+                let pct = progress_pct(s);
+                if tty {
+                    println!("{} {} {:.1}%", uri, progress_bar(pct), pct);
+                } else {
+                    println!("{} progress_pct: {:.1}%", uri, pct);
+                }
+            }
+        }
+    };
+
+    Ok(())
+}
+
+/// Progress of the rebuild with a finer granularity than the whole percent
+/// reported by the server
+fn progress_pct(stats: &rpc::RebuildStatsReply) -> f64 {
+    if stats.blocks_total == 0 {
+        return stats.progress as f64;
+    }
+    stats.blocks_recovered as f64 * 100.0 / stats.blocks_total as f64
+}
+
+fn progress_bar(pct: f64) -> String {
+    let done = ((pct / 100.0) * PROGRESS_BAR_WIDTH as f64) as usize;
+    let done = std::cmp::min(done, PROGRESS_BAR_WIDTH);
+    format!(
+        "[{}{}]",
+        "#".repeat(done),
+        " ".repeat(PROGRESS_BAR_WIDTH - done)
+    )
+}
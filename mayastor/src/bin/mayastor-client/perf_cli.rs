@@ -33,7 +33,9 @@ pub async fn handler(
     matches: &ArgMatches<'_>,
 ) -> crate::Result<()> {
     match matches.subcommand() {
-        ("resource", Some(args)) => get_resource_usage(ctx, args).await,
+        ("resource", Some(args)) => {
+            ctx.watch(|ctx| get_resource_usage(ctx, args)).await
+        }
         (cmd, _) => {
             Err(Status::not_found(format!("command {} does not exist", cmd)))
                 .context(GrpcStatus)
@@ -65,7 +67,7 @@ async fn get_resource_usage(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             if let Some(usage) = &response.get_ref().usage {
                 table.push(vec![
                     usage.soft_faults.to_string(),
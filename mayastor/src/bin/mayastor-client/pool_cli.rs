@@ -8,6 +8,7 @@ use byte_unit::Byte;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::ToColoredJson;
 use snafu::ResultExt;
+use std::collections::HashMap;
 use tonic::Status;
 
 pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
@@ -34,6 +35,26 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .index(1)
                 .help("Storage pool name"),
         );
+    let stats = SubCommand::with_name("stats")
+        .about("Display storage pool utilisation")
+        .arg(
+            Arg::with_name("pool")
+                .required(false)
+                .index(1)
+                .help("Storage pool name, all pools if omitted"),
+        )
+        .arg(
+            Arg::with_name("threshold-pct")
+                .long("threshold-pct")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|v| {
+                    v.parse::<f64>()
+                        .map(|_| ())
+                        .map_err(|_| format!("invalid percentage: {}", v))
+                })
+                .help("Highlight pools which are more than N% used"),
+        );
     SubCommand::with_name("pool")
         .settings(&[
             AppSettings::SubcommandRequiredElseHelp,
@@ -44,6 +65,7 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .subcommand(create)
         .subcommand(destroy)
         .subcommand(SubCommand::with_name("list").about("List storage pools"))
+        .subcommand(stats)
 }
 
 pub async fn handler(
@@ -53,7 +75,8 @@ pub async fn handler(
     match matches.subcommand() {
         ("create", Some(args)) => create(ctx, args).await,
         ("destroy", Some(args)) => destroy(ctx, args).await,
-        ("list", Some(args)) => list(ctx, args).await,
+        ("list", Some(args)) => ctx.watch(|ctx| list(ctx, args)).await,
+        ("stats", Some(args)) => ctx.watch(|ctx| stats(ctx, args)).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {} does not exist", cmd)))
                 .context(GrpcStatus)
@@ -98,7 +121,7 @@ async fn create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &name);
         }
     };
@@ -135,7 +158,7 @@ async fn destroy(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &name);
         }
     };
@@ -165,7 +188,7 @@ async fn list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let pools: &Vec<rpc::Pool> = &response.get_ref().pools;
             if pools.is_empty() {
                 ctx.v1("No pools found");
@@ -197,6 +220,138 @@ async fn list(
     Ok(())
 }
 
+async fn stats(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let name = matches.value_of("pool");
+    let threshold = matches
+        .value_of("threshold-pct")
+        .map(|v| v.parse::<f64>().unwrap());
+
+    ctx.v2("Requesting a list of pools and replicas");
+
+    let pools = ctx
+        .client
+        .list_pools(rpc::Null {})
+        .await
+        .context(GrpcStatus)?
+        .into_inner()
+        .pools;
+
+    let replicas = ctx
+        .client
+        .list_replicas(rpc::Null {})
+        .await
+        .context(GrpcStatus)?
+        .into_inner()
+        .replicas;
+
+    // space committed to replicas, which may exceed the used space of the
+    // pool as replicas can be thin provisioned
+    let mut committed: HashMap<String, u64> = HashMap::new();
+    for r in &replicas {
+        *committed.entry(r.pool.clone()).or_insert(0) += r.size;
+    }
+
+    let pools: Vec<&rpc::Pool> = pools
+        .iter()
+        .filter(|p| name.map_or(true, |n| n == p.name))
+        .collect();
+
+    if let Some(name) = name {
+        if pools.is_empty() {
+            return Err(Status::not_found(format!(
+                "pool {} does not exist",
+                name
+            )))
+            .context(GrpcStatus);
+        }
+    }
+
+    match ctx.output {
+        OutputFormat::Json => {
+            let stats: Vec<serde_json::Value> = pools
+                .iter()
+                .map(|p| {
+                    let committed =
+                        committed.get(&p.name).cloned().unwrap_or(0);
+                    serde_json::json!({
+                        "name": p.name,
+                        "capacity": p.capacity,
+                        "used": p.used,
+                        "available": p.capacity.saturating_sub(p.used),
+                        "committed": committed,
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&stats)
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
+            if pools.is_empty() {
+                ctx.v1("No pools found");
+                return Ok(());
+            }
+
+            let table = pools
+                .iter()
+                .map(|p| {
+                    let cap = Byte::from_bytes(p.capacity.into());
+                    let used = Byte::from_bytes(p.used.into());
+                    let available = Byte::from_bytes(
+                        p.capacity.saturating_sub(p.used).into(),
+                    );
+                    let committed = Byte::from_bytes(
+                        committed.get(&p.name).cloned().unwrap_or(0).into(),
+                    );
+                    vec![
+                        p.name.clone(),
+                        ctx.units(cap),
+                        ctx.units(used),
+                        ctx.units(available),
+                        ctx.units(committed),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            let headers =
+                vec!["POOL", ">CAPACITY", ">USED", ">AVAILABLE", ">COMMITTED"];
+            // only the table can highlight the pools over the threshold
+            if ctx.output != OutputFormat::Default {
+                ctx.print_list(headers, table);
+                return Ok(());
+            }
+            let rows = table.len();
+
+            let lines = ctx.format_list(headers, table);
+            // skip the header line, if present
+            let header = lines.len() - rows;
+            let highlight = atty::is(atty::Stream::Stdout);
+
+            for (idx, line) in lines.iter().enumerate() {
+                let over = idx >= header
+                    && threshold.map_or(false, |pct| {
+                        let p = pools[idx - header];
+                        p.capacity > 0
+                            && (p.used as f64 * 100.0 / p.capacity as f64) > pct
+                    });
+                if over && highlight {
+                    println!("\x1b[31m{}\x1b[0m", line);
+                } else {
+                    println!("{}", line);
+                }
+            }
+        }
+    };
+
+    Ok(())
+}
+
 fn pool_state_to_str(idx: i32) -> &'static str {
     match rpc::PoolState::from_i32(idx).unwrap() {
         rpc::PoolState::PoolUnknown => "unknown",
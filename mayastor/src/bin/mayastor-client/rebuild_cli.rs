@@ -3,10 +3,12 @@
 
 use crate::{
     context::{Context, OutputFormat},
+    parse_size,
     Error,
     GrpcStatus,
 };
 use ::rpc::mayastor as rpc;
+use byte_unit::Byte;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::ToColoredJson;
 use snafu::ResultExt;
@@ -21,9 +23,9 @@ pub async fn handler(
         ("stop", Some(args)) => stop(ctx, &args).await,
         ("pause", Some(args)) => pause(ctx, &args).await,
         ("resume", Some(args)) => resume(ctx, &args).await,
-        ("state", Some(args)) => state(ctx, &args).await,
-        ("stats", Some(args)) => stats(ctx, &args).await,
-        ("progress", Some(args)) => progress(ctx, &args).await,
+        ("state", Some(args)) => ctx.watch(|ctx| state(ctx, args)).await,
+        ("stats", Some(args)) => ctx.watch(|ctx| stats(ctx, args)).await,
+        ("progress", Some(args)) => ctx.watch(|ctx| progress(ctx, args)).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {} does not exist", cmd)))
                 .context(GrpcStatus)
@@ -45,6 +47,20 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(2)
                 .help("uri of child to start rebuilding"),
+        )
+        .arg(
+            Arg::with_name("rate-limit")
+                .long("rate-limit")
+                .takes_value(true)
+                .value_name("BYTES")
+                .validator(|v| parse_size(&v).map(|_| ()))
+                .help("Maximum number of bytes to copy per second, e.g. 50MiB"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .takes_value(false)
+                .help("Compare the rebuilt data with the source once copied"),
         );
 
     let stop = SubCommand::with_name("stop")
@@ -169,12 +185,19 @@ async fn start(
             field: "uri".to_string(),
         })?
         .to_string();
+    let rate_limit = matches
+        .value_of("rate-limit")
+        .map(|v| parse_size(v).unwrap().get_bytes() as u64)
+        .unwrap_or(0);
+    let verify = matches.is_present("verify");
 
     let response = ctx
         .client
         .start_rebuild(rpc::StartRebuildRequest {
             uuid: uuid.clone(),
             uri: uri.clone(),
+            rate_limit,
+            verify,
         })
         .await
         .context(GrpcStatus)?;
@@ -189,7 +212,7 @@ async fn start(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uri);
         }
     };
@@ -230,7 +253,7 @@ async fn stop(mut ctx: Context, matches: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uri);
         }
     };
@@ -274,7 +297,7 @@ async fn pause(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uri);
         }
     };
@@ -318,7 +341,7 @@ async fn resume(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uri);
         }
     };
@@ -362,10 +385,19 @@ async fn state(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
+            let reply = response.get_ref();
             ctx.print_list(
-                vec!["state"],
-                vec![vec![response.get_ref().state.clone()]],
+                vec!["state", "progress", "eta", "throughput"],
+                vec![vec![
+                    reply.state.clone(),
+                    format!("{:.1}%", reply.progress_percent),
+                    format!("{}s", reply.eta_seconds),
+                    format!(
+                        "{}/s",
+                        ctx.units(Byte::from_bytes(reply.throughput.into()))
+                    ),
+                ]],
             );
         }
     };
@@ -413,7 +445,7 @@ async fn stats(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let response = &response.get_ref();
             ctx.print_list(
                 vec![
@@ -480,7 +512,7 @@ async fn progress(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             ctx.print_list(
                 vec!["progress (%)"],
                 vec![vec![response.get_ref().progress.to_string()]],
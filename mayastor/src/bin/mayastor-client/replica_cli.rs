@@ -91,9 +91,9 @@ pub async fn handler(
     match matches.subcommand() {
         ("create", Some(args)) => replica_create(ctx, &args).await,
         ("destroy", Some(args)) => replica_destroy(ctx, &args).await,
-        ("list", Some(args)) => replica_list(ctx, &args).await,
+        ("list", Some(args)) => ctx.watch(|ctx| replica_list(ctx, args)).await,
         ("share", Some(args)) => replica_share(ctx, &args).await,
-        ("stats", Some(args)) => replica_stat(ctx, &args).await,
+        ("stats", Some(args)) => ctx.watch(|ctx| replica_stat(ctx, args)).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {} does not exist", cmd)))
                 .context(GrpcStatus)
@@ -147,7 +147,7 @@ async fn replica_create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -184,7 +184,7 @@ async fn replica_destroy(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &uuid);
         }
     };
@@ -212,7 +212,7 @@ async fn replica_list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let replicas = &response.get_ref().replicas;
             if replicas.is_empty() {
                 ctx.v1("No replicas found");
@@ -271,7 +271,7 @@ async fn replica_share(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -299,7 +299,7 @@ async fn replica_stat(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Yaml | OutputFormat::Csv => {
             let replicas = &response.get_ref().replicas;
             if replicas.is_empty() {
                 ctx.v1("No replicas have been created");
@@ -26,9 +26,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // passed, we will use it regardless.
 
     if !args.log_components.is_empty() {
-        logger::init("TRACE");
+        logger::init_with_format("TRACE", args.log_format);
     } else {
-        logger::init("INFO");
+        logger::init_with_format("INFO", args.log_format);
     }
 
     let hugepage_path = Path::new("/sys/kernel/mm/hugepages/hugepages-2048kB");
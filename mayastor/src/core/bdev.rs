@@ -12,6 +12,8 @@ use nix::errno::Errno;
 use snafu::ResultExt;
 
 use spdk_sys::{
+    bdev_nvme_get_ctrlr,
+    nvme_bdev_get_ns,
     spdk_bdev,
     spdk_bdev_event_type,
     spdk_bdev_first,
@@ -19,42 +21,68 @@ use spdk_sys::{
     spdk_bdev_get_block_size,
     spdk_bdev_get_buf_align,
     spdk_bdev_get_by_name,
+    spdk_bdev_get_data_block_size,
     spdk_bdev_get_device_stat,
+    spdk_bdev_get_md_size,
     spdk_bdev_get_name,
     spdk_bdev_get_num_blocks,
+    spdk_bdev_get_optimal_io_boundary,
     spdk_bdev_get_product_name,
     spdk_bdev_get_uuid,
     spdk_bdev_io_stat,
     spdk_bdev_io_type_supported,
+    spdk_bdev_is_md_interleaved,
     spdk_bdev_next,
+    spdk_bdev_notify_blockcnt_change,
     spdk_bdev_open_ext,
+    spdk_nvme_ctrlr_get_max_xfer_size,
+    spdk_nvme_ctrlr_get_transport_id,
+    spdk_nvme_ns_data,
+    spdk_nvme_ns_get_data,
     spdk_uuid_generate,
 };
 
 use crate::{
-    bdev::lookup_child_from_bdev,
+    bdev::{lookup_child_from_bdev, nexus_lookup_by_bdev_name},
     core::{
         share::{Protocol, Share},
         uuid::Uuid,
         CoreError,
         Descriptor,
         IoType,
+        NvmeTransportId,
         ShareIscsi,
         ShareNvmf,
         UnshareIscsi,
         UnshareNvmf,
     },
     ffihelper::{cb_arg, AsStr},
+    nexus_uri::NexusBdevError,
     subsys::NvmfSubsystem,
     target::{iscsi, nvmf, Side},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct BdevStats {
     pub num_read_ops: u64,
     pub num_write_ops: u64,
+    pub num_unmap_ops: u64,
     pub bytes_read: u64,
     pub bytes_written: u64,
+    pub bytes_unmapped: u64,
+}
+
+impl From<&spdk_bdev_io_stat> for BdevStats {
+    fn from(stat: &spdk_bdev_io_stat) -> Self {
+        Self {
+            num_read_ops: stat.num_read_ops,
+            num_write_ops: stat.num_write_ops,
+            num_unmap_ops: stat.num_unmap_ops,
+            bytes_read: stat.bytes_read,
+            bytes_written: stat.bytes_written,
+            bytes_unmapped: stat.bytes_unmapped,
+        }
+    }
 }
 
 /// Newtype structure that represents a block device. The soundness of the API
@@ -180,12 +208,30 @@ impl Bdev {
                 }
             }
             spdk_sys::SPDK_BDEV_EVENT_RESIZE => {
-                warn!("Received resize event for bdev {}", bdev.name())
+                info!(
+                    "Received resize event for bdev {}, {} blocks",
+                    bdev.name(),
+                    bdev.num_blocks()
+                );
+                if let Some(nexus) = nexus_lookup_by_bdev_name(&bdev.name()) {
+                    nexus.invalidate_min_num_blocks_cache();
+                }
+            }
+            spdk_sys::SPDK_BDEV_EVENT_MEDIA_MANAGEMENT => {
+                // the device manages its media itself, a child of a nexus
+                // stays online as any error shows up in its IO anyway
+                match nexus_lookup_by_bdev_name(&bdev.name()) {
+                    Some(nexus) => warn!(
+                        "{}: received media management event for child bdev {}",
+                        nexus.name,
+                        bdev.name()
+                    ),
+                    None => warn!(
+                        "Received media management event for bdev {}",
+                        bdev.name()
+                    ),
+                }
             }
-            spdk_sys::SPDK_BDEV_EVENT_MEDIA_MANAGEMENT => warn!(
-                "Received media management event for bdev {}",
-                bdev.name()
-            ),
             _ => error!(
                 "Received unknown event {} for bdev {}",
                 event,
@@ -214,10 +260,33 @@ impl Bdev {
                 source: Errno::from_i32(rc),
             })
         } else {
-            Ok(Descriptor::from_null_checked(descriptor).unwrap())
+            Ok(Descriptor::from_null_checked(descriptor, read_write).unwrap())
         }
     }
 
+    /// open the current bdev for reading only
+    pub fn open_read_only(&self) -> Result<Descriptor, CoreError> {
+        self.open(false)
+    }
+
+    /// open the current bdev for reading and writing
+    pub fn open_read_write(&self) -> Result<Descriptor, CoreError> {
+        self.open(true)
+    }
+
+    /// open the current bdev for reading and writing and claim it, so no
+    /// other descriptor can write to it until the descriptor is released,
+    /// i.e. for formatting the bdev
+    pub fn open_exclusive(&self) -> Result<Descriptor, CoreError> {
+        let desc = self.open(true)?;
+        if !desc.claim() {
+            return Err(CoreError::OpenBdev {
+                source: Errno::EBUSY,
+            });
+        }
+        Ok(desc)
+    }
+
     /// returns true if this bdev is claimed by some other component
     pub fn is_claimed(&self) -> bool {
         !unsafe { self.0.as_ref().internal.claim_module.is_null() }
@@ -254,6 +323,24 @@ impl Bdev {
         unsafe { spdk_bdev_get_num_blocks(self.0.as_ptr()) }
     }
 
+    /// returns the size of the data of a block, which is smaller than the
+    /// block_len when metadata is interleaved with the data
+    pub fn data_block_len(&self) -> u32 {
+        unsafe { spdk_bdev_get_data_block_size(self.0.as_ptr()) }
+    }
+
+    /// returns the size of the metadata of a block, zero if the device has
+    /// no metadata
+    pub fn md_size(&self) -> u32 {
+        unsafe { spdk_bdev_get_md_size(self.0.as_ptr()) }
+    }
+
+    /// returns true when the metadata of a block is interleaved with its
+    /// data, rather than transferred in a separate buffer
+    pub fn is_md_interleaved(&self) -> bool {
+        unsafe { spdk_bdev_is_md_interleaved(self.0.as_ptr()) }
+    }
+
     /// set the block count of this device
     pub fn set_block_count(&mut self, count: u64) {
         unsafe {
@@ -261,6 +348,25 @@ impl Bdev {
         }
     }
 
+    /// change the number of blocks of the device, the descriptors open on it
+    /// are notified with a resize event. A device which is open cannot
+    /// shrink.
+    ///
+    /// # Safety
+    ///
+    /// Only the block count of the device is changed, the storage behind it
+    /// is not. The caller must make sure that the storage holds the new
+    /// number of blocks, otherwise IO beyond the end of the storage accesses
+    /// memory or media that does not belong to the device.
+    pub unsafe fn resize(&self, num_blocks: u64) -> Result<(), Errno> {
+        let rc = spdk_bdev_notify_blockcnt_change(self.0.as_ptr(), num_blocks);
+        if rc == 0 {
+            Ok(())
+        } else {
+            Err(Errno::from_i32(rc.abs()))
+        }
+    }
+
     /// set the block length of the device in bytes
     pub fn set_block_len(&mut self, len: u32) {
         unsafe {
@@ -333,6 +439,42 @@ impl Bdev {
         ret == 0
     }
 
+    /// Set an alias on the bdev, failing when it cannot be registered, for
+    /// example because another bdev already goes by that name. An alias the
+    /// bdev already has is accepted, so that creating a bdev again succeeds.
+    pub fn add_alias_checked(
+        &mut self,
+        alias: &str,
+    ) -> Result<(), NexusBdevError> {
+        if self.aliases().iter().any(|a| a == alias) {
+            return Ok(());
+        }
+
+        let c_alias = CString::new(alias).unwrap();
+        let ret = unsafe {
+            spdk_sys::spdk_bdev_alias_add(self.0.as_ptr(), c_alias.as_ptr())
+        };
+
+        if ret == 0 {
+            return Ok(());
+        }
+
+        match Errno::from_i32(ret.abs()) {
+            Errno::EEXIST => Err(NexusBdevError::AliasExists {
+                alias: alias.to_string(),
+                name: self.name(),
+            }),
+            errno => Err(NexusBdevError::UriInvalid {
+                uri: alias.to_string(),
+                message: format!(
+                    "cannot be added as an alias of bdev {}: {}",
+                    self.name(),
+                    errno
+                ),
+            }),
+        }
+    }
+
     /// Get list of bdev aliases
     pub fn aliases(&self) -> Vec<String> {
         let mut aliases = Vec::new();
@@ -347,6 +489,65 @@ impl Bdev {
         aliases
     }
 
+    /// returns the largest amount of data, in bytes, that a single IO to
+    /// this bdev may transfer. For NVMe bdevs this is derived from the MDTS
+    /// field of the identify controller data (2^mdts * minimum page size),
+    /// for any other bdev there is no limit and u64::MAX is returned.
+    pub fn max_transfer_size(&self) -> u64 {
+        let ctrlr = unsafe { bdev_nvme_get_ctrlr(self.0.as_ptr()) };
+        if ctrlr.is_null() {
+            return u64::MAX;
+        }
+        u64::from(unsafe { spdk_nvme_ctrlr_get_max_xfer_size(ctrlr) })
+    }
+
+    /// returns the transport ID of the controller of an NVMe bdev, which
+    /// identifies the path the bdev is reached through
+    pub fn nvme_transport_id(&self) -> Option<NvmeTransportId> {
+        let ctrlr = unsafe { bdev_nvme_get_ctrlr(self.0.as_ptr()) };
+        if ctrlr.is_null() {
+            return None;
+        }
+        unsafe { spdk_nvme_ctrlr_get_transport_id(ctrlr).as_ref() }
+            .map(NvmeTransportId::from)
+    }
+
+    /// returns the identify namespace data of an NVMe bdev
+    fn nvme_ns_data(&self) -> Option<&spdk_nvme_ns_data> {
+        let ns = unsafe { nvme_bdev_get_ns(self.0.as_ptr()) };
+        if ns.is_null() {
+            None
+        } else {
+            unsafe { spdk_nvme_ns_get_data(ns).as_ref() }
+        }
+    }
+
+    /// returns the granularity, in bytes, at which unmaps should be issued.
+    /// For NVMe bdevs this is derived from the NAWUN field of the identify
+    /// namespace data, for any other bdev it is the block size.
+    pub fn discard_granularity(&self) -> u64 {
+        let block_len = u64::from(self.block_len());
+        match self.nvme_ns_data() {
+            Some(data) => (u64::from(data.nawun) + 1) * block_len,
+            None => block_len,
+        }
+    }
+
+    /// returns the preferred size, in bytes, of an IO to this bdev. For NVMe
+    /// bdevs this is the NOIOB field of the identify namespace data, for any
+    /// other bdev its optimal IO boundary. If neither is reported the block
+    /// size is returned.
+    pub fn optimal_io_size(&self) -> u64 {
+        let block_len = u64::from(self.block_len());
+        let blocks = match self.nvme_ns_data() {
+            Some(data) => u64::from(data.noiob),
+            None => u64::from(unsafe {
+                spdk_bdev_get_optimal_io_boundary(self.0.as_ptr())
+            }),
+        };
+        blocks.max(1) * block_len
+    }
+
     /// returns whenever the bdev supports the requested IO type
     pub fn io_type_supported(&self, io_type: IoType) -> bool {
         unsafe { spdk_bdev_io_type_supported(self.0.as_ptr(), io_type.into()) }
@@ -410,12 +611,7 @@ impl Bdev {
             Err(errno)
         } else {
             // stat is populated with the stats by now
-            Ok(BdevStats {
-                num_read_ops: stat.num_read_ops,
-                num_write_ops: stat.num_write_ops,
-                bytes_read: stat.bytes_read,
-                bytes_written: stat.bytes_written,
-            })
+            Ok(BdevStats::from(&stat))
         }
     }
 
@@ -246,7 +246,6 @@ impl Bio {
 
     /// get the block length of this IO
     #[inline]
-    #[allow(dead_code)]
     pub(crate) fn block_len(&self) -> u64 {
         self.bdev().block_len() as u64
     }
@@ -2,6 +2,7 @@ use std::{
     convert::TryFrom,
     fmt::{Debug, Error, Formatter},
     os::raw::c_void,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use futures::channel::oneshot;
@@ -27,8 +28,9 @@ use crate::{
 /// descriptors that are open before the bdev has been claimed will remain as
 /// is. Typically, the target, exporting the bdev will claim the device. In the
 /// case of the nexus, we do not claim the children for exclusive access to
-/// allow for the rebuild to happen across multiple cores.
-pub struct Descriptor(*mut spdk_bdev_desc);
+/// allow for the rebuild to happen across multiple cores. The second field
+/// tracks whether the descriptor allows writes as spdk does not expose it.
+pub struct Descriptor(*mut spdk_bdev_desc, AtomicBool);
 
 impl Descriptor {
     /// returns the underling ptr
@@ -65,9 +67,17 @@ impl Descriptor {
 
         let name = self.get_bdev().name();
         debug!("claimed bdev {}", name);
+        if err == 0 {
+            self.1.store(true, Ordering::Relaxed);
+        }
         err == 0
     }
 
+    /// returns true if writes can be submitted through this descriptor
+    pub fn is_writable(&self) -> bool {
+        self.1.load(Ordering::Relaxed)
+    }
+
     /// unclaim a previously claimed bdev
     pub(crate) fn unclaim(&self) {
         unsafe {
@@ -93,13 +103,16 @@ impl Descriptor {
         Bdev::from(bdev)
     }
 
-    /// create a Descriptor from a raw spdk_bdev_desc pointer this is the only
-    /// way to create a new descriptor
-    pub fn from_null_checked(desc: *mut spdk_bdev_desc) -> Option<Descriptor> {
+    /// create a Descriptor from a raw spdk_bdev_desc pointer, opened read/write
+    /// or not, this is the only way to create a new descriptor
+    pub fn from_null_checked(
+        desc: *mut spdk_bdev_desc,
+        read_write: bool,
+    ) -> Option<Descriptor> {
         if desc.is_null() {
             None
         } else {
-            Some(Descriptor(desc))
+            Some(Descriptor(desc, AtomicBool::new(read_write)))
         }
     }
 
@@ -1,8 +1,11 @@
 use std::{
+    cell::Cell,
     env,
     ffi::CString,
-    net::Ipv4Addr,
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs},
     os::raw::{c_char, c_void},
+    path::Path,
     pin::Pin,
     sync::{
         atomic::{AtomicBool, Ordering::SeqCst},
@@ -13,11 +16,17 @@ use std::{
 };
 
 use byte_unit::{Byte, ByteUnit};
-use futures::{channel::oneshot, future};
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+};
+use futures_timer::Delay;
 use once_cell::sync::{Lazy, OnceCell};
+use signal_hook::iterator::Signals;
 use snafu::Snafu;
 use structopt::StructOpt;
 use tokio::runtime::Builder;
+use uuid::Uuid;
 
 use spdk_sys::{
     maya_log,
@@ -42,13 +51,20 @@ use crate::{
         Cores,
         Mthread,
     },
+    ffihelper::cb_arg,
     grpc,
-    logger,
+    logger::{self, LogFormat},
     subsys::{self, Config},
     target::iscsi,
 };
 
-fn parse_mb(src: &str) -> Result<i32, String> {
+/// parse the memory size given to -s, which is in MiB unless a unit is given,
+/// into MiB. The size must not be negative and fit in an i32 as EAL expects.
+pub fn parse_mb(src: &str) -> Result<i32, String> {
+    if src.trim_start().starts_with('-') {
+        return Err(format!("Negative memory size {}", src));
+    }
+
     // For compatibility, we check to see if there are no alphabetic characters
     // passed in, if, so we interpret the value to be in MiB which is what the
     // EAL expects it to be in.
@@ -56,13 +72,16 @@ fn parse_mb(src: &str) -> Result<i32, String> {
     let has_unit = src.trim_end().chars().any(|c| c.is_alphabetic());
 
     if let Ok(val) = Byte::from_str(src) {
-        let value;
-        if has_unit {
-            value = val.get_adjusted_unit(ByteUnit::MiB).get_value() as i32
+        let value = if has_unit {
+            val.get_adjusted_unit(ByteUnit::MiB).get_value()
         } else {
-            value = val.get_bytes() as i32
+            val.get_bytes() as f64
+        };
+        if value > f64::from(i32::MAX) {
+            Err(format!("Memory size {} is too large", src))
+        } else {
+            Ok(value as i32)
         }
-        Ok(value)
     } else {
         Err(format!("Invalid argument {}", src))
     }
@@ -82,6 +101,13 @@ pub struct MayastorCliArgs {
     #[structopt(short = "L")]
     /// Enable logging for sub components.
     pub log_components: Vec<String>,
+    #[structopt(
+        long = "log-format",
+        default_value = "text",
+        possible_values = &["text", "json"]
+    )]
+    /// Format of the log messages, json writes one object per line.
+    pub log_format: LogFormat,
     #[structopt(short = "m", default_value = "0x1")]
     /// The reactor mask to be used for starting up the instance
     pub reactor_mask: String,
@@ -112,7 +138,8 @@ pub struct MayastorCliArgs {
     /// Path to child status config file.
     pub child_status_config: Option<String>,
     #[structopt(long = "huge-dir")]
-    /// Path to hugedir.
+    /// Path to hugedir. EAL only uses the hugetlbfs mount at this path, when
+    /// omitted it uses all of them, such as separate 2MiB and 1GiB mounts.
     pub hugedir: Option<String>,
     #[structopt(long = "env-context")]
     /// Pass additional arguments to the EAL environment.
@@ -121,6 +148,10 @@ pub struct MayastorCliArgs {
     /// List of cores to run on instead of using the core mask. When specified
     /// it supersedes the core mask (-m) argument.
     pub core_list: Option<String>,
+    #[structopt(long = "shutdown-timeout", default_value = "30")]
+    /// Seconds to wait for a graceful shutdown, after which the reactors are
+    /// stopped regardless.
+    pub shutdown_timeout: u64,
 }
 
 /// Defaults are redefined here in case of using it during tests
@@ -136,10 +167,12 @@ impl Default for MayastorCliArgs {
             rpc_address: "/var/tmp/mayastor.sock".to_string(),
             no_pci: true,
             log_components: vec![],
+            log_format: LogFormat::Text,
             mayastor_config: None,
             child_status_config: None,
             hugedir: None,
             core_list: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT.as_secs(),
         }
     }
 }
@@ -181,10 +214,78 @@ pub enum EnvError {
     InitLog,
     #[snafu(display("Failed to initialize {} target", target))]
     InitTarget { target: String },
+    #[snafu(display("Invalid IP address: MY_POD_IP={}", addr))]
+    InvalidPodIp { addr: String },
+    #[snafu(display("Invalid core list {}", list))]
+    InvalidCoreList { list: String },
+    #[snafu(display("Invalid core mask {}", mask))]
+    InvalidCoreMask { mask: String },
+    #[snafu(display("Core {} not found, the host has {} cores", core, count))]
+    CoreNotFound { core: u32, count: u32 },
 }
 
 type Result<T, E = EnvError> = std::result::Result<T, E>;
 
+/// check that all cores are below the number of cores of the host
+fn check_cores(cores: Vec<u32>, count: u32) -> Result<Vec<u32>> {
+    match cores.iter().find(|core| **core >= count) {
+        Some(core) => Err(EnvError::CoreNotFound {
+            core: *core,
+            count,
+        }),
+        None => Ok(cores),
+    }
+}
+
+/// parse a core list as accepted by -l, such as "0-3,6", and check that the
+/// cores exist on a host with the given number of cores
+pub fn parse_core_list(list: &str, count: u32) -> Result<Vec<u32>> {
+    let invalid = || EnvError::InvalidCoreList {
+        list: list.into(),
+    };
+    let mut cores = Vec::new();
+
+    for range in list.split(',') {
+        let mut bounds = range.splitn(2, '-').map(|b| b.trim().parse::<u32>());
+        let first = bounds.next().unwrap().map_err(|_| invalid())?;
+        let last = match bounds.next() {
+            Some(last) => last.map_err(|_| invalid())?,
+            None => first,
+        };
+        if last < first {
+            return Err(invalid());
+        }
+        cores.extend(first ..= last);
+    }
+
+    check_cores(cores, count)
+}
+
+/// parse a hexadecimal core mask as accepted by -m, such as "0x3", and check
+/// that the cores exist on a host with the given number of cores
+pub fn parse_core_mask(mask: &str, count: u32) -> Result<Vec<u32>> {
+    let invalid = || EnvError::InvalidCoreMask {
+        mask: mask.into(),
+    };
+    let digits = mask.trim_start_matches("0x").trim_start_matches("0X");
+    let mut cores = Vec::new();
+
+    for (i, digit) in digits.chars().rev().enumerate() {
+        let value = digit.to_digit(16).ok_or_else(invalid)?;
+        for bit in 0 .. 4 {
+            if value & (1 << bit) != 0 {
+                cores.push(i as u32 * 4 + bit);
+            }
+        }
+    }
+
+    if cores.is_empty() {
+        return Err(invalid());
+    }
+
+    check_cores(cores, count)
+}
+
 /// Mayastor argument
 #[derive(Debug, Clone)]
 pub struct MayastorEnvironment {
@@ -218,6 +319,7 @@ pub struct MayastorEnvironment {
     unlink_hugepage: bool,
     log_component: Vec<String>,
     core_list: Option<String>,
+    shutdown_timeout: Duration,
 }
 
 impl Default for MayastorEnvironment {
@@ -253,19 +355,27 @@ impl Default for MayastorEnvironment {
             unlink_hugepage: true,
             log_component: vec![],
             core_list: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
         }
     }
 }
 
+/// stop all reactors, which makes mayastor exit with the given code
+fn reactors_stop(rc: i32) {
+    Reactors::iter().for_each(|r| r.shutdown());
+    *GLOBAL_RC.lock().unwrap() = rc;
+}
+
 /// The actual routine which does the mayastor shutdown.
 /// Must be called on the same thread which did the init.
 async fn do_shutdown(arg: *mut c_void) {
     // we must enter the init thread explicitly here as this, typically, gets
     // called by the signal handler
     // callback for when the subsystems have shutdown
-    extern "C" fn reactors_stop(arg: *mut c_void) {
-        Reactors::iter().for_each(|r| r.shutdown());
-        *GLOBAL_RC.lock().unwrap() = arg as i32;
+    extern "C" fn subsystem_fini_done(arg: *mut c_void) {
+        let sender = unsafe { Box::from_raw(arg as *mut oneshot::Sender<()>) };
+        // the shutdown may have timed out and stopped waiting for us
+        let _ = sender.send(());
     }
 
     let rc = arg as i32;
@@ -275,10 +385,30 @@ async fn do_shutdown(arg: *mut c_void) {
     }
 
     iscsi::fini();
-    nexus::nexus_children_to_destroying_state().await;
-    unsafe {
-        spdk_rpc_finish();
-        spdk_subsystem_fini(Some(reactors_stop), arg);
+
+    let stage = Cell::new("nexus children");
+    let shutdown = async {
+        nexus::nexus_children_to_destroying_state().await;
+        stage.set("subsystems");
+        let (s, r) = oneshot::channel::<()>();
+        unsafe {
+            spdk_rpc_finish();
+            spdk_subsystem_fini(Some(subsystem_fini_done), cb_arg(s));
+        }
+        let _ = r.await;
+    };
+
+    let timeout = MayastorEnvironment::global_or_default().shutdown_timeout;
+    match future::select(Box::pin(shutdown), Delay::new(timeout)).await {
+        Either::Left(_) => reactors_stop(rc),
+        Either::Right(_) => {
+            error!(
+                "Shutdown did not complete within {:?}, stuck on the {}",
+                timeout,
+                stage.get()
+            );
+            reactors_stop(if rc != 0 { rc } else { -1 });
+        }
     }
 }
 
@@ -311,12 +441,79 @@ extern "C" fn mayastor_signal_handler(signo: i32) {
 
     warn!("Received SIGNO: {}", signo);
     SIG_RECEIVED.store(true, SeqCst);
-    unsafe {
+    let rc = unsafe {
         spdk_thread_send_critical_msg(
             Mthread::get_init().into_raw(),
             Some(signal_trampoline),
-        );
+        )
     };
+    if rc != 0 {
+        // the message was not queued, allow the next signal to try again
+        error!("Failed to initiate shutdown on SIGNO {}: {}", signo, rc);
+        SIG_RECEIVED.store(false, SeqCst);
+    }
+}
+
+/// re-read the configuration file and report the sections that changed. None
+/// of them can be applied to a running instance, so these take effect once
+/// mayastor is restarted. This reads the file, so it must not be called on a
+/// reactor.
+fn reload_config() {
+    let file = match MayastorEnvironment::global_or_default().mayastor_config {
+        Some(file) => file,
+        None => {
+            warn!("No configuration file to reload");
+            return;
+        }
+    };
+
+    let content = match fs::read(&file) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("Failed to reload configuration file {}: {}", file, e);
+            return;
+        }
+    };
+
+    // the file may have been rewritten by mayastor itself, in which case it
+    // holds the current state rather than any change
+    if Config::is_file_content(&content) {
+        info!("Configuration file {} is unchanged", file);
+        return;
+    }
+
+    match Config::parse(&file, &content) {
+        Ok(config) => {
+            let changed = Config::get().changed_sections(&config);
+            if changed.is_empty() {
+                info!("Configuration file {} is unchanged", file);
+            } else {
+                warn!(
+                    "Configuration file {} changed {}, restart to apply",
+                    file,
+                    changed.join(", ")
+                );
+            }
+        }
+        Err(e) => {
+            error!("Failed to reload configuration file {}: {}", file, e)
+        }
+    }
+}
+
+/// wait for SIGHUP and reload the configuration file on each, from a thread
+/// of its own such that the file is not read on a reactor and the critical
+/// message of the init thread is left to the shutdown signals
+fn mayastor_reload_handler(signals: Signals) {
+    for signo in signals.forever() {
+        // there is no point in reloading when shutting down
+        if SIG_RECEIVED.load(SeqCst) {
+            continue;
+        }
+
+        info!("Received SIGNO: {}", signo);
+        reload_config();
+    }
 }
 
 #[derive(Debug)]
@@ -326,6 +523,43 @@ struct SubsystemCtx {
 }
 
 static MAYASTOR_DEFAULT_ENV: OnceCell<MayastorEnvironment> = OnceCell::new();
+
+/// file that holds the node id, it must be on a persistent filesystem
+pub const NODE_ID_FILE: &str = "/var/local/mayastor/node-id";
+const MACHINE_ID_FILE: &str = "/etc/machine-id";
+/// time given to a graceful shutdown before the reactors are stopped anyway
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+static NODE_ID: OnceCell<Uuid> = OnceCell::new();
+
+/// Return the node id persisted in `file`. When there is none yet, the id is
+/// derived from the machine id in `machine_id` or, failing that, generated,
+/// and then persisted such that it survives restarts.
+pub fn load_node_id(file: &Path, machine_id: &Path) -> Uuid {
+    if let Ok(id) = fs::read_to_string(file) {
+        match Uuid::parse_str(id.trim()) {
+            Ok(id) => return id,
+            Err(e) => warn!("Ignoring invalid node id in {:?}: {}", file, e),
+        }
+    }
+
+    let id = fs::read_to_string(machine_id)
+        .ok()
+        .and_then(|id| Uuid::parse_str(id.trim()).ok())
+        .unwrap_or_else(Uuid::new_v4);
+
+    let persisted = match file.parent() {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+    .and_then(|_| fs::write(file, id.to_string()));
+    if let Err(e) = persisted {
+        warn!("Failed to persist node id to {:?}: {}", file, e);
+    }
+
+    info!("Using node id {}", id);
+    id
+}
 impl MayastorEnvironment {
     pub fn new(args: MayastorCliArgs) -> Self {
         Self {
@@ -342,6 +576,7 @@ impl MayastorEnvironment {
             hugedir: args.hugedir,
             env_context: args.env_context,
             core_list: args.core_list,
+            shutdown_timeout: Duration::from_secs(args.shutdown_timeout),
             ..Default::default()
         }
         .setup_static()
@@ -361,6 +596,27 @@ impl MayastorEnvironment {
         }
     }
 
+    /// Stable id of this mayastor instance, unlike the node name it is
+    /// unique across clusters and does not change across restarts.
+    pub fn node_id() -> Uuid {
+        *NODE_ID.get_or_init(|| {
+            load_node_id(Path::new(NODE_ID_FILE), Path::new(MACHINE_ID_FILE))
+        })
+    }
+
+    /// Generate a random UUID whose first six bytes are taken from the node
+    /// id, such that UUIDs generated on different nodes never collide. The
+    /// version and variant fields follow them, so they do not overwrite any
+    /// part of the node id.
+    pub fn node_scoped_uuid() -> Uuid {
+        let mut bytes = *Uuid::new_v4().as_bytes();
+        bytes[.. 6].copy_from_slice(&Self::node_id().as_bytes()[.. 6]);
+        uuid::Builder::from_bytes(bytes)
+            .set_variant(uuid::Variant::RFC4122)
+            .set_version(uuid::Version::Random)
+            .build()
+    }
+
     /// configure signal handling
     fn install_signal_handlers(&self) {
         unsafe {
@@ -376,6 +632,21 @@ impl MayastorEnvironment {
             })
         }
         .unwrap();
+
+        let signals = Signals::new(&[signal_hook::SIGHUP]).unwrap();
+        Mthread::spawn_unaffinitized(move || mayastor_reload_handler(signals));
+    }
+
+    /// check that the cores to run on exist, as EAL fails without saying
+    /// which one does not
+    fn check_cores(&self) -> Result<()> {
+        let count = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) } as u32;
+        match &self.core_list {
+            // lcore to cpu mappings are left for EAL to check
+            Some(list) if list.contains(&['@', '('][..]) => Ok(()),
+            Some(list) => parse_core_list(list, count).map(|_| ()),
+            None => parse_core_mask(&self.reactor_mask, count).map(|_| ()),
+        }
     }
 
     /// construct an array of options to be passed to EAL and start it
@@ -411,14 +682,12 @@ impl MayastorEnvironment {
             args.push(CString::new("--single-file-segments").unwrap());
         }
 
-        if self.hugedir.is_some() {
-            args.push(
-                CString::new(format!(
-                    "--huge-dir={}",
-                    &self.hugedir.as_ref().unwrap().clone()
-                ))
-                .unwrap(),
-            )
+        if let Some(hugedir) = &self.hugedir {
+            // EAL only reports that no huge pages are available
+            if !Path::new(hugedir).is_dir() {
+                panic!("Huge page directory {} does not exist", hugedir);
+            }
+            args.push(CString::new(format!("--huge-dir={}", hugedir)).unwrap())
         }
 
         if cfg!(target_os = "linux") {
@@ -531,16 +800,26 @@ impl MayastorEnvironment {
     /// We implement our own default target init code here. Note that if there
     /// is an existing target we will fail the init process.
     extern "C" fn target_init() -> bool {
-        let address = MayastorEnvironment::get_pod_ip()
-            .map_err(|e| {
-                error!("Invalid IP address: MY_POD_IP={}", e);
+        let address = match MayastorEnvironment::get_pod_ip() {
+            Ok(address) => address,
+            Err(addr) => {
+                error!("{}", EnvError::InvalidPodIp {
+                    addr
+                });
                 mayastor_env_stop(-1);
-            })
-            .unwrap();
+                return false;
+            }
+        };
 
         let cfg = Config::get();
 
         if cfg.nexus_opts.iscsi_enable {
+            // IPv6 addresses are enclosed in brackets, both for the portal
+            // and the URIs of the targets, to tell them apart from the port
+            let address = match address {
+                IpAddr::V4(address) => address.to_string(),
+                IpAddr::V6(address) => format!("[{}]", address),
+            };
             if let Err(msg) = iscsi::init(&address) {
                 error!("Failed to initialize Mayastor iSCSI target: {}", msg);
                 return false;
@@ -550,19 +829,42 @@ impl MayastorEnvironment {
         true
     }
 
-    pub(crate) fn get_pod_ip() -> Result<String, String> {
+    /// returns the IP address set in MY_POD_IP, which may be either an IPv4
+    /// or an IPv6 address, or the raw value when it is neither. When MY_POD_IP
+    /// is not set, the first address the hostname resolves to is returned and
+    /// if that fails the loopback address. These are IPv6 addresses when
+    /// MY_POD_IP_FAMILY is set to v6 and IPv4 addresses otherwise.
+    pub fn get_pod_ip() -> Result<IpAddr, String> {
         match env::var("MY_POD_IP") {
-            Ok(val) => {
-                if val.parse::<Ipv4Addr>().is_ok() {
-                    Ok(val)
-                } else {
-                    Err(val)
-                }
+            Ok(val) => val.parse::<IpAddr>().map_err(|_| val),
+            Err(_) => {
+                let loopback = Self::loopback_ip();
+                Ok(Self::hostname_ip(loopback.is_ipv6()).unwrap_or(loopback))
             }
-            Err(_) => Ok("127.0.0.1".to_owned()),
         }
     }
 
+    /// returns the loopback address of the family given by MY_POD_IP_FAMILY,
+    /// which is IPv6 when set to v6 and IPv4 otherwise
+    pub(crate) fn loopback_ip() -> IpAddr {
+        match env::var("MY_POD_IP_FAMILY") {
+            Ok(family) if family == "v6" => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            _ => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }
+    }
+
+    /// resolve the hostname to its first IPv6 or IPv4 address
+    fn hostname_ip(ipv6: bool) -> Option<IpAddr> {
+        let mut buf = [0u8; 256];
+        let hostname =
+            nix::unistd::gethostname(&mut buf).ok()?.to_str().ok()?;
+        (hostname, 0)
+            .to_socket_addrs()
+            .ok()?
+            .find(|addr| addr.is_ipv6() == ipv6)
+            .map(|addr| addr.ip())
+    }
+
     /// start the JSON rpc server which listens only to a local path
     extern "C" fn start_rpc(rc: i32, arg: *mut c_void) {
         let ctx = unsafe { Box::from_raw(arg as *mut SubsystemCtx) };
@@ -588,7 +890,9 @@ impl MayastorEnvironment {
         let cfg = if let Some(yaml) = &self.mayastor_config {
             info!("loading YAML config file {}", yaml);
             Config::get_or_init(|| {
-                if let Ok(cfg) = Config::read(yaml) {
+                let content = fs::read(yaml).unwrap_or_default();
+                if let Ok(cfg) = Config::parse(yaml, &content) {
+                    Config::set_file_content(content);
                     cfg
                 } else {
                     // if the configuration is invalid exit early
@@ -604,8 +908,11 @@ impl MayastorEnvironment {
     // load the child status file
     fn load_child_status(&self) {
         ChildStatusConfig::get_or_init(|| {
-            if let Ok(cfg) = ChildStatusConfig::load(&self.child_status_config)
-            {
+            // the reactors are not running yet, so there is none to block
+            let cfg = futures::executor::block_on(
+                ChildStatusConfig::load_async(&self.child_status_config),
+            );
+            if let Ok(cfg) = cfg {
                 cfg
             } else {
                 // if the configuration is invalid exit early
@@ -626,6 +933,10 @@ impl MayastorEnvironment {
 
         self.load_child_status();
 
+        if let Err(e) = self.check_cores() {
+            panic!("{}", e);
+        }
+
         // bootstrap DPDK and its magic
         self.initialize_eal();
 
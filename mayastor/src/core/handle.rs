@@ -1,38 +1,89 @@
 use std::{
     convert::TryFrom,
     fmt::{Debug, Error, Formatter},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use futures::channel::oneshot;
+use futures_timer::Delay;
 use libc::c_void;
 use nix::errno::Errno;
 
 use spdk_sys::{
+    spdk_bdev_compare_blocks,
     spdk_bdev_desc,
+    spdk_bdev_flush_blocks,
     spdk_bdev_free_io,
+    spdk_bdev_get_io_stat,
     spdk_bdev_io,
+    spdk_bdev_io_get_nvme_status,
+    spdk_bdev_io_stat,
+    spdk_bdev_nvme_admin_passthru,
     spdk_bdev_nvme_admin_passthru_ro,
+    spdk_bdev_nvme_io_passthru,
     spdk_bdev_read,
     spdk_bdev_reset,
+    spdk_bdev_unmap_blocks,
     spdk_bdev_write,
+    spdk_bdev_write_zeroes_blocks,
     spdk_io_channel,
 };
 
 use crate::{
     core::{
         nvme_admin_opc,
+        nvme_nvm_opc,
         Bdev,
+        BdevStats,
         CoreError,
         Descriptor,
         DmaBuf,
         DmaError,
         IoChannel,
+        IoType,
+        NvmeReservation,
+        NvmeReservationAcquireAction,
+        NvmeReservationRegisterAction,
+        NvmeReservationReleaseAction,
     },
     ffihelper::cb_arg,
     subsys,
 };
 
+/// byte offset of the Optional NVM Command Support (ONCS) field within the
+/// identify controller data
+const IDENTIFY_ONCS_OFFSET: usize = 520;
+/// ONCS bit indicating support for the Copy command
+const ONCS_COPY: u16 = 1 << 8;
+/// size of a format 0 source range entry of the Copy command
+const COPY_RANGE_ENTRY_SIZE: usize = 32;
+/// the number of ranges is a zero based 8 bit field
+const COPY_MAX_RANGES: usize = 256;
+/// the number of blocks of a range is a zero based 16 bit field
+const COPY_MAX_RANGE_BLOCKS: u32 = 1 << 16;
+/// the largest amount of data we read at once when copying on the host
+const HOST_COPY_CHUNK_SIZE: u64 = 1 << 20;
+/// NVMe status code type of generic command status
+const SCT_GENERIC: i32 = 0x00;
+/// NVMe status code type of media and data integrity errors
+const SCT_MEDIA_ERROR: i32 = 0x02;
+/// NVMe status code of a command that conflicts with a reservation
+const SC_RESERVATION_CONFLICT: i32 = 0x83;
+/// NVMe status code of a Compare command that found the data to differ
+const SC_COMPARE_FAILURE: i32 = 0x85;
+
+/// NVMe status code type and status code of a failed IO
+type NvmeStatusCode = (i32, i32);
+/// number of times a read or write is resubmitted when SPDK runs out of
+/// bdev IOs, unless changed through ['BdevHandle::set_enomem_retries']
+const DEFAULT_ENOMEM_RETRIES: u32 = 10;
+/// time to back off before resubmitting an IO that failed with ENOMEM
+const ENOMEM_RETRY_DELAY: Duration = Duration::from_millis(1);
+
 /// A handle to a bdev, is an interface to submit IO. The ['Descriptor'] may be
 /// shared between cores freely. The ['IoChannel'] however, must be allocated on
 /// the core where the IO is submitted from.
@@ -41,6 +92,10 @@ pub struct BdevHandle {
     /// dropped before we close the descriptor
     channel: IoChannel,
     desc: Arc<Descriptor>,
+    /// when set, writes are rejected without being submitted to the bdev
+    write_protected: AtomicBool,
+    /// number of times a read or write is resubmitted on ENOMEM
+    enomem_retries: AtomicU32,
 }
 
 impl BdevHandle {
@@ -84,11 +139,53 @@ impl BdevHandle {
         self.desc.get_bdev()
     }
 
+    /// enable or disable write protection of this handle. While enabled, any
+    /// write issued through the handle fails with ['CoreError::WriteFailed']
+    /// without being submitted. Other handles to the same bdev are not
+    /// affected.
+    pub fn set_write_protect(&self, enable: bool) -> Result<(), CoreError> {
+        debug!(
+            "{}: write protection {}",
+            self.get_bdev().name(),
+            if enable { "enabled" } else { "disabled" }
+        );
+        self.write_protected.store(enable, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// returns true when writes through this handle are rejected
+    pub fn is_write_protected(&self) -> bool {
+        self.write_protected.load(Ordering::SeqCst)
+    }
+
+    /// set the number of times a read or write is resubmitted when SPDK has
+    /// no bdev IO left to submit it with. Once the retries are exhausted the
+    /// IO fails with ['CoreError::ReadFailed'] or ['CoreError::WriteFailed'].
+    /// A value of zero fails such IO right away.
+    pub fn set_enomem_retries(&self, retries: u32) {
+        self.enomem_retries.store(retries, Ordering::SeqCst);
+    }
+
     /// return a tuple to be used directly for read/write operations
     pub fn io_tuple(&self) -> (*mut spdk_bdev_desc, *mut spdk_io_channel) {
         (self.desc.as_ptr(), self.channel.as_ptr())
     }
 
+    /// returns the IO statistics of the channel of this handle. Channels are
+    /// per core and shared by all handles to the bdev on that core, so this
+    /// covers their IO too, see ['Bdev::stats'] for the totals of all cores.
+    pub fn io_stats(&self) -> BdevStats {
+        let mut stat = spdk_bdev_io_stat::default();
+        unsafe {
+            spdk_bdev_get_io_stat(
+                self.get_bdev().as_ptr(),
+                self.channel.as_ptr(),
+                &mut stat,
+            )
+        };
+        BdevStats::from(&stat)
+    }
+
     /// Allocate memory from the memory pool (the mem is zeroed out)
     /// with given size and proper alignment for the bdev.
     pub fn dma_malloc(&self, size: u64) -> Result<DmaBuf, DmaError> {
@@ -114,6 +211,110 @@ impl BdevHandle {
         sender.send(success).expect("io completion error");
     }
 
+    /// io completion callback that sends back the NVMe status code type and
+    /// status code of a failed IO, so that callers can tell specific failures
+    /// apart
+    extern "C" fn nvme_status_completion_cb(
+        io: *mut spdk_bdev_io,
+        success: bool,
+        arg: *mut c_void,
+    ) {
+        let sender = unsafe {
+            Box::from_raw(
+                arg as *const _
+                    as *mut oneshot::Sender<Result<(), NvmeStatusCode>>,
+            )
+        };
+
+        let status = if success {
+            Ok(())
+        } else {
+            let mut cdw0: u32 = 0;
+            let mut sct: i32 = 0;
+            let mut sc: i32 = 0;
+            unsafe {
+                spdk_bdev_io_get_nvme_status(io, &mut cdw0, &mut sct, &mut sc)
+            };
+            Err((sct, sc))
+        };
+
+        unsafe {
+            spdk_bdev_free_io(io);
+        }
+
+        sender.send(status).expect("io completion error");
+    }
+
+    /// submit an IO using the given function, which is passed the callback
+    /// argument to hand to SPDK, and return the receiving end of the channel
+    /// the completion is sent on. Submission is retried, after a short delay,
+    /// for as long as SPDK returns ENOMEM and retries are left. When the IO is
+    /// not submitted, the errno is returned.
+    async fn submit_io<F>(
+        &self,
+        submit: F,
+    ) -> Result<oneshot::Receiver<bool>, i32>
+    where
+        F: Fn(*mut c_void) -> i32,
+    {
+        let mut retries = self.enomem_retries.load(Ordering::SeqCst);
+        loop {
+            let (s, r) = oneshot::channel::<bool>();
+            let arg = cb_arg(s);
+            let errno = submit(arg);
+            if errno == 0 {
+                return Ok(r);
+            }
+
+            // the callback will never be called so reclaim its argument
+            drop(unsafe { Box::from_raw(arg as *mut oneshot::Sender<bool>) });
+
+            if errno != -libc::ENOMEM || retries == 0 {
+                return Err(errno);
+            }
+
+            retries -= 1;
+            Delay::new(ENOMEM_RETRY_DELAY).await;
+        }
+    }
+
+    /// io completion callback that sends back completion queue entry dword 0
+    /// of a successful NVMe command, which holds command specific results
+    extern "C" fn nvme_cdw0_completion_cb(
+        io: *mut spdk_bdev_io,
+        success: bool,
+        arg: *mut c_void,
+    ) {
+        let sender = unsafe {
+            Box::from_raw(arg as *const _ as *mut oneshot::Sender<Option<u32>>)
+        };
+
+        let cdw0 = if success {
+            let mut cdw0: u32 = 0;
+            let mut sct: i32 = 0;
+            let mut sc: i32 = 0;
+            unsafe {
+                spdk_bdev_io_get_nvme_status(io, &mut cdw0, &mut sct, &mut sc)
+            };
+            Some(cdw0)
+        } else {
+            None
+        };
+
+        unsafe {
+            spdk_bdev_free_io(io);
+        }
+
+        sender.send(cdw0).expect("io completion error");
+    }
+
+    /// returns true if the IO was cancelled through its token
+    fn is_cancelled(cancel: &Option<Arc<AtomicBool>>) -> bool {
+        cancel
+            .as_ref()
+            .map_or(false, |token| token.load(Ordering::SeqCst))
+    }
+
     /// write the ['DmaBuf'] to the given offset. This function is implemented
     /// using a ['Future'] and is not intended for non-internal IO.
     pub async fn write_at(
@@ -121,28 +322,71 @@ impl BdevHandle {
         offset: u64,
         buffer: &DmaBuf,
     ) -> Result<usize, CoreError> {
-        let (s, r) = oneshot::channel::<bool>();
-        let errno = unsafe {
-            spdk_bdev_write(
-                self.desc.as_ptr(),
-                self.channel.as_ptr(),
-                **buffer,
+        self.write_at_cancellable(offset, buffer, None).await
+    }
+
+    /// write the ['DmaBuf'] to the given offset, see write_at. When the cancel
+    /// token is set by the time the IO completes, the write fails with
+    /// ['CoreError::IoCancelled'] whatever the outcome of the IO, which
+    /// allows to abandon IO cooperatively without aborting it.
+    pub async fn write_at_cancellable(
+        &self,
+        offset: u64,
+        buffer: &DmaBuf,
+        cancel: Option<Arc<AtomicBool>>,
+    ) -> Result<usize, CoreError> {
+        if self.is_write_protected() {
+            return Err(CoreError::WriteFailed {
                 offset,
-                buffer.len() as u64,
-                Some(Self::io_completion_cb),
-                cb_arg(s),
-            )
-        };
+                len: buffer.len(),
+            });
+        }
 
-        if errno != 0 {
+        if buffer.len() > self.get_bdev().max_transfer_size() {
             return Err(CoreError::WriteDispatch {
-                source: Errno::from_i32(errno.abs()),
+                source: Errno::E2BIG,
                 offset,
                 len: buffer.len(),
             });
         }
 
-        if r.await.expect("Failed awaiting write IO") {
+        let r = match self
+            .submit_io(|arg| unsafe {
+                spdk_bdev_write(
+                    self.desc.as_ptr(),
+                    self.channel.as_ptr(),
+                    **buffer,
+                    offset,
+                    buffer.len() as u64,
+                    Some(Self::io_completion_cb),
+                    arg,
+                )
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(errno) if errno == -libc::ENOMEM => {
+                return Err(CoreError::WriteFailed {
+                    offset,
+                    len: buffer.len(),
+                });
+            }
+            Err(errno) => {
+                return Err(CoreError::WriteDispatch {
+                    source: Errno::from_i32(errno.abs()),
+                    offset,
+                    len: buffer.len(),
+                });
+            }
+        };
+
+        let success = r.await.expect("Failed awaiting write IO");
+        if Self::is_cancelled(&cancel) {
+            Err(CoreError::IoCancelled {
+                offset,
+                len: buffer.len(),
+            })
+        } else if success {
             Ok(buffer.len() as usize)
         } else {
             Err(CoreError::WriteFailed {
@@ -158,37 +402,249 @@ impl BdevHandle {
         offset: u64,
         buffer: &mut DmaBuf,
     ) -> Result<u64, CoreError> {
+        self.read_at_cancellable(offset, buffer, None).await
+    }
+
+    /// read at given offset into the ['DmaBuf'], see read_at. When the cancel
+    /// token is set by the time the IO completes, the read fails with
+    /// ['CoreError::IoCancelled'] whatever the outcome of the IO.
+    pub async fn read_at_cancellable(
+        &self,
+        offset: u64,
+        buffer: &mut DmaBuf,
+        cancel: Option<Arc<AtomicBool>>,
+    ) -> Result<u64, CoreError> {
+        if buffer.len() > self.get_bdev().max_transfer_size() {
+            return Err(CoreError::ReadDispatch {
+                source: Errno::E2BIG,
+                offset,
+                len: buffer.len(),
+            });
+        }
+
+        let r = match self
+            .submit_io(|arg| unsafe {
+                spdk_bdev_read(
+                    self.desc.as_ptr(),
+                    self.channel.as_ptr(),
+                    **buffer,
+                    offset,
+                    buffer.len() as u64,
+                    Some(Self::io_completion_cb),
+                    arg,
+                )
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(errno) if errno == -libc::ENOMEM => {
+                return Err(CoreError::ReadFailed {
+                    offset,
+                    len: buffer.len(),
+                });
+            }
+            Err(errno) => {
+                return Err(CoreError::ReadDispatch {
+                    source: Errno::from_i32(errno.abs()),
+                    offset,
+                    len: buffer.len(),
+                });
+            }
+        };
+
+        let success = r.await.expect("Failed awaiting read IO");
+        if Self::is_cancelled(&cancel) {
+            Err(CoreError::IoCancelled {
+                offset,
+                len: buffer.len(),
+            })
+        } else if success {
+            Ok(buffer.len())
+        } else {
+            Err(CoreError::ReadFailed {
+                offset,
+                len: buffer.len(),
+            })
+        }
+    }
+
+    /// unmap the given range of blocks
+    pub async fn unmap_blocks(
+        &self,
+        offset_blocks: u64,
+        num_blocks: u64,
+    ) -> Result<(), CoreError> {
+        if self.is_write_protected() {
+            let block_len = u64::from(self.get_bdev().block_len());
+            return Err(CoreError::WriteFailed {
+                offset: offset_blocks * block_len,
+                len: num_blocks * block_len,
+            });
+        }
+
         let (s, r) = oneshot::channel::<bool>();
         let errno = unsafe {
-            spdk_bdev_read(
+            spdk_bdev_unmap_blocks(
+                self.desc.as_ptr(),
+                self.channel.as_ptr(),
+                offset_blocks,
+                num_blocks,
+                Some(Self::io_completion_cb),
+                cb_arg(s),
+            )
+        };
+
+        if errno != 0 {
+            return Err(CoreError::UnmapDispatch {
+                source: Errno::from_i32(errno.abs()),
+                offset: offset_blocks,
+                len: num_blocks,
+            });
+        }
+
+        if r.await.expect("Failed awaiting unmap IO") {
+            Ok(())
+        } else {
+            Err(CoreError::UnmapFailed {
+                offset: offset_blocks,
+                len: num_blocks,
+            })
+        }
+    }
+
+    /// compare the blocks starting at offset_blocks with the ['DmaBuf'],
+    /// which must hold a whole number of blocks. The data is compared by the
+    /// device when it supports the NVMe Compare command, otherwise SPDK reads
+    /// it back and compares it on the host. A difference is reported as
+    /// ['CoreError::CompareMismatch'].
+    pub async fn compare_blocks(
+        &self,
+        offset_blocks: u64,
+        buffer: &DmaBuf,
+    ) -> Result<(), CoreError> {
+        let block_len = u64::from(self.get_bdev().block_len());
+        if buffer.len() == 0 || buffer.len() % block_len != 0 {
+            return Err(CoreError::InvalidArgument {
+                reason: format!(
+                    "compare buffer of {} bytes is not a multiple of the block \
+                     size {}",
+                    buffer.len(),
+                    block_len
+                ),
+            });
+        }
+        let num_blocks = buffer.len() / block_len;
+
+        let (s, r) = oneshot::channel::<Result<(), NvmeStatusCode>>();
+        let errno = unsafe {
+            spdk_bdev_compare_blocks(
                 self.desc.as_ptr(),
                 self.channel.as_ptr(),
                 **buffer,
-                offset,
-                buffer.len() as u64,
+                offset_blocks,
+                num_blocks,
+                Some(Self::nvme_status_completion_cb),
+                cb_arg(s),
+            )
+        };
+
+        if errno != 0 {
+            return Err(CoreError::CompareDispatch {
+                source: Errno::from_i32(errno.abs()),
+                offset: offset_blocks,
+                len: num_blocks,
+            });
+        }
+
+        match r.await.expect("Failed awaiting compare IO") {
+            Ok(()) => Ok(()),
+            Err((SCT_MEDIA_ERROR, SC_COMPARE_FAILURE)) => {
+                Err(CoreError::CompareMismatch {
+                    offset: offset_blocks,
+                    len: num_blocks,
+                })
+            }
+            Err(_) => Err(CoreError::CompareFailed {
+                offset: offset_blocks,
+                len: num_blocks,
+            }),
+        }
+    }
+
+    /// zero the given range of blocks. Unlike an unmap, which a device may
+    /// treat as a hint, the blocks are guaranteed to read back as zeroes. If
+    /// the bdev has no native support, SPDK writes zeroed buffers instead.
+    pub async fn write_zeroes(
+        &self,
+        offset_blocks: u64,
+        num_blocks: u64,
+    ) -> Result<(), CoreError> {
+        if self.is_write_protected() {
+            let block_len = u64::from(self.get_bdev().block_len());
+            return Err(CoreError::WriteFailed {
+                offset: offset_blocks * block_len,
+                len: num_blocks * block_len,
+            });
+        }
+
+        let (s, r) = oneshot::channel::<bool>();
+        let errno = unsafe {
+            spdk_bdev_write_zeroes_blocks(
+                self.desc.as_ptr(),
+                self.channel.as_ptr(),
+                offset_blocks,
+                num_blocks,
                 Some(Self::io_completion_cb),
                 cb_arg(s),
             )
         };
 
         if errno != 0 {
-            return Err(CoreError::ReadDispatch {
+            return Err(CoreError::WriteZeroesDispatch {
                 source: Errno::from_i32(errno.abs()),
-                offset,
-                len: buffer.len(),
+                offset: offset_blocks,
+                len: num_blocks,
             });
         }
 
-        if r.await.expect("Failed awaiting read IO") {
-            Ok(buffer.len())
+        if r.await.expect("Failed awaiting write zeroes IO") {
+            Ok(())
         } else {
-            Err(CoreError::ReadFailed {
-                offset,
-                len: buffer.len(),
+            Err(CoreError::WriteZeroesFailed {
+                offset: offset_blocks,
+                len: num_blocks,
             })
         }
     }
 
+    /// flush the whole bdev, so that any data held in a volatile write cache
+    /// of the device is made durable
+    pub async fn flush(&self) -> Result<(), CoreError> {
+        let (s, r) = oneshot::channel::<bool>();
+        let errno = unsafe {
+            spdk_bdev_flush_blocks(
+                self.desc.as_ptr(),
+                self.channel.as_ptr(),
+                0,
+                self.get_bdev().num_blocks(),
+                Some(Self::io_completion_cb),
+                cb_arg(s),
+            )
+        };
+
+        if errno != 0 {
+            return Err(CoreError::FlushDispatch {
+                source: Errno::from_i32(errno.abs()),
+            });
+        }
+
+        if r.await.expect("Failed awaiting flush IO") {
+            Ok(())
+        } else {
+            Err(CoreError::FlushFailed {})
+        }
+    }
+
     pub async fn reset(&self) -> Result<usize, CoreError> {
         let (s, r) = oneshot::channel::<bool>();
         let errno = unsafe {
@@ -238,6 +694,473 @@ impl BdevHandle {
         self.nvme_admin(&cmd, Some(&mut buffer)).await
     }
 
+    /// read the log page with the given identifier into the buffer, for
+    /// example 0x02 for the SMART / health information. The page is returned
+    /// raw for the caller to parse, its size is given by the buffer.
+    pub async fn nvme_get_log_page(
+        &self,
+        log_id: u8,
+        buffer: &mut DmaBuf,
+    ) -> Result<(), CoreError> {
+        if buffer.len() == 0 || buffer.len() % 4 != 0 {
+            return Err(CoreError::InvalidArgument {
+                reason: format!(
+                    "log page buffer of {} bytes is not a multiple of dwords",
+                    buffer.len()
+                ),
+            });
+        }
+
+        // number of dwords to transfer, zero based
+        let numd = (buffer.len() / 4 - 1) as u32;
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_admin_opc::GET_LOG_PAGE.into());
+        cmd.nsid = 0xffffffff;
+        unsafe {
+            *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) =
+                u32::from(log_id) | ((numd & 0xffff) << 16);
+            *spdk_sys::nvme_cmd_cdw11_get(&mut cmd) = numd >> 16;
+        }
+        self.nvme_admin(&cmd, Some(buffer)).await
+    }
+
+    /// download a firmware image to the controller, starting at the given
+    /// offset (in dwords) into the image. The image is sent in chunks with
+    /// one Firmware Image Download command each, no larger than the maximum
+    /// transfer size of the controller, see nvme_firmware_commit to activate
+    /// it. Read only or write protected handles are rejected with
+    /// ['CoreError::NvmeWriteProtected'].
+    pub async fn nvme_firmware_download(
+        &self,
+        image: &[u8],
+        offset_dwords: u32,
+    ) -> Result<(), CoreError> {
+        if image.is_empty() || image.len() % 4 != 0 {
+            return Err(CoreError::InvalidArgument {
+                reason: format!(
+                    "firmware image size {} is not a non-zero multiple of 4",
+                    image.len()
+                ),
+            });
+        }
+
+        // every chunk must be a whole number of dwords and fit in a single
+        // transfer
+        let chunk_size = subsys::Config::get()
+            .nvme_bdev_opts
+            .firmware_chunk_size
+            .min(self.get_bdev().max_transfer_size())
+            .max(4)
+            & !3;
+        let mut offset = offset_dwords;
+
+        for chunk in image.chunks(chunk_size as usize) {
+            let mut buf = self
+                .dma_malloc(chunk.len() as u64)
+                .map_err(|source| CoreError::DmaAllocation {
+                    source,
+                })?;
+            buf.as_mut_slice().copy_from_slice(chunk);
+
+            let dwords = (chunk.len() / 4) as u32;
+            let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+            cmd.set_opc(nvme_admin_opc::FIRMWARE_IMAGE_DOWNLOAD.into());
+            unsafe {
+                // number of dwords, 0's based
+                *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) = dwords - 1;
+                *spdk_sys::nvme_cmd_cdw11_get(&mut cmd) = offset;
+            }
+            self.nvme_admin_write(&cmd, Some(&mut buf)).await?;
+            offset += dwords;
+        }
+
+        Ok(())
+    }
+
+    /// commit a downloaded firmware image to the given slot, the action is
+    /// one of the Commit Action values of the NVMe spec:
+    /// 0 (replace the image in the slot, activate on the next reset),
+    /// 1 (replace the image in the slot and activate it without a reset),
+    /// 2 (activate the existing image in the slot on the next reset),
+    /// 3 (activate the image in the slot without a reset or download)
+    /// Read only or write protected handles are rejected with
+    /// ['CoreError::NvmeWriteProtected'].
+    pub async fn nvme_firmware_commit(
+        &self,
+        slot: u8,
+        action: u8,
+    ) -> Result<(), CoreError> {
+        if slot > 7 || action > 3 {
+            return Err(CoreError::InvalidArgument {
+                reason: format!(
+                    "invalid firmware slot {} or commit action {}",
+                    slot, action
+                ),
+            });
+        }
+
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_admin_opc::FIRMWARE_COMMIT.into());
+        unsafe {
+            *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) =
+                u32::from(slot) | (u32::from(action) << 3);
+        }
+        self.nvme_admin_write(&cmd, None)
+            .await
+            .map_err(|error| match error {
+                CoreError::NvmeWriteProtected {
+                    ..
+                } => error,
+                _ => CoreError::FirmwareUpdateFailed {
+                    slot,
+                    reason: error.to_string(),
+                },
+            })
+    }
+
+    /// copy the given source ranges, each a tuple of (start_lba, num_blocks),
+    /// back to back to dest_lba and return the number of blocks copied.
+    /// When the controller supports the NVMe Simple Copy command the data
+    /// never leaves the controller, otherwise it is read and written back by
+    /// the host.
+    pub async fn nvme_copy(
+        &self,
+        dest_lba: u64,
+        ranges: &[(u64, u32)],
+    ) -> Result<u64, CoreError> {
+        if self.is_write_protected() {
+            let num_blocks: u64 = ranges.iter().map(|(_, n)| *n as u64).sum();
+            let block_len = self.get_bdev().block_len() as u64;
+            return Err(CoreError::WriteFailed {
+                offset: dest_lba * block_len,
+                len: num_blocks * block_len,
+            });
+        }
+
+        if self.nvme_copy_supported().await {
+            self.nvme_simple_copy(dest_lba, ranges).await
+        } else {
+            debug!(
+                "{}: simple copy not supported, copying on the host",
+                self.get_bdev().name()
+            );
+            self.host_copy(dest_lba, ranges).await
+        }
+    }
+
+    /// check the ONCS field of the identify controller data for Copy support
+    async fn nvme_copy_supported(&self) -> bool {
+        let bdev = self.get_bdev();
+        if !bdev.io_type_supported(IoType::NvmeAdmin)
+            || !bdev.io_type_supported(IoType::NvmeIo)
+        {
+            return false;
+        }
+
+        let mut buf = match self.dma_malloc(4096) {
+            Ok(buf) => buf,
+            Err(_) => return false,
+        };
+
+        if self.nvme_identify_ctrlr(&mut buf).await.is_err() {
+            return false;
+        }
+
+        let oncs = &buf.as_slice()
+            [IDENTIFY_ONCS_OFFSET .. IDENTIFY_ONCS_OFFSET + 2];
+        u16::from_le_bytes([oncs[0], oncs[1]]) & ONCS_COPY != 0
+    }
+
+    /// issue one or more NVMe Copy commands, splitting the ranges such that
+    /// they fit within the limits of the source range entries
+    async fn nvme_simple_copy(
+        &self,
+        dest_lba: u64,
+        ranges: &[(u64, u32)],
+    ) -> Result<u64, CoreError> {
+        let mut entries = Vec::new();
+        for &(mut lba, mut num_blocks) in ranges {
+            while num_blocks > 0 {
+                let n = num_blocks.min(COPY_MAX_RANGE_BLOCKS);
+                entries.push((lba, n));
+                lba += n as u64;
+                num_blocks -= n;
+            }
+        }
+
+        let mut copied = 0;
+        for chunk in entries.chunks(COPY_MAX_RANGES) {
+            let mut buf = self
+                .dma_malloc((chunk.len() * COPY_RANGE_ENTRY_SIZE) as u64)
+                .map_err(|source| CoreError::DmaAllocation {
+                    source,
+                })?;
+
+            for (i, (lba, n)) in chunk.iter().enumerate() {
+                let start = i * COPY_RANGE_ENTRY_SIZE;
+                let entry = &mut buf.as_mut_slice()
+                    [start .. start + COPY_RANGE_ENTRY_SIZE];
+                entry[8 .. 16].copy_from_slice(&lba.to_le_bytes());
+                entry[16 .. 18]
+                    .copy_from_slice(&((n - 1) as u16).to_le_bytes());
+            }
+
+            let dest = dest_lba + copied;
+            let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+            cmd.set_opc(nvme_nvm_opc::COPY.into());
+            unsafe {
+                *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) = dest as u32;
+                *spdk_sys::nvme_cmd_cdw11_get(&mut cmd) = (dest >> 32) as u32;
+                // number of ranges (zero based), descriptor format 0
+                *spdk_sys::nvme_cmd_cdw12_get(&mut cmd) =
+                    (chunk.len() - 1) as u32;
+            }
+
+            self.nvme_io_passthru(&cmd, &mut buf).await?;
+            copied += chunk.iter().map(|(_, n)| *n as u64).sum::<u64>();
+        }
+
+        Ok(copied)
+    }
+
+    /// copy the ranges by reading them into memory and writing them out again
+    async fn host_copy(
+        &self,
+        dest_lba: u64,
+        ranges: &[(u64, u32)],
+    ) -> Result<u64, CoreError> {
+        let bdev = self.get_bdev();
+        let block_len = bdev.block_len() as u64;
+        let chunk_size = HOST_COPY_CHUNK_SIZE.min(bdev.max_transfer_size());
+        let max_blocks = (chunk_size / block_len).max(1);
+
+        let mut copied = 0;
+        for &(lba, num_blocks) in ranges {
+            let mut done = 0;
+            while done < num_blocks as u64 {
+                let n = (num_blocks as u64 - done).min(max_blocks);
+                let mut buf = self.dma_malloc(n * block_len).map_err(
+                    |source| CoreError::DmaAllocation {
+                        source,
+                    },
+                )?;
+                self.read_at((lba + done) * block_len, &mut buf).await?;
+                self.write_at((dest_lba + copied + done) * block_len, &buf)
+                    .await?;
+                done += n;
+            }
+            copied += num_blocks as u64;
+        }
+
+        Ok(copied)
+    }
+
+    /// returns true if commands that write can be sent through this handle
+    fn writes_allowed(&self) -> bool {
+        self.desc.is_writable() && !self.is_write_protected()
+    }
+
+    /// sends the specified NVMe IO command with the given data buffer.
+    /// Commands that may write are rejected with
+    /// ['CoreError::NvmeWriteProtected'] when the handle is read only or
+    /// write protected.
+    pub async fn nvme_io_passthru(
+        &self,
+        nvme_cmd: &spdk_sys::spdk_nvme_cmd,
+        buffer: &mut DmaBuf,
+    ) -> Result<(), CoreError> {
+        if !self.writes_allowed()
+            && nvme_nvm_opc::is_write(nvme_cmd.opc() as u8)
+        {
+            return Err(CoreError::NvmeWriteProtected {
+                opcode: nvme_cmd.opc(),
+            });
+        }
+
+        trace!("Sending nvme_io {}", nvme_cmd.opc());
+        let (s, r) = oneshot::channel::<bool>();
+        let errno = unsafe {
+            spdk_bdev_nvme_io_passthru(
+                self.desc.as_ptr(),
+                self.channel.as_ptr(),
+                &*nvme_cmd,
+                **buffer,
+                buffer.len(),
+                Some(Self::io_completion_cb),
+                cb_arg(s),
+            )
+        };
+
+        if errno != 0 {
+            return Err(CoreError::NvmeIoPassthruDispatch {
+                source: Errno::from_i32(errno.abs()),
+                opcode: (*nvme_cmd).opc(),
+            });
+        }
+
+        if r.await.expect("Failed awaiting NVMe IO") {
+            Ok(())
+        } else {
+            Err(CoreError::NvmeIoPassthruFailed {
+                opcode: (*nvme_cmd).opc(),
+            })
+        }
+    }
+
+    /// register, unregister or replace a reservation key on the namespace.
+    /// Both keys are sent, new_key is ignored when unregistering.
+    pub async fn reservation_register(
+        &self,
+        current_key: u64,
+        new_key: u64,
+        action: NvmeReservationRegisterAction,
+    ) -> Result<(), CoreError> {
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_nvm_opc::RESERVATION_REGISTER.into());
+        unsafe { *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) = action as u32 };
+        self.nvme_reservation(&cmd, &[current_key, new_key]).await
+    }
+
+    /// acquire a reservation of the given type on the namespace, or preempt
+    /// the reservation held under preempt_key
+    pub async fn reservation_acquire(
+        &self,
+        current_key: u64,
+        preempt_key: u64,
+        rtype: NvmeReservation,
+        action: NvmeReservationAcquireAction,
+    ) -> Result<(), CoreError> {
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_nvm_opc::RESERVATION_ACQUIRE.into());
+        unsafe {
+            *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) =
+                action as u32 | ((rtype as u32) << 8);
+        }
+        self.nvme_reservation(&cmd, &[current_key, preempt_key])
+            .await
+    }
+
+    /// release the reservation of the given type held on the namespace, or
+    /// clear all reservations and registrations
+    pub async fn reservation_release(
+        &self,
+        current_key: u64,
+        rtype: NvmeReservation,
+        action: NvmeReservationReleaseAction,
+    ) -> Result<(), CoreError> {
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_nvm_opc::RESERVATION_RELEASE.into());
+        unsafe {
+            *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) =
+                action as u32 | ((rtype as u32) << 8);
+        }
+        self.nvme_reservation(&cmd, &[current_key]).await
+    }
+
+    /// sends a reservation command with the given keys as data, a
+    /// reservation conflict is reported as ['CoreError::ReservationConflict']
+    async fn nvme_reservation(
+        &self,
+        nvme_cmd: &spdk_sys::spdk_nvme_cmd,
+        keys: &[u64],
+    ) -> Result<(), CoreError> {
+        let opcode = nvme_cmd.opc();
+        let mut buffer = self
+            .dma_malloc(keys.len() as u64 * 8)
+            .map_err(|source| CoreError::DmaAllocation {
+                source,
+            })?;
+        for (key, data) in keys.iter().zip(buffer.as_mut_slice().chunks_mut(8))
+        {
+            data.copy_from_slice(&key.to_le_bytes());
+        }
+
+        trace!("Sending nvme reservation {:x}h", opcode);
+        let (s, r) = oneshot::channel::<Result<(), NvmeStatusCode>>();
+        let errno = unsafe {
+            spdk_bdev_nvme_io_passthru(
+                self.desc.as_ptr(),
+                self.channel.as_ptr(),
+                &*nvme_cmd,
+                *buffer,
+                buffer.len(),
+                Some(Self::nvme_status_completion_cb),
+                cb_arg(s),
+            )
+        };
+
+        if errno != 0 {
+            return Err(CoreError::NvmeIoPassthruDispatch {
+                source: Errno::from_i32(errno.abs()),
+                opcode,
+            });
+        }
+
+        match r.await.expect("Failed awaiting NVMe reservation IO") {
+            Ok(()) => Ok(()),
+            Err((SCT_GENERIC, SC_RESERVATION_CONFLICT)) => {
+                Err(CoreError::ReservationConflict {
+                    opcode,
+                })
+            }
+            Err(_) => Err(CoreError::NvmeIoPassthruFailed {
+                opcode,
+            }),
+        }
+    }
+
+    /// abort the command with the given identifier on the submission queue
+    /// with the given identifier. Returns true when the controller aborted
+    /// the command, and false when it did not, for instance because it
+    /// completed already. The abort itself failing is an error.
+    pub async fn nvme_abort(
+        &self,
+        sqid: u16,
+        cid: u16,
+    ) -> Result<bool, CoreError> {
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_admin_opc::ABORT.into());
+        unsafe {
+            *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) =
+                u32::from(sqid) | (u32::from(cid) << 16);
+        }
+
+        let cdw0 = self.nvme_admin_cdw0(&cmd, None).await?;
+        // bit 0 of dword 0 is cleared when the command was aborted
+        Ok(cdw0 & 1 == 0)
+    }
+
+    /// get the current value of the feature with the given identifier, as
+    /// returned in dword 0 of the completion
+    pub async fn nvme_get_feature(
+        &self,
+        feature_id: u8,
+    ) -> Result<u32, CoreError> {
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_admin_opc::GET_FEATURES.into());
+        unsafe {
+            // select the current value of the feature
+            *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) = u32::from(feature_id);
+        }
+        self.nvme_admin_cdw0(&cmd, None).await
+    }
+
+    /// set the feature with the given identifier, the meaning of cdw11
+    /// depends on the feature. The value is not saved across power cycles.
+    pub async fn nvme_set_feature(
+        &self,
+        feature_id: u8,
+        cdw11: u32,
+    ) -> Result<(), CoreError> {
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(nvme_admin_opc::SET_FEATURES.into());
+        unsafe {
+            *spdk_sys::nvme_cmd_cdw10_get(&mut cmd) = u32::from(feature_id);
+            *spdk_sys::nvme_cmd_cdw11_get(&mut cmd) = cdw11;
+        }
+        self.nvme_admin_write(&cmd, None).await
+    }
+
     /// sends an NVMe Admin command, only for read commands without buffer
     pub async fn nvme_admin_custom(&self, opcode: u8) -> Result<(), CoreError> {
         let mut cmd = spdk_sys::spdk_nvme_cmd::default();
@@ -251,12 +1174,58 @@ impl BdevHandle {
         nvme_cmd: &spdk_sys::spdk_nvme_cmd,
         buffer: Option<&mut DmaBuf>,
     ) -> Result<(), CoreError> {
+        self.nvme_admin_cdw0(nvme_cmd, buffer).await.map(|_| ())
+    }
+
+    /// sends the specified NVMe Admin command, only read commands, and
+    /// returns dword 0 of its completion
+    pub async fn nvme_admin_cdw0(
+        &self,
+        nvme_cmd: &spdk_sys::spdk_nvme_cmd,
+        buffer: Option<&mut DmaBuf>,
+    ) -> Result<u32, CoreError> {
+        self.nvme_admin_passthru(nvme_cmd, buffer, false).await
+    }
+
+    /// sends the specified NVMe Admin command which changes the state of the
+    /// controller or namespace. The command is rejected with
+    /// ['CoreError::NvmeWriteProtected'] when the handle is read only or
+    /// write protected.
+    async fn nvme_admin_write(
+        &self,
+        nvme_cmd: &spdk_sys::spdk_nvme_cmd,
+        buffer: Option<&mut DmaBuf>,
+    ) -> Result<(), CoreError> {
+        if !self.writes_allowed() {
+            return Err(CoreError::NvmeWriteProtected {
+                opcode: nvme_cmd.opc(),
+            });
+        }
+
+        self.nvme_admin_passthru(nvme_cmd, buffer, true)
+            .await
+            .map(|_| ())
+    }
+
+    /// sends the specified NVMe Admin command and returns dword 0 of its
+    /// completion. Unless write is set, the command is sent with the spdk-sys
+    /// variant of spdk_bdev_nvme_admin_passthru that assumes read commands,
+    /// so that it can be sent through a read only descriptor.
+    async fn nvme_admin_passthru(
+        &self,
+        nvme_cmd: &spdk_sys::spdk_nvme_cmd,
+        buffer: Option<&mut DmaBuf>,
+        write: bool,
+    ) -> Result<u32, CoreError> {
         trace!("Sending nvme_admin {}", nvme_cmd.opc());
-        let (s, r) = oneshot::channel::<bool>();
-        // Use the spdk-sys variant spdk_bdev_nvme_admin_passthru that
-        // assumes read commands
+        let (s, r) = oneshot::channel::<Option<u32>>();
+        let passthru = if write {
+            spdk_bdev_nvme_admin_passthru
+        } else {
+            spdk_bdev_nvme_admin_passthru_ro
+        };
         let errno = unsafe {
-            spdk_bdev_nvme_admin_passthru_ro(
+            passthru(
                 self.desc.as_ptr(),
                 self.channel.as_ptr(),
                 &*nvme_cmd,
@@ -268,7 +1237,7 @@ impl BdevHandle {
                     Some(b) => b.len(),
                     None => 0,
                 },
-                Some(Self::io_completion_cb),
+                Some(Self::nvme_cdw0_completion_cb),
                 cb_arg(s),
             )
         };
@@ -280,12 +1249,11 @@ impl BdevHandle {
             });
         }
 
-        if r.await.expect("Failed awaiting NVMe Admin IO") {
-            Ok(())
-        } else {
-            Err(CoreError::NvmeAdminFailed {
+        match r.await.expect("Failed awaiting NVMe Admin IO") {
+            Some(cdw0) => Ok(cdw0),
+            None => Err(CoreError::NvmeAdminFailed {
                 opcode: (*nvme_cmd).opc(),
-            })
+            }),
         }
     }
 }
@@ -305,6 +1273,8 @@ impl TryFrom<Descriptor> for BdevHandle {
             return Ok(Self {
                 desc: Arc::new(desc),
                 channel,
+                write_protected: AtomicBool::new(false),
+                enomem_retries: AtomicU32::new(DEFAULT_ENOMEM_RETRIES),
             });
         }
 
@@ -322,6 +1292,8 @@ impl TryFrom<Arc<Descriptor>> for BdevHandle {
             return Ok(Self {
                 desc,
                 channel,
+                write_protected: AtomicBool::new(false),
+                enomem_retries: AtomicU32::new(DEFAULT_ENOMEM_RETRIES),
             });
         }
 
@@ -0,0 +1,155 @@
+//! IO counters which are updated from multiple cores concurrently.
+//!
+//! Unlike the stats of a bdev which are maintained by SPDK and only ever
+//! increase, these counters can be reset which allows callers to obtain the
+//! stats since the last time they were queried.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::core::IoType;
+
+#[derive(Debug, Default)]
+pub struct IoStats {
+    num_read_ops: AtomicU64,
+    num_write_ops: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl IoStats {
+    /// account a successfully completed IO of the given type and size
+    pub(crate) fn record(&self, io_type: IoType, bytes: u64) {
+        match io_type {
+            IoType::Read => {
+                self.num_read_ops.fetch_add(1, Ordering::Relaxed);
+                self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+            }
+            IoType::Write => {
+                self.num_write_ops.fetch_add(1, Ordering::Relaxed);
+                self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    /// return a copy of the current values of the counters
+    pub fn snapshot(&self) -> IoStats {
+        IoStats {
+            num_read_ops: AtomicU64::new(self.num_read_ops()),
+            num_write_ops: AtomicU64::new(self.num_write_ops()),
+            bytes_read: AtomicU64::new(self.bytes_read()),
+            bytes_written: AtomicU64::new(self.bytes_written()),
+        }
+    }
+
+    /// return a copy of the current values of the counters and reset them to
+    /// zero. Every counter is reset individually, so an IO completing
+    /// concurrently is either part of the returned values or of the next
+    /// snapshot but never lost.
+    pub fn snapshot_and_reset(&self) -> IoStats {
+        IoStats {
+            num_read_ops: AtomicU64::new(Self::take(&self.num_read_ops)),
+            num_write_ops: AtomicU64::new(Self::take(&self.num_write_ops)),
+            bytes_read: AtomicU64::new(Self::take(&self.bytes_read)),
+            bytes_written: AtomicU64::new(Self::take(&self.bytes_written)),
+        }
+    }
+
+    /// reset the counter to zero returning the value it had
+    fn take(counter: &AtomicU64) -> u64 {
+        let mut current = counter.load(Ordering::Relaxed);
+        loop {
+            match counter.compare_exchange(
+                current,
+                0,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(value) => return value,
+                Err(value) => current = value,
+            }
+        }
+    }
+
+    pub fn num_read_ops(&self) -> u64 {
+        self.num_read_ops.load(Ordering::Relaxed)
+    }
+
+    pub fn num_write_ops(&self) -> u64 {
+        self.num_write_ops.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+}
+
+/// Number of buckets of an IoSizeHistogram
+pub const IO_SIZE_BUCKETS: usize = 9;
+
+/// Histogram of the sizes, in blocks, of the IOs submitted to a device. The
+/// size of an IO is rounded up to the next power of two, bucket i counting
+/// the IOs of up to 2^i blocks. The last bucket counts all IOs of 256 or more
+/// blocks.
+#[derive(Debug, Default)]
+pub struct IoSizeHistogram {
+    reads: [AtomicU64; IO_SIZE_BUCKETS],
+    writes: [AtomicU64; IO_SIZE_BUCKETS],
+}
+
+impl IoSizeHistogram {
+    /// account an IO of the given type and number of blocks
+    pub(crate) fn record(&self, io_type: IoType, num_blocks: u64) {
+        let buckets = match io_type {
+            IoType::Read => &self.reads,
+            IoType::Write => &self.writes,
+            _ => return,
+        };
+        buckets[Self::bucket(num_blocks)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// index of the bucket counting IOs of the given number of blocks
+    fn bucket(num_blocks: u64) -> usize {
+        let bucket = num_blocks.max(1).next_power_of_two().trailing_zeros();
+        (bucket as usize).min(IO_SIZE_BUCKETS - 1)
+    }
+
+    /// return the number of IOs of the given type that fall into the same
+    /// bucket as an IO of num_blocks blocks
+    pub fn count(&self, io_type: IoType, num_blocks: u64) -> u64 {
+        let buckets = match io_type {
+            IoType::Read => &self.reads,
+            IoType::Write => &self.writes,
+            _ => return 0,
+        };
+        buckets[Self::bucket(num_blocks)].load(Ordering::Relaxed)
+    }
+
+    /// return the counters of the read buckets
+    pub fn reads(&self) -> Vec<u64> {
+        self.reads
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// return the counters of the write buckets
+    pub fn writes(&self) -> Vec<u64> {
+        self.writes
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// reset all buckets to zero
+    pub fn reset(&self) {
+        self.reads
+            .iter()
+            .chain(self.writes.iter())
+            .for_each(|c| c.store(0, Ordering::Relaxed));
+    }
+}
@@ -11,16 +11,33 @@ pub use cpu_cores::{Core, Cores};
 pub use descriptor::{Descriptor, RangeContext};
 pub use dma::{DmaBuf, DmaError};
 pub use env::{
+    load_node_id,
     mayastor_env_stop,
+    parse_core_list,
+    parse_core_mask,
+    parse_mb,
+    EnvError,
     MayastorCliArgs,
     MayastorEnvironment,
     GLOBAL_RC,
+    NODE_ID_FILE,
     SIG_RECEIVED,
 };
 
 pub use bio::{Bio, IoStatus, IoType};
 pub use handle::BdevHandle;
-pub use nvme::{nvme_admin_opc, GenericStatusCode, NvmeStatus};
+pub use io_stats::{IoSizeHistogram, IoStats, IO_SIZE_BUCKETS};
+pub use nvme::{
+    nvme_admin_opc,
+    nvme_nvm_opc,
+    GenericStatusCode,
+    NvmeReservation,
+    NvmeReservationAcquireAction,
+    NvmeReservationRegisterAction,
+    NvmeReservationReleaseAction,
+    NvmeStatus,
+    NvmeTransportId,
+};
 pub use reactor::{Reactor, ReactorState, Reactors, REACTOR_LIST};
 pub use share::{Protocol, Share};
 pub use thread::Mthread;
@@ -34,6 +51,7 @@ mod dma;
 mod env;
 mod handle;
 pub mod io_driver;
+mod io_stats;
 mod nvme;
 pub mod poller;
 mod reactor;
@@ -83,6 +101,40 @@ pub enum CoreError {
         offset: u64,
         len: u64,
     },
+    #[snafu(display(
+        "Failed to dispatch unmap at offset {} length {}",
+        offset,
+        len
+    ))]
+    UnmapDispatch {
+        source: Errno,
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display(
+        "Failed to dispatch compare at offset {} length {}",
+        offset,
+        len
+    ))]
+    CompareDispatch {
+        source: Errno,
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display(
+        "Failed to dispatch write zeroes at offset {} length {}",
+        offset,
+        len
+    ))]
+    WriteZeroesDispatch {
+        source: Errno,
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display("Failed to dispatch flush"))]
+    FlushDispatch {
+        source: Errno,
+    },
     #[snafu(display("Failed to dispatch reset",))]
     ResetDispatch {
         source: Errno,
@@ -92,6 +144,18 @@ pub enum CoreError {
         source: Errno,
         opcode: u16,
     },
+    #[snafu(display("Failed to dispatch NVMe IO command {:x}h", opcode))]
+    NvmeIoPassthruDispatch {
+        source: Errno,
+        opcode: u16,
+    },
+    #[snafu(display(
+        "NVMe command {:x}h writes through a write protected handle",
+        opcode
+    ))]
+    NvmeWriteProtected {
+        opcode: u16,
+    },
     #[snafu(display("Write failed at offset {} length {}", offset, len))]
     WriteFailed {
         offset: u64,
@@ -102,12 +166,58 @@ pub enum CoreError {
         offset: u64,
         len: u64,
     },
+    #[snafu(display("IO cancelled at offset {} length {}", offset, len))]
+    IoCancelled {
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display("Unmap failed at offset {} length {}", offset, len))]
+    UnmapFailed {
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display("Compare failed at offset {} length {}", offset, len))]
+    CompareFailed {
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display(
+        "Data differs in the range at offset {} length {}",
+        offset,
+        len
+    ))]
+    CompareMismatch {
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display(
+        "Write zeroes failed at offset {} length {}",
+        offset,
+        len
+    ))]
+    WriteZeroesFailed {
+        offset: u64,
+        len: u64,
+    },
+    #[snafu(display("Flush failed"))]
+    FlushFailed {},
     #[snafu(display("Reset failed"))]
     ResetFailed {},
     #[snafu(display("NVMe Admin command {:x}h failed", opcode))]
     NvmeAdminFailed {
         opcode: u16,
     },
+    #[snafu(display(
+        "NVMe reservation command {:x}h failed with a reservation conflict",
+        opcode
+    ))]
+    ReservationConflict {
+        opcode: u16,
+    },
+    #[snafu(display("NVMe IO command {:x}h failed", opcode))]
+    NvmeIoPassthruFailed {
+        opcode: u16,
+    },
     #[snafu(display("failed to share {}", source))]
     ShareNvmf {
         source: NvmfError,
@@ -132,4 +242,17 @@ pub enum CoreError {
     ReactorError {
         source: Errno,
     },
+    #[snafu(display("failed to allocate DMA buffer: {}", source))]
+    DmaAllocation {
+        source: DmaError,
+    },
+    #[snafu(display("invalid argument: {}", reason))]
+    InvalidArgument {
+        reason: String,
+    },
+    #[snafu(display("firmware update of slot {} failed: {}", slot, reason))]
+    FirmwareUpdateFailed {
+        slot: u8,
+        reason: String,
+    },
 }
@@ -1,3 +1,11 @@
+use std::{
+    ffi::CStr,
+    fmt::{Display, Formatter},
+    os::raw::c_char,
+};
+
+use serde::{Deserialize, Serialize};
+
 use crate::core::{
     nvme::StatusCodeType::{
         CommandSpecificStatus,
@@ -8,7 +16,12 @@ use crate::core::{
     },
     Bio,
 };
-use spdk_sys::spdk_bdev_io_get_nvme_status;
+use spdk_sys::{
+    spdk_bdev_io_get_nvme_status,
+    spdk_nvme_transport_id,
+    spdk_nvme_transport_id_adrfam_str,
+    spdk_nvme_transport_id_trtype_str,
+};
 
 #[derive(Debug, Copy, Clone, Eq, PartialOrd, PartialEq)]
 pub enum StatusCodeType {
@@ -195,13 +208,122 @@ impl From<&Bio> for NvmeStatus {
     }
 }
 
+/// Transport ID of an NVMe controller, which identifies the path a device is
+/// reached through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NvmeTransportId {
+    /// transport type i.e. "TCP" or "PCIe"
+    pub trtype: String,
+    /// address of the target, or the PCI address of a local device
+    pub traddr: String,
+    /// service ID of the target, the port for TCP
+    pub trsvcid: String,
+    /// NQN of the subsystem
+    pub subnqn: String,
+    /// address family i.e. "IPv4"
+    pub adrfam: String,
+}
+
+/// Convert a string owned by spdk, which can be NULL
+fn spdk_str(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+    }
+}
+
+impl From<&spdk_nvme_transport_id> for NvmeTransportId {
+    fn from(trid: &spdk_nvme_transport_id) -> Self {
+        unsafe {
+            Self {
+                trtype: spdk_str(spdk_nvme_transport_id_trtype_str(
+                    trid.trtype,
+                )),
+                traddr: spdk_str(trid.traddr.as_ptr()),
+                trsvcid: spdk_str(trid.trsvcid.as_ptr()),
+                subnqn: spdk_str(trid.subnqn.as_ptr()),
+                adrfam: spdk_str(spdk_nvme_transport_id_adrfam_str(
+                    trid.adrfam,
+                )),
+            }
+        }
+    }
+}
+
+impl Display for NvmeTransportId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "trtype:{} adrfam:{} traddr:{} trsvcid:{} subnqn:{}",
+            self.trtype, self.adrfam, self.traddr, self.trsvcid, self.subnqn
+        )
+    }
+}
+
 /// NVMe Admin opcode, from nvme_spec.h
 pub mod nvme_admin_opc {
-    // pub const GET_LOG_PAGE: u8 = 0x02;
+    pub const GET_LOG_PAGE: u8 = 0x02;
     pub const IDENTIFY: u8 = 0x06;
-    // pub const ABORT: u8 = 0x08;
-    // pub const SET_FEATURES: u8 = 0x09;
-    // pub const GET_FEATURES: u8 = 0x0a;
+    pub const ABORT: u8 = 0x08;
+    pub const SET_FEATURES: u8 = 0x09;
+    pub const GET_FEATURES: u8 = 0x0a;
+    pub const FIRMWARE_COMMIT: u8 = 0x10;
+    pub const FIRMWARE_IMAGE_DOWNLOAD: u8 = 0x11;
     // Vendor-specific
     pub const CREATE_SNAPSHOT: u8 = 0xc0;
 }
+
+/// NVMe NVM command set opcode, from nvme_spec.h
+pub mod nvme_nvm_opc {
+    pub const FLUSH: u8 = 0x00;
+    pub const READ: u8 = 0x02;
+    pub const COMPARE: u8 = 0x05;
+    pub const VERIFY: u8 = 0x0c;
+    pub const RESERVATION_REGISTER: u8 = 0x0d;
+    pub const RESERVATION_REPORT: u8 = 0x0e;
+    pub const RESERVATION_ACQUIRE: u8 = 0x11;
+    pub const RESERVATION_RELEASE: u8 = 0x15;
+    pub const COPY: u8 = 0x19;
+
+    /// returns true unless the command is known to leave the data of the
+    /// namespace untouched
+    pub fn is_write(opcode: u8) -> bool {
+        !matches!(opcode, FLUSH | READ | COMPARE | VERIFY | RESERVATION_REPORT)
+    }
+}
+
+/// NVMe reservation type, which decides who may access a namespace while a
+/// reservation is held
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NvmeReservation {
+    WriteExclusive = 1,
+    ExclusiveAccess = 2,
+    WriteExclusiveRegsOnly = 3,
+    ExclusiveAccessRegsOnly = 4,
+    WriteExclusiveAllRegs = 5,
+    ExclusiveAccessAllRegs = 6,
+}
+
+/// action of the NVMe Reservation Register command
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NvmeReservationRegisterAction {
+    Register = 0,
+    Unregister = 1,
+    ReplaceKey = 2,
+}
+
+/// action of the NVMe Reservation Acquire command
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NvmeReservationAcquireAction {
+    Acquire = 0,
+    Preempt = 1,
+    PreemptAbort = 2,
+}
+
+/// action of the NVMe Reservation Release command
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NvmeReservationReleaseAction {
+    Release = 0,
+    Clear = 1,
+}
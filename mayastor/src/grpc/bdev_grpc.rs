@@ -7,11 +7,13 @@ use url::Url;
 use rpc::mayastor::{
     bdev_rpc_server::BdevRpc,
     Bdev as RpcBdev,
+    BdevFilter,
     BdevShareReply,
     BdevShareRequest,
     BdevUri,
     Bdevs,
     CreateReply,
+    ListBdevsRequest,
     Null,
 };
 
@@ -55,16 +57,64 @@ impl From<Bdev> for RpcBdev {
     }
 }
 
+/// Check if the bdev meets all the criteria of the filter
+fn bdev_matches(bdev: &Bdev, filter: &BdevFilter) -> bool {
+    (filter.driver.is_empty()
+        || bdev.driver().eq_ignore_ascii_case(&filter.driver))
+        && (filter.name.is_empty() || glob_match(&filter.name, &bdev.name()))
+        && (filter.uuid.is_empty()
+            || bdev
+                .uuid_as_string()
+                .to_lowercase()
+                .starts_with(&filter.uuid.to_lowercase()))
+}
+
+/// Match a name against a pattern where `*` matches any sequence of
+/// characters and `?` matches a single character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` in the pattern and of the name where the
+    // sequence it matches ends, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // let the `*` match one more character
+            star = Some((star_p, star_n + 1));
+            p = star_p + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p ..].iter().all(|c| *c == '*')
+}
+
 #[derive(Debug)]
 pub struct BdevSvc;
 
 #[tonic::async_trait]
 impl BdevRpc for BdevSvc {
     #[instrument(level = "debug", err)]
-    async fn list(&self, _request: Request<Null>) -> GrpcResult<Bdevs> {
+    async fn list(
+        &self,
+        request: Request<ListBdevsRequest>,
+    ) -> GrpcResult<Bdevs> {
+        let filter = request.into_inner().filter.unwrap_or_default();
         let mut list: Vec<RpcBdev> = Vec::new();
         if let Some(bdev) = Bdev::bdev_first() {
-            bdev.into_iter().for_each(|bdev| list.push(bdev.into()))
+            bdev.into_iter()
+                .filter(|bdev| bdev_matches(bdev, &filter))
+                .for_each(|bdev| list.push(bdev.into()))
         }
 
         Ok(Response::new(Bdevs {
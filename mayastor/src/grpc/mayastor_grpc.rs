@@ -19,6 +19,7 @@ use crate::{
         nexus_create,
         Reason,
     },
+    core::MayastorEnvironment,
     grpc::{
         nexus_grpc::{
             nexus_add_child,
@@ -131,7 +132,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
             }};
             let nexus = nexus_lookup(&uuid)?;
             info!("Created nexus {}", uuid);
-            Ok(Response::new(nexus.to_grpc()))
+            Ok(Response::new(nexus.to_grpc().await))
         }).await
     }
 
@@ -158,11 +159,12 @@ impl mayastor_server::Mayastor for MayastorSvc {
     ) -> GrpcResult<ListNexusReply> {
         let args = request.into_inner();
         trace!("{:?}", args);
+        let mut nexus_list = Vec::new();
+        for n in instances().iter() {
+            nexus_list.push(n.to_grpc().await);
+        }
         let reply = ListNexusReply {
-            nexus_list: instances()
-                .iter()
-                .map(|n| n.to_grpc())
-                .collect::<Vec<_>>(),
+            nexus_list,
         };
         trace!("{:?}", reply);
         Ok(Response::new(reply))
@@ -335,6 +337,137 @@ impl mayastor_server::Mayastor for MayastorSvc {
         Ok(Response::new(Null {}))
     }
 
+    #[instrument(level = "debug", err)]
+    async fn set_nexus_read_preference(
+        &self,
+        request: Request<SetNexusReadPreferenceRequest>,
+    ) -> GrpcResult<Null> {
+        let args = request.into_inner();
+        let uuid = args.uuid.clone();
+        debug!("Setting read preference for nexus {} ...", uuid);
+
+        let pref = match ReadPreference::from_i32(args.preference) {
+            Some(ReadPreference::ReadRoundRobin) => {
+                nexus_bdev::ReadPreference::RoundRobin
+            }
+            Some(ReadPreference::ReadLatencyBased) => {
+                nexus_bdev::ReadPreference::LatencyBased {
+                    ewma_alpha: args.ewma_alpha,
+                }
+            }
+            Some(ReadPreference::ReadPreferLocal) => {
+                nexus_bdev::ReadPreference::PreferLocal
+            }
+            Some(ReadPreference::ReadSticky) => {
+                nexus_bdev::ReadPreference::Sticky
+            }
+            None => {
+                return Err(nexus_bdev::Error::InvalidReadPreference {
+                    pref_value: args.preference,
+                }
+                .into());
+            }
+        };
+
+        locally! { async move {
+            nexus_lookup(&args.uuid)?.set_read_preference(pref).await
+        }};
+
+        info!("Set nexus {} read preference {:?}", uuid, pref);
+        Ok(Response::new(Null {}))
+    }
+
+    #[instrument(level = "debug", err)]
+    async fn set_nexus_min_healthy_children(
+        &self,
+        request: Request<SetNexusMinHealthyChildrenRequest>,
+    ) -> GrpcResult<Null> {
+        let args = request.into_inner();
+        let uuid = args.uuid.clone();
+        let count = args.min_healthy_children as usize;
+        debug!("Setting min healthy children for nexus {} ...", uuid);
+
+        locally! { async move {
+            nexus_lookup(&args.uuid)
+                .map(|nexus| nexus.set_min_healthy_children(count))
+        }};
+
+        info!("Set nexus {} min healthy children {}", uuid, count);
+        Ok(Response::new(Null {}))
+    }
+
+    #[instrument(level = "debug", err)]
+    async fn reset_io_stats(
+        &self,
+        request: Request<ResetIoStatsRequest>,
+    ) -> GrpcResult<ResetIoStatsReply> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+
+        let children = nexus_lookup(&args.uuid)?
+            .children
+            .iter()
+            .map(|c| ChildIoStats {
+                uri: c.name.clone(),
+                stats: Some(c.io_stats().snapshot_and_reset().into()),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Response::new(ResetIoStatsReply {
+            children,
+        }))
+    }
+
+    #[instrument(level = "debug", err)]
+    async fn get_child_stats(
+        &self,
+        request: Request<GetChildStatsRequest>,
+    ) -> GrpcResult<GetChildStatsReply> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+
+        let child = nexus_lookup(&args.uuid)?.get_child_by_name(&args.uri)?;
+        let histogram = child.iops_histogram();
+
+        Ok(Response::new(GetChildStatsReply {
+            stats: Some(child.io_stats().snapshot().into()),
+            read_histogram: histogram.reads(),
+            write_histogram: histogram.writes(),
+            uri: args.uri,
+        }))
+    }
+
+    #[instrument(level = "debug", err)]
+    async fn get_nexus_label(
+        &self,
+        request: Request<GetNexusLabelRequest>,
+    ) -> GrpcResult<GetNexusLabelReply> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+
+        let (uri, summary) = locally! { async move {
+            nexus_lookup(&args.uuid)?.label_summary().await
+        }};
+
+        Ok(Response::new(GetNexusLabelReply {
+            uri,
+            disk_guid: summary.disk_guid,
+            block_size: summary.block_size,
+            status: format!("{:?}", summary.status).to_lowercase(),
+            partitions: summary
+                .partitions
+                .into_iter()
+                .map(|p| LabelPartition {
+                    name: p.name,
+                    type_guid: p.type_guid,
+                    start_lba: p.start_lba,
+                    end_lba: p.end_lba,
+                    size_bytes: p.size_bytes,
+                })
+                .collect(),
+        }))
+    }
+
     #[instrument(level = "debug", err)]
     async fn child_operation(
         &self,
@@ -372,7 +505,12 @@ impl mayastor_server::Mayastor for MayastorSvc {
         let args = request.into_inner();
         trace!("{:?}", args);
         locally! { async move {
-            nexus_lookup(&args.uuid)?.start_rebuild(&args.uri).await.map(|_|{})
+            let nexus = nexus_lookup(&args.uuid)?;
+            nexus.start_rebuild(&args.uri, args.verify).await?;
+            if args.rate_limit > 0 {
+                nexus.set_rebuild_rate_limit(&args.uri, args.rate_limit)?;
+            }
+            Ok::<_, nexus_bdev::Error>(())
         }};
 
         Ok(Response::new(Null {}))
@@ -498,4 +636,17 @@ impl mayastor_server::Mayastor for MayastorSvc {
         trace!("{:?}", reply);
         Ok(Response::new(reply))
     }
+
+    #[instrument(level = "debug", err)]
+    async fn get_node_info(
+        &self,
+        _request: Request<Null>,
+    ) -> GrpcResult<GetNodeInfoReply> {
+        let reply = GetNodeInfoReply {
+            node_name: MayastorEnvironment::global_or_default().node_name,
+            node_id: MayastorEnvironment::node_id().to_string(),
+        };
+        trace!("{:?}", reply);
+        Ok(Response::new(reply))
+    }
 }
@@ -8,8 +8,9 @@ use crate::{
     bdev::nexus::{
         instances,
         nexus_bdev::{Error, Nexus, NexusStatus},
-        nexus_child::{ChildState, NexusChild, Reason},
+        nexus_child::{ChildState, ChildStatus, NexusChild, Reason},
     },
+    core::IoStats,
     rebuild::RebuildJob,
 };
 
@@ -27,15 +28,51 @@ impl From<ChildState> for rpc::ChildState {
                 Reason::OutOfSync => rpc::ChildState::ChildDegraded,
                 _ => rpc::ChildState::ChildFaulted,
             },
+            ChildState::Rebuilding {
+                ..
+            } => rpc::ChildState::ChildDegraded,
         }
     }
 }
+impl From<IoStats> for rpc::Stats {
+    fn from(stats: IoStats) -> Self {
+        rpc::Stats {
+            num_read_ops: stats.num_read_ops(),
+            num_write_ops: stats.num_write_ops(),
+            bytes_read: stats.bytes_read(),
+            bytes_written: stats.bytes_written(),
+        }
+    }
+}
+
 impl From<NexusStatus> for rpc::NexusState {
     fn from(nexus: NexusStatus) -> Self {
         match nexus {
             NexusStatus::Faulted => rpc::NexusState::NexusFaulted,
             NexusStatus::Degraded => rpc::NexusState::NexusDegraded,
             NexusStatus::Online => rpc::NexusState::NexusOnline,
+            NexusStatus::Rebuilding => rpc::NexusState::NexusRebuilding,
+            NexusStatus::Quorum => rpc::NexusState::NexusQuorum,
+        }
+    }
+}
+
+impl From<ChildStatus> for rpc::Child {
+    fn from(status: ChildStatus) -> Self {
+        rpc::Child {
+            uri: status.uri,
+            state: rpc::ChildState::from(status.state) as i32,
+            rebuild_progress: status
+                .rebuild_progress
+                .map(|p| (p * 100.0) as i32)
+                .unwrap_or(-1),
+            transport_id: status.transport_id.map(|t| rpc::NvmeTransportId {
+                trtype: t.trtype,
+                traddr: t.traddr,
+                trsvcid: t.trsvcid,
+                subnqn: t.subnqn,
+                adrfam: t.adrfam,
+            }),
         }
     }
 }
@@ -46,11 +83,7 @@ impl NexusChild {
     /// We cannot use From trait because it is not value to value conversion.
     /// All we have is a reference to a child.
     pub fn to_grpc(&self) -> rpc::Child {
-        rpc::Child {
-            uri: self.name.clone(),
-            state: rpc::ChildState::from(self.state()) as i32,
-            rebuild_progress: self.get_rebuild_progress(),
-        }
+        rpc::Child::from(self.status())
     }
 }
 
@@ -59,16 +92,17 @@ impl Nexus {
     ///
     /// We cannot use From trait because it is not value to value conversion.
     /// All we have is a reference to nexus.
-    pub fn to_grpc(&self) -> rpc::Nexus {
+    pub async fn to_grpc(&self) -> rpc::Nexus {
         rpc::Nexus {
             uuid: name_to_uuid(&self.name).to_string(),
             size: self.size,
             state: rpc::NexusState::from(self.status()) as i32,
             device_uri: self.get_share_uri().unwrap_or_default(),
             children: self
-                .children
-                .iter()
-                .map(|ch| ch.to_grpc())
+                .get_children_status()
+                .await
+                .into_iter()
+                .map(rpc::Child::from)
                 .collect::<Vec<_>>(),
             rebuilds: RebuildJob::count() as u32,
         }
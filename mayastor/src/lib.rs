@@ -24,6 +24,7 @@ pub mod rebuild;
 pub mod replica;
 pub mod subsys;
 pub mod target;
+pub mod utils;
 
 #[macro_export]
 macro_rules! CPS_INIT {
@@ -1,4 +1,4 @@
-use std::{ffi::CStr, os::raw::c_char, path::Path};
+use std::{ffi::CStr, os::raw::c_char, path::Path, str::FromStr};
 
 use ansi_term::{Colour, Style};
 
@@ -216,9 +216,31 @@ impl std::fmt::Display for Location<'_> {
     }
 }
 
+/// The format log messages are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// human readable text, one message per line
+    Text,
+    /// one JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            s => Err(format!("Invalid log format: {}", s)),
+        }
+    }
+}
+
 // Custom struct used to format trace events.
 struct CustomFormat {
     ansi: bool,
+    json: bool,
 }
 
 // Format a trace event.
@@ -236,6 +258,25 @@ where
         let normalized = event.normalized_metadata();
         let meta = normalized.as_ref().unwrap_or_else(|| event.metadata());
 
+        if self.json {
+            let mut message = String::new();
+            context.format_fields(&mut message, event)?;
+
+            let timestamp = chrono::Local::now().format("%FT%T%.9f%Z");
+            let mut entry = serde_json::json!({
+                "timestamp": timestamp.to_string(),
+                "level": meta.level().to_string(),
+                "component": meta.target(),
+                "message": message,
+            });
+            if let (Some(file), Some(line)) = (meta.file(), meta.line()) {
+                entry["file"] = basename(file).into();
+                entry["line"] = line.into();
+            }
+
+            return writeln!(writer, "{}", entry);
+        }
+
         write!(
             writer,
             "[{} {} {}{}{}] ",
@@ -259,6 +300,12 @@ where
 /// We might want to suppress certain messages, as some of them are redundant,
 /// in particular, the NOTICE messages as such, they are mapped to debug.
 pub fn init(level: &str) {
+    init_with_format(level, LogFormat::Text)
+}
+
+/// Same as init, but the messages are written in the given format. Messages
+/// from SPDK are formatted in the same way, as they are logged through here.
+pub fn init_with_format(level: &str, format: LogFormat) {
     // Set up a "logger" that simply translates any "log" messages it receives
     // to trace events. This is for our custom spdk log messages, but also
     // for any other third party crates still using the logging facade.
@@ -266,7 +313,8 @@ pub fn init(level: &str) {
 
     // Our own custom format for displaying trace events.
     let format = CustomFormat {
-        ansi: atty::is(atty::Stream::Stdout),
+        ansi: format == LogFormat::Text && atty::is(atty::Stream::Stdout),
+        json: format == LogFormat::Json,
     };
 
     // Create a default subscriber.
@@ -56,6 +56,8 @@ pub enum NexusBdevError {
     // Bdev create/destroy errors
     #[snafu(display("bdev {} already exists", name))]
     BdevExists { name: String },
+    #[snafu(display("alias {} of bdev {} is already in use", alias, name))]
+    AliasExists { alias: String, name: String },
     #[snafu(display("bdev {} not found", name))]
     BdevNotFound { name: String },
     #[snafu(display("Invalid parameters for bdev create {}", name))]
@@ -64,8 +66,14 @@ pub enum NexusBdevError {
     CreateBdev { source: Errno, name: String },
     #[snafu(display("Failed to destroy bdev {}", name))]
     DestroyBdev { source: Errno, name: String },
-    #[snafu(display("Command canceled for bdev {}", name))]
-    CancelBdev { source: Canceled, name: String },
+    #[snafu(display("Failed to resize bdev {}", name))]
+    ResizeBdev { source: Errno, name: String },
+    #[snafu(display("Command {} canceled for bdev {}", operation, name))]
+    CancelBdev {
+        source: Canceled,
+        name: String,
+        operation: String,
+    },
 }
 
 /// Parse URI and create bdev described in the URI.
@@ -81,6 +89,21 @@ pub async fn bdev_destroy(uri: &str) -> Result<(), NexusBdevError> {
     Uri::parse(uri)?.destroy().await
 }
 
+/// Parse a loopback URI and change the number of blocks of the existing bdev
+/// it refers to, e.g. after the storage behind it has grown.
+///
+/// # Safety
+///
+/// The storage behind the bdev must hold the new number of blocks, the bdev
+/// is not prevented from growing past it, see [`Bdev::resize`].
+pub unsafe fn bdev_resize(
+    uri: &str,
+    num_blocks: u64,
+) -> Result<(), NexusBdevError> {
+    info!(?uri, num_blocks, "resize");
+    crate::bdev::dev::loopback_resize(uri, num_blocks)
+}
+
 pub fn bdev_get_name(uri: &str) -> Result<String, NexusBdevError> {
     Ok(Uri::parse(uri)?.get_name())
 }
@@ -1,9 +1,12 @@
 #![warn(missing_docs)]
 
-use std::fmt;
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
 
 use crossbeam::channel::{Receiver, Sender};
-use futures::channel::oneshot;
+use futures::channel::{mpsc, oneshot};
 use snafu::Snafu;
 
 use crate::{
@@ -74,11 +77,98 @@ pub enum RebuildError {
     BdevInvalidUri { source: NexusBdevError, uri: String },
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, Snafu, Clone)]
+#[snafu(visibility = "pub(crate)")]
+#[allow(missing_docs)]
+/// Reason why a rebuild job has failed
+pub enum RebuildFailureReason {
+    #[snafu(display("Failed to read from the source at lba {}", lba))]
+    SourceIoError { lba: u64, source: CoreError },
+    #[snafu(display("Failed to write to the destination at lba {}", lba))]
+    DestinationIoError { lba: u64, source: CoreError },
+    #[snafu(display(
+        "Source and destination differ in the segment at lba {}, \
+         crc32 {:08x} != {:08x}",
+        lba,
+        source_crc,
+        destination_crc,
+    ))]
+    VerificationMismatch {
+        lba: u64,
+        source_crc: u32,
+        destination_crc: u32,
+    },
+    #[snafu(display("Failed to verify the segment at lba {}", lba))]
+    VerifyError { lba: u64, source: RebuildError },
+    #[snafu(display("Failed to copy the segment at lba {}", lba))]
+    CopyError { lba: u64, source: RebuildError },
+    #[snafu(display("The rebuild was cancelled"))]
+    Cancelled {},
+}
+
+impl RebuildFailureReason {
+    /// Map the error of a segment copy starting at `lba` to a failure reason
+    pub(crate) fn from_copy_error(lba: u64, error: RebuildError) -> Self {
+        match error {
+            RebuildError::ReadIoError {
+                source, ..
+            } => Self::SourceIoError {
+                lba,
+                source,
+            },
+            RebuildError::WriteIoError {
+                source, ..
+            } => Self::DestinationIoError {
+                lba,
+                source,
+            },
+            source => Self::CopyError {
+                lba,
+                source,
+            },
+        }
+    }
+
+    /// Block at which the rebuild failed, if any
+    pub fn lba(&self) -> Option<u64> {
+        match self {
+            Self::SourceIoError {
+                lba, ..
+            }
+            | Self::DestinationIoError {
+                lba, ..
+            }
+            | Self::VerificationMismatch {
+                lba, ..
+            }
+            | Self::VerifyError {
+                lba, ..
+            }
+            | Self::CopyError {
+                lba, ..
+            } => Some(*lba),
+            Self::Cancelled {} => None,
+        }
+    }
+}
+
+/// The underlying errors cannot be compared so two reasons are equal when
+/// they are of the same kind and refer to the same block
+impl PartialEq for RebuildFailureReason {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && self.lba() == other.lba()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 /// allowed states for a rebuild job
 pub enum RebuildState {
     /// Init when the job is newly created
     Init,
+    /// Queued when the job was started while the maximum number of rebuild
+    /// jobs were running, it runs once one of them is done
+    Queued,
     /// Running when the job is rebuilding
     Running,
     /// Stopped when the job is halted as requested through stop
@@ -88,7 +178,10 @@ pub enum RebuildState {
     Paused,
     /// Failed when an IO (R/W) operation was failed
     /// there are no retries as it currently stands
-    Failed,
+    Failed {
+        /// what caused the rebuild to fail
+        reason: RebuildFailureReason,
+    },
     /// Completed when the rebuild was successfully completed
     Completed,
 }
@@ -97,15 +190,21 @@ impl fmt::Display for RebuildState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             RebuildState::Init => write!(f, "init"),
+            RebuildState::Queued => write!(f, "queued"),
             RebuildState::Running => write!(f, "running"),
             RebuildState::Stopped => write!(f, "stopped"),
             RebuildState::Paused => write!(f, "paused"),
-            RebuildState::Failed => write!(f, "failed"),
+            RebuildState::Failed {
+                ..
+            } => write!(f, "failed"),
             RebuildState::Completed => write!(f, "completed"),
         }
     }
 }
 
+/// Number of bytes to rebuild between two checkpoints of the progress
+pub const REBUILD_CHECKPOINT_INTERVAL: u64 = 64 * 1024 * 1024;
+
 /// A rebuild job is responsible for managing a rebuild (copy) which reads
 /// from source_hdl and writes into destination_hdl from specified start to end
 #[derive(Debug)]
@@ -121,6 +220,8 @@ pub struct RebuildJob {
     pub(super) block_size: u64,
     pub(super) range: std::ops::Range<u64>,
     pub(super) next: u64,
+    /// block up to which the progress was last checkpointed
+    pub(super) last_checkpoint: u64,
     pub(super) segment_size_blks: u64,
     pub(super) task_pool: RebuildTasks,
     pub(super) notify_fn: fn(String, String) -> (),
@@ -132,6 +233,34 @@ pub struct RebuildJob {
     pub(super) complete_chan: Vec<oneshot::Sender<RebuildState>>,
     /// rebuild copy error, if any
     pub error: Option<RebuildError>,
+    /// called with the nexus and destination URI as arguments whenever
+    /// the progress should be checkpointed
+    pub(super) checkpoint_fn: Option<fn(String, String)>,
+    /// called with the nexus and destination URI and the progress in percent
+    /// as arguments whenever another percent has been rebuilt
+    pub(super) progress_fn: Option<fn(String, String, f64)>,
+    /// whole percent of the progress last reported to progress_fn
+    pub(super) last_progress: u64,
+    /// paces the copy of the segments to the rate limit of the job
+    pub(super) throttle: RebuildThrottle,
+    /// wakes the job up while it waits for the rate limit, whenever an
+    /// operation is executed or the rate limit changes
+    pub(super) control_chan:
+        (mpsc::UnboundedSender<()>, mpsc::UnboundedReceiver<()>),
+    /// when the job first started running
+    pub(super) started: Option<Instant>,
+    /// number of bytes copied by this job
+    pub(super) bytes_copied: u64,
+    /// moving average of the throughput over the last sampling intervals,
+    /// in bytes per second
+    pub(super) recent_throughput: f64,
+    /// start of the current throughput sampling interval and the number of
+    /// bytes copied at that time
+    pub(super) throughput_sample: Option<(Instant, u64)>,
+    /// compare the destination with the source once the copy has completed
+    pub(super) verify: bool,
+    /// when the job was queued, the longest queued job runs first
+    pub(super) queued_at: Option<Instant>,
 }
 
 /// rebuild statistics
@@ -192,6 +321,123 @@ impl RebuildJob {
         Self::lookup(destination)
     }
 
+    /// Registers a callback which is called every
+    /// REBUILD_CHECKPOINT_INTERVAL bytes of rebuilt data, with the nexus and
+    /// destination URI as arguments, so that the progress can be persisted
+    pub fn set_checkpoint_fn(&mut self, checkpoint_fn: fn(String, String)) {
+        self.checkpoint_fn = Some(checkpoint_fn);
+    }
+
+    /// Registers a callback which is called with the nexus and destination
+    /// URI and the progress in percent whenever another percent has been
+    /// rebuilt
+    pub fn set_progress_fn(&mut self, progress_fn: fn(String, String, f64)) {
+        self.progress_fn = Some(progress_fn);
+    }
+
+    /// Limits the rate at which the job copies data to the given number of
+    /// bytes per second, so that it does not starve the IO of the nexus.
+    /// A rate of 0 removes the limit. The new rate applies right away,
+    /// also to a job which is already running.
+    pub fn set_rate_limit(&mut self, bps: u64) {
+        self.throttle.set_rate(bps);
+        self.control_chan.0.unbounded_send(()).ok();
+    }
+
+    /// Once the copy has completed, re-read both the source and the
+    /// destination and compare the checksums of each segment before the job
+    /// completes. The job fails on the first segment which differs.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Returns the rate limit of the job in bytes per second, 0 if the job
+    /// is not rate limited
+    pub fn rate_limit(&self) -> u64 {
+        self.throttle.rate()
+    }
+
+    /// Returns the average number of bytes per second the job has copied
+    /// since it first started running
+    pub fn throughput(&self) -> u64 {
+        match self.started {
+            Some(started) => {
+                let elapsed = started.elapsed().as_secs_f64();
+                if elapsed > 0.0 {
+                    (self.bytes_copied as f64 / elapsed) as u64
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns the number of blocks to rebuild and the number of blocks
+    /// which have been rebuilt
+    pub fn blocks(&self) -> (u64, u64) {
+        let blocks_total = self.range.end - self.range.start;
+        let blocks_transferred = std::cmp::min(
+            self.task_pool.segments_done() * self.segment_size_blks,
+            blocks_total,
+        );
+        (blocks_total, blocks_transferred)
+    }
+
+    /// Returns the estimated time until the rebuild completes, based on the
+    /// recent throughput of the job or its average throughput until the
+    /// first sampling interval has passed. None if nothing has been copied
+    /// yet. The estimate of a paused job does not change until it resumes.
+    pub fn eta(&self) -> Option<Duration> {
+        let throughput = if self.recent_throughput > 0.0 {
+            self.recent_throughput
+        } else {
+            self.throughput() as f64
+        };
+        if throughput <= 0.0 {
+            return None;
+        }
+
+        let (total, transferred) = self.blocks();
+        let remaining = (total - transferred) * self.block_size;
+        Some(Duration::from_secs_f64(remaining as f64 / throughput))
+    }
+
+    /// Returns the percentage of the range that has been rebuilt
+    pub fn progress_pct(&self) -> f64 {
+        let (blocks_total, blocks_recovered) = self.blocks();
+        blocks_recovered as f64 * 100.0 / blocks_total as f64
+    }
+
+    /// Returns the block below which all blocks of the range have been
+    /// rebuilt, a rebuild resumed from this block does not miss any data
+    pub fn checkpoint_lba(&self) -> u64 {
+        self.task_pool.lowest_active_blk().unwrap_or(self.next)
+    }
+
+    /// Resumes the rebuild from the given block instead of from the start of
+    /// the range. The blocks before it are assumed to have been rebuilt by a
+    /// previous job. Only a job which has not been started can be resumed.
+    pub fn resume_from(&mut self, lba: u64) -> Result<(), RebuildError> {
+        if self.state() != RebuildState::Init {
+            return Err(RebuildError::OpError {
+                operation: "ResumeFrom".to_string(),
+                state: self.state().to_string(),
+            });
+        }
+        if lba < self.range.start || lba > self.range.end {
+            return Err(RebuildError::InvalidParameters {});
+        }
+
+        // always restart at the beginning of a segment
+        let segments = (lba - self.range.start) / self.segment_size_blks;
+        self.next = self.range.start + segments * self.segment_size_blks;
+        self.last_checkpoint = self.next;
+        self.task_pool.set_segments_done(segments);
+        self.last_progress = self.progress_pct() as u64;
+        Ok(())
+    }
+
     /// Lookup a rebuild job by its destination uri and return it
     pub fn lookup(name: &str) -> Result<&mut Self, RebuildError> {
         if let Some(job) = Self::get_instances().get_mut(name) {
@@ -229,7 +475,17 @@ impl RebuildJob {
 
     /// State of the rebuild job
     pub fn state(&self) -> RebuildState {
-        self.states.current
+        self.states.current.clone()
+    }
+
+    /// Reason for the failure of the rebuild job, if it has failed
+    pub fn failure_reason(&self) -> Option<&RebuildFailureReason> {
+        match &self.states.current {
+            RebuildState::Failed {
+                reason,
+            } => Some(reason),
+            _ => None,
+        }
     }
 
     /// Error description
@@ -249,7 +505,14 @@ impl RebuildJob {
 
 impl RebuildState {
     /// Final update for a rebuild job
-    pub fn done(self) -> bool {
-        matches!(self, Self::Stopped | Self::Failed | Self::Completed)
+    pub fn done(&self) -> bool {
+        matches!(
+            self,
+            Self::Stopped
+                | Self::Failed {
+                    ..
+                }
+                | Self::Completed
+        )
     }
 }
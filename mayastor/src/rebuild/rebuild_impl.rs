@@ -1,12 +1,19 @@
 #![warn(missing_docs)]
 
-use std::{cell::UnsafeCell, collections::HashMap};
+use std::{
+    cell::UnsafeCell,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
+use crc::crc32;
 use crossbeam::channel::unbounded;
 use futures::{
     channel::{mpsc, oneshot},
+    future::{self, Either},
     StreamExt,
 };
+use futures_timer::Delay;
 use once_cell::sync::OnceCell;
 use snafu::ResultExt;
 
@@ -16,6 +23,7 @@ use crate::{
     bdev::VerboseError,
     core::{Bdev, BdevHandle, DmaBuf, RangeContext, Reactors},
     nexus_uri::bdev_get_name,
+    subsys::Config,
 };
 
 use super::rebuild_api::*;
@@ -41,6 +49,11 @@ struct TaskResult {
     error: Option<RebuildError>,
 }
 
+/// Length of the intervals over which the recent throughput is sampled
+const THROUGHPUT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Weight of the latest sample in the moving average of the throughput
+const THROUGHPUT_SAMPLE_WEIGHT: f64 = 0.5;
+
 /// Number of concurrent copy tasks per rebuild job
 const SEGMENT_TASKS: usize = 16;
 /// Size of each segment used by the copy task
@@ -53,6 +66,8 @@ struct RebuildTask {
     buffer: DmaBuf,
     sender: mpsc::Sender<TaskResult>,
     error: Option<TaskResult>,
+    /// block of the segment being copied, if any
+    blk: Option<u64>,
 }
 
 /// Pool of rebuild tasks and progress tracking
@@ -69,6 +84,73 @@ pub(super) struct RebuildTasks {
     segments_done: u64,
 }
 
+/// Token bucket pacing the copy of the segments of a rebuild job to a number
+/// of bytes per second. The bucket holds up to one second worth of tokens, or
+/// one segment if that is larger. Taking more tokens than are available puts
+/// the bucket in debt, which is paid back by waiting.
+#[derive(Debug)]
+pub(super) struct RebuildThrottle {
+    /// bytes per second, 0 if the rate is not limited
+    rate: u64,
+    /// bytes which can be copied without waiting, negative when in debt
+    tokens: f64,
+    /// last time the bucket was refilled
+    refilled: Instant,
+}
+
+impl RebuildThrottle {
+    fn new() -> Self {
+        Self {
+            rate: 0,
+            tokens: 0.0,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// bytes per second, 0 if the rate is not limited
+    pub(super) fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// change the rate, starting over with an empty bucket. A debt is
+    /// carried over, after taking the tokens gathered at the old rate into
+    /// account, so changing the rate does not skip paying it back.
+    pub(super) fn set_rate(&mut self, rate: u64) {
+        let now = Instant::now();
+        if self.rate == 0 {
+            self.tokens = 0.0;
+        } else {
+            let elapsed = now.duration_since(self.refilled).as_secs_f64();
+            self.tokens =
+                f64::min(self.tokens + elapsed * self.rate as f64, 0.0);
+        }
+        self.rate = rate;
+        self.refilled = now;
+    }
+
+    /// Takes the tokens to copy the given number of bytes and returns how
+    /// long to wait before the copy can start, if at all
+    fn take(&mut self, bytes: u64) -> Option<Duration> {
+        if self.rate == 0 {
+            return None;
+        }
+
+        let now = Instant::now();
+        let capacity = std::cmp::max(self.rate, bytes) as f64;
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens =
+            f64::min(self.tokens + elapsed * self.rate as f64, capacity);
+        self.refilled = now;
+
+        self.tokens -= bytes as f64;
+        if self.tokens < 0.0 {
+            Some(Duration::from_secs_f64(-self.tokens / self.rate as f64))
+        } else {
+            None
+        }
+    }
+}
+
 /// Checks whether a range is contained within another range
 pub trait Within<T> {
     /// True if `self` is contained within `right`, otherwise false
@@ -144,6 +226,7 @@ impl RebuildJob {
                 buffer: copy_buffer,
                 sender: tasks.channel.0.clone(),
                 error: None,
+                blk: None,
             });
         }
 
@@ -164,6 +247,7 @@ impl RebuildJob {
             source,
             destination,
             next: range.start,
+            last_checkpoint: range.start,
             range,
             block_size,
             segment_size_blks,
@@ -173,6 +257,17 @@ impl RebuildJob {
             states: Default::default(),
             complete_chan: Vec::new(),
             error: None,
+            checkpoint_fn: None,
+            progress_fn: None,
+            last_progress: 0,
+            throttle: RebuildThrottle::new(),
+            control_chan: mpsc::unbounded(),
+            started: None,
+            bytes_copied: 0,
+            recent_throughput: 0.0,
+            throughput_sample: None,
+            verify: false,
+            queued_at: None,
         })
     }
 
@@ -180,11 +275,22 @@ impl RebuildJob {
     // awaits each completion. When any task completes it kicks off another
     // until the bdev is fully rebuilt
     async fn run(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+        // a paused job resumes sampling from scratch
+        self.throughput_sample = Some((Instant::now(), self.bytes_copied));
         self.start_all_tasks();
         while self.task_pool.active > 0 {
             match self.await_one_task().await {
                 Some(r) => match r.error {
                     None => {
+                        self.checkpoint();
+                        self.progress();
+                        self.sample_throughput();
+                        if self.keep_running() {
+                            self.throttle().await;
+                        }
+                        // the job may have been paused or stopped while
+                        // waiting for the rate limit
                         match self.states.pending {
                             None | Some(RebuildState::Running) => {
                                 self.start_task_by_id(r.id);
@@ -199,7 +305,10 @@ impl RebuildJob {
                     }
                     Some(e) => {
                         error!("Failed to rebuild segment id {} block {} with error: {}", r.id, r.blk, e);
-                        self.fail();
+                        self.fail(RebuildFailureReason::from_copy_error(
+                            r.blk,
+                            e.clone(),
+                        ));
                         self.await_all_tasks().await;
                         self.error = Some(e);
                         break;
@@ -213,9 +322,80 @@ impl RebuildJob {
                 }
             }
         }
+        if self.verify && self.states.pending_equals(RebuildState::Completed) {
+            if let Err(reason) = self.verify_all().await {
+                error!(
+                    "Failed to verify the rebuild of {}: {}",
+                    self.destination,
+                    reason.verbose()
+                );
+                self.fail(reason);
+            }
+        }
         self.reconcile();
     }
 
+    /// Compares the destination with the source once the copy has completed,
+    /// one segment at a time
+    async fn verify_all(&mut self) -> Result<(), RebuildFailureReason> {
+        info!(
+            "Verifying the rebuild of {} from {}",
+            self.destination, self.source
+        );
+        let mut blk = self.range.start;
+        while blk < self.range.end {
+            // the job was terminated while being verified
+            if !self.states.pending_equals(RebuildState::Completed) {
+                return Ok(());
+            }
+
+            let (source_crc, destination_crc) =
+                self.locked_verify_one(blk).await.map_err(|source| {
+                    RebuildFailureReason::VerifyError {
+                        lba: blk,
+                        source,
+                    }
+                })?;
+            if source_crc != destination_crc {
+                return Err(RebuildFailureReason::VerificationMismatch {
+                    lba: blk,
+                    source_crc,
+                    destination_crc,
+                });
+            }
+            blk += self.segment_size_blks;
+        }
+        Ok(())
+    }
+
+    /// Whether no operation is pending which stops the job from copying
+    /// further segments
+    fn keep_running(&self) -> bool {
+        matches!(self.states.pending, None | Some(RebuildState::Running))
+    }
+
+    /// Wait until the rate limit allows for another segment to be copied, or
+    /// until an operation stops the job from copying further segments. The
+    /// wait is recalculated when the rate limit changes.
+    async fn throttle(&mut self) {
+        let bytes = self.segment_size_blks * self.block_size;
+        let mut wait = self.throttle.take(bytes);
+        while let Some(delay) = wait {
+            // discard the wake ups which were already accounted for
+            while let Ok(Some(())) = self.control_chan.1.try_next() {}
+            if !self.keep_running() {
+                return;
+            }
+
+            match future::select(Delay::new(delay), self.control_chan.1.next())
+                .await
+            {
+                Either::Left(_) => return,
+                Either::Right(_) => wait = self.throttle.take(0),
+            }
+        }
+    }
+
     /// Return the size of the segment to be copied.
     fn get_segment_size_blks(&self, blk: u64) -> u64 {
         // Adjust the segments size for the last segment
@@ -281,6 +461,73 @@ impl RebuildJob {
         result
     }
 
+    /// Verifies one segment with its LBA range locked, so that front end
+    /// writes cannot make the source and destination differ while they are
+    /// being read. The same safety requirements as for locked_copy_one apply.
+    async fn locked_verify_one(
+        &self,
+        blk: u64,
+    ) -> Result<(u32, u32), RebuildError> {
+        let len = self.get_segment_size_blks(blk);
+        let mut ctx = RangeContext::new(blk - self.range.start, len);
+        let ch = self
+            .nexus_descriptor
+            .get_channel()
+            .expect("Failed to get nexus channel");
+
+        self.nexus_descriptor
+            .lock_lba_range(&mut ctx, &ch)
+            .await
+            .context(RangeLockError {
+                blk,
+                len,
+            })?;
+
+        let result = self.verify_one(blk).await;
+
+        self.nexus_descriptor
+            .unlock_lba_range(&mut ctx, &ch)
+            .await
+            .context(RangeUnLockError {
+                blk,
+                len,
+            })?;
+
+        result
+    }
+
+    /// Reads one segment from both the source and the destination and
+    /// returns the crc32 of each.
+    async fn verify_one(&self, blk: u64) -> Result<(u32, u32), RebuildError> {
+        let len = self.get_segment_size_blks(blk) * self.block_size;
+        let source_hdl = RebuildJob::open_handle(&self.source, false, false)?;
+        let destination_hdl =
+            RebuildJob::open_handle(&self.destination, false, false)?;
+
+        let mut source_buf =
+            source_hdl.dma_malloc(len).context(NoCopyBuffer {})?;
+        let mut destination_buf =
+            destination_hdl.dma_malloc(len).context(NoCopyBuffer {})?;
+
+        source_hdl
+            .read_at(blk * self.block_size, &mut source_buf)
+            .await
+            .context(ReadIoError {
+                bdev: &self.source,
+            })?;
+        destination_hdl
+            .read_at(blk * self.block_size, &mut destination_buf)
+            .await
+            .context(ReadIoError {
+                bdev: &self.destination,
+            })?;
+
+        Ok((
+            crc32::checksum_ieee(source_buf.as_slice()),
+            crc32::checksum_ieee(destination_buf.as_slice()),
+        ))
+    }
+
     /// Copies one segment worth of data from source into destination.
     async fn copy_one(
         &mut self,
@@ -333,6 +580,59 @@ impl RebuildJob {
         self.send_notify();
     }
 
+    /// Calls the job's registered progress fn callback whenever another
+    /// percent of the range has been rebuilt
+    fn progress(&mut self) {
+        let progress = self.progress_pct();
+        if progress as u64 <= self.last_progress {
+            return;
+        }
+
+        self.last_progress = progress as u64;
+        if let Some(progress_fn) = self.progress_fn {
+            progress_fn(self.nexus.clone(), self.destination.clone(), progress);
+        }
+    }
+
+    /// Folds the throughput of the current sampling interval into the recent
+    /// throughput once the interval has passed
+    fn sample_throughput(&mut self) {
+        let (start, bytes) = match self.throughput_sample {
+            Some(sample) => sample,
+            None => return,
+        };
+        let elapsed = start.elapsed();
+        if elapsed < THROUGHPUT_SAMPLE_INTERVAL {
+            return;
+        }
+
+        let sample = (self.bytes_copied - bytes) as f64 / elapsed.as_secs_f64();
+        self.recent_throughput = if self.recent_throughput > 0.0 {
+            THROUGHPUT_SAMPLE_WEIGHT * sample
+                + (1.0 - THROUGHPUT_SAMPLE_WEIGHT) * self.recent_throughput
+        } else {
+            sample
+        };
+        self.throughput_sample = Some((Instant::now(), self.bytes_copied));
+    }
+
+    /// Calls the job's registered checkpoint fn callback once another
+    /// REBUILD_CHECKPOINT_INTERVAL bytes have been rebuilt since the last
+    /// checkpoint
+    fn checkpoint(&mut self) {
+        let lba = self.checkpoint_lba();
+        if (lba - self.last_checkpoint) * self.block_size
+            < REBUILD_CHECKPOINT_INTERVAL
+        {
+            return;
+        }
+
+        self.last_checkpoint = lba;
+        if let Some(checkpoint_fn) = self.checkpoint_fn {
+            checkpoint_fn(self.nexus.clone(), self.destination.clone());
+        }
+    }
+
     /// Calls the job's registered notify fn callback and notify sender channel
     fn send_notify(&mut self) {
         // should this return a status before we notify the sender channel?
@@ -340,6 +640,11 @@ impl RebuildJob {
         if let Err(e) = self.notify_chan.0.send(self.state()) {
             error!("Rebuild Job {} of nexus {} failed to send complete via the unbound channel with err {}", self.destination, self.nexus, e);
         }
+        if self.state().done() {
+            for chan in self.complete_chan.drain(..) {
+                chan.send(self.states.current.clone()).ok();
+            }
+        }
     }
 
     /// Check if the source and destination block devices are compatible for
@@ -367,6 +672,59 @@ impl RebuildJob {
                 self.destination, old, new
             );
             self.notify();
+
+            if new.done() {
+                // the job no longer counts towards the concurrent jobs
+                Reactors::master().send_future(async {
+                    RebuildJob::start_queued();
+                });
+            }
+        }
+    }
+
+    /// Whether another job may run without exceeding the maximum number of
+    /// concurrent rebuild jobs
+    fn slot_available() -> bool {
+        let max = Config::get().nexus_opts.max_rebuild_jobs as usize;
+        max == 0 || Self::active_jobs() < max
+    }
+
+    /// Number of jobs which have been started and are not done yet, a paused
+    /// job keeps its place
+    fn active_jobs() -> usize {
+        Self::get_instances()
+            .values()
+            .filter(|job| {
+                matches!(
+                    job.state(),
+                    RebuildState::Running | RebuildState::Paused
+                ) || job.states.pending_equals(RebuildState::Running)
+            })
+            .count()
+    }
+
+    /// Starts the queued jobs, longest queued first, for as long as the
+    /// maximum number of concurrent rebuild jobs allows for it
+    fn start_queued() {
+        while Self::slot_available() {
+            let next = Self::get_instances()
+                .values()
+                .filter(|job| job.state() == RebuildState::Queued)
+                .min_by_key(|job| job.queued_at)
+                .map(|job| job.destination.clone());
+
+            let job = match next.map(|destination| Self::lookup(&destination)) {
+                Some(Ok(job)) => job,
+                _ => break,
+            };
+            if let Err(e) = job.exec_internal_op(RebuildOperation::Start) {
+                error!(
+                    "Failed to start the queued rebuild job {}: {}",
+                    job.destination,
+                    e.verbose()
+                );
+                break;
+            }
         }
     }
 
@@ -381,7 +739,9 @@ impl RebuildJob {
     }
     fn schedule(&self) {
         match self.state() {
-            RebuildState::Paused | RebuildState::Init => {
+            RebuildState::Paused
+            | RebuildState::Init
+            | RebuildState::Queued => {
                 let destination = self.destination.clone();
                 Reactors::master().send_future(async move {
                     let job = match RebuildJob::lookup(&destination) {
@@ -456,7 +816,7 @@ enum RebuildOperation {
     /// Internal Operations
     ///
     /// an IO error has occurred
-    Fail,
+    Fail(RebuildFailureReason),
     /// rebuild completed successfully
     Complete,
 }
@@ -469,13 +829,8 @@ impl std::fmt::Display for RebuildOperation {
 
 impl ClientOperations for RebuildJob {
     fn stats(&self) -> RebuildStats {
-        let blocks_total = self.range.end - self.range.start;
-
         // segment size may not be aligned to the total size
-        let blocks_recovered = std::cmp::min(
-            self.task_pool.segments_done * self.segment_size_blks,
-            blocks_total,
-        );
+        let (blocks_total, blocks_recovered) = self.blocks();
 
         let progress = (blocks_recovered * 100) / blocks_total;
 
@@ -536,14 +891,14 @@ impl ClientOperations for RebuildJob {
 /// Internal facing operations on a Rebuild Job
 trait InternalOperations {
     /// Fails the job, overriding any pending client operation
-    fn fail(&mut self);
+    fn fail(&mut self, reason: RebuildFailureReason);
     /// Completes the job, overriding any pending operation
     fn complete(&mut self);
 }
 
 impl InternalOperations for RebuildJob {
-    fn fail(&mut self) {
-        self.exec_internal_op(RebuildOperation::Fail).ok();
+    fn fail(&mut self, reason: RebuildFailureReason) {
+        self.exec_internal_op(RebuildOperation::Fail(reason)).ok();
     }
 
     fn complete(&mut self) {
@@ -559,9 +914,14 @@ impl RebuildJob {
             self.task_pool.active
         );
 
+        let bytes = self.segment_size_blks * self.block_size;
         for n in 0 .. self.task_pool.total {
             self.next = match self.send_segment_task(n) {
                 Some(next) => {
+                    // the first segments are copied right away, any debt
+                    // is paid back before the next segment is copied
+                    self.throttle.take(bytes);
+                    self.task_pool.tasks[n].blk = Some(self.next);
                     self.task_pool.active += 1;
                     next
                 }
@@ -574,6 +934,7 @@ impl RebuildJob {
     fn start_task_by_id(&mut self, id: usize) {
         match self.send_segment_task(id) {
             Some(next) => {
+                self.task_pool.tasks[id].blk = Some(self.next);
                 self.task_pool.active += 1;
                 self.next = next;
             }
@@ -590,6 +951,9 @@ impl RebuildJob {
             self.task_pool.active -= 1;
             if f.error.is_none() {
                 self.task_pool.segments_done += 1;
+                self.bytes_copied +=
+                    self.get_segment_size_blks(f.blk) * self.block_size;
+                self.task_pool.tasks[f.id].blk = None;
             } else {
                 self.task_pool.tasks[f.id].error = Some(f.clone());
             }
@@ -605,7 +969,7 @@ impl RebuildJob {
         while self.task_pool.active > 0 {
             if self.await_one_task().await.is_none() {
                 error!("Failed to wait for {} rebuild tasks due mpsc channel failure.", self.task_pool.active);
-                self.fail();
+                self.fail(RebuildFailureReason::Cancelled {});
                 return;
             }
         }
@@ -648,6 +1012,23 @@ impl RebuildJob {
     }
 }
 
+impl RebuildTasks {
+    /// Lowest block of the segments that are being copied, if any
+    pub(super) fn lowest_active_blk(&self) -> Option<u64> {
+        self.tasks.iter().filter_map(|t| t.blk).min()
+    }
+
+    /// Number of segments which have been rebuilt
+    pub(super) fn segments_done(&self) -> u64 {
+        self.segments_done
+    }
+
+    /// Account the segments which were rebuilt before the job was created
+    pub(super) fn set_segments_done(&mut self, segments: u64) {
+        self.segments_done = segments;
+    }
+}
+
 #[derive(Debug, Default)]
 pub(super) struct RebuildStates {
     /// Current state of the rebuild job
@@ -677,8 +1058,8 @@ impl RebuildStates {
         state: RebuildState,
         override_pending: bool,
     ) -> Result<(), RebuildError> {
-        match self.pending {
-            Some(pending) if !override_pending && (pending != state) => {
+        match &self.pending {
+            Some(pending) if !override_pending && (*pending != state) => {
                 Err(RebuildError::StatePending {
                     state: pending.to_string(),
                 })
@@ -696,17 +1077,16 @@ impl RebuildStates {
 
     /// a change to `state` is pending
     fn pending_equals(&self, state: RebuildState) -> bool {
-        self.pending == Some(state)
+        self.pending.as_ref() == Some(&state)
     }
 
     /// reconcile the pending state into the current state
     fn reconcile(&mut self) -> RebuildState {
-        if let Some(pending) = self.pending {
+        if let Some(pending) = self.pending.take() {
             self.current = pending;
-            self.pending = None;
         }
 
-        self.current
+        self.current.clone()
     }
 }
 
@@ -743,14 +1123,31 @@ impl RebuildJob {
             override_pending
         );
 
+        // the job only runs again once the operation has been executed, so it
+        // sees the state the operation leaves behind when it wakes up
+        self.control_chan.0.unbounded_send(()).ok();
+
         match op {
             RebuildOperation::Start => {
                 match self.state() {
                     // start only allowed when... starting
-                    S::Stopped | S::Paused | S::Failed | S::Completed => Err(e),
+                    S::Stopped
+                    | S::Paused
+                    | S::Failed {
+                        ..
+                    }
+                    | S::Completed => Err(e),
                     // for idempotence sake
                     S::Running => Ok(()),
-                    S::Init => {
+                    S::Init | S::Queued if !Self::slot_available() => {
+                        if self.state() == S::Init {
+                            self.states.set_pending(S::Queued, false)?;
+                            self.queued_at = Some(Instant::now());
+                            self.reconcile();
+                        }
+                        Ok(())
+                    }
+                    S::Init | S::Queued => {
                         self.states.set_pending(S::Running, false)?;
                         self.schedule();
                         Ok(())
@@ -760,7 +1157,10 @@ impl RebuildJob {
             RebuildOperation::Stop => {
                 match self.state() {
                     // We're already stopping anyway, so all is well
-                    S::Failed | S::Completed => Err(e),
+                    S::Failed {
+                        ..
+                    }
+                    | S::Completed => Err(e),
                     // for idempotence sake
                     S::Stopped => Ok(()),
                     S::Running => {
@@ -768,7 +1168,7 @@ impl RebuildJob {
                             .set_pending(S::Stopped, override_pending)?;
                         Ok(())
                     }
-                    S::Init | S::Paused => {
+                    S::Init | S::Queued | S::Paused => {
                         self.states
                             .set_pending(S::Stopped, override_pending)?;
 
@@ -779,33 +1179,60 @@ impl RebuildJob {
                 }
             }
             RebuildOperation::Pause => match self.state() {
-                S::Stopped | S::Failed | S::Completed => Err(e),
+                S::Queued
+                | S::Stopped
+                | S::Failed {
+                    ..
+                }
+                | S::Completed => Err(e),
                 S::Init | S::Running | S::Paused => {
                     self.states.set_pending(S::Paused, false)?;
                     Ok(())
                 }
             },
             RebuildOperation::Resume => match self.state() {
-                S::Init | S::Stopped | S::Failed | S::Completed => Err(e),
+                S::Init
+                | S::Queued
+                | S::Stopped
+                | S::Failed {
+                    ..
+                }
+                | S::Completed => Err(e),
                 S::Running | S::Paused => {
                     self.states.set_pending(S::Running, false)?;
                     self.schedule();
                     Ok(())
                 }
             },
-            RebuildOperation::Fail => match self.state() {
-                S::Init | S::Stopped | S::Paused | S::Completed => Err(e),
+            RebuildOperation::Fail(reason) => match self.state() {
+                S::Init
+                | S::Queued
+                | S::Stopped
+                | S::Paused
+                | S::Completed => Err(e),
                 // for idempotence sake
-                S::Failed => Ok(()),
+                S::Failed {
+                    ..
+                } => Ok(()),
                 S::Running => {
-                    self.states.set_pending(S::Failed, override_pending)?;
+                    self.states.set_pending(
+                        S::Failed {
+                            reason,
+                        },
+                        override_pending,
+                    )?;
                     Ok(())
                 }
             },
             RebuildOperation::Complete => match self.state() {
-                S::Init | S::Paused | S::Stopped | S::Failed | S::Completed => {
-                    Err(e)
+                S::Init
+                | S::Queued
+                | S::Paused
+                | S::Stopped
+                | S::Failed {
+                    ..
                 }
+                | S::Completed => Err(e),
                 S::Running => {
                     self.states.set_pending(S::Completed, override_pending)?;
                     Ok(())
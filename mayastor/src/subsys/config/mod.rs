@@ -13,11 +13,12 @@ use std::{
     fs::File,
     io::Write,
     path::Path,
+    sync::Mutex,
 };
 
 use byte_unit::Byte;
 use futures::FutureExt;
-use once_cell::sync::OnceCell;
+use once_cell::sync::{Lazy, OnceCell};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 use spdk_sys::{
@@ -56,6 +57,7 @@ use crate::{
         },
         NvmfSubsystem,
     },
+    utils::write_file_atomic,
 };
 
 #[derive(Debug, Clone, Snafu)]
@@ -70,6 +72,10 @@ pub(crate) mod opts;
 
 pub static CONFIG: OnceCell<Config> = OnceCell::new();
 
+/// content of the config file as it was last loaded or written by mayastor
+static FILE_CONTENT: Lazy<Mutex<Option<Vec<u8>>>> =
+    Lazy::new(|| Mutex::new(None));
+
 pub struct ConfigSubsystem(pub *mut spdk_subsystem);
 
 impl Default for ConfigSubsystem {
@@ -88,7 +94,7 @@ impl ConfigSubsystem {
             let f = async move {
                 let cfg = Config::get().refresh();
                 if let Some(target) = cfg.source.as_ref() {
-                    if let Err(e) = cfg.write(&target) {
+                    if let Err(e) = cfg.write_atomic(&target) {
                         error!("error writing config file {} {}", target, e);
                     }
                 } else {
@@ -213,11 +219,19 @@ impl Config {
     {
         debug!("loading configuration file from {}", file);
         let cfg = fs::read(&file).unwrap_or_default();
+        Config::parse(file, &cfg)
+    }
+
+    /// parse the content of the given config file, see read
+    pub fn parse<P>(file: P, cfg: &[u8]) -> Result<Config, serde_yaml::Error>
+    where
+        P: Display + ToString,
+    {
         let mut config;
         // only parse the file when its not empty, otherwise
         // just store the filepath to write it out later
         if !cfg.is_empty() {
-            match serde_yaml::from_slice(&cfg) {
+            match serde_yaml::from_slice(cfg) {
                 Ok(v) => config = v,
                 Err(e) => {
                     error!("{}", e);
@@ -237,6 +251,46 @@ impl Config {
         Ok(config)
     }
 
+    /// returns the names of the sections that differ between this and the
+    /// other config, the location of the config file is not compared
+    pub fn changed_sections(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.nvmf_tcp_tgt_conf != other.nvmf_tcp_tgt_conf {
+            changed.push("nvmf_tcp_tgt_conf");
+        }
+        if self.iscsi_tgt_conf != other.iscsi_tgt_conf {
+            changed.push("iscsi_tgt_conf");
+        }
+        if self.nvme_bdev_opts != other.nvme_bdev_opts {
+            changed.push("nvme_bdev_opts");
+        }
+        if self.bdev_opts != other.bdev_opts {
+            changed.push("bdev_opts");
+        }
+        if self.nexus_opts != other.nexus_opts {
+            changed.push("nexus_opts");
+        }
+        if self.pools != other.pools {
+            changed.push("pools");
+        }
+        if self.base_bdevs != other.base_bdevs {
+            changed.push("base_bdevs");
+        }
+        if self.nexus_bdevs != other.nexus_bdevs {
+            changed.push("nexus_bdevs");
+        }
+        if self.implicit_share_base != other.implicit_share_base {
+            changed.push("implicit_share_base");
+        }
+        if self.sync_disable != other.sync_disable {
+            changed.push("sync_disable");
+        }
+        if self.socket_opts != other.socket_opts {
+            changed.push("socket_opts");
+        }
+        changed
+    }
+
     /// collect current configuration snapshot into a new Config object that can
     /// be exported to a file (YAML or JSON)
     pub fn refresh(&self) -> Self {
@@ -322,8 +376,7 @@ impl Config {
         });
 
         if let Ok(s) = serde_yaml::to_string(&pools) {
-            let mut file = File::create(file)?;
-            return file.write_all(s.as_bytes());
+            return self.write_file(file.as_ref(), s.into_bytes());
         }
         Err(std::io::Error::new(
             std::io::ErrorKind::Other,
@@ -331,8 +384,78 @@ impl Config {
         ))
     }
 
-    /// write the current configuration to disk
-    pub fn write<P>(&self, file: P) -> Result<(), std::io::Error>
+    /// write the current configuration to disk, replacing the file atomically
+    /// so a crash while writing does not leave a truncated config file behind
+    pub fn write_atomic<P>(&self, file: P) -> Result<(), std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        if let Ok(s) = serde_yaml::to_string(&self) {
+            return self.write_file(file.as_ref(), s.into_bytes());
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "failed to serialize config",
+        ))
+    }
+
+    /// replace the file with the content atomically and remember the content
+    /// when the file is the config file
+    fn write_file(
+        &self,
+        file: &Path,
+        content: Vec<u8>,
+    ) -> Result<(), std::io::Error> {
+        write_file_atomic(file, &content)?;
+        if self.source.as_deref().map(Path::new) == Some(file) {
+            Config::set_file_content(content);
+        }
+        Ok(())
+    }
+
+    /// remember the content of the config file as it was loaded or written
+    pub(crate) fn set_file_content(content: Vec<u8>) {
+        *FILE_CONTENT.lock().unwrap() = Some(content);
+    }
+
+    /// returns true if the content is the same as the content of the config
+    /// file when it was last loaded or written by mayastor
+    pub(crate) fn is_file_content(content: &[u8]) -> bool {
+        FILE_CONTENT.lock().unwrap().as_deref() == Some(content)
+    }
+
+    /// rename a nexus in the config file, so that it is created under its new
+    /// name when mayastor is restarted. Nothing is written when the nexus is
+    /// not in the config file.
+    pub(crate) fn rename_nexus(
+        name: &str,
+        new_name: &str,
+    ) -> Result<(), std::io::Error> {
+        let source = match Config::get().source.as_ref() {
+            Some(source) => source,
+            None => return Ok(()),
+        };
+
+        let mut config = Config::read(source).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+        })?;
+
+        match config
+            .nexus_bdevs
+            .iter_mut()
+            .flatten()
+            .find(|nexus| nexus.name == name)
+        {
+            Some(nexus) => nexus.name = new_name.to_string(),
+            None => return Ok(()),
+        }
+
+        config.write_atomic(source)
+    }
+
+    /// write the current configuration to disk in place
+    #[deprecated(note = "use write_atomic, which survives a crash mid-write")]
+    pub fn write_unsafe<P>(&self, file: P) -> Result<(), std::io::Error>
     where
         P: AsRef<Path>,
     {
@@ -440,7 +563,7 @@ impl Config {
                     for child in degraded_children {
                         dbg!("Start rebuilding child {}", &child.name);
                         if nexus_instance
-                            .start_rebuild(&child.name)
+                            .start_rebuild(&child.name, false)
                             .await
                             .is_err()
                         {
@@ -52,6 +52,9 @@ pub struct NexusOpts {
     pub iscsi_nexus_port: u16,
     /// Port for replica target portal
     pub iscsi_replica_port: u16,
+    /// maximum number of rebuild jobs which run at once, further jobs are
+    /// queued until one of them is done; 0 for no limit
+    pub max_rebuild_jobs: u32,
 }
 
 /// Default nvmf port used for replicas.
@@ -74,6 +77,7 @@ impl Default for NexusOpts {
             iscsi_enable: true,
             iscsi_nexus_port: ISCSI_PORT_NEXUS,
             iscsi_replica_port: ISCSI_PORT_REPLICA,
+            max_rebuild_jobs: 0,
         }
     }
 }
@@ -243,6 +247,23 @@ pub struct NvmeBdevOpts {
     pub io_queue_requests: u32,
     /// allow for batching of commands
     pub delay_cmd_submit: bool,
+    /// number of times to retry getting a handle to a child while probing
+    /// its label, to ride out a reconnect of the controller
+    pub probe_max_retries: u32,
+    /// delay between retries to get a handle while probing a label
+    pub probe_retry_delay_ms: u64,
+    /// size of the chunks in which a firmware image is downloaded
+    pub firmware_chunk_size: u64,
+    /// IO latency above which a nexus child is considered slow and excluded
+    /// from the read path, 0 disables the detection of slow children
+    pub slow_child_threshold_us: u64,
+    /// length of the windows over which the latency of a child is sampled
+    pub slow_child_window_ms: u64,
+    /// interval at which the open children of a nexus are probed with a
+    /// read, 0 disables the health check
+    pub health_check_interval_ms: u64,
+    /// number of consecutive failed probes after which a child is faulted
+    pub health_check_max_failures: u32,
 }
 
 impl GetOpts for NvmeBdevOpts {
@@ -251,7 +272,18 @@ impl GetOpts for NvmeBdevOpts {
         unsafe {
             bdev_nvme_get_opts(&opts as *const _ as *mut spdk_bdev_nvme_opts)
         };
-        opts.into()
+        // the probe, firmware, slow child and health check settings are not
+        // known to spdk
+        Self {
+            probe_max_retries: self.probe_max_retries,
+            probe_retry_delay_ms: self.probe_retry_delay_ms,
+            firmware_chunk_size: self.firmware_chunk_size,
+            slow_child_threshold_us: self.slow_child_threshold_us,
+            slow_child_window_ms: self.slow_child_window_ms,
+            health_check_interval_ms: self.health_check_interval_ms,
+            health_check_max_failures: self.health_check_max_failures,
+            ..opts.into()
+        }
     }
 
     fn set(&self) -> bool {
@@ -282,6 +314,31 @@ impl Default for NvmeBdevOpts {
             nvme_ioq_poll_period_us: try_from_env("NVME_IOQ_POLL_PERIOD_US", 0),
             io_queue_requests: 0,
             delay_cmd_submit: true,
+            probe_max_retries: try_from_env("NVME_PROBE_MAX_RETRIES", 3),
+            probe_retry_delay_ms: try_from_env(
+                "NVME_PROBE_RETRY_DELAY_MS",
+                1_000,
+            ),
+            firmware_chunk_size: try_from_env(
+                "NVME_FIRMWARE_CHUNK_SIZE",
+                4 * 1024 * 1024,
+            ),
+            slow_child_threshold_us: try_from_env(
+                "NVME_SLOW_CHILD_THRESHOLD_US",
+                50_000,
+            ),
+            slow_child_window_ms: try_from_env(
+                "NVME_SLOW_CHILD_WINDOW_MS",
+                1_000,
+            ),
+            health_check_interval_ms: try_from_env(
+                "NVME_HEALTH_CHECK_INTERVAL_MS",
+                0,
+            ),
+            health_check_max_failures: try_from_env(
+                "NVME_HEALTH_CHECK_MAX_FAILURES",
+                3,
+            ),
         }
     }
 }
@@ -301,6 +358,7 @@ impl From<spdk_bdev_nvme_opts> for NvmeBdevOpts {
             nvme_ioq_poll_period_us: o.nvme_ioq_poll_period_us,
             io_queue_requests: o.io_queue_requests,
             delay_cmd_submit: o.delay_cmd_submit,
+            ..Default::default()
         }
     }
 }
@@ -37,7 +37,7 @@ use crate::{
             poll_groups::PollGroup,
             subsystem::NvmfSubsystem,
             transport,
-            transport::{get_ipv4_address, TransportId},
+            transport::{get_address, TransportId},
             Error,
             NVMF_PGS,
         },
@@ -295,7 +295,7 @@ impl Target {
         }
         info!(
             "nvmf target listening on {}:({},{})",
-            get_ipv4_address().unwrap(),
+            get_address().unwrap(),
             trid_nexus.trsvcid.as_str(),
             trid_replica.trsvcid.as_str(),
         );
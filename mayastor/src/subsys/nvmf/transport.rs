@@ -2,7 +2,7 @@ use std::{
     env,
     ffi::CString,
     fmt::{Debug, Display, Formatter},
-    net::Ipv4Addr,
+    net::IpAddr,
     ops::{Deref, DerefMut},
     ptr::copy_nonoverlapping,
 };
@@ -17,10 +17,12 @@ use spdk_sys::{
     spdk_nvmf_transport_create,
     SPDK_NVME_TRANSPORT_TCP,
     SPDK_NVMF_ADRFAM_IPV4,
+    SPDK_NVMF_ADRFAM_IPV6,
     SPDK_NVMF_TRSVCID_MAX_LEN,
 };
 
 use crate::{
+    core::MayastorEnvironment,
     ffihelper::{
         cb_arg,
         done_errno_cb,
@@ -85,15 +87,19 @@ impl DerefMut for TransportId {
 
 impl TransportId {
     pub fn new(port: u16) -> Self {
-        let address = get_ipv4_address().unwrap();
+        let address = get_address().unwrap();
 
         let mut trid = spdk_nvme_transport_id {
             trtype: SPDK_NVME_TRANSPORT_TCP,
-            adrfam: SPDK_NVMF_ADRFAM_IPV4,
+            adrfam: if address.is_ipv6() {
+                SPDK_NVMF_ADRFAM_IPV6
+            } else {
+                SPDK_NVMF_ADRFAM_IPV4
+            },
             ..Default::default()
         };
 
-        let c_addr = address.into_cstring();
+        let c_addr = address.to_string().into_cstring();
         let port = format!("{}", port);
 
         assert!(port.len() < SPDK_NVMF_TRSVCID_MAX_LEN as usize);
@@ -126,12 +132,21 @@ impl TransportId {
 
 impl Display for TransportId {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "nvmf://{}:{}",
-            self.0.traddr.as_str(),
-            self.0.trsvcid.as_str()
-        )
+        if self.0.adrfam == SPDK_NVMF_ADRFAM_IPV6 {
+            write!(
+                f,
+                "nvmf://[{}]:{}",
+                self.0.traddr.as_str(),
+                self.0.trsvcid.as_str()
+            )
+        } else {
+            write!(
+                f,
+                "nvmf://{}:{}",
+                self.0.traddr.as_str(),
+                self.0.trsvcid.as_str()
+            )
+        }
     }
 }
 
@@ -145,19 +160,13 @@ impl Debug for TransportId {
             .finish()
     }
 }
-pub(crate) fn get_ipv4_address() -> Result<String, Error> {
-    let address = match env::var("MY_POD_IP") {
-        Ok(val) => {
-            if val.parse::<Ipv4Addr>().is_ok() {
-                Ok(val)
-            } else {
-                Err(Error::CreateTarget {
-                    msg: "Invalid IPv4 address".into(),
-                })
-            }
-        }
-        Err(_) => Ok("127.0.0.1".to_owned()),
-    }?;
-
-    Ok(address)
+/// returns the IP address set in MY_POD_IP, or the loopback address when it
+/// is not set
+pub(crate) fn get_address() -> Result<IpAddr, Error> {
+    match env::var("MY_POD_IP") {
+        Ok(val) => val.parse::<IpAddr>().map_err(|_| Error::CreateTarget {
+            msg: "Invalid IP address".into(),
+        }),
+        Err(_) => Ok(MayastorEnvironment::loopback_ip()),
+    }
 }
@@ -0,0 +1,49 @@
+//! Small helpers that do not belong to any particular subsystem.
+
+use std::{
+    fs,
+    fs::File,
+    io,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// returns the first element if all the elements of the iterator are equal,
+/// None if any of them differs or the iterator is empty
+pub fn all_equal<T: PartialEq>(mut iter: impl Iterator<Item = T>) -> Option<T> {
+    let first = iter.next()?;
+    if iter.all(|item| item == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// Atomically replace the file at path with contents. The contents are written
+/// to `<path>.tmp` and synced to disk first, which is then renamed over the
+/// target, so a crash leaves either the old or the new file behind but never a
+/// partially written one. The parent directory is synced last to persist the
+/// rename itself.
+pub fn write_file_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+
+    if let Err(e) = result {
+        // the target is left untouched, do not leave the half written file
+        fs::remove_file(&tmp).ok();
+        return Err(e);
+    }
+
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
@@ -2,7 +2,7 @@
 extern crate assert_matches;
 
 use mayastor::{
-    bdev::{nexus_create, nexus_lookup, ChildState, Reason},
+    bdev::{nexus_create, nexus_lookup, ChildState},
     core::MayastorCliArgs,
 };
 
@@ -52,10 +52,12 @@ async fn add_child() {
             .expect("Failed to add child");
         assert_eq!(nexus.children.len(), 2);
 
-        // Expect the added child to be in the out-of-sync state
+        // Expect the added child to be rebuilt
         assert_matches!(
             nexus.children[1].state(),
-            ChildState::Faulted(Reason::OutOfSync)
+            ChildState::Rebuilding {
+                ..
+            }
         );
     })
     .await;
@@ -90,10 +92,12 @@ async fn add_child() {
             .expect("Failed to add child");
         assert_eq!(nexus.children.len(), 2);
 
-        // Expect the added child to be in the out-of-sync state
+        // Expect the added child to be rebuilt
         assert_matches!(
             nexus.children[1].state(),
-            ChildState::Faulted(Reason::OutOfSync)
+            ChildState::Rebuilding {
+                ..
+            }
         );
     })
     .await;
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use mayastor::utils::all_equal;
+
+#[test]
+fn all_equal_empty() {
+    assert_eq!(all_equal(Vec::<u64>::new().into_iter()), None);
+}
+
+#[test]
+fn all_equal_single() {
+    assert_eq!(all_equal(vec![42u64].into_iter()), Some(42));
+}
+
+#[test]
+fn all_equal_same() {
+    assert_eq!(all_equal(vec![512u64, 512, 512].into_iter()), Some(512));
+
+    let uuid = Uuid::new_v4();
+    let uuids = [uuid, uuid];
+    assert_eq!(all_equal(uuids.iter()), Some(&uuid));
+
+    let mut map = HashMap::new();
+    map.insert("a", 4096);
+    map.insert("b", 4096);
+    assert_eq!(all_equal(map.values()), Some(&4096));
+}
+
+#[test]
+fn all_equal_diverging() {
+    assert_eq!(all_equal(vec![512u64, 512, 4096].into_iter()), None);
+    assert_eq!(all_equal([Uuid::new_v4(), Uuid::new_v4()].iter()), None);
+}
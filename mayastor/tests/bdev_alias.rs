@@ -0,0 +1,65 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{Bdev, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy, NexusBdevError},
+};
+
+pub mod common;
+
+static MALLOC: &str = "malloc:///alias_malloc?size_mb=64";
+static TAKEN: [(&str, &str); 2] = [
+    (
+        "malloc:///alias_taken?size_mb=64&uuid=5d1c8e4f-29c4-4f8b-9d0e-3a6b7c2f1e90",
+        "alias_taken",
+    ),
+    (
+        "null:///alias_taken?size_mb=64&uuid=5d1c8e4f-29c4-4f8b-9d0e-3a6b7c2f1e90",
+        "alias_taken",
+    ),
+];
+static OTHER: &str = "malloc:///alias_other?size_mb=64";
+
+#[tokio::test]
+async fn bdev_alias() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(MALLOC).await.unwrap();
+        let bdev = Bdev::lookup_by_name("alias_malloc").unwrap();
+
+        // a bdev which cannot be registered under its URI is not created
+        for (uri, name) in &TAKEN {
+            assert!(bdev.add_alias(uri));
+            match bdev_create(uri).await {
+                Err(NexusBdevError::AliasExists {
+                    alias,
+                    name: bdev_name,
+                }) => {
+                    assert_eq!(alias, *uri);
+                    assert_eq!(bdev_name, *name);
+                }
+                result => panic!("unexpected result {:?} for {}", result, uri),
+            }
+            assert!(Bdev::lookup_by_name(name).is_none());
+        }
+
+        // opening a bdev by loopback URI more than once is fine
+        bdev_create("bdev:///alias_malloc").await.unwrap();
+        bdev_create("bdev:///alias_malloc").await.unwrap();
+
+        // but not when the URI is already an alias of another bdev
+        bdev_create(OTHER).await.unwrap();
+        assert!(bdev.add_alias("bdev:///alias_other"));
+        match bdev_create("bdev:///alias_other").await {
+            Err(NexusBdevError::AliasExists {
+                alias, ..
+            }) => assert_eq!(alias, "bdev:///alias_other"),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        bdev_destroy(OTHER).await.unwrap();
+        bdev_destroy(MALLOC).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,21 @@
+use futures::channel::oneshot;
+
+use mayastor::nexus_uri::NexusBdevError;
+
+#[test]
+fn cancel_bdev_error_names_operation() {
+    // the completion callback is dropped without being called, as happens
+    // when mayastor shuts down while spdk is still creating the bdev
+    let (sender, receiver) = oneshot::channel::<()>();
+    drop(sender);
+    let source = futures::executor::block_on(receiver).unwrap_err();
+
+    let error = NexusBdevError::CancelBdev {
+        source,
+        name: "cancel_disk0".into(),
+        operation: "bdev_nvme_create".into(),
+    };
+    let msg = error.to_string();
+    assert!(msg.contains("cancel_disk0"), "{}", msg);
+    assert!(msg.contains("bdev_nvme_create"), "{}", msg);
+}
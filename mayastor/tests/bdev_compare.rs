@@ -0,0 +1,52 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///compare_disk?size_mb=64&blk_size=512";
+
+#[tokio::test]
+async fn bdev_compare() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let h = BdevHandle::open("compare_disk", true, false).unwrap();
+
+        let mut buf = h.dma_malloc(8 * 512).unwrap();
+        buf.fill(0xa5);
+        h.write_at(4 * 512, &buf).await.unwrap();
+
+        h.compare_blocks(4, &buf).await.unwrap();
+
+        // a single differing byte is a mismatch rather than a failure
+        buf.as_mut_slice()[7 * 512] = 0;
+        match h.compare_blocks(4, &buf).await {
+            Err(CoreError::CompareMismatch {
+                offset,
+                len,
+            }) => {
+                assert_eq!(offset, 4);
+                assert_eq!(len, 8);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // only whole blocks can be compared
+        let partial = h.dma_malloc(100).unwrap();
+        assert!(matches!(
+            h.compare_blocks(0, &partial).await,
+            Err(CoreError::InvalidArgument {
+                ..
+            })
+        ));
+
+        drop(h);
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,34 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///flush_disk?size_mb=64";
+
+#[tokio::test]
+async fn bdev_flush() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let h = BdevHandle::open("flush_disk", true, false).unwrap();
+
+        let mut buf = h.dma_malloc(4096).unwrap();
+        buf.fill(0xff);
+        h.write_at(0, &buf).await.unwrap();
+        h.flush().await.unwrap();
+
+        // the data written before the flush is still there
+        buf.fill(0);
+        h.read_at(0, &mut buf).await.unwrap();
+        assert!(buf.as_slice().iter().all(|b| *b == 0xff));
+
+        drop(h);
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,48 @@
+use std::env;
+
+use futures::future::join_all;
+
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///io_enomem?size_mb=64";
+
+const NUM_IOS: u64 = 256;
+
+#[tokio::test]
+async fn bdev_io_enomem() {
+    // a small pool of bdev IOs makes it easy to run out of them
+    env::set_var("BDEV_IO_POOL_SIZE", "64");
+    env::set_var("BDEV_IO_CACHE_SIZE", "1");
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let h = BdevHandle::open("io_enomem", true, false).unwrap();
+        let buf = h.dma_malloc(4096).unwrap();
+
+        // without retries, the writes that do not get a bdev IO fail
+        h.set_enomem_retries(0);
+        let writes = (0 .. NUM_IOS).map(|i| h.write_at(i * 4096, &buf));
+        let results = join_all(writes).await;
+        assert!(results
+            .iter()
+            .any(|r| matches!(r, Err(CoreError::WriteFailed { .. }))));
+        assert!(results.iter().any(|r| r.is_ok()));
+
+        // with enough retries, they all make it eventually
+        h.set_enomem_retries(1000);
+        let writes = (0 .. NUM_IOS).map(|i| h.write_at(i * 4096, &buf));
+        assert!(join_all(writes).await.iter().all(|r| r.is_ok()));
+
+        drop(h);
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,47 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///io_stats?size_mb=64&blk_size=512";
+
+#[tokio::test]
+async fn bdev_io_stats() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let h = BdevHandle::open("io_stats", true, false).unwrap();
+        let before = h.io_stats();
+        assert_eq!(before.num_read_ops, 0);
+        assert_eq!(before.num_write_ops, 0);
+
+        let mut buf = h.dma_malloc(4096).unwrap();
+        for i in 0 .. 3 {
+            h.write_at(i * 4096, &buf).await.unwrap();
+        }
+        for i in 0 .. 2 {
+            h.read_at(i * 4096, &mut buf).await.unwrap();
+        }
+        h.unmap_blocks(0, 16).await.unwrap();
+
+        let stats = h.io_stats();
+        assert_eq!(stats.num_write_ops, 3);
+        assert_eq!(stats.bytes_written, 3 * 4096);
+        assert_eq!(stats.num_read_ops, 2);
+        assert_eq!(stats.bytes_read, 2 * 4096);
+        assert_eq!(stats.num_unmap_ops, 1);
+        assert_eq!(stats.bytes_unmapped, 16 * 512);
+
+        // all IO went through the channel of this core
+        assert_eq!(h.get_bdev().stats().await.unwrap(), stats);
+
+        drop(h);
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,101 @@
+use rpc::mayastor::{BdevFilter, BdevUri, ListBdevsRequest};
+
+pub mod common;
+use common::compose::{Builder, RpcHandle};
+
+/// names of the bdevs matching the filter, in order
+async fn list(h: &mut RpcHandle, filter: BdevFilter) -> Vec<String> {
+    let mut names: Vec<String> = h
+        .bdev
+        .list(ListBdevsRequest {
+            filter: Some(filter),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .bdevs
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn bdev_list_filter() {
+    let test = Builder::new()
+        .name("bdev-list-filter")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+    let h = &mut hdls[0];
+
+    for uri in &[
+        "malloc:///disk0?size_mb=64",
+        "malloc:///disk1?size_mb=64",
+        "null:///null0?size_mb=64",
+    ] {
+        h.bdev
+            .create(BdevUri {
+                uri: uri.to_string(),
+            })
+            .await
+            .unwrap();
+    }
+
+    // the driver is matched regardless of the case
+    let names = list(
+        h,
+        BdevFilter {
+            driver: "MALLOC".into(),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(names, vec!["disk0", "disk1"]);
+
+    let names = list(
+        h,
+        BdevFilter {
+            name: "*0".into(),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(names, vec!["disk0", "null0"]);
+
+    let names = list(
+        h,
+        BdevFilter {
+            driver: "malloc".into(),
+            name: "d?sk1".into(),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(names, vec!["disk1"]);
+
+    let all = h
+        .bdev
+        .list(ListBdevsRequest::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .bdevs;
+    assert_eq!(all.len(), 3);
+    let null0 = all.iter().find(|b| b.name == "null0").unwrap();
+    let names = list(
+        h,
+        BdevFilter {
+            uuid: null0.uuid[.. 8].to_uppercase(),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(names, vec!["null0"]);
+}
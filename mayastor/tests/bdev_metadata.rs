@@ -0,0 +1,49 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{Bdev, IoType, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static PLAIN: &str = "null:///md_plain?size_mb=64";
+static SEPARATE: &str = "null:///md_separate?size_mb=64&md_size=8";
+static INTERLEAVED: &str =
+    "null:///md_interleaved?size_mb=64&md_size=8&md_interleave=true";
+
+#[tokio::test]
+async fn bdev_metadata() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        for uri in &[PLAIN, SEPARATE, INTERLEAVED] {
+            bdev_create(uri).await.unwrap();
+        }
+
+        let bdev = Bdev::lookup_by_name("md_plain").unwrap();
+        assert_eq!(bdev.md_size(), 0);
+        assert_eq!(bdev.block_len(), 512);
+        assert_eq!(bdev.data_block_len(), 512);
+        assert!(bdev.io_type_supported(IoType::WriteZeros));
+
+        // metadata in a separate buffer does not change the block size
+        let bdev = Bdev::lookup_by_name("md_separate").unwrap();
+        assert_eq!(bdev.md_size(), 8);
+        assert!(!bdev.is_md_interleaved());
+        assert_eq!(bdev.block_len(), 512);
+        assert_eq!(bdev.data_block_len(), 512);
+
+        // interleaved metadata is part of every block
+        let bdev = Bdev::lookup_by_name("md_interleaved").unwrap();
+        assert_eq!(bdev.md_size(), 8);
+        assert!(bdev.is_md_interleaved());
+        assert_eq!(bdev.block_len(), 520);
+        assert_eq!(bdev.data_block_len(), 512);
+
+        for uri in &[PLAIN, SEPARATE, INTERLEAVED] {
+            bdev_destroy(uri).await.unwrap();
+        }
+    })
+    .await;
+}
@@ -0,0 +1,52 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy, NexusBdevError},
+};
+
+pub mod common;
+
+static HEX: &str = "null:///pattern_hex?size_mb=64&pattern=0xAB";
+static DECIMAL: &str = "null:///pattern_decimal?size_mb=64&pattern=171";
+static NONE: &str = "null:///pattern_none?size_mb=64";
+
+#[tokio::test]
+async fn bdev_null_pattern() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        for (uri, name) in &[(HEX, "pattern_hex"), (DECIMAL, "pattern_decimal")]
+        {
+            bdev_create(uri).await.unwrap();
+            let h = BdevHandle::open(name, false, false).unwrap();
+            let mut buf = h.dma_malloc(8192).unwrap();
+            h.read_at(4096, &mut buf).await.unwrap();
+            assert!(buf.as_slice().iter().all(|b| *b == 0xab));
+            h.close();
+            bdev_destroy(uri).await.unwrap();
+        }
+
+        // without a pattern reads still succeed
+        bdev_create(NONE).await.unwrap();
+        let h = BdevHandle::open("pattern_none", false, false).unwrap();
+        let mut buf = h.dma_malloc(4096).unwrap();
+        h.read_at(0, &mut buf).await.unwrap();
+        h.close();
+        bdev_destroy(NONE).await.unwrap();
+
+        for pattern in &["0x100", "256", "-1", "0x", "ab"] {
+            let uri = format!(
+                "null:///pattern_invalid?size_mb=64&pattern={}",
+                pattern
+            );
+            match bdev_create(&uri).await {
+                Err(NexusBdevError::UriInvalid {
+                    ..
+                }) => {}
+                result => panic!("unexpected result {:?} for {}", result, uri),
+            }
+        }
+    })
+    .await;
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::{Bdev, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy, bdev_resize, NexusBdevError},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "resize_nexus";
+static MALLOC: &str = "malloc:///resize_child?size_mb=64";
+static CHILD: &str = "bdev:///resize_child";
+static LARGE: &str = "malloc:///resize_large?size_mb=64";
+
+#[tokio::test]
+async fn bdev_resize_loopback() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        // shrink the malloc while it is not in use, so that it is backed by
+        // enough memory to grow again
+        bdev_create(MALLOC).await.unwrap();
+        let child = Bdev::lookup_by_name("resize_child").unwrap();
+        let num_blocks = child.num_blocks();
+        // the malloc is backed by enough memory for all the sizes used below
+        unsafe { bdev_resize(CHILD, num_blocks / 2) }.unwrap();
+        assert_eq!(child.num_blocks(), num_blocks / 2);
+
+        nexus_create(
+            NEXUS_NAME,
+            16 * 1024 * 1024,
+            None,
+            &[CHILD.into(), LARGE.into()],
+        )
+        .await
+        .unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        assert_eq!(nexus.min_num_blocks(), num_blocks / 2);
+
+        // the nexus learns about the new size from the resize event
+        unsafe { bdev_resize(CHILD, num_blocks) }.unwrap();
+        assert_eq!(child.num_blocks(), num_blocks);
+        Delay::new(Duration::from_millis(10)).await;
+        assert_eq!(nexus.min_num_blocks(), num_blocks);
+
+        // a bdev which is open cannot shrink
+        match unsafe { bdev_resize(CHILD, num_blocks / 2) } {
+            Err(NexusBdevError::ResizeBdev {
+                ..
+            }) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        match unsafe { bdev_resize("bdev:///resize_missing", 1024) } {
+            Err(NexusBdevError::BdevNotFound {
+                name,
+            }) => assert_eq!(name, "resize_missing"),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        nexus.destroy().await.unwrap();
+        bdev_destroy(MALLOC).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,62 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{nvme_nvm_opc, BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///write_protect?size_mb=64";
+
+#[tokio::test]
+async fn bdev_write_protect() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+
+        let h = BdevHandle::open("write_protect", true, false).unwrap();
+        assert!(!h.is_write_protected());
+
+        let buf = h.dma_malloc(4096).unwrap();
+        h.write_at(0, &buf).await.unwrap();
+
+        h.set_write_protect(true).unwrap();
+        assert!(h.is_write_protected());
+        match h.write_at(0, &buf).await {
+            Err(CoreError::WriteFailed {
+                offset,
+                len,
+            }) => {
+                assert_eq!(offset, 0);
+                assert_eq!(len, 4096);
+            }
+            r => panic!("write to a write protected handle: {:?}", r),
+        }
+
+        // reads are not affected
+        let mut rbuf = h.dma_malloc(4096).unwrap();
+        h.read_at(0, &mut rbuf).await.unwrap();
+
+        // nor can NVMe commands that write be passed through
+        let mut cmd = spdk_sys::spdk_nvme_cmd::default();
+        cmd.set_opc(0x01);
+        match h.nvme_io_passthru(&cmd, &mut rbuf).await {
+            Err(CoreError::NvmeWriteProtected {
+                opcode,
+            }) => assert_eq!(opcode, 0x01),
+            r => panic!("NVMe write to a write protected handle: {:?}", r),
+        }
+        assert!(nvme_nvm_opc::is_write(0x01));
+        assert!(!nvme_nvm_opc::is_write(nvme_nvm_opc::READ));
+
+        h.set_write_protect(false).unwrap();
+        assert!(!h.is_write_protected());
+        h.write_at(0, &buf).await.unwrap();
+
+        h.close();
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
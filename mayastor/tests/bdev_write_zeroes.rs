@@ -0,0 +1,43 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///zeroes_disk?size_mb=64&blk_size=512";
+
+#[tokio::test]
+async fn bdev_write_zeroes() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let h = BdevHandle::open("zeroes_disk", true, false).unwrap();
+
+        let mut buf = h.dma_malloc(8 * 512).unwrap();
+        buf.fill(0xff);
+        h.write_at(0, &buf).await.unwrap();
+
+        // only the given blocks are zeroed
+        h.write_zeroes(2, 4).await.unwrap();
+        h.read_at(0, &mut buf).await.unwrap();
+        for (i, block) in buf.as_slice().chunks(512).enumerate() {
+            let expected = if (2 .. 6).contains(&i) { 0 } else { 0xff };
+            assert!(block.iter().all(|b| *b == expected), "block {}", i);
+        }
+
+        // zeroing is a write, so a write protected handle rejects it
+        h.set_write_protect(true).unwrap();
+        assert!(matches!(
+            h.write_zeroes(0, 1).await,
+            Err(CoreError::WriteFailed { .. })
+        ));
+
+        drop(h);
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
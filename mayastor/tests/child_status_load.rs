@@ -0,0 +1,68 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+use mayastor::{
+    bdev::nexus_child_status_config::ChildStatusConfig,
+    core::MayastorCliArgs,
+};
+
+pub mod common;
+
+static CFG_FILE: &str = "/tmp/child_status_load.yaml";
+
+#[tokio::test]
+async fn child_status_load_async() {
+    // about 1 MB worth of child states
+    let mut cfg = String::from("---\nstatus:\n");
+    let mut i = 0;
+    while cfg.len() < 1024 * 1024 {
+        cfg.push_str(&format!(
+            "  \"nvmf://10.0.0.1:8420/nqn.2019-05.io.openebs:child{}\": Open\n",
+            i
+        ));
+        i += 1;
+    }
+    std::fs::write(CFG_FILE, cfg).unwrap();
+
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+    let max_stall = ms
+        .spawn(async {
+            let done = Cell::new(false);
+
+            // the longest time the reactor did not get to poll the ticker
+            let ticker = async {
+                let mut max_stall = Duration::default();
+                let mut last = Instant::now();
+                while !done.get() {
+                    Delay::new(Duration::from_millis(1)).await;
+                    let now = Instant::now();
+                    max_stall = max_stall.max(now - last);
+                    last = now;
+                }
+                max_stall
+            };
+            let load = async {
+                let cfg =
+                    ChildStatusConfig::load_async(&Some(CFG_FILE.into())).await;
+                done.set(true);
+                cfg
+            };
+
+            let (cfg, max_stall) = futures::join!(load, ticker);
+            assert!(cfg.is_ok());
+            max_stall
+        })
+        .await;
+
+    common::delete_file(&[CFG_FILE.to_string()]);
+    assert!(
+        max_stall < Duration::from_millis(10),
+        "the reactor stalled for {:?}",
+        max_stall
+    );
+}
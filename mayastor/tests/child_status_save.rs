@@ -0,0 +1,51 @@
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, NexusChild},
+    core::{MayastorCliArgs, MayastorEnvironment, Reactor},
+};
+
+pub mod common;
+
+static CFG_FILE: &str = "/tmp/child_status_save.yaml";
+static NEXUS_NAME: &str = "status_save_nexus";
+static CHILD0: &str = "malloc:///status_save0?size_mb=64";
+static CHILD1: &str = "malloc:///status_save1?size_mb=64";
+
+#[test]
+fn save_state_change_async() {
+    common::mayastor_test_init();
+    common::delete_file(&[CFG_FILE.to_string()]);
+
+    MayastorEnvironment::new(MayastorCliArgs {
+        reactor_mask: "0x1".to_string(),
+        child_status_config: Some(CFG_FILE.to_string()),
+        ..Default::default()
+    })
+    .init();
+
+    Reactor::block_on(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD0.to_string(), CHILD1.to_string()],
+        )
+        .await
+        .unwrap();
+
+        common::delete_file(&[CFG_FILE.to_string()]);
+        NexusChild::save_state_change_async().await.unwrap();
+
+        let cfg = std::fs::read_to_string(CFG_FILE).unwrap();
+        assert!(cfg.contains(CHILD0));
+        assert!(cfg.contains(CHILD1));
+        assert!(cfg.contains("Open"));
+        // the temporary file has been renamed over the configuration file
+        assert!(!std::path::Path::new(&format!("{}.tmp", CFG_FILE)).exists());
+
+        nexus_lookup(NEXUS_NAME).unwrap().destroy().await.unwrap();
+    });
+
+    let cfg = std::fs::read_to_string(CFG_FILE).unwrap();
+    assert!(!cfg.contains(CHILD0));
+    common::delete_file(&[CFG_FILE.to_string()]);
+}
@@ -1,6 +1,55 @@
 use mayastor::core::{BdevHandle, CoreError};
 
-pub async fn write_some(
+/// size of the IOs issued by the helpers that do not take an offset
+const IO_SIZE: u64 = 512;
+
+/// write a buffer filled with 0xff at offset 0
+pub async fn write_some(nexus_name: &str) -> Result<(), CoreError> {
+    fill_pattern(nexus_name, 0xff).await
+}
+
+/// read a buffer at offset 0
+pub async fn read_some(nexus_name: &str) -> Result<(), CoreError> {
+    let h = BdevHandle::open(nexus_name, true, false)?;
+    let mut buf = h.dma_malloc(IO_SIZE).expect("failed to allocate buffer");
+
+    let len = h.read_at(0, &mut buf).await?;
+    assert_eq!(len, IO_SIZE);
+
+    h.close();
+    Ok(())
+}
+
+/// unmap the given range of blocks
+pub async fn unmap_some(
+    nexus_name: &str,
+    offset_blocks: u64,
+    num_blocks: u64,
+) -> Result<(), CoreError> {
+    let h = BdevHandle::open(nexus_name, true, false)?;
+    h.unmap_blocks(offset_blocks, num_blocks).await?;
+
+    h.close();
+    Ok(())
+}
+
+/// write a buffer filled with the given pattern at offset 0
+pub async fn fill_pattern(
+    nexus_name: &str,
+    pattern: u8,
+) -> Result<(), CoreError> {
+    let h = BdevHandle::open(nexus_name, true, false)?;
+    let mut buf = h.dma_malloc(IO_SIZE).expect("failed to allocate buffer");
+    buf.fill(pattern);
+
+    h.write_at(0, &buf).await?;
+
+    h.close();
+    Ok(())
+}
+
+/// write two blocks filled with the given value at the given offset
+pub async fn write_at_offset(
     nexus_name: &str,
     offset: u64,
     fill: u8,
@@ -14,10 +63,14 @@ pub async fn write_some(
     assert_eq!(s[0], fill);
 
     h.write_at(offset, &buf).await?;
+
+    h.close();
     Ok(())
 }
 
-pub async fn read_some(
+/// read two blocks at the given offset and verify that the first 512 bytes
+/// match the given value
+pub async fn read_at_offset(
     nexus_name: &str,
     offset: u64,
     fill: u8,
@@ -40,5 +93,7 @@ pub async fn read_some(
     for &it in slice.iter().take(512) {
         assert_eq!(it, fill);
     }
+
+    h.close();
     Ok(())
 }
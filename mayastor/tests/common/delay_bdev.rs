@@ -0,0 +1,24 @@
+use spdk_sys::create_delay_disk;
+
+/// create a delay bdev on top of the given bdev adding the read latency, in
+/// microseconds, to every read while leaving writes untouched
+pub fn create_delay_bdev(
+    delay_device: &str,
+    backing_device: &str,
+    read_latency_us: u64,
+) {
+    let cname = std::ffi::CString::new(delay_device).unwrap();
+    let base = std::ffi::CString::new(backing_device).unwrap();
+
+    let retval = unsafe {
+        create_delay_disk(
+            base.as_ptr(),
+            cname.as_ptr(),
+            read_latency_us,
+            read_latency_us,
+            0,
+            0,
+        )
+    };
+    assert_eq!(retval, 0);
+}
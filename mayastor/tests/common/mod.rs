@@ -21,6 +21,7 @@ use mayastor::{
 
 pub mod bdev_io;
 pub mod compose;
+pub mod delay_bdev;
 pub mod error_bdev;
 
 pub use compose::MayastorTest;
@@ -417,11 +418,15 @@ pub fn get_device_size(nexus_device: &str) -> u64 {
 }
 
 /// Waits for the rebuild to reach `state`, up to `timeout`
-pub fn wait_for_rebuild(name: String, state: RebuildState, timeout: Duration) {
+pub fn wait_for_rebuild(
+    name: String,
+    state: RebuildState,
+    timeout: Duration,
+) -> Result<(), &'static str> {
     let (s, r) = unbounded::<()>();
     let job = match RebuildJob::lookup(&name) {
         Ok(job) => job,
-        Err(_) => return,
+        Err(_) => return Ok(()),
     };
     job.as_client().stats();
 
@@ -432,18 +437,20 @@ pub fn wait_for_rebuild(name: String, state: RebuildState, timeout: Duration) {
         let now = std::time::Instant::now();
         let mut error = Ok(());
         while curr_state != state && error.is_ok() {
+            let remaining =
+                timeout.checked_sub(now.elapsed()).unwrap_or_default();
             select! {
                 recv(ch) -> state => {
                     trace!("rebuild of child {} signalled with state {:?}", cname, state);
                     curr_state = state.unwrap_or_else(|e| {
                         error!("failed to wait for the rebuild with error: {}", e);
-                        error = Err(());
+                        error = Err("failed to wait for rebuild state");
                         curr_state
                     })
                 },
-                recv(after(timeout - now.elapsed())) -> _ => {
+                recv(after(remaining)) -> _ => {
                     error!("timed out waiting for the rebuild after {:?}", timeout);
-                    error = Err(())
+                    error = Err("timed out waiting for rebuild state")
                 }
             }
         }
@@ -455,7 +462,12 @@ pub fn wait_for_rebuild(name: String, state: RebuildState, timeout: Duration) {
     if let Ok(job) = RebuildJob::lookup(&name) {
         job.as_client().stats();
     }
-    t.join().unwrap().unwrap();
+    t.join().unwrap()
+}
+
+/// Waits for the rebuild to complete, up to 5 minutes
+pub fn wait_for_rebuild_complete(name: String) -> Result<(), &'static str> {
+    wait_for_rebuild(name, RebuildState::Completed, Duration::from_secs(300))
 }
 
 pub fn fio_verify_size(device: &str, size: u64) -> i32 {
@@ -0,0 +1,19 @@
+use mayastor::subsys::Config;
+
+pub mod common;
+
+#[test]
+fn config_changed_sections() {
+    let running = Config::default();
+    let mut config = Config::default();
+    // where the config was loaded from is not a change
+    config.source = Some("/tmp/config_changed_sections.yaml".into());
+    assert!(running.changed_sections(&config).is_empty());
+
+    config.nexus_opts.iscsi_enable = !running.nexus_opts.iscsi_enable;
+    config.sync_disable = !running.sync_disable;
+    assert_eq!(
+        running.changed_sections(&config),
+        vec!["nexus_opts", "sync_disable"]
+    );
+}
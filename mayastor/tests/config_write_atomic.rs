@@ -0,0 +1,31 @@
+use std::fs;
+
+use mayastor::subsys::Config;
+
+pub mod common;
+
+static CONFIG_FILE: &str = "/tmp/config_write_atomic.yaml";
+static TMP_FILE: &str = "/tmp/config_write_atomic.yaml.tmp";
+
+#[test]
+fn config_write_atomic() {
+    common::delete_file(&[CONFIG_FILE.into(), TMP_FILE.into()]);
+
+    let config = Config::default();
+    config.write_atomic(CONFIG_FILE).unwrap();
+    let original = fs::read(CONFIG_FILE).unwrap();
+    assert!(!original.is_empty());
+    assert!(Config::read(CONFIG_FILE).is_ok());
+    // nothing is left behind once the file is replaced
+    assert!(fs::metadata(TMP_FILE).is_err());
+
+    // simulate a crash while writing by making the temporary file impossible
+    // to create, the original config must be left intact
+    fs::create_dir_all(format!("{}/busy", TMP_FILE)).unwrap();
+    let mut config = Config::default();
+    config.source = Some("changed".into());
+    assert!(config.write_atomic(CONFIG_FILE).is_err());
+    assert_eq!(fs::read(CONFIG_FILE).unwrap(), original);
+
+    common::delete_file(&[CONFIG_FILE.into(), TMP_FILE.into()]);
+}
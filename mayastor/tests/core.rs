@@ -75,10 +75,25 @@ async fn works() {
     let b = Bdev::lookup_by_name("core_nexus").unwrap();
     assert_eq!(b.name(), "core_nexus");
 
-    let desc = Bdev::open_by_name("core_nexus", false).unwrap();
+    let desc = Bdev::lookup_by_name("core_nexus")
+        .unwrap()
+        .open_read_only()
+        .unwrap();
+    assert!(!desc.is_writable());
     let channel = desc.get_channel().expect("failed to get IO channel");
     drop(channel);
     drop(desc);
+
+    let desc = b.open_read_write().unwrap();
+    assert!(desc.is_writable());
+    drop(desc);
+
+    // only a single exclusive descriptor can exist at any time
+    let desc = b.open_exclusive().unwrap();
+    assert!(desc.is_writable());
+    assert!(b.open_exclusive().is_err());
+    desc.release();
+    drop(desc);
     let n = nexus_lookup("core_nexus").expect("nexus not found");
     n.destroy().await.unwrap();
 }
@@ -91,9 +106,13 @@ async fn core_2() {
 
             let n = nexus_lookup("core_nexus").expect("failed to lookup nexus");
 
-            let d1 = Bdev::open_by_name("core_nexus", true)
+            let d1 = Bdev::lookup_by_name("core_nexus")
+                .unwrap()
+                .open_read_write()
                 .expect("failed to open first desc to nexus");
-            let d2 = Bdev::open_by_name("core_nexus", true)
+            let d2 = Bdev::lookup_by_name("core_nexus")
+                .unwrap()
+                .open_read_write()
                 .expect("failed to open second desc to nexus");
 
             let ch1 = d1.get_channel().expect("failed to get channel!");
@@ -0,0 +1,56 @@
+use mayastor::core::{parse_core_list, parse_core_mask, EnvError};
+
+pub mod common;
+
+#[test]
+fn core_list() {
+    assert_eq!(parse_core_list("0-3", 4).unwrap(), vec![0, 1, 2, 3]);
+    assert_eq!(parse_core_list("0,2-3", 4).unwrap(), vec![0, 2, 3]);
+
+    assert!(matches!(
+        parse_core_list("0,1,99", 4),
+        Err(EnvError::CoreNotFound {
+            core: 99,
+            count: 4
+        })
+    ));
+    assert!(matches!(
+        parse_core_list("3-1", 4),
+        Err(EnvError::InvalidCoreList {
+            ..
+        })
+    ));
+    assert!(matches!(
+        parse_core_list("0,one", 4),
+        Err(EnvError::InvalidCoreList {
+            ..
+        })
+    ));
+}
+
+#[test]
+fn core_mask() {
+    assert_eq!(parse_core_mask("0x1", 4).unwrap(), vec![0]);
+    assert_eq!(parse_core_mask("0xa", 4).unwrap(), vec![1, 3]);
+    assert_eq!(parse_core_mask("f", 4).unwrap(), vec![0, 1, 2, 3]);
+
+    assert!(matches!(
+        parse_core_mask("0x10", 4),
+        Err(EnvError::CoreNotFound {
+            core: 4,
+            count: 4
+        })
+    ));
+    assert!(matches!(
+        parse_core_mask("0x0", 4),
+        Err(EnvError::InvalidCoreMask {
+            ..
+        })
+    ));
+    assert!(matches!(
+        parse_core_mask("0xg", 4),
+        Err(EnvError::InvalidCoreMask {
+            ..
+        })
+    ));
+}
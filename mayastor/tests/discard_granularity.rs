@@ -0,0 +1,34 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{Bdev, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///discard_granularity?size_mb=64&blk_size=4096";
+
+#[tokio::test]
+async fn discard_granularity() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let bdev = Bdev::lookup_by_name("discard_granularity").unwrap();
+        let block_len = u64::from(bdev.block_len());
+
+        let granularity = bdev.discard_granularity();
+        assert_ne!(granularity, 0);
+        assert_eq!(granularity % block_len, 0);
+        // non NVMe bdevs report their block size
+        assert_eq!(granularity, block_len);
+
+        let optimal = bdev.optimal_io_size();
+        assert_ne!(optimal, 0);
+        assert_eq!(optimal % block_len, 0);
+
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,52 @@
+use std::process::{Command, Output};
+
+pub mod common;
+
+// a file backed bdev keeps the pattern written by the test across the
+// invocations of the initiator
+static DISKNAME: &str = "/tmp/initiator_trim.img";
+static BDEVNAME: &str = "aio:///tmp/initiator_trim.img?blk_size=512";
+
+const PATTERN: u8 = 0xa5;
+const TRIM_OFFSET: usize = 64 * 1024;
+const TRIM_LENGTH: usize = 128 * 1024;
+
+fn initiator(args: &[&str]) -> Output {
+    Command::new("../target/debug/initiator")
+        .arg(BDEVNAME)
+        .args(args)
+        .output()
+        .expect("failed to run the initiator")
+}
+
+#[test]
+fn initiator_trim() {
+    common::delete_file(&[DISKNAME.into()]);
+    std::fs::write(DISKNAME, vec![PATTERN; 1024 * 1024]).unwrap();
+
+    let output = initiator(&[
+        "trim",
+        &format!("--offset={}", TRIM_OFFSET),
+        &format!("--length={}", TRIM_LENGTH),
+        "--verify",
+    ]);
+    let log = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.status.success(), "trim failed: {}", log);
+    assert!(log.contains("256 blocks unmapped"), "{}", log);
+
+    let data = std::fs::read(DISKNAME).unwrap();
+    let end = TRIM_OFFSET + TRIM_LENGTH;
+    assert!(data[.. TRIM_OFFSET].iter().all(|b| *b == PATTERN));
+    assert!(data[TRIM_OFFSET .. end].iter().all(|b| *b == 0));
+    assert!(data[end ..].iter().all(|b| *b == PATTERN));
+
+    // the range must be aligned to the block size
+    let output = initiator(&["trim", "--offset=100", "--length=512"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    common::delete_file(&[DISKNAME.into()]);
+}
@@ -0,0 +1,59 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///io_cancel?size_mb=64";
+
+#[tokio::test]
+async fn io_cancel() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let h = BdevHandle::open("io_cancel", true, false).unwrap();
+        let mut buf = h.dma_malloc(4096).unwrap();
+
+        // an IO that is not cancelled completes as usual
+        let token = Arc::new(AtomicBool::new(false));
+        h.write_at_cancellable(0, &buf, Some(token.clone()))
+            .await
+            .unwrap();
+        h.read_at_cancellable(0, &mut buf, Some(token.clone()))
+            .await
+            .unwrap();
+
+        // the token is set once the IO has been submitted
+        let cancel = async {
+            token.store(true, Ordering::SeqCst);
+        };
+        let (write, _) = futures::join!(
+            h.write_at_cancellable(0, &buf, Some(token.clone())),
+            cancel
+        );
+        assert!(matches!(write, Err(CoreError::IoCancelled { .. })));
+
+        token.store(false, Ordering::SeqCst);
+        let cancel = async {
+            token.store(true, Ordering::SeqCst);
+        };
+        let (read, _) = futures::join!(
+            h.read_at_cancellable(0, &mut buf, Some(token.clone())),
+            cancel
+        );
+        assert!(matches!(read, Err(CoreError::IoCancelled { .. })));
+
+        drop(h);
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
@@ -45,7 +45,10 @@ struct ShareableContext {
 impl ShareableContext {
     /// Create a new Shareable Context
     pub fn new(offset: u64, len: u64) -> ShareableContext {
-        let nexus = Bdev::open_by_name(NEXUS_NAME, true).unwrap();
+        let nexus = Bdev::lookup_by_name(NEXUS_NAME)
+            .unwrap()
+            .open_read_write()
+            .unwrap();
         Self {
             ctx: Rc::new(RefCell::new(RangeContext::new(offset, len))),
             ch: Rc::new(RefCell::new(nexus.get_channel().unwrap())),
@@ -109,7 +112,10 @@ async fn lock_range(
     ctx: &mut RangeContext,
     ch: &IoChannel,
 ) -> Result<(), nix::errno::Errno> {
-    let nexus = Bdev::open_by_name(NEXUS_NAME, true).unwrap();
+    let nexus = Bdev::lookup_by_name(NEXUS_NAME)
+        .unwrap()
+        .open_read_write()
+        .unwrap();
     nexus.lock_lba_range(ctx, ch).await
 }
 
@@ -117,7 +123,10 @@ async fn unlock_range(
     ctx: &mut RangeContext,
     ch: &IoChannel,
 ) -> Result<(), nix::errno::Errno> {
-    let nexus = Bdev::open_by_name(NEXUS_NAME, true).unwrap();
+    let nexus = Bdev::lookup_by_name(NEXUS_NAME)
+        .unwrap()
+        .open_read_write()
+        .unwrap();
     nexus.unlock_lba_range(ctx, ch).await
 }
 
@@ -126,7 +135,10 @@ async fn unlock_range(
 fn lock_unlock() {
     test_ini();
     Reactor::block_on(async {
-        let nexus = Bdev::open_by_name(NEXUS_NAME, true).unwrap();
+        let nexus = Bdev::lookup_by_name(NEXUS_NAME)
+            .unwrap()
+            .open_read_write()
+            .unwrap();
         let mut ctx = RangeContext::new(1, 5);
         let ch = nexus.get_channel().unwrap();
         nexus
@@ -146,7 +158,10 @@ fn lock_unlock() {
 fn lock_unlock_different_context() {
     test_ini();
     Reactor::block_on(async {
-        let nexus = Bdev::open_by_name(NEXUS_NAME, true).unwrap();
+        let nexus = Bdev::lookup_by_name(NEXUS_NAME)
+            .unwrap()
+            .open_read_write()
+            .unwrap();
 
         let mut ctx = RangeContext::new(1, 5);
         let ch = nexus.get_channel().unwrap();
@@ -268,7 +283,10 @@ fn lock_then_fe_io() {
     // Issue front-end I/O
     let (io_sender, io_receiver) = unbounded::<()>();
     reactor.send_future(async move {
-        let nexus_desc = Bdev::open_by_name(&NEXUS_NAME, true).unwrap();
+        let nexus_desc = Bdev::lookup_by_name(&NEXUS_NAME)
+            .unwrap()
+            .open_read_write()
+            .unwrap();
         let h = nexus_desc.into_handle().unwrap();
 
         let blk = 2;
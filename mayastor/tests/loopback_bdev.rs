@@ -0,0 +1,37 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::MayastorCliArgs,
+    nexus_uri::{bdev_create, NexusBdevError},
+};
+
+pub mod common;
+
+static MISSING_ALLOWED: &str =
+    "bdev:///loopback_missing?create_if_missing=true";
+
+#[tokio::test]
+async fn loopback_bdev() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        // the bdev must already exist
+        match bdev_create("bdev:///loopback_missing").await {
+            Err(NexusBdevError::BdevNotFound {
+                name,
+            }) => assert_eq!(name, "loopback_missing"),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // unless told otherwise
+        let name = bdev_create(MISSING_ALLOWED).await.unwrap();
+        assert_eq!(name, "loopback_missing");
+
+        bdev_create("malloc:///loopback_disk?size_mb=64")
+            .await
+            .unwrap();
+        let name = bdev_create("bdev:///loopback_disk").await.unwrap();
+        assert_eq!(name, "loopback_disk");
+    })
+    .await;
+}
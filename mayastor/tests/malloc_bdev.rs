@@ -24,8 +24,14 @@ async fn malloc_bdev() {
     .await;
 
     ms.spawn(async {
-        let m0 = Bdev::open_by_name("malloc0", true).unwrap();
-        let m1 = Bdev::open_by_name("malloc1", true).unwrap();
+        let m0 = Bdev::lookup_by_name("malloc0")
+            .unwrap()
+            .open_read_write()
+            .unwrap();
+        let m1 = Bdev::lookup_by_name("malloc1")
+            .unwrap()
+            .open_read_write()
+            .unwrap();
 
         assert_eq!(
             m0.get_bdev().size_in_bytes(),
@@ -0,0 +1,82 @@
+use nix::errno::Errno;
+
+use mayastor::{
+    core::{Bdev, BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::bdev_create,
+};
+use rpc::mayastor::{BdevShareRequest, BdevUri};
+
+pub mod common;
+use common::{compose, MayastorTest};
+
+#[tokio::test]
+async fn max_transfer_size() {
+    let test = compose::Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+
+    hdls[0]
+        .bdev
+        .create(BdevUri {
+            uri: "malloc:///disk0?size_mb=64".into(),
+        })
+        .await
+        .unwrap();
+    let uri = hdls[0]
+        .bdev
+        .share(BdevShareRequest {
+            name: "disk0".into(),
+            proto: "nvmf".into(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .uri;
+
+    let mayastor = MayastorTest::new(MayastorCliArgs::default());
+    mayastor
+        .spawn(async move {
+            let name = bdev_create(&uri).await.unwrap();
+            let bdev = Bdev::lookup_by_name(&name).unwrap();
+
+            let page_size = 4096;
+            let mts = bdev.max_transfer_size();
+            assert!(mts >= page_size);
+            assert!(mts.is_power_of_two());
+            assert_ne!(mts, u64::MAX);
+
+            let h = BdevHandle::open(&name, true, false).unwrap();
+            let mut buf = h.dma_malloc(mts).unwrap();
+            h.read_at(0, &mut buf).await.unwrap();
+            h.write_at(0, &buf).await.unwrap();
+
+            let mut buf = h.dma_malloc(mts + page_size).unwrap();
+            assert!(matches!(
+                h.read_at(0, &mut buf).await,
+                Err(CoreError::ReadDispatch {
+                    source: Errno::E2BIG,
+                    ..
+                })
+            ));
+            assert!(matches!(
+                h.write_at(0, &buf).await,
+                Err(CoreError::WriteDispatch {
+                    source: Errno::E2BIG,
+                    ..
+                })
+            ));
+
+            // bdevs other than NVMe do not limit the transfer size
+            bdev_create("malloc:///m0?size_mb=8").await.unwrap();
+            let m0 = Bdev::lookup_by_name("m0").unwrap();
+            assert_eq!(m0.max_transfer_size(), u64::MAX);
+        })
+        .await
+}
@@ -3,7 +3,7 @@ use mayastor::{
     core::{Bdev, MayastorCliArgs},
     nexus_uri::bdev_create,
 };
-use rpc::mayastor::{BdevShareRequest, BdevUri, Null};
+use rpc::mayastor::{BdevShareRequest, BdevUri, ListBdevsRequest};
 
 pub mod common;
 use common::{compose::Builder, MayastorTest};
@@ -26,7 +26,7 @@ async fn compose_up_down() {
 
     // create and share a bdev on each container
     for h in &mut hdls {
-        h.bdev.list(Null {}).await.unwrap();
+        h.bdev.list(ListBdevsRequest::default()).await.unwrap();
         h.bdev
             .create(BdevUri {
                 uri: "malloc:///disk0?size_mb=100".into(),
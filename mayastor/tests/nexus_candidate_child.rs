@@ -0,0 +1,81 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, Error},
+    core::{Bdev, MayastorCliArgs},
+    nexus_uri::bdev_create,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "candidate_nexus";
+static CHILD_1: &str = "malloc:///candidate_child1?size_mb=64";
+static CHILD_2: &str = "malloc:///candidate_child2?size_mb=64";
+static CANDIDATE: &str = "malloc:///candidate?size_mb=64";
+static SMALL: &str = "malloc:///candidate_small?size_mb=16";
+static SPARE: &str = "malloc:///candidate_spare?size_mb=64";
+
+#[tokio::test]
+async fn nexus_candidate_child() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD_1.into(), CHILD_2.into()],
+        )
+        .await
+        .unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+
+        // a blank device is a valid candidate, and is not kept around
+        nexus.validate_candidate_child(CANDIDATE).await.unwrap();
+        assert!(Bdev::lookup_by_name("candidate").is_none());
+
+        match nexus.validate_candidate_child(SMALL).await {
+            Err(Error::ChildGeometry {
+                child_num_blocks,
+                num_blocks,
+                ..
+            }) => assert!(child_num_blocks < num_blocks),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(Bdev::lookup_by_name("candidate_small").is_none());
+
+        match nexus.validate_candidate_child(CHILD_1).await {
+            Err(Error::ChildExists {
+                child, ..
+            }) => assert_eq!(child, CHILD_1),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(Bdev::lookup_by_name("candidate_child1").is_some());
+
+        // the bdev of a child cannot be a candidate under another uri
+        match nexus
+            .validate_candidate_child("bdev:///candidate_child2")
+            .await
+        {
+            Err(Error::ChildInUse {
+                nexus: owner, ..
+            }) => assert_eq!(owner, NEXUS_NAME),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(Bdev::lookup_by_name("candidate_child2").is_some());
+
+        // an existing bdev is a valid candidate, and is left in place
+        bdev_create(SPARE).await.unwrap();
+        nexus
+            .validate_candidate_child("bdev:///candidate_spare")
+            .await
+            .unwrap();
+        assert!(Bdev::lookup_by_name("candidate_spare").is_some());
+
+        // the nexus itself is left untouched
+        assert_eq!(nexus.children.len(), 2);
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,74 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, Error},
+    core::MayastorCliArgs,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "geometry_nexus";
+static CHILD_1: &str = "malloc:///geometry_child1?size_mb=64";
+static CHILD_2: &str = "malloc:///geometry_child2?size_mb=128";
+static SMALL: &str = "malloc:///geometry_small?size_mb=16";
+static WRONG_BLK_SIZE: &str =
+    "malloc:///geometry_blk_size?size_mb=64&blk_size=4096";
+
+#[tokio::test]
+async fn nexus_child_geometry() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD_1.into(), CHILD_2.into()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        assert_eq!(
+            nexus.children_geometry(),
+            vec![
+                (CHILD_1.to_string(), 512, 131_072),
+                (CHILD_2.to_string(), 512, 262_144)
+            ]
+        );
+        assert_eq!(nexus.min_num_blocks(), 131_072);
+        assert_eq!(nexus.max_num_blocks(), 262_144);
+
+        match nexus.add_child(SMALL, false).await {
+            Err(Error::ChildGeometry {
+                child_block_len,
+                child_num_blocks,
+                block_len,
+                num_blocks,
+                ..
+            }) => {
+                assert_eq!(child_block_len, 512);
+                assert_eq!(child_num_blocks, 32_768);
+                assert_eq!(block_len, 512);
+                assert_eq!(num_blocks, 131_072);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        match nexus.add_child(WRONG_BLK_SIZE, false).await {
+            Err(Error::ChildGeometry {
+                child_block_len,
+                block_len,
+                ..
+            }) => {
+                assert_eq!(child_block_len, 4096);
+                assert_eq!(block_len, 512);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(nexus.children_geometry().len(), 2);
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
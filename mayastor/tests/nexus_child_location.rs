@@ -2,7 +2,7 @@ use mayastor::{
     bdev::{nexus_create, nexus_lookup},
     core::MayastorCliArgs,
 };
-use rpc::mayastor::{BdevShareRequest, BdevUri, Null};
+use rpc::mayastor::{BdevShareRequest, BdevUri, ListBdevsRequest};
 
 pub mod common;
 use common::{compose::Builder, MayastorTest};
@@ -25,7 +25,7 @@ async fn child_location() {
     let mut hdls = test.grpc_handles().await.unwrap();
 
     // Create and share a bdev over nvmf
-    hdls[0].bdev.list(Null {}).await.unwrap();
+    hdls[0].bdev.list(ListBdevsRequest::default()).await.unwrap();
     hdls[0]
         .bdev
         .create(BdevUri {
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, ChildState},
+    core::{BdevHandle, MayastorCliArgs},
+    nexus_uri::bdev_create,
+    rebuild::RebuildJob,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "reconnect_nexus";
+static CHILD_1: &str = "malloc:///reconnect_child1?size_mb=64";
+static CHILD_2_BASE: &str = "malloc:///reconnect_child2?size_mb=64";
+// the underlying malloc bdev, and so its data, outlives the child
+static CHILD_2: &str = "bdev:///reconnect_child2";
+
+#[tokio::test]
+async fn nexus_child_reconnect() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(CHILD_2_BASE).await.unwrap();
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD_1.into(), CHILD_2.into()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.offline_child(CHILD_2).await.unwrap();
+        Delay::new(Duration::from_secs(1)).await;
+        let child = nexus.get_child_by_name(CHILD_2).unwrap();
+        assert!(child.time_since_offline().unwrap() >= Duration::from_secs(1));
+
+        // nothing was written in the meantime, so no rebuild is needed
+        nexus.reconnect_child(CHILD_2, 10).await.unwrap();
+        let child = nexus.get_child_by_name(CHILD_2).unwrap();
+        assert_eq!(child.state(), ChildState::Open);
+        assert!(child.time_since_offline().is_none());
+        assert!(RebuildJob::lookup(CHILD_2).is_err());
+
+        // a child that missed a write has to be rebuilt
+        nexus.offline_child(CHILD_2).await.unwrap();
+        let h = BdevHandle::open(NEXUS_NAME, true, false).unwrap();
+        let buf = h.dma_malloc(4096).unwrap();
+        h.write_at(0, &buf).await.unwrap();
+        drop(h);
+
+        nexus.reconnect_child(CHILD_2, 10).await.unwrap();
+        let child = nexus.get_child_by_name(CHILD_2).unwrap();
+        assert_ne!(child.state(), ChildState::Open);
+        assert!(RebuildJob::lookup(CHILD_2).is_ok());
+
+        nexus_lookup(NEXUS_NAME).unwrap().destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, ChildState},
+    core::MayastorCliArgs,
+    nexus_uri::bdev_create,
+};
+
+pub mod common;
+use common::delay_bdev::create_delay_bdev;
+
+static NEXUS_NAME: &str = "status_nexus";
+static SRC_BASE_BDEV: &str = "malloc:///status_src_base?size_mb=64";
+static SRC_BDEV: &str = "status_src";
+static DST_BDEV: &str = "malloc:///status_dst?size_mb=64";
+
+async fn rebuild_progress() -> Option<f64> {
+    nexus_lookup(NEXUS_NAME)
+        .unwrap()
+        .get_children_status()
+        .await
+        .into_iter()
+        .find(|c| c.uri == DST_BDEV)
+        .unwrap()
+        .rebuild_progress
+}
+
+#[tokio::test]
+async fn nexus_children_status() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(SRC_BASE_BDEV).await.unwrap();
+        // slow down the reads of the rebuild source so that we can catch the
+        // rebuild while it is in progress
+        create_delay_bdev(SRC_BDEV, "status_src_base", 10_000);
+
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[format!("bdev:///{}", SRC_BDEV)],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_child(DST_BDEV, true).await.unwrap();
+
+        let status = nexus.get_children_status().await;
+        assert_eq!(status.len(), 2);
+        assert!(status.iter().all(|c| c.rebuild_progress.is_none()));
+
+        let src = status.iter().find(|c| c.name == SRC_BDEV).unwrap();
+        assert!(src.healthy);
+        assert_eq!(src.state, ChildState::Open);
+
+        let dst = status.iter().find(|c| c.uri == DST_BDEV).unwrap();
+        assert_eq!(dst.name, "status_dst");
+        assert!(!dst.healthy);
+
+        let _ = nexus.start_rebuild(DST_BDEV, false).await.unwrap();
+    })
+    .await;
+
+    // wait until some, but not all, of the blocks have been rebuilt
+    let mut retries = 100;
+    while ms.spawn(rebuild_progress()).await == Some(0.0) {
+        retries -= 1;
+        assert_ne!(retries, 0, "rebuild did not make any progress");
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+    }
+
+    ms.spawn(async {
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.pause_rebuild(DST_BDEV).await.unwrap();
+
+        let rebuilding = nexus
+            .get_children_status()
+            .await
+            .into_iter()
+            .filter_map(|c| c.rebuild_progress)
+            .collect::<Vec<_>>();
+
+        assert_eq!(rebuilding.len(), 1);
+        assert!(
+            rebuilding[0] > 0.0 && rebuilding[0] < 1.0,
+            "unexpected rebuild progress {}",
+            rebuilding[0]
+        );
+
+        match nexus.get_child_by_name(DST_BDEV).unwrap().state() {
+            ChildState::Rebuilding {
+                source,
+                progress_pct,
+            } => {
+                assert_eq!(source, format!("bdev:///{}", SRC_BDEV));
+                assert!(
+                    progress_pct >= 0.0 && progress_pct < 100.0,
+                    "unexpected rebuild progress {}%",
+                    progress_pct
+                );
+            }
+            state => panic!("child is not rebuilding but {}", state),
+        }
+
+        nexus.remove_child(DST_BDEV).await.unwrap();
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::MayastorCliArgs,
+    nexus_uri::bdev_create,
+    rebuild::RebuildJob,
+};
+
+pub mod common;
+use common::delay_bdev::create_delay_bdev;
+
+static NEXUS_NAME: &str = "destroy_rebuild_nexus";
+static SRC_BASE_BDEV: &str = "malloc:///destroy_src_base?size_mb=64";
+static SRC_BDEV: &str = "destroy_src";
+static DST_BDEV: &str = "malloc:///destroy_dst?size_mb=64";
+
+#[tokio::test]
+async fn nexus_destroy_cancels_rebuild() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    let destroy = ms.spawn(async {
+        bdev_create(SRC_BASE_BDEV).await.unwrap();
+        // keep the rebuild running long enough for the destroy to catch it
+        create_delay_bdev(SRC_BDEV, "destroy_src_base", 10_000);
+
+        let src = format!("bdev:///{}", SRC_BDEV);
+        nexus_create(NEXUS_NAME, 32 * 1024 * 1024, None, &[src.clone()])
+            .await
+            .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_child(DST_BDEV, true).await.unwrap();
+        let _ = nexus.start_rebuild(DST_BDEV, false).await.unwrap();
+        assert!(RebuildJob::lookup(DST_BDEV).is_ok());
+
+        nexus.destroy().await.unwrap();
+
+        assert!(nexus_lookup(NEXUS_NAME).is_none());
+        assert!(RebuildJob::lookup(DST_BDEV).is_err());
+        assert!(RebuildJob::lookup_src(&src).is_empty());
+    });
+
+    tokio::time::timeout(Duration::from_secs(5), destroy)
+        .await
+        .expect("cancelling the rebuild on destroy took too long");
+}
@@ -0,0 +1,58 @@
+use common::{bdev_io, MayastorTest};
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, Nexus, NexusDetachedState},
+    core::{Bdev, MayastorCliArgs},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "detach_nexus";
+static CHILD_1: &str = "malloc:///detach_child1?size_mb=64";
+static CHILD_2: &str = "malloc:///detach_child2?size_mb=64";
+
+#[tokio::test]
+async fn nexus_detach_attach() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD_1.into(), CHILD_2.into()],
+        )
+        .await
+        .unwrap();
+
+        let uuid = Bdev::lookup_by_name(NEXUS_NAME).unwrap().uuid_as_string();
+        bdev_io::write_at_offset(NEXUS_NAME, 4096, 0xa5).await.unwrap();
+
+        let state = nexus_lookup(NEXUS_NAME).unwrap().detach().await;
+        assert!(nexus_lookup(NEXUS_NAME).is_none());
+
+        // the state survives a restart of the process
+        let state: NexusDetachedState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap())
+                .unwrap();
+
+        Nexus::attach(state.clone()).await.unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        assert_eq!(nexus.children.len(), 2);
+        assert_eq!(
+            Bdev::lookup_by_name(NEXUS_NAME).unwrap().uuid_as_string(),
+            uuid
+        );
+
+        // the data written before the nexus was detached is still there
+        bdev_io::read_at_offset(NEXUS_NAME, 4096, 0xa5).await.unwrap();
+        bdev_io::write_some(NEXUS_NAME).await.unwrap();
+        bdev_io::read_some(NEXUS_NAME).await.unwrap();
+
+        // a nexus which is attached cannot be attached twice
+        assert!(Nexus::attach(state).await.is_err());
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
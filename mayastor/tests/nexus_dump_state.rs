@@ -0,0 +1,56 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::nexus_create,
+    core::{MayastorCliArgs, Uuid},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "dump_state_nexus";
+static NEXUS_UUID: &str = "cdc2a7db-3ac3-403a-af80-7fadc1581c47";
+static CHILD0: &str = "malloc:///dump0?size_mb=64";
+static CHILD1: &str = "malloc:///dump1?size_mb=64";
+
+#[tokio::test]
+async fn nexus_dump_state() {
+    let args = MayastorCliArgs::default();
+    let rpc_address = args.rpc_address.clone();
+    let ms = MayastorTest::new(args);
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            Some(NEXUS_UUID),
+            &[CHILD0.to_string(), CHILD1.to_string()],
+        )
+        .await
+        .unwrap();
+    })
+    .await;
+
+    let dump: serde_json::Value =
+        jsonrpc::call::<(), _>(&rpc_address, "mayastor_dump_state", None)
+            .await
+            .unwrap();
+
+    let nexus = dump["nexus"].as_array().unwrap();
+    assert!(!nexus.is_empty());
+
+    let nexus = nexus
+        .iter()
+        .find(|n| n["name"] == NEXUS_NAME)
+        .expect("nexus missing from the state dump");
+    let uuid = Uuid::parse_str(nexus["uuid"].as_str().unwrap()).unwrap();
+    assert_eq!(uuid.to_string(), NEXUS_UUID);
+    assert_eq!(nexus["state"], "open");
+    assert_eq!(nexus["status"], "online");
+
+    let children = nexus["children"].as_array().unwrap();
+    assert_eq!(children.len(), 2);
+    for child in children {
+        assert_eq!(child["state"], "Child is open");
+        assert!(child["rebuild_progress"].is_null());
+    }
+}
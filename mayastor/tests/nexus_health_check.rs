@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, ChildState, Reason},
+    core::MayastorCliArgs,
+    subsys::{Config, NvmeBdevOpts},
+};
+
+pub mod common;
+use common::error_bdev::{
+    create_error_bdev,
+    inject_error,
+    SPDK_BDEV_IO_TYPE_READ,
+    VBDEV_IO_FAILURE,
+};
+
+static NEXUS_NAME: &str = "health_check_nexus";
+static HEALTHY_CHILD: &str = "malloc:///health_check_healthy?size_mb=64";
+
+static DISKS: [&str; 2] =
+    ["/tmp/health_check_disk0.img", "/tmp/health_check_disk1.img"];
+static ERROR_DEVICES: [&str; 2] =
+    ["health_check_error0", "health_check_error1"];
+
+const INTERVAL_MS: u64 = 100;
+const MAX_FAILURES: u32 = 3;
+
+fn error_child(index: usize) -> String {
+    format!("bdev:///EE_{}", ERROR_DEVICES[index])
+}
+
+/// fail just enough reads to fault the child, so that the error bdev can be
+/// reused afterwards
+fn fail_reads(index: usize) {
+    inject_error(
+        &format!("EE_{}", ERROR_DEVICES[index]),
+        SPDK_BDEV_IO_TYPE_READ,
+        VBDEV_IO_FAILURE,
+        MAX_FAILURES,
+    );
+}
+
+#[tokio::test]
+async fn nexus_health_check() {
+    Config::get_or_init(|| Config {
+        nvme_bdev_opts: NvmeBdevOpts {
+            health_check_interval_ms: INTERVAL_MS,
+            health_check_max_failures: MAX_FAILURES,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let disks = DISKS.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+    common::delete_file(&disks);
+    for disk in &DISKS {
+        common::truncate_file(disk, 64 * 1024);
+    }
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        for (device, disk) in ERROR_DEVICES.iter().zip(DISKS.iter()) {
+            create_error_bdev(device, disk);
+        }
+
+        // a child that keeps failing its probes is faulted
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[HEALTHY_CHILD.to_string(), error_child(0)],
+        )
+        .await
+        .unwrap();
+
+        fail_reads(0);
+        Delay::new(Duration::from_millis(INTERVAL_MS * 10)).await;
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        assert_eq!(
+            nexus.get_child_by_name(HEALTHY_CHILD).unwrap().state(),
+            ChildState::Open
+        );
+        assert_eq!(
+            nexus.get_child_by_name(&error_child(0)).unwrap().state(),
+            ChildState::Faulted(Reason::IoError)
+        );
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+
+    ms.spawn(async {
+        // when all children fail, the last healthy one is not faulted
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[error_child(0), error_child(1)],
+        )
+        .await
+        .unwrap();
+
+        fail_reads(0);
+        fail_reads(1);
+        Delay::new(Duration::from_millis(INTERVAL_MS * 10)).await;
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        let states =
+            nexus.children.iter().map(|c| c.state()).collect::<Vec<_>>();
+        assert_eq!(
+            states.iter().filter(|s| **s == ChildState::Open).count(),
+            1
+        );
+        assert!(states.contains(&ChildState::Faulted(Reason::IoError)));
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+
+    common::delete_file(&disks);
+}
@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::{BdevHandle, MayastorCliArgs},
+    nexus_uri::bdev_create,
+};
+
+pub mod common;
+use common::delay_bdev::create_delay_bdev;
+
+static NEXUS_NAME: &str = "io_fence_nexus";
+static BASE_BDEV: &str = "malloc:///io_fence_base?size_mb=64";
+static DELAY_BDEV: &str = "io_fence_delay";
+
+const NUM_READS: u64 = 10;
+const IO_SIZE: u64 = 4096;
+
+#[tokio::test]
+async fn nexus_io_fence() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BASE_BDEV).await.unwrap();
+        // every read takes 100ms so they are still active on the fence
+        create_delay_bdev(DELAY_BDEV, "io_fence_base", 100_000);
+
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[format!("bdev:///{}", DELAY_BDEV)],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        // without any IO the fence resolves immediately
+        nexus.io_fence().await.unwrap();
+
+        let h = BdevHandle::open(NEXUS_NAME, true, false).unwrap();
+        let mut bufs = (0 .. NUM_READS)
+            .map(|_| h.dma_malloc(IO_SIZE).unwrap())
+            .collect::<Vec<_>>();
+
+        let reads = async {
+            join_all(
+                bufs.iter_mut()
+                    .enumerate()
+                    .map(|(i, buf)| h.read_at(i as u64 * IO_SIZE, buf)),
+            )
+            .await
+            .into_iter()
+            .for_each(|r| {
+                r.unwrap();
+            });
+            Instant::now()
+        };
+
+        // the reads are submitted when first polled, before the fence
+        let fence = async {
+            assert_eq!(nexus.active_ios(), NUM_READS);
+            nexus.io_fence().await.unwrap();
+            Instant::now()
+        };
+
+        let (reads_done, fence_done) = futures::join!(reads, fence);
+        assert!(fence_done >= reads_done);
+        assert!(fence_done - reads_done < Duration::from_secs(1));
+        assert_eq!(nexus.active_ios(), 0);
+
+        h.close();
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
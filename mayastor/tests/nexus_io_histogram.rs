@@ -0,0 +1,51 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::{BdevHandle, IoType, MayastorCliArgs},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "histogram_nexus";
+static CHILD: &str = "malloc:///histogram_child?blk_size=512&size_mb=64";
+
+#[tokio::test]
+async fn nexus_child_iops_histogram() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(NEXUS_NAME, 32 * 1024 * 1024, None, &[CHILD.into()])
+            .await
+            .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.children[0].reset_iops_histogram();
+
+        let h = BdevHandle::open(NEXUS_NAME, true, false).unwrap();
+        let single = h.dma_malloc(512).unwrap();
+        for i in 0 .. 100 {
+            h.write_at(i * 512, &single).await.unwrap();
+        }
+        let eight = h.dma_malloc(8 * 512).unwrap();
+        for i in 0 .. 50 {
+            h.write_at(i * 8 * 512, &eight).await.unwrap();
+        }
+        drop(h);
+
+        let histogram = nexus.children[0].iops_histogram();
+        assert_eq!(histogram.count(IoType::Write, 1), 100);
+        assert_eq!(histogram.count(IoType::Write, 8), 50);
+        assert_eq!(histogram.writes().iter().sum::<u64>(), 150);
+        assert_eq!(histogram.reads().iter().sum::<u64>(), 0);
+
+        nexus.children[0].reset_iops_histogram();
+        assert_eq!(
+            nexus.children[0].iops_histogram().count(IoType::Write, 1),
+            0
+        );
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,69 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::{BdevHandle, MayastorCliArgs},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "io_stats_nexus";
+static CHILD0: &str = "malloc:///io_stats0?size_mb=64";
+static CHILD1: &str = "malloc:///io_stats1?size_mb=64";
+
+const IO_SIZE: u64 = 4096;
+const IO_COUNT: u64 = 16;
+
+#[tokio::test]
+async fn nexus_child_io_stats() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD0.to_string(), CHILD1.to_string()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        // discard whatever IO creating the nexus has done
+        for child in &nexus.children {
+            child.io_stats().snapshot_and_reset();
+        }
+
+        let h = BdevHandle::open(NEXUS_NAME, true, false).unwrap();
+        let mut buf = h.dma_malloc(IO_SIZE).unwrap();
+        for i in 0 .. IO_COUNT {
+            h.write_at(i * IO_SIZE, &buf).await.unwrap();
+            h.read_at(i * IO_SIZE, &mut buf).await.unwrap();
+        }
+        h.close();
+
+        let mut reads = 0;
+        for child in &nexus.children {
+            // writes go to all children
+            let stats = child.io_stats().snapshot();
+            assert_eq!(stats.num_write_ops(), IO_COUNT);
+            assert_eq!(stats.bytes_written(), IO_COUNT * IO_SIZE);
+
+            // a snapshot does not reset the counters
+            let stats = child.io_stats().snapshot_and_reset();
+            assert_eq!(stats.num_write_ops(), IO_COUNT);
+            reads += stats.num_read_ops();
+
+            let stats = child.io_stats().snapshot_and_reset();
+            assert_eq!(stats.num_read_ops(), 0);
+            assert_eq!(stats.num_write_ops(), 0);
+            assert_eq!(stats.bytes_read(), 0);
+            assert_eq!(stats.bytes_written(), 0);
+        }
+        // reads are served by one of the children
+        assert_eq!(reads, IO_COUNT);
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
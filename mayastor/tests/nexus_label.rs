@@ -1,12 +1,27 @@
 use std::{
     io::{Cursor, Read, Seek, SeekFrom},
     process::Command,
+    str::FromStr,
 };
 
-use bincode::serialize_into;
+use bincode::{deserialize, serialize, serialize_into};
+use uuid::Uuid;
 
 use mayastor::{
-    bdev::{nexus_create, nexus_lookup, GptEntry, GptHeader},
+    bdev::{
+        nexus_create,
+        nexus_lookup,
+        GptEntry,
+        GptGuid,
+        GptHeader,
+        GptName,
+        LabelError,
+        Nexus,
+        NexusLabel,
+        NexusLabelStatus,
+        Pmbr,
+        ProbeError,
+    },
     core::{
         mayastor_env_stop,
         DmaBuf,
@@ -59,8 +74,19 @@ fn read_label() {
 
 async fn start() {
     test_known_label();
+    test_unsupported_revision();
+    test_guid_bytes();
+    test_maya_partition_errors();
+    test_gpt_name_length();
+    test_gpt_name_surrogate_pairs();
+    test_data_alignment();
     make_nexus().await;
     label_child().await;
+    repair_child_label().await;
+    child_label_summary().await;
+    for block_size in &[512, 4096] {
+        label_block_size(*block_size).await;
+    }
     mayastor_env_stop(0);
 }
 
@@ -111,8 +137,165 @@ fn test_known_label() {
     assert_eq!(array_checksum, hdr.table_crc);
 }
 
+/// Test the conversion of a GUID from and to its on disk and RFC 4122 byte
+/// representations.
+fn test_guid_bytes() {
+    let mut file = std::fs::File::open("./gpt_primary_test_data.bin").unwrap();
+    file.seek(SeekFrom::Start(512)).unwrap();
+    let mut hdr_buf: [u8; 512] = [0; 512];
+    file.read_exact(&mut hdr_buf).unwrap();
+
+    // the disk GUID is stored at offset 56 of the header
+    let mut disk_bytes = [0; 16];
+    disk_bytes.copy_from_slice(&hdr_buf[56 .. 72]);
+    let guid = GptGuid::from_bytes(&disk_bytes);
+    assert_eq!(guid.to_string(), HDR_GUID);
+    assert_eq!(guid.to_bytes(), disk_bytes);
+
+    let guid = GptGuid::new_random();
+    assert_eq!(GptGuid::from_bytes(&guid.to_bytes()), guid);
+    assert_eq!(&guid.to_uuid_bytes(), Uuid::from(guid).as_bytes());
+}
+
+/// Test that a header with a revision we do not know about is rejected, and
+/// that the error reports both the revision found and the one expected.
+fn test_unsupported_revision() {
+    let mut hdr = GptHeader::new(512, 131_072, GptGuid::default());
+    hdr.revision = [0x00, 0x00, 0x02, 0x00];
+    hdr.checksum();
+
+    let buf = serialize(&hdr).unwrap();
+
+    match GptHeader::from_slice(&buf) {
+        Err(ProbeError::GptRevision {
+            actual,
+            expected,
+        }) => {
+            assert_eq!(actual, [0x00, 0x00, 0x02, 0x00]);
+            assert_eq!(expected, [0x00, 0x00, 0x01, 0x00]);
+        }
+        other => panic!("expected GptRevision error, got {:?}", other),
+    }
+
+    let err = GptHeader::from_slice(&buf).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unsupported GPT header revision: expected version 1.0, got version 2.0"
+    );
+}
+
+/// Test that each violation of the layout of the "MayaMeta" and "MayaData"
+/// partitions is reported on its own.
+fn test_maya_partition_errors() {
+    let part_type =
+        GptGuid::from_str(Nexus::METADATA_PARTITION_TYPE_ID).unwrap();
+    let reference = vec![
+        GptEntry {
+            ent_type: part_type,
+            ent_start: 2048,
+            ent_end: 10239,
+            ent_name: "MayaMeta".into(),
+            ..Default::default()
+        },
+        GptEntry {
+            ent_type: part_type,
+            ent_start: 10240,
+            ent_end: 131_038,
+            ent_name: "MayaData".into(),
+            ..Default::default()
+        },
+    ];
+    let label = |partitions: Vec<GptEntry>| NexusLabel {
+        status: NexusLabelStatus::Both,
+        mbr: Pmbr::default(),
+        primary: GptHeader::new(512, 131_072, GptGuid::default()),
+        partitions,
+        secondary: GptHeader::new(512, 131_072, GptGuid::default()),
+    };
+
+    assert!(label(reference.clone()).is_maya_formatted(&reference, 512));
+
+    let mut wrong_type = reference.clone();
+    wrong_type[1].ent_type = GptGuid::new_random();
+    let mut wrong_start = reference.clone();
+    wrong_start[1].ent_start += 1;
+    let mut wrong_end = reference.clone();
+    wrong_end[0].ent_end += 1;
+    let missing = vec![reference[0].clone()];
+
+    for partitions in &[wrong_type, wrong_start, wrong_end, missing] {
+        let label = label(partitions.clone());
+        let errors = label.maya_partition_errors(&reference, 512);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(!label.is_maya_formatted(&reference, 512));
+    }
+}
+
 /// as replica URIs are new, so this will, implicitly, create a label on the
 /// device
+fn test_gpt_name_length() {
+    let name = "a".repeat(GptName::MAX_LEN);
+    assert_eq!(GptName::new(&name).unwrap().name, name);
+
+    // the limit is in UTF-16 code units rather than characters
+    let name = "\u{1f4be}".repeat(GptName::MAX_LEN / 2 + 1);
+    match GptName::new(&name) {
+        Err(LabelError::NameTooLong {
+            len,
+            ..
+        }) => assert_eq!(len, GptName::MAX_LEN + 2),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert!(GptName::new(&"a".repeat(40)).is_err());
+}
+
+fn test_gpt_name_surrogate_pairs() {
+    // a name filling the entry, ending in a character outside the BMP
+    let name = format!("{}\u{1f4be}", "a".repeat(GptName::MAX_LEN - 2));
+    let buf = serialize(&GptName::new(&name).unwrap()).unwrap();
+    assert_eq!(buf.len(), GptName::MAX_LEN * 2);
+    let decoded: GptName = deserialize(&buf).unwrap();
+    assert_eq!(decoded.name, name);
+
+    let name = "disk \u{1f4be} data";
+    let buf = serialize(&GptName::new(name).unwrap()).unwrap();
+    let decoded: GptName = deserialize(&buf).unwrap();
+    assert_eq!(decoded.name, name);
+
+    // anything following the first zero code unit is not part of the name
+    let mut buf = serialize(&GptName::new("disk").unwrap()).unwrap();
+    buf[10..12].copy_from_slice(&u16::to_le_bytes(u16::from(b'x')));
+    let decoded: GptName = deserialize(&buf).unwrap();
+    assert_eq!(decoded.name, "disk");
+}
+
+/// The data partition of a reference label starts on the requested boundary,
+/// right after the 1MiB gap and the 4MiB metadata partition by default.
+fn test_data_alignment() {
+    let guid = GptGuid::new_random();
+    let data_blocks = 65536;
+
+    for (alignment, data_start) in &[
+        (Nexus::DATA_PARTITION_ALIGNMENT, 10240),
+        (128 * 1024, 10240),
+        (4 * 1024 * 1024, 16384),
+        (3 * 1024 * 1024, 12288),
+        (0, 10240),
+    ] {
+        let hdr = GptHeader::reference(512, data_blocks, guid, *alignment);
+        assert_eq!(hdr.lba_start, 2048);
+        assert_eq!(hdr.lba_end, data_start + data_blocks - 1);
+    }
+
+    let hdr = GptHeader::reference(
+        4096,
+        data_blocks,
+        guid,
+        Nexus::DATA_PARTITION_ALIGNMENT,
+    );
+    assert_eq!(hdr.lba_end, 1280 + data_blocks - 1);
+}
+
 async fn make_nexus() {
     let ch = vec![BDEVNAME1.to_string(), BDEVNAME2.to_string()];
     nexus_create("gpt_nexus", 512 * 131_072, None, &ch)
@@ -155,3 +338,112 @@ async fn label_child() {
     assert_eq!(&nl.partitions[0].ent_guid.to_string(), &PART0_GUID);
     assert_eq!(&nl.partitions[1].ent_guid.to_string(), &PART1_GUID);
 }
+
+// clobber either half of the label and heal it from the other one
+async fn repair_child_label() {
+    let nexus = nexus_lookup("gpt_nexus").unwrap();
+    let child = &nexus.children[1];
+    let hdl = child.handle().unwrap();
+    let zeroes = hdl.dma_malloc(512).unwrap();
+
+    // secondary GPT header
+    hdl.write_at(131_071 * 512, &zeroes).await.unwrap();
+    let label = child.probe_label().await.unwrap();
+    assert_eq!(label.status, NexusLabelStatus::Primary);
+    let status = child.repair_label(&label).await.unwrap();
+    assert_eq!(status, NexusLabelStatus::Both);
+    let label = child.probe_label().await.unwrap();
+    assert_eq!(label.status, NexusLabelStatus::Both);
+
+    // repairing a healthy label does nothing
+    let status = child.repair_label(&label).await.unwrap();
+    assert_eq!(status, NexusLabelStatus::Both);
+
+    // primary GPT header
+    hdl.write_at(512, &zeroes).await.unwrap();
+    let label = child.probe_label().await.unwrap();
+    assert_eq!(label.status, NexusLabelStatus::Secondary);
+    let status = child.repair_label(&label).await.unwrap();
+    assert_eq!(status, NexusLabelStatus::Both);
+    let label = child.probe_label().await.unwrap();
+    assert_eq!(label.status, NexusLabelStatus::Both);
+}
+
+// the summary describes the partitions of the label
+async fn child_label_summary() {
+    let nexus = nexus_lookup("gpt_nexus").unwrap();
+    let child = &nexus.children[1];
+    let label = child.probe_label().await.unwrap();
+
+    let summary = child.label_summary().await.unwrap();
+    assert_eq!(summary.disk_guid, label.primary.guid.to_string());
+    assert_eq!(summary.block_size, 512);
+    assert_eq!(summary.status, NexusLabelStatus::Both);
+    let names: Vec<&str> =
+        summary.partitions.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["MayaMeta", "MayaData"]);
+    for (partition, entry) in summary.partitions.iter().zip(&label.partitions) {
+        assert_eq!(partition.start_lba, entry.ent_start);
+        assert_eq!(partition.end_lba, entry.ent_end);
+        assert_eq!(
+            partition.size_bytes,
+            (entry.ent_end - entry.ent_start + 1) * 512
+        );
+    }
+}
+
+// build a label for children with the given block size, and make sure it is
+// laid out as expected and accepted when the labels are validated again
+async fn label_block_size(block_size: u64) {
+    let name = format!("gpt_nexus_{}", block_size);
+    let children: Vec<String> = (0 .. 2)
+        .map(|i| {
+            format!(
+                "malloc:///gpt_{}_{}?size_mb=64&blk_size={}",
+                block_size, i, block_size
+            )
+        })
+        .collect();
+    nexus_create(&name, 32 * 1024 * 1024, None, &children)
+        .await
+        .unwrap();
+
+    let nexus = nexus_lookup(&name).unwrap();
+    let table_blocks = GptHeader::PARTITION_TABLE_SIZE / block_size;
+    let num_blocks = 64 * 1024 * 1024 / block_size;
+
+    for child in &nexus.children {
+        let label = child.probe_label().await.unwrap();
+        assert_eq!(label.status, NexusLabelStatus::Both);
+
+        assert_eq!(label.primary.lba_self, 1);
+        assert_eq!(label.primary.lba_table, 2);
+        assert_eq!(label.primary.lba_start * block_size, 1024 * 1024);
+        assert!(
+            label.primary.lba_table + table_blocks <= label.primary.lba_start
+        );
+
+        assert_eq!(label.secondary.lba_self, num_blocks - 1);
+        assert_eq!(label.secondary.lba_table, label.primary.lba_end + 1);
+        assert_eq!(
+            label.secondary.lba_table + table_blocks,
+            label.secondary.lba_self
+        );
+
+        let data = &label.partitions[1];
+        assert_eq!(
+            data.ent_start * block_size % Nexus::DATA_PARTITION_ALIGNMENT,
+            0
+        );
+
+        let meta = &label.partitions[0];
+        assert_eq!(meta.ent_name.name, "MayaMeta");
+        assert_eq!(
+            (meta.ent_end - meta.ent_start + 1) * block_size,
+            Nexus::METADATA_PARTITION_SIZE
+        );
+    }
+
+    nexus.validate_child_labels().await.unwrap();
+    nexus.destroy().await.unwrap();
+}
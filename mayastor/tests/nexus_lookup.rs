@@ -0,0 +1,50 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{
+        nexus_create,
+        nexus_lookup,
+        nexus_lookup_by_bdev_name,
+        nexus_lookup_by_child_uri,
+    },
+    core::MayastorCliArgs,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "lookup_nexus";
+static CHILD0: &str = "malloc:///lookup0?size_mb=64";
+static CHILD1: &str = "malloc:///lookup1?size_mb=64";
+
+#[tokio::test]
+async fn nexus_reverse_lookup() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD0.to_string(), CHILD1.to_string()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup_by_bdev_name("lookup0").unwrap();
+        assert_eq!(nexus.name, NEXUS_NAME);
+        let nexus = nexus_lookup_by_bdev_name("lookup1").unwrap();
+        assert_eq!(nexus.name, NEXUS_NAME);
+        assert!(nexus_lookup_by_bdev_name("lookup2").is_none());
+        // the nexus itself is not one of its children
+        assert!(nexus_lookup_by_bdev_name(NEXUS_NAME).is_none());
+
+        let nexus = nexus_lookup_by_child_uri(CHILD1).unwrap();
+        assert_eq!(nexus.name, NEXUS_NAME);
+        assert!(nexus_lookup_by_child_uri("lookup1").is_none());
+
+        nexus_lookup(NEXUS_NAME).unwrap().destroy().await.unwrap();
+        assert!(nexus_lookup_by_bdev_name("lookup0").is_none());
+        assert!(nexus_lookup_by_child_uri(CHILD0).is_none());
+    })
+    .await;
+}
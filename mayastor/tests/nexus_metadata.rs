@@ -1,13 +1,25 @@
-use std::{process::Command, time::SystemTime};
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    process::Command,
+    time::SystemTime,
+};
+
+use bincode::{serialize, serialize_into};
+use crc::crc32;
 
 use mayastor::{
     bdev::{
         nexus_create,
         nexus_lookup,
+        MetaDataError,
+        MetaDataHeader,
+        MetaDataIndexEntry,
         NexusConfig,
         NexusConfigVersion1,
         NexusConfigVersion2,
         NexusConfigVersion3,
+        NexusMetaData,
     },
     core::{mayastor_env_stop, MayastorCliArgs, MayastorEnvironment, Reactor},
 };
@@ -41,6 +53,9 @@ fn get_metadata() {
 async fn start() {
     make_nexus().await;
     read_write_metadata().await;
+    interrupted_update().await;
+    migrate_metadata().await;
+    migrate_v0_layout().await;
     mayastor_env_stop(0);
 }
 
@@ -123,3 +138,156 @@ async fn read_write_metadata() {
     let config = child.get_latest_config_object(&metadata).await.unwrap();
     assert_eq!(config.unwrap(), data[2]);
 }
+
+// simulate a power failure during an update of the index, by journaling the
+// update without committing it, and check that the update is rolled back
+async fn interrupted_update() {
+    let nexus = nexus_lookup("metadata_nexus").unwrap();
+    let child = &mut nexus.children[0];
+
+    let metadata = child.get_metadata().await.unwrap();
+    let _sequence = child.journal_begin(&metadata).await.unwrap();
+
+    // partially written index with an additional entry
+    let mut partial = metadata.clone();
+    partial.index.push(partial.index[0].clone());
+    partial.header.used_entries += 1;
+    partial.header.index_checksum =
+        MetaDataIndexEntry::checksum(&partial.index);
+    partial.header.checksum();
+    child.write_index(&partial).await.unwrap();
+
+    let current = child.get_metadata().await.unwrap();
+    assert_eq!(
+        current.header.used_entries,
+        metadata.header.used_entries + 1
+    );
+
+    // reinitialise - the uncommitted update should be rolled back
+    let recovered = child.check_or_initialise_index().await.unwrap();
+    assert_eq!(recovered, metadata);
+
+    // the journal has been trimmed, so this should be a no-op
+    let recovered = child.check_or_initialise_index().await.unwrap();
+    assert_eq!(recovered, metadata);
+}
+
+// serialize metadata in the current format and check that it is accepted by
+// the migration path, and that unknown versions are rejected
+async fn migrate_metadata() {
+    let nexus = nexus_lookup("metadata_nexus").unwrap();
+    let child = &mut nexus.children[0];
+
+    let metadata = child.get_metadata().await.unwrap();
+    assert_eq!(metadata.get_version(), MetaDataHeader::CURRENT_VERSION);
+    assert_eq!(metadata.get_version(), 1);
+
+    let mut data: Vec<u8> = Vec::new();
+    serialize_into(&mut data, &metadata.header).unwrap();
+    for entry in &metadata.index {
+        serialize_into(&mut data, entry).unwrap();
+    }
+
+    let migrated = NexusMetaData::migrate_to_current(1, &data).unwrap();
+    assert_eq!(migrated, metadata);
+
+    match NexusMetaData::migrate_to_current(2, &data) {
+        Err(MetaDataError::FormatTooNew {
+            found,
+            current,
+        }) => {
+            assert_eq!(found, 2);
+            assert_eq!(current, 1);
+        }
+        other => panic!("expected FormatTooNew error, got {:?}", other),
+    }
+}
+
+// rewrite the metadata in the version 0 layout, which has no journal and
+// uses the blocks of the journal for data, and check that it is migrated to
+// the current layout when the child is opened
+async fn migrate_v0_layout() {
+    let nexus = nexus_lookup("metadata_nexus").unwrap();
+    let child = &mut nexus.children[0];
+    let block_size = 512;
+
+    let metadata = child.get_metadata().await.unwrap();
+    let header = &metadata.header;
+    let mut objects = Vec::new();
+    for selected in 0 .. header.used_entries {
+        objects.push(
+            child
+                .get_config_object(&metadata, selected)
+                .await
+                .unwrap()
+                .unwrap(),
+        );
+    }
+
+    // the version 0 header lacks the format_version field
+    let journal_blocks = header.journal_blocks(block_size);
+    let v0_header = |self_checksum: u32| {
+        serialize(&(
+            header.signature,
+            72u32,
+            self_checksum,
+            header.generation,
+            header.self_lba,
+            header.index_start,
+            header.used_entries,
+            header.max_entries,
+            header.entry_size,
+            header.index_checksum,
+            header.data_start,
+            header.data_end + journal_blocks,
+        ))
+        .unwrap()
+    };
+    let mut data = v0_header(crc32::checksum_ieee(&v0_header(0)));
+    data.resize((header.index_start * block_size) as usize, 0);
+    for entry in &metadata.index {
+        serialize_into(&mut data, entry).unwrap();
+    }
+
+    let journal_offset =
+        (header.self_lba + header.journal_start()) * block_size;
+    let journal_len = (journal_blocks * block_size) as usize;
+    let mut disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(DISKNAME)
+        .unwrap();
+    disk.seek(SeekFrom::Start(header.self_lba * block_size))
+        .unwrap();
+    disk.write_all(&data).unwrap();
+    disk.seek(SeekFrom::Start(journal_offset)).unwrap();
+    disk.write_all(&vec![0xa5; journal_len]).unwrap();
+    disk.sync_all().unwrap();
+
+    // opening the child writes the index back in the current layout and
+    // trims the journal, while the config objects are left intact
+    let migrated = child.check_or_initialise_index().await.unwrap();
+    assert_eq!(migrated, metadata);
+    let stored = child.get_metadata().await.unwrap();
+    assert_eq!(stored.get_version(), MetaDataHeader::CURRENT_VERSION);
+    assert_eq!(stored, metadata);
+    for (selected, object) in objects.into_iter().enumerate() {
+        let config = child
+            .get_config_object(&stored, selected as u32)
+            .await
+            .unwrap();
+        assert_eq!(config.unwrap(), object);
+    }
+
+    // only the slot used to write back the index holds a journal record
+    let mut journal = vec![0; journal_len];
+    disk.seek(SeekFrom::Start(journal_offset)).unwrap();
+    disk.read_exact(&mut journal).unwrap();
+    let slot_len =
+        (header.journal_slot_blocks(block_size) * block_size) as usize;
+    let used = journal
+        .chunks(slot_len)
+        .filter(|slot| slot.iter().any(|b| *b != 0))
+        .count();
+    assert_eq!(used, 1);
+}
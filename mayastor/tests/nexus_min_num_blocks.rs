@@ -0,0 +1,44 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::MayastorCliArgs,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "min_blocks_nexus";
+static CHILD_1: &str = "malloc:///min_blocks_child1?size_mb=64&blk_size=512";
+static CHILD_2: &str = "malloc:///min_blocks_child2?size_mb=96&blk_size=512";
+static CHILD_3: &str = "malloc:///min_blocks_child3?size_mb=128&blk_size=512";
+
+const MB_BLOCKS: u64 = 1024 * 1024 / 512;
+
+#[tokio::test]
+async fn nexus_min_num_blocks() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(NEXUS_NAME, 32 * 1024 * 1024, None, &[CHILD_1.into()])
+            .await
+            .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        assert_eq!(nexus.min_num_blocks(), 64 * MB_BLOCKS);
+
+        // larger children do not change the smallest size
+        nexus.add_child(CHILD_3, true).await.unwrap();
+        nexus.add_child(CHILD_2, true).await.unwrap();
+        assert_eq!(nexus.min_num_blocks(), 64 * MB_BLOCKS);
+
+        // removing the smallest child updates the cached value
+        nexus.remove_child(CHILD_1).await.unwrap();
+        assert_eq!(nexus.min_num_blocks(), 96 * MB_BLOCKS);
+
+        nexus.remove_child(CHILD_2).await.unwrap();
+        assert_eq!(nexus.min_num_blocks(), 128 * MB_BLOCKS);
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
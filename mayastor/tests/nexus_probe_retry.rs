@@ -0,0 +1,74 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, LabelError},
+    core::MayastorCliArgs,
+    nexus_uri::bdev_create,
+    subsys::{Config, NvmeBdevOpts},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "probe_retry_nexus";
+static CHILD_1: &str = "malloc:///probe_retry_child1?size_mb=64";
+static CHILD_2_BASE: &str = "malloc:///probe_retry_child2?size_mb=64";
+// the underlying malloc bdev, and so the label, outlives the child
+static CHILD_2: &str = "bdev:///probe_retry_child2";
+
+#[tokio::test]
+async fn nexus_child_probe_label_retry() {
+    Config::get_or_init(|| Config {
+        nvme_bdev_opts: NvmeBdevOpts {
+            probe_max_retries: 2,
+            probe_retry_delay_ms: 200,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(CHILD_2_BASE).await.unwrap();
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD_1.into(), CHILD_2.into()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.offline_child(CHILD_2).await.unwrap();
+
+        // the child has no handle for longer than the retries last
+        let label = nexus
+            .get_child_by_name(CHILD_2)
+            .unwrap()
+            .probe_label()
+            .await;
+        assert!(matches!(label, Err(LabelError::HandleError { .. })));
+
+        // the child gets a handle again while the probe waits to retry
+        let probe = async {
+            nexus_lookup(NEXUS_NAME)
+                .unwrap()
+                .get_child_by_name(CHILD_2)
+                .unwrap()
+                .probe_label()
+                .await
+        };
+        let online = async {
+            nexus_lookup(NEXUS_NAME)
+                .unwrap()
+                .online_child(CHILD_2)
+                .await
+                .unwrap();
+        };
+        let (label, _) = futures::join!(probe, online);
+        assert!(label.is_ok());
+
+        nexus_lookup(NEXUS_NAME).unwrap().destroy().await.unwrap();
+    })
+    .await;
+}
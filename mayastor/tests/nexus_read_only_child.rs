@@ -0,0 +1,71 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, NexusStatus, ReadPreference, Reason},
+    core::{BdevHandle, MayastorCliArgs},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "read_only_nexus";
+static CHILD: &str = "malloc:///read_only_child?size_mb=64";
+static READ_ONLY_CHILD: &str = "malloc:///read_only_source?size_mb=64";
+
+/// read the first blocks of the nexus as many times as there are children,
+/// so that each reader of the nexus serves at least one of the reads
+async fn read_back(pattern: u8) {
+    let h = BdevHandle::open(NEXUS_NAME, false, false).unwrap();
+    let mut buf = h.dma_malloc(4096).unwrap();
+    for _ in 0 .. 4 {
+        buf.fill(0);
+        h.read_at(0, &mut buf).await.unwrap();
+        assert!(buf.as_slice().iter().all(|b| *b == pattern));
+    }
+    h.close();
+}
+
+#[tokio::test]
+async fn nexus_read_only_child() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(NEXUS_NAME, 32 * 1024 * 1024, None, &[CHILD.into()])
+            .await
+            .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_read_only_child(READ_ONLY_CHILD).await.unwrap();
+
+        let h = BdevHandle::open(NEXUS_NAME, true, false).unwrap();
+        let mut buf = h.dma_malloc(4096).unwrap();
+        buf.fill(0xa5);
+        h.write_at(0, &buf).await.unwrap();
+        h.close();
+
+        // the read only child did not receive the write, so none of the
+        // reads may be served by it
+        read_back(0xa5).await;
+        nexus
+            .set_read_preference(ReadPreference::Sticky)
+            .await
+            .unwrap();
+        read_back(0xa5).await;
+        nexus
+            .set_read_preference(ReadPreference::LatencyBased {
+                ewma_alpha: 0.5,
+            })
+            .await
+            .unwrap();
+        read_back(0xa5).await;
+
+        // writes are not acknowledged once only the read only child is left
+        nexus.fault_child(CHILD, Reason::Unknown).await.unwrap();
+        assert_eq!(nexus.status(), NexusStatus::Faulted);
+        let h = BdevHandle::open(NEXUS_NAME, true, false).unwrap();
+        assert!(h.write_at(0, &buf).await.is_err());
+        h.close();
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
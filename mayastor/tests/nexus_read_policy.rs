@@ -0,0 +1,110 @@
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, ReadPreference},
+    core::{Bdev, BdevHandle, MayastorCliArgs},
+};
+use rpc::mayastor::{BdevShareRequest, BdevUri};
+
+pub mod common;
+use common::{compose::Builder, MayastorTest};
+
+static NEXUS_NAME: &str = "read_policy_nexus";
+static LOCAL_BDEV: &str = "read_policy_local";
+
+const READS: u64 = 100;
+
+/// issue READS reads to the nexus and return how many of them were served
+/// by the local child
+async fn local_reads() -> u64 {
+    let stats = || async {
+        Bdev::lookup_by_name(LOCAL_BDEV)
+            .unwrap()
+            .stats()
+            .await
+            .unwrap()
+            .num_read_ops
+    };
+    let before = stats().await;
+
+    let h = BdevHandle::open(NEXUS_NAME, false, false).unwrap();
+    let mut buf = h.dma_malloc(4096).unwrap();
+    for i in 0 .. READS {
+        h.read_at((i % 1024) * 4096, &mut buf).await.unwrap();
+    }
+    h.close();
+
+    stats().await - before
+}
+
+#[tokio::test]
+async fn nexus_read_policy() {
+    let test = Builder::new()
+        .name("nexus_read_policy")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+    hdls[0]
+        .bdev
+        .create(BdevUri {
+            uri: "malloc:///disk0?size_mb=64".into(),
+        })
+        .await
+        .unwrap();
+    hdls[0]
+        .bdev
+        .share(BdevShareRequest {
+            name: "disk0".into(),
+            proto: "nvmf".into(),
+        })
+        .await
+        .unwrap();
+
+    let mayastor = MayastorTest::new(MayastorCliArgs::default());
+    mayastor
+        .spawn(async move {
+            // the remote child comes first, so that a round robin starting
+            // at either child would not pass for preferring the local one
+            nexus_create(
+                NEXUS_NAME,
+                32 * 1024 * 1024,
+                None,
+                &[
+                    format!(
+                        "nvmf://{}:8420/nqn.2019-05.io.openebs:disk0",
+                        hdls[0].endpoint.ip()
+                    ),
+                    format!("malloc:///{}?size_mb=64", LOCAL_BDEV),
+                ],
+            )
+            .await
+            .unwrap();
+
+            let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+            assert_eq!(nexus.children[0].is_local(), Some(false));
+            assert_eq!(nexus.children[1].is_local(), Some(true));
+
+            // round robin spreads the reads over both children
+            assert_eq!(local_reads().await, READS / 2);
+
+            nexus
+                .set_read_preference(ReadPreference::PreferLocal)
+                .await
+                .unwrap();
+            assert_eq!(local_reads().await, READS);
+
+            // a sticky nexus reads from one child only, whichever it is
+            nexus
+                .set_read_preference(ReadPreference::Sticky)
+                .await
+                .unwrap();
+            let local = local_reads().await;
+            assert!(local == 0 || local == READS);
+
+            nexus.destroy().await.unwrap();
+        })
+        .await;
+}
@@ -0,0 +1,84 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, ReadPreference},
+    core::{Bdev, BdevHandle, MayastorCliArgs},
+    nexus_uri::bdev_create,
+};
+
+pub mod common;
+use common::delay_bdev::create_delay_bdev;
+
+static NEXUS_NAME: &str = "read_pref_nexus";
+static FAST_BDEV: &str = "malloc:///fast?size_mb=64";
+static SLOW_BASE_BDEV: &str = "malloc:///slow_base?size_mb=64";
+static SLOW_BDEV: &str = "slow";
+
+const READS: u64 = 1000;
+
+async fn read_ops(name: &str) -> u64 {
+    Bdev::lookup_by_name(name)
+        .unwrap()
+        .stats()
+        .await
+        .unwrap()
+        .num_read_ops
+}
+
+#[tokio::test]
+async fn nexus_read_preference() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(FAST_BDEV).await.unwrap();
+        bdev_create(SLOW_BASE_BDEV).await.unwrap();
+        // every read of this child takes an additional millisecond
+        create_delay_bdev(SLOW_BDEV, "slow_base", 1000);
+
+        let children = vec![
+            FAST_BDEV.to_string(),
+            format!("bdev:///{}", SLOW_BDEV),
+        ];
+        nexus_create(NEXUS_NAME, 32 * 1024 * 1024, None, &children)
+            .await
+            .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        for alpha in &[0.0, -0.5, 1.5] {
+            assert!(nexus
+                .set_read_preference(ReadPreference::LatencyBased {
+                    ewma_alpha: *alpha,
+                })
+                .await
+                .is_err());
+        }
+
+        nexus
+            .set_read_preference(ReadPreference::LatencyBased {
+                ewma_alpha: 0.2,
+            })
+            .await
+            .unwrap();
+
+        let fast_before = read_ops("fast").await;
+        let slow_before = read_ops(SLOW_BDEV).await;
+
+        let h = BdevHandle::open(NEXUS_NAME, false, false).unwrap();
+        let mut buf = h.dma_malloc(4096).unwrap();
+        for i in 0 .. READS {
+            h.read_at((i % 1024) * 4096, &mut buf).await.unwrap();
+        }
+        h.close();
+
+        let fast = read_ops("fast").await - fast_before;
+        let slow = read_ops(SLOW_BDEV).await - slow_before;
+        assert_eq!(fast + slow, READS);
+        assert!(
+            fast * 100 >= READS * 85,
+            "low latency child served {} out of {} reads",
+            fast,
+            READS
+        );
+    })
+    .await;
+}
@@ -5,14 +5,23 @@ use once_cell::sync::Lazy;
 use tracing::error;
 
 use mayastor::{
-    bdev::nexus_lookup,
+    bdev::{nexus_lookup, NexusStatus},
     core::{MayastorCliArgs, MayastorEnvironment, Mthread, Reactor},
-    rebuild::{RebuildJob, RebuildState},
+    rebuild::{RebuildFailureReason, RebuildJob, RebuildState},
 };
 use rpc::mayastor::ShareProtocolNexus;
 
 pub mod common;
-use common::wait_for_rebuild;
+use common::{
+    error_bdev::{
+        create_error_bdev,
+        inject_error,
+        SPDK_BDEV_IO_TYPE_WRITE,
+        VBDEV_IO_FAILURE,
+    },
+    wait_for_rebuild,
+    wait_for_rebuild_complete,
+};
 
 // each test `should` use a different nexus name to prevent clashing with
 // one another. This allows the failed tests to `panic gracefully` improving
@@ -132,7 +141,10 @@ fn rebuild_lookup() {
                 .any(|_| panic!("Should not have found any jobs!"));
         }
 
-        let _ = nexus.start_rebuild(&get_dev(children)).await.unwrap();
+        let _ = nexus
+            .start_rebuild(&get_dev(children), false)
+            .await
+            .unwrap();
         for child in 0 .. children {
             RebuildJob::lookup(&get_dev(child))
                 .expect_err("rebuild job not created yet");
@@ -171,13 +183,17 @@ fn rebuild_lookup() {
         wait_for_rebuild(
             get_dev(children),
             RebuildState::Running,
-            Duration::from_secs(1),
-        );
+            Duration::from_secs(60),
+        )
+        .expect("rebuild did not start");
         nexus.pause_rebuild(&get_dev(children)).await.unwrap();
         assert_eq!(RebuildJob::lookup_src(&src).len(), 1);
 
         nexus.add_child(&get_dev(children + 1), true).await.unwrap();
-        let _ = nexus.start_rebuild(&get_dev(children + 1)).await.unwrap();
+        let _ = nexus
+            .start_rebuild(&get_dev(children + 1), false)
+            .await
+            .unwrap();
         assert_eq!(RebuildJob::lookup_src(&src).len(), 2);
 
         nexus.remove_child(&get_dev(children)).await.unwrap();
@@ -187,3 +203,120 @@ fn rebuild_lookup() {
 
     test_fini();
 }
+
+#[test]
+fn rebuild_wait_timeout() {
+    test_ini("rebuild_wait_timeout");
+
+    Reactor::block_on(async move {
+        nexus_create(NEXUS_SIZE, 1, false).await;
+        let nexus = nexus_lookup(nexus_name()).unwrap();
+        nexus.add_child(&get_dev(1), true).await.unwrap();
+
+        let _ = nexus.start_rebuild(&get_dev(1), false).await.unwrap();
+        wait_for_rebuild(
+            get_dev(1),
+            RebuildState::Running,
+            Duration::from_secs(60),
+        )
+        .expect("rebuild did not start");
+        nexus.pause_rebuild(&get_dev(1)).await.unwrap();
+
+        // a paused rebuild never completes so the wait has to time out
+        assert_eq!(
+            wait_for_rebuild(
+                get_dev(1),
+                RebuildState::Completed,
+                Duration::from_millis(100),
+            ),
+            Err("timed out waiting for rebuild state")
+        );
+
+        nexus.resume_rebuild(&get_dev(1)).await.unwrap();
+        wait_for_rebuild_complete(get_dev(1))
+            .expect("rebuild did not complete");
+
+        nexus_lookup(nexus_name()).unwrap().destroy().await.unwrap();
+    });
+
+    test_fini();
+}
+
+#[test]
+fn rebuild_failure_reason() {
+    test_ini("rebuild_failure_reason");
+    let backing_file = get_disk(1);
+    get_err_bdev().push(1);
+
+    Reactor::block_on(async move {
+        create_error_bdev(&get_disk(1), &backing_file);
+        nexus_create(NEXUS_SIZE, 1, false).await;
+        let nexus = nexus_lookup(nexus_name()).unwrap();
+        nexus.add_child(&get_dev(1), true).await.unwrap();
+
+        // fail the first write the rebuild issues to the destination
+        inject_error(
+            &format!("EE_{}", get_disk(1)),
+            SPDK_BDEV_IO_TYPE_WRITE,
+            VBDEV_IO_FAILURE,
+            1,
+        );
+
+        let complete = nexus.start_rebuild(&get_dev(1), false).await.unwrap();
+        match complete.await.unwrap() {
+            RebuildState::Failed {
+                reason:
+                    RebuildFailureReason::DestinationIoError {
+                        lba, ..
+                    },
+            } => {
+                // the data partition starts after the child's metadata
+                assert_ne!(lba, 0);
+            }
+            state => panic!("unexpected rebuild state {:?}", state),
+        }
+
+        nexus_lookup(nexus_name()).unwrap().destroy().await.unwrap();
+    });
+
+    test_fini();
+}
+
+#[test]
+fn rebuild_replace_child() {
+    test_ini("rebuild_replace_child");
+
+    Reactor::block_on(async move {
+        nexus_create(NEXUS_SIZE, 2, false).await;
+        let device = nexus_share().await;
+        let nexus = nexus_lookup(nexus_name()).unwrap();
+        assert_eq!(nexus.status(), NexusStatus::Online);
+
+        // keep IO going to the nexus while the child is being replaced
+        let nexus_device = device.clone();
+        let (s, r) = unbounded::<i32>();
+        Mthread::spawn_unaffinitized(move || {
+            s.send(common::dd_urandom_blkdev(&nexus_device))
+        });
+
+        let status = nexus
+            .atomic_child_set_replace(&get_dev(1), &get_dev(2))
+            .await
+            .unwrap();
+        assert_eq!(status, NexusStatus::Online);
+
+        let dd_result: i32;
+        reactor_poll!(r, dd_result);
+        assert_eq!(dd_result, 0, "IO failed while replacing the child");
+
+        assert_eq!(nexus.status(), NexusStatus::Online);
+        assert_eq!(nexus.children.len(), 2);
+        assert!(nexus.get_child_by_name(&get_dev(0)).is_ok());
+        assert!(nexus.get_child_by_name(&get_dev(2)).is_ok());
+        assert!(nexus.get_child_by_name(&get_dev(1)).is_err());
+
+        nexus_lookup(nexus_name()).unwrap().destroy().await.unwrap();
+    });
+
+    test_fini();
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::MayastorCliArgs,
+    nexus_uri::bdev_create,
+};
+
+pub mod common;
+use common::delay_bdev::create_delay_bdev;
+
+static NEXUS_NAME: &str = "checkpoint_nexus";
+static SRC_BASE_BDEV: &str = "malloc:///checkpoint_src_base?size_mb=64";
+static SRC_BDEV: &str = "bdev:///checkpoint_src";
+static DST_BASE_BDEV: &str = "malloc:///checkpoint_dst?size_mb=64";
+static DST_BDEV: &str = "bdev:///checkpoint_dst";
+
+async fn rebuild_progress() -> u64 {
+    nexus_lookup(NEXUS_NAME)
+        .unwrap()
+        .get_rebuild_stats(DST_BDEV)
+        .await
+        .unwrap()
+        .progress
+}
+
+#[tokio::test]
+async fn nexus_rebuild_resumes_from_checkpoint() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(SRC_BASE_BDEV).await.unwrap();
+        bdev_create(DST_BASE_BDEV).await.unwrap();
+        // slow down the reads of the rebuild source so that we can catch the
+        // rebuild half way
+        create_delay_bdev("checkpoint_src", "checkpoint_src_base", 100_000);
+
+        nexus_create(NEXUS_NAME, 32 * 1024 * 1024, None, &[SRC_BDEV.into()])
+            .await
+            .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_child(DST_BDEV, true).await.unwrap();
+        let _ = nexus.start_rebuild(DST_BDEV, false).await.unwrap();
+    })
+    .await;
+
+    // wait until half of the blocks have been rebuilt
+    let mut retries = 1000;
+    while ms.spawn(rebuild_progress()).await < 50 {
+        retries -= 1;
+        assert_ne!(retries, 0, "rebuild did not reach 50%");
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+    }
+
+    ms.spawn(async {
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.pause_rebuild(DST_BDEV).await.unwrap();
+        nexus.checkpoint_rebuild(DST_BDEV).await.unwrap();
+        nexus.destroy().await.unwrap();
+
+        // the children are not destroyed along with the nexus as they are
+        // bdev:// URIs, so the checkpoint survives on the destination
+        nexus_create(NEXUS_NAME, 32 * 1024 * 1024, None, &[SRC_BDEV.into()])
+            .await
+            .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_child(DST_BDEV, true).await.unwrap();
+        let _ = nexus.start_rebuild(DST_BDEV, false).await.unwrap();
+    })
+    .await;
+
+    let progress = ms.spawn(rebuild_progress()).await;
+    assert!(
+        (45 ..= 60).contains(&progress),
+        "rebuild resumed from {}% instead of ~50%",
+        progress
+    );
+
+    // a stopped rebuild is not resumed as the child no longer receives the
+    // writes to the nexus
+    ms.spawn(async {
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.checkpoint_rebuild(DST_BDEV).await.unwrap();
+        nexus.stop_rebuild(DST_BDEV).await.unwrap();
+    })
+    .await;
+    tokio::time::delay_for(Duration::from_millis(500)).await;
+
+    ms.spawn(async {
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        let child = nexus.get_child_by_name(DST_BDEV).unwrap();
+        assert_eq!(child.read_rebuild_checkpoint().await.unwrap(), None);
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
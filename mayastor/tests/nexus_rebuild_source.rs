@@ -0,0 +1,66 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, Error, Reason},
+    core::MayastorCliArgs,
+    rebuild::{RebuildJob, RebuildState},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "source_nexus";
+static CHILD_1: &str = "malloc:///source_child1?size_mb=64";
+static CHILD_2: &str = "malloc:///source_child2?size_mb=64";
+static CHILD_3: &str = "malloc:///source_child3?size_mb=64";
+static CHILD_4: &str = "malloc:///source_child4?size_mb=64";
+
+#[tokio::test]
+async fn nexus_rebuild_source() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD_1.into(), CHILD_2.into()],
+        )
+        .await
+        .unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_child(CHILD_3, true).await.unwrap();
+
+        match nexus.start_rebuild_from(CHILD_3, "malloc:///none").await {
+            Err(Error::ChildNotFound {
+                child, ..
+            }) => assert_eq!(child, "malloc:///none"),
+            result => panic!("unexpected result {:?}", result.err()),
+        }
+        match nexus.start_rebuild_from(CHILD_3, CHILD_3).await {
+            Err(Error::InvalidRebuildSource {
+                child, ..
+            }) => assert_eq!(child, CHILD_3),
+            result => panic!("unexpected result {:?}", result.err()),
+        }
+
+        // the nexus would pick the first child
+        let complete =
+            nexus.start_rebuild_from(CHILD_3, CHILD_2).await.unwrap();
+        assert_eq!(RebuildJob::lookup(CHILD_3).unwrap().source, CHILD_2);
+        assert_eq!(complete.await.unwrap(), RebuildState::Completed);
+
+        // a faulted child cannot be the source
+        nexus.add_child(CHILD_4, true).await.unwrap();
+        nexus.fault_child(CHILD_1, Reason::IoError).await.unwrap();
+        match nexus.start_rebuild_from(CHILD_4, CHILD_1).await {
+            Err(Error::InvalidRebuildSource {
+                child, ..
+            }) => assert_eq!(child, CHILD_1),
+            result => panic!("unexpected result {:?}", result.err()),
+        }
+        assert!(RebuildJob::lookup(CHILD_4).is_err());
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,81 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::{Bdev, MayastorCliArgs},
+    subsys::Config,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "rename_nexus";
+static NEXUS_RENAMED: &str = "rename_nexus_new";
+static OTHER_NEXUS: &str = "rename_nexus_other";
+static CONFIG_FILE: &str = "/tmp/nexus_rename.yaml";
+
+fn nexus_names(file: &str) -> Vec<String> {
+    Config::read(file)
+        .unwrap()
+        .nexus_bdevs
+        .unwrap_or_default()
+        .into_iter()
+        .map(|nexus| nexus.name)
+        .collect()
+}
+
+#[tokio::test]
+async fn nexus_rename() {
+    // the nexus to rename is created from the config file
+    std::fs::write(
+        CONFIG_FILE,
+        format!(
+            "nexus_bdevs:\n\
+             - name: {}\n  \
+               uuid: 2c58c9f0-da89-4cb9-8097-dc67fa132493\n  \
+               size: 32MiB\n  \
+               children:\n  \
+               - malloc:///rename_child1?size_mb=64\n",
+            NEXUS_NAME
+        ),
+    )
+    .unwrap();
+
+    let ms = MayastorTest::new(MayastorCliArgs {
+        mayastor_config: Some(CONFIG_FILE.to_string()),
+        ..Default::default()
+    });
+
+    ms.spawn(async {
+        nexus_create(
+            OTHER_NEXUS,
+            32 * 1024 * 1024,
+            None,
+            &["malloc:///rename_child2?size_mb=64".into()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+
+        // the name of another nexus cannot be taken
+        assert!(nexus.rename(OTHER_NEXUS).await.is_err());
+
+        assert_eq!(nexus_names(CONFIG_FILE), vec![NEXUS_NAME]);
+
+        nexus.rename(NEXUS_RENAMED).await.unwrap();
+        assert!(nexus_lookup(NEXUS_NAME).is_none());
+        assert!(Bdev::lookup_by_name(NEXUS_NAME).is_none());
+
+        // the nexus is created under its new name after a restart
+        assert_eq!(nexus_names(CONFIG_FILE), vec![NEXUS_RENAMED]);
+
+        let nexus = nexus_lookup(NEXUS_RENAMED).unwrap();
+        assert!(Bdev::lookup_by_name(NEXUS_RENAMED).is_some());
+
+        nexus.destroy().await.unwrap();
+        nexus_lookup(OTHER_NEXUS).unwrap().destroy().await.unwrap();
+    })
+    .await;
+
+    std::fs::remove_file(CONFIG_FILE).unwrap();
+}
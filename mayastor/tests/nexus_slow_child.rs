@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::{Bdev, BdevHandle, MayastorCliArgs},
+    nexus_uri::bdev_create,
+    subsys::{Config, NvmeBdevOpts},
+};
+
+pub mod common;
+use common::delay_bdev::create_delay_bdev;
+
+static NEXUS_NAME: &str = "slow_child_nexus";
+static FAST_BDEV: &str = "malloc:///slow_child_fast?size_mb=64";
+static SLOW_BASE_BDEV: &str = "malloc:///slow_child_base?size_mb=64";
+static SLOW_BDEV: &str = "slow_child";
+static SLOW_CHILD: &str = "bdev:///slow_child";
+
+const WINDOW_MS: u64 = 200;
+
+async fn read_ops(name: &str) -> u64 {
+    Bdev::lookup_by_name(name)
+        .unwrap()
+        .stats()
+        .await
+        .unwrap()
+        .num_read_ops
+}
+
+#[tokio::test]
+async fn nexus_slow_child() {
+    Config::get_or_init(|| Config {
+        nvme_bdev_opts: NvmeBdevOpts {
+            slow_child_threshold_us: 50_000,
+            slow_child_window_ms: WINDOW_MS,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(FAST_BDEV).await.unwrap();
+        bdev_create(SLOW_BASE_BDEV).await.unwrap();
+        // every read of this child takes an additional 60ms
+        create_delay_bdev(SLOW_BDEV, "slow_child_base", 60_000);
+
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[FAST_BDEV.to_string(), SLOW_CHILD.to_string()],
+        )
+        .await
+        .unwrap();
+
+        let h = BdevHandle::open(NEXUS_NAME, false, false).unwrap();
+        let mut buf = h.dma_malloc(4096).unwrap();
+
+        // keep reading until the slow child is excluded, which takes 3
+        // windows, the first of which may have started before the reads
+        let start = Instant::now();
+        let deadline = Duration::from_millis(4 * WINDOW_MS);
+        while !nexus_lookup(NEXUS_NAME)
+            .unwrap()
+            .get_child_by_name(SLOW_CHILD)
+            .unwrap()
+            .is_slow()
+        {
+            assert!(
+                start.elapsed() < deadline,
+                "the slow child was not excluded after {:?}",
+                start.elapsed()
+            );
+            h.read_at(0, &mut buf).await.unwrap();
+        }
+
+        // let the IO channels be refreshed, all reads are then served by the
+        // fast child
+        Delay::new(Duration::from_millis(100)).await;
+        let slow_before = read_ops(SLOW_BDEV).await;
+        for i in 0 .. 100 {
+            h.read_at(i * 4096, &mut buf).await.unwrap();
+        }
+        assert_eq!(read_ops(SLOW_BDEV).await, slow_before);
+
+        h.close();
+        nexus_lookup(NEXUS_NAME).unwrap().destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,75 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::MayastorCliArgs,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "split_io_nexus";
+static CHILD_1: &str = "malloc:///split_io_child1?size_mb=64&blk_size=512";
+static CHILD_2: &str = "malloc:///split_io_child2?size_mb=64&blk_size=512";
+static CHILD_3: &str = "malloc:///split_io_child3?size_mb=64&blk_size=512";
+
+const NUM_BLOCKS: u64 = 8;
+
+#[tokio::test]
+async fn nexus_split_io() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &[CHILD_1.into(), CHILD_2.into(), CHILD_3.into()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        let offset = nexus.data_ent_offset * 512;
+
+        // all children start out holding the same data
+        let hdl = nexus.children[0].handle().unwrap();
+        let mut buf = hdl.dma_malloc(NUM_BLOCKS * 512).unwrap();
+        buf.fill(0xaa);
+        for child in &nexus.children {
+            let hdl = child.handle().unwrap();
+            hdl.write_at(offset, &buf).await.unwrap();
+        }
+
+        let compare = nexus.split_io_compare(0, NUM_BLOCKS).await.unwrap();
+        assert_eq!(compare.len(), 3);
+        assert!(compare.iter().all(|(_, same)| *same));
+
+        // the children diverge behind the back of the nexus
+        for (i, child) in nexus.children.iter().enumerate().skip(1) {
+            buf.fill(i as u8);
+            let hdl = child.handle().unwrap();
+            hdl.write_at(offset, &buf).await.unwrap();
+        }
+
+        let reads = nexus.split_io_read(0, NUM_BLOCKS).await.unwrap();
+        for (i, (name, data)) in reads.iter().enumerate() {
+            assert_eq!(name, &nexus.children[i].name);
+            assert_eq!(data.len() as u64, NUM_BLOCKS * 512);
+            let pattern = if i == 0 { 0xaa } else { i as u8 };
+            assert!(data.iter().all(|b| *b == pattern));
+        }
+
+        let compare = nexus.split_io_compare(0, NUM_BLOCKS).await.unwrap();
+        let same: Vec<bool> = compare.iter().map(|(_, same)| *same).collect();
+        assert_eq!(same, vec![true, false, false]);
+
+        // the region must lie within the nexus
+        let blocks = nexus.bdev.num_blocks();
+        assert!(nexus.split_io_read(blocks - 1, 2).await.is_err());
+        // including when the end of the region does not fit in a u64
+        assert!(nexus.split_io_read(1, u64::MAX).await.is_err());
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,56 @@
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, NexusStatus, Reason},
+    core::MayastorCliArgs,
+    nexus_uri::bdev_create,
+};
+
+pub mod common;
+use common::delay_bdev::create_delay_bdev;
+
+static NEXUS_NAME: &str = "nexus_status";
+static SRC_BASE_BDEV: &str = "malloc:///nexus_status_src_base?size_mb=64";
+static CHILD_2: &str = "malloc:///nexus_status_child2?size_mb=64";
+static CHILD_3: &str = "malloc:///nexus_status_child3?size_mb=64";
+
+#[tokio::test]
+async fn nexus_status_rebuilding_quorum() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(SRC_BASE_BDEV).await.unwrap();
+        // keep the rebuild running while the status is checked
+        create_delay_bdev("nexus_status_src", "nexus_status_src_base", 100_000);
+
+        nexus_create(
+            NEXUS_NAME,
+            32 * 1024 * 1024,
+            None,
+            &["bdev:///nexus_status_src".into(), CHILD_2.into()],
+        )
+        .await
+        .unwrap();
+
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        assert_eq!(nexus.status(), NexusStatus::Online);
+
+        // the new child is not being rebuilt
+        nexus.add_child(CHILD_3, true).await.unwrap();
+        assert_eq!(nexus.status(), NexusStatus::Degraded);
+
+        // all the original children are healthy while the new one is rebuilt
+        let _ = nexus.start_rebuild(CHILD_3, false).await.unwrap();
+        assert_eq!(nexus.status(), NexusStatus::Rebuilding);
+
+        nexus.fault_child(CHILD_2, Reason::Unknown).await.unwrap();
+        assert_eq!(nexus.status(), NexusStatus::Degraded);
+
+        // a single healthy child remains
+        nexus.set_min_healthy_children(2);
+        assert_eq!(nexus.status(), NexusStatus::Quorum);
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,51 @@
+use std::{fs, path::Path};
+
+use mayastor::core::{load_node_id, MayastorEnvironment};
+
+pub mod common;
+
+static NODE_ID_DIR: &str = "/tmp/node_id_test";
+
+#[test]
+fn node_id() {
+    common::mayastor_test_init();
+
+    let first = MayastorEnvironment::node_id();
+    let second = MayastorEnvironment::node_id();
+    assert_eq!(first, second);
+
+    // nexus UUIDs generated on this node share the node id as prefix
+    let uuid = MayastorEnvironment::node_scoped_uuid();
+    assert_eq!(&uuid.as_bytes()[.. 6], &first.as_bytes()[.. 6]);
+    assert_ne!(uuid, MayastorEnvironment::node_scoped_uuid());
+}
+
+#[test]
+fn node_id_persisted() {
+    common::mayastor_test_init();
+
+    fs::create_dir_all(NODE_ID_DIR).unwrap();
+    let file = Path::new(NODE_ID_DIR).join("state/node-id");
+    let machine_id = Path::new(NODE_ID_DIR).join("machine-id");
+    let _ = fs::remove_file(&file);
+    let _ = fs::remove_file(&machine_id);
+
+    // the directory of the file is created, and a restart reads back the
+    // same id
+    let id = load_node_id(&file, &machine_id);
+    assert_eq!(id, load_node_id(&file, &machine_id));
+
+    // without the persisted id a new one is generated
+    fs::remove_file(&file).unwrap();
+    let new_id = load_node_id(&file, &machine_id);
+    assert_ne!(id, new_id);
+    assert_eq!(new_id, load_node_id(&file, &machine_id));
+
+    // which is derived from the machine id when there is one
+    fs::remove_file(&file).unwrap();
+    fs::write(&machine_id, "5b2c1a3e9d4f4e2a8c7b6d5e4f3a2b1c\n").unwrap();
+    let id = load_node_id(&file, &machine_id);
+    assert_eq!(id.to_string(), "5b2c1a3e-9d4f-4e2a-8c7b-6d5e4f3a2b1c");
+
+    fs::remove_dir_all(NODE_ID_DIR).unwrap();
+}
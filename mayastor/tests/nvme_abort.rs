@@ -0,0 +1,74 @@
+use mayastor::{
+    core::{BdevHandle, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+use rpc::mayastor::{
+    CreatePoolRequest,
+    CreateReplicaRequest,
+    ShareProtocolReplica,
+};
+
+pub mod common;
+use common::{compose::Builder, MayastorTest};
+
+static POOL_NAME: &str = "tpool";
+static UUID: &str = "0b1f3ad8-6b3c-4f0e-8a57-9c2d4e61f7b3";
+
+/// a command identifier that is not in use by any outstanding command
+const UNUSED_CID: u16 = 0xfffe;
+
+#[tokio::test]
+async fn nvme_abort() {
+    let test = Builder::new()
+        .name("nvme_abort")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+
+    hdls[0]
+        .mayastor
+        .create_pool(CreatePoolRequest {
+            name: POOL_NAME.to_string(),
+            disks: vec!["malloc:///disk0?size_mb=64".into()],
+        })
+        .await
+        .unwrap();
+
+    // create replica, shared over nvmf
+    hdls[0]
+        .mayastor
+        .create_replica(CreateReplicaRequest {
+            uuid: UUID.to_string(),
+            pool: POOL_NAME.to_string(),
+            size: 32 * 1024 * 1024,
+            thin: false,
+            share: ShareProtocolReplica::ReplicaNvmf as i32,
+        })
+        .await
+        .unwrap();
+
+    let mayastor = MayastorTest::new(MayastorCliArgs::default());
+    let uri = format!(
+        "nvmf://{}:8420/nqn.2019-05.io.openebs:{}",
+        hdls[0].endpoint.ip(),
+        UUID
+    );
+
+    mayastor
+        .spawn(async move {
+            let name = bdev_create(&uri).await.unwrap();
+            let h = BdevHandle::open(&name, true, false).unwrap();
+
+            // there is nothing to abort, which is not an error
+            assert!(!h.nvme_abort(1, UNUSED_CID).await.unwrap());
+
+            drop(h);
+            bdev_destroy(&uri).await.unwrap();
+        })
+        .await;
+}
@@ -0,0 +1,70 @@
+use common::MayastorTest;
+use crc::crc32;
+use rand::Rng;
+
+use mayastor::{
+    core::{BdevHandle, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+const BLOCK_SIZE: u64 = 512;
+const COPY_SIZE: u64 = 1 << 20;
+const DEST_LBA: u64 = 8192;
+
+#[tokio::test]
+async fn nvme_copy() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+    ms.spawn(async {
+        bdev_create("malloc:///copy0?blk_size=512&size_mb=64")
+            .await
+            .unwrap();
+
+        let h = BdevHandle::open("copy0", true, false).unwrap();
+
+        let mut src = h.dma_malloc(COPY_SIZE).unwrap();
+        rand::thread_rng().fill(src.as_mut_slice());
+        h.write_at(0, &src).await.unwrap();
+
+        // a malloc bdev does not support simple copy so this exercises the
+        // host side fallback
+        let copied = h
+            .nvme_copy(DEST_LBA, &[(0, (COPY_SIZE / BLOCK_SIZE) as u32)])
+            .await
+            .unwrap();
+        assert_eq!(copied, COPY_SIZE / BLOCK_SIZE);
+
+        let mut dst = h.dma_malloc(COPY_SIZE).unwrap();
+        h.read_at(DEST_LBA * BLOCK_SIZE, &mut dst).await.unwrap();
+
+        assert_eq!(
+            crc32::checksum_ieee(src.as_slice()),
+            crc32::checksum_ieee(dst.as_slice())
+        );
+
+        // multiple ranges are copied back to back to the destination
+        let copied = h
+            .nvme_copy(DEST_LBA, &[(1024, 1024), (0, 1024)])
+            .await
+            .unwrap();
+        assert_eq!(copied, 2048);
+
+        h.read_at(DEST_LBA * BLOCK_SIZE, &mut dst).await.unwrap();
+        let half = (COPY_SIZE / 2) as usize;
+        assert_eq!(
+            crc32::checksum_ieee(&src.as_slice()[half ..]),
+            crc32::checksum_ieee(&dst.as_slice()[.. half])
+        );
+        assert_eq!(
+            crc32::checksum_ieee(&src.as_slice()[.. half]),
+            crc32::checksum_ieee(&dst.as_slice()[half ..])
+        );
+
+        h.close();
+        bdev_destroy("malloc:///copy0?blk_size=512&size_mb=64")
+            .await
+            .unwrap();
+    })
+    .await;
+}
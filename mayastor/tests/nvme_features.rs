@@ -0,0 +1,91 @@
+use mayastor::{
+    core::{BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+use rpc::mayastor::{
+    CreatePoolRequest,
+    CreateReplicaRequest,
+    ShareProtocolReplica,
+};
+
+pub mod common;
+use common::{compose::Builder, MayastorTest};
+
+static POOL_NAME: &str = "tpool";
+static UUID: &str = "7c9a4e12-3f58-4d2b-b1e6-58a0d3c9e4f1";
+
+const FEAT_ARBITRATION: u8 = 0x01;
+const FEAT_NUMBER_OF_QUEUES: u8 = 0x07;
+/// a feature identifier that is reserved by the specification
+const FEAT_RESERVED: u8 = 0x7f;
+
+#[tokio::test]
+async fn nvme_features() {
+    let test = Builder::new()
+        .name("nvme_features")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+
+    hdls[0]
+        .mayastor
+        .create_pool(CreatePoolRequest {
+            name: POOL_NAME.to_string(),
+            disks: vec!["malloc:///disk0?size_mb=64".into()],
+        })
+        .await
+        .unwrap();
+
+    // create replica, shared over nvmf
+    hdls[0]
+        .mayastor
+        .create_replica(CreateReplicaRequest {
+            uuid: UUID.to_string(),
+            pool: POOL_NAME.to_string(),
+            size: 32 * 1024 * 1024,
+            thin: false,
+            share: ShareProtocolReplica::ReplicaNvmf as i32,
+        })
+        .await
+        .unwrap();
+
+    let mayastor = MayastorTest::new(MayastorCliArgs::default());
+    let uri = format!(
+        "nvmf://{}:8420/nqn.2019-05.io.openebs:{}",
+        hdls[0].endpoint.ip(),
+        UUID
+    );
+
+    mayastor
+        .spawn(async move {
+            let name = bdev_create(&uri).await.unwrap();
+            let h = BdevHandle::open(&name, true, false).unwrap();
+
+            // at least one IO queue pair was allocated
+            let queues =
+                h.nvme_get_feature(FEAT_NUMBER_OF_QUEUES).await.unwrap();
+            assert!(queues & 0xffff < 0xffff);
+
+            // arbitration burst of 8 commands
+            h.nvme_set_feature(FEAT_ARBITRATION, 3).await.unwrap();
+            let arbitration =
+                h.nvme_get_feature(FEAT_ARBITRATION).await.unwrap();
+            assert_eq!(arbitration & 0x7, 3);
+
+            assert!(matches!(
+                h.nvme_get_feature(FEAT_RESERVED).await,
+                Err(CoreError::NvmeAdminFailed {
+                    ..
+                })
+            ));
+
+            drop(h);
+            bdev_destroy(&uri).await.unwrap();
+        })
+        .await;
+}
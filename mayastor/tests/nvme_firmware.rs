@@ -0,0 +1,61 @@
+use common::MayastorTest;
+
+use mayastor::{
+    core::{BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+
+pub mod common;
+
+static BDEV: &str = "malloc:///firmware_disk?size_mb=64";
+
+#[tokio::test]
+async fn nvme_firmware_invalid_arguments() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        bdev_create(BDEV).await.unwrap();
+        let h = BdevHandle::open("firmware_disk", true, false).unwrap();
+
+        // the image is checked before any command is sent to the device
+        assert!(matches!(
+            h.nvme_firmware_download(&[], 0).await,
+            Err(CoreError::InvalidArgument {
+                ..
+            })
+        ));
+        assert!(matches!(
+            h.nvme_firmware_download(&[0; 6], 0).await,
+            Err(CoreError::InvalidArgument {
+                ..
+            })
+        ));
+        assert!(matches!(
+            h.nvme_firmware_commit(8, 1).await,
+            Err(CoreError::InvalidArgument {
+                ..
+            })
+        ));
+
+        drop(h);
+
+        // the firmware can not be changed through a read only handle
+        let h = BdevHandle::open("firmware_disk", false, false).unwrap();
+        assert!(matches!(
+            h.nvme_firmware_download(&[0; 8], 0).await,
+            Err(CoreError::NvmeWriteProtected {
+                ..
+            })
+        ));
+        assert!(matches!(
+            h.nvme_firmware_commit(1, 1).await,
+            Err(CoreError::NvmeWriteProtected {
+                ..
+            })
+        ));
+
+        drop(h);
+        bdev_destroy(BDEV).await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,84 @@
+use mayastor::{
+    core::{BdevHandle, CoreError, MayastorCliArgs},
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+use rpc::mayastor::{
+    CreatePoolRequest,
+    CreateReplicaRequest,
+    ShareProtocolReplica,
+};
+
+pub mod common;
+use common::{compose::Builder, MayastorTest};
+
+static POOL_NAME: &str = "tpool";
+static UUID: &str = "5d5e2fe3-7a68-4b4a-9d4e-2b8ce0c1d1a2";
+
+/// SMART / health information log page
+const LOG_HEALTH: u8 = 0x02;
+
+#[tokio::test]
+async fn nvme_log_page() {
+    let test = Builder::new()
+        .name("nvme_log_page")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+
+    hdls[0]
+        .mayastor
+        .create_pool(CreatePoolRequest {
+            name: POOL_NAME.to_string(),
+            disks: vec!["malloc:///disk0?size_mb=64".into()],
+        })
+        .await
+        .unwrap();
+
+    // create replica, shared over nvmf
+    hdls[0]
+        .mayastor
+        .create_replica(CreateReplicaRequest {
+            uuid: UUID.to_string(),
+            pool: POOL_NAME.to_string(),
+            size: 32 * 1024 * 1024,
+            thin: false,
+            share: ShareProtocolReplica::ReplicaNvmf as i32,
+        })
+        .await
+        .unwrap();
+
+    let mayastor = MayastorTest::new(MayastorCliArgs::default());
+    let uri = format!(
+        "nvmf://{}:8420/nqn.2019-05.io.openebs:{}",
+        hdls[0].endpoint.ip(),
+        UUID
+    );
+
+    mayastor
+        .spawn(async move {
+            let name = bdev_create(&uri).await.unwrap();
+            let h = BdevHandle::open(&name, true, false).unwrap();
+
+            let mut buf = h.dma_malloc(512).unwrap();
+            h.nvme_get_log_page(LOG_HEALTH, &mut buf).await.unwrap();
+            assert_eq!(buf.len(), 512);
+
+            // the page is transferred in whole dwords
+            let mut buf = h.dma_malloc(510).unwrap();
+            assert!(matches!(
+                h.nvme_get_log_page(LOG_HEALTH, &mut buf).await,
+                Err(CoreError::InvalidArgument {
+                    ..
+                })
+            ));
+
+            drop(h);
+            bdev_destroy(&uri).await.unwrap();
+        })
+        .await;
+}
@@ -0,0 +1,123 @@
+use mayastor::{
+    core::{
+        BdevHandle,
+        CoreError,
+        MayastorCliArgs,
+        NvmeReservation,
+        NvmeReservationAcquireAction,
+        NvmeReservationRegisterAction,
+        NvmeReservationReleaseAction,
+    },
+    nexus_uri::{bdev_create, bdev_destroy},
+};
+use rpc::mayastor::{
+    CreatePoolRequest,
+    CreateReplicaRequest,
+    ShareProtocolReplica,
+};
+
+pub mod common;
+use common::{compose::Builder, MayastorTest};
+
+static POOL_NAME: &str = "tpool";
+static UUID: &str = "7b3b4c5e-1f0a-4d8e-9a61-3c2f1e0d9b8a";
+
+const KEY: u64 = 0x1234_5678_9abc_def0;
+const WRONG_KEY: u64 = 0xdead_beef;
+
+#[tokio::test]
+async fn nvme_reservation() {
+    let test = Builder::new()
+        .name("nvme_reservation")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+
+    hdls[0]
+        .mayastor
+        .create_pool(CreatePoolRequest {
+            name: POOL_NAME.to_string(),
+            disks: vec!["malloc:///disk0?size_mb=64".into()],
+        })
+        .await
+        .unwrap();
+
+    // create replica, shared over nvmf
+    hdls[0]
+        .mayastor
+        .create_replica(CreateReplicaRequest {
+            uuid: UUID.to_string(),
+            pool: POOL_NAME.to_string(),
+            size: 32 * 1024 * 1024,
+            thin: false,
+            share: ShareProtocolReplica::ReplicaNvmf as i32,
+        })
+        .await
+        .unwrap();
+
+    let mayastor = MayastorTest::new(MayastorCliArgs::default());
+    let uri = format!(
+        "nvmf://{}:8420/nqn.2019-05.io.openebs:{}",
+        hdls[0].endpoint.ip(),
+        UUID
+    );
+
+    mayastor
+        .spawn(async move {
+            let name = bdev_create(&uri).await.unwrap();
+            let h = BdevHandle::open(&name, true, false).unwrap();
+
+            h.reservation_register(
+                0,
+                KEY,
+                NvmeReservationRegisterAction::Register,
+            )
+            .await
+            .unwrap();
+            h.reservation_acquire(
+                KEY,
+                0,
+                NvmeReservation::WriteExclusive,
+                NvmeReservationAcquireAction::Acquire,
+            )
+            .await
+            .unwrap();
+
+            // a key that is not registered conflicts with the reservation
+            assert!(matches!(
+                h.reservation_release(
+                    WRONG_KEY,
+                    NvmeReservation::WriteExclusive,
+                    NvmeReservationReleaseAction::Release,
+                )
+                .await,
+                Err(CoreError::ReservationConflict {
+                    ..
+                })
+            ));
+
+            h.reservation_release(
+                KEY,
+                NvmeReservation::WriteExclusive,
+                NvmeReservationReleaseAction::Release,
+            )
+            .await
+            .unwrap();
+            h.reservation_register(
+                KEY,
+                0,
+                NvmeReservationRegisterAction::Unregister,
+            )
+            .await
+            .unwrap();
+
+            drop(h);
+            bdev_destroy(&uri).await.unwrap();
+        })
+        .await;
+}
@@ -0,0 +1,62 @@
+use mayastor::{
+    bdev::device_get_transport_id,
+    core::MayastorCliArgs,
+    nexus_uri::bdev_create,
+};
+use rpc::mayastor::{BdevShareRequest, BdevUri};
+
+pub mod common;
+use common::{compose::Builder, MayastorTest};
+
+static NQN: &str = "nqn.2019-05.io.openebs:disk0";
+
+#[tokio::test]
+async fn nvme_transport_id() {
+    let test = Builder::new()
+        .name("nvme_transport_id")
+        .network("10.1.0.0/16")
+        .add_container("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut hdls = test.grpc_handles().await.unwrap();
+    hdls[0]
+        .bdev
+        .create(BdevUri {
+            uri: "malloc:///disk0?size_mb=64".into(),
+        })
+        .await
+        .unwrap();
+    hdls[0]
+        .bdev
+        .share(BdevShareRequest {
+            name: "disk0".into(),
+            proto: "nvmf".into(),
+        })
+        .await
+        .unwrap();
+
+    let ip = hdls[0].endpoint.ip().to_string();
+    let mayastor = MayastorTest::new(MayastorCliArgs::default());
+    mayastor
+        .spawn(async move {
+            let name = bdev_create(&format!("nvmf://{}:8420/{}", ip, NQN))
+                .await
+                .unwrap();
+
+            let trid = device_get_transport_id(&name).unwrap();
+            assert_eq!(trid.subnqn, NQN);
+            assert_eq!(trid.traddr, ip);
+            assert_eq!(trid.trsvcid, "8420");
+            assert_eq!(trid.trtype, "TCP");
+
+            // a local device is not reached over NVMe
+            let name = bdev_create("malloc:///trid_malloc?size_mb=64")
+                .await
+                .unwrap();
+            assert!(device_get_transport_id(&name).is_none());
+        })
+        .await;
+}
@@ -0,0 +1,22 @@
+use mayastor::core::parse_mb;
+
+pub mod common;
+
+#[test]
+fn parse_mb_valid() {
+    // without a unit the value is in MiB
+    assert_eq!(parse_mb("2048"), Ok(2048));
+    assert_eq!(parse_mb("2GiB"), Ok(2048));
+    assert_eq!(parse_mb("0"), Ok(0));
+    // 100TiB is still within range when expressed in MiB
+    assert_eq!(parse_mb("100TiB"), Ok(100 * 1024 * 1024));
+}
+
+#[test]
+fn parse_mb_invalid() {
+    assert!(parse_mb("-10").is_err());
+    assert!(parse_mb("-2GiB").is_err());
+    assert!(parse_mb("3000000000").is_err());
+    assert!(parse_mb("4096PiB").is_err());
+    assert!(parse_mb("lots").is_err());
+}
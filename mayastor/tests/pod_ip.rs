@@ -0,0 +1,40 @@
+use std::{
+    env,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use mayastor::core::MayastorEnvironment;
+
+// the environment is shared by all the tests of a binary, so the variable is
+// only modified from within a single test
+#[test]
+fn get_pod_ip() {
+    env::set_var("MY_POD_IP", "10.1.0.4");
+    assert_eq!(
+        MayastorEnvironment::get_pod_ip(),
+        Ok(IpAddr::V4(Ipv4Addr::new(10, 1, 0, 4)))
+    );
+
+    env::set_var("MY_POD_IP", "::1");
+    assert_eq!(
+        MayastorEnvironment::get_pod_ip(),
+        Ok(IpAddr::V6(Ipv6Addr::LOCALHOST))
+    );
+
+    env::set_var("MY_POD_IP", "not-an-ip");
+    assert_eq!(
+        MayastorEnvironment::get_pod_ip(),
+        Err("not-an-ip".to_string())
+    );
+
+    // falls back to the address of the hostname or the loopback address
+    env::remove_var("MY_POD_IP");
+    let ip = MayastorEnvironment::get_pod_ip().unwrap();
+    assert!(ip.is_ipv4());
+
+    // of the requested family
+    env::set_var("MY_POD_IP_FAMILY", "v6");
+    let ip = MayastorEnvironment::get_pod_ip().unwrap();
+    assert!(ip.is_ipv6());
+    env::remove_var("MY_POD_IP_FAMILY");
+}
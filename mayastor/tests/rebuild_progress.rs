@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::MayastorCliArgs,
+    rebuild::RebuildState,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "progress_nexus";
+static CHILD_1: &str = "malloc:///progress_child1?size_mb=64";
+static CHILD_2: &str = "malloc:///progress_child2?size_mb=64";
+
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+// slow enough for the rebuild to take a few seconds
+const RATE_LIMIT: u64 = 4 * 1024 * 1024;
+
+#[tokio::test]
+async fn rebuild_progress() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(NEXUS_NAME, NEXUS_SIZE, None, &[CHILD_1.into()])
+            .await
+            .unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_child(CHILD_2, true).await.unwrap();
+
+        let complete = nexus.start_rebuild(CHILD_2, false).await.unwrap();
+        nexus.set_rebuild_rate_limit(CHILD_2, RATE_LIMIT).unwrap();
+
+        Delay::new(Duration::from_millis(1500)).await;
+        let state = nexus.get_rebuild_state(CHILD_2).await.unwrap();
+        assert_eq!(state.state, "running");
+        assert!(state.blocks_total > 0);
+        assert!(state.blocks_transferred > 0);
+        assert!(state.blocks_transferred < state.blocks_total);
+        let progress =
+            state.blocks_transferred as f64 * 100.0 / state.blocks_total as f64;
+        assert!((state.progress_percent - progress).abs() < 0.01);
+        assert!(state.eta_seconds > 0);
+
+        // a paused rebuild keeps its estimate
+        nexus.pause_rebuild(CHILD_2).await.unwrap();
+        Delay::new(Duration::from_millis(300)).await;
+        let paused = nexus.get_rebuild_state(CHILD_2).await.unwrap();
+        assert_eq!(paused.state, "paused");
+        assert!(paused.eta_seconds > 0);
+        Delay::new(Duration::from_secs(1)).await;
+        let still_paused = nexus.get_rebuild_state(CHILD_2).await.unwrap();
+        assert_eq!(still_paused.blocks_transferred, paused.blocks_transferred);
+        assert_eq!(still_paused.eta_seconds, paused.eta_seconds);
+
+        nexus.resume_rebuild(CHILD_2).await.unwrap();
+        assert_eq!(complete.await.unwrap(), RebuildState::Completed);
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
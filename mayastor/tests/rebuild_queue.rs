@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, Nexus},
+    core::MayastorCliArgs,
+    rebuild::RebuildState,
+    subsys::{Config, NexusOpts},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "queue_nexus";
+static CHILD: &str = "malloc:///queue_child0?size_mb=64";
+static DESTINATIONS: [&str; 3] = [
+    "malloc:///queue_child1?size_mb=64",
+    "malloc:///queue_child2?size_mb=64",
+    "malloc:///queue_child3?size_mb=64",
+];
+
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+// each rebuild takes about half a second at this rate
+const RATE_LIMIT: u64 = 32 * 1024 * 1024;
+
+/// states of the rebuild jobs of the destinations from `first` on, the jobs
+/// of the previous ones are done and gone
+async fn states(nexus: &mut Nexus, first: usize) -> Vec<String> {
+    let mut states = Vec::new();
+    for destination in &DESTINATIONS[first ..] {
+        states.push(nexus.get_rebuild_state(destination).await.unwrap().state);
+    }
+    states
+}
+
+#[tokio::test]
+async fn rebuild_queue() {
+    Config::get_or_init(|| Config {
+        nexus_opts: NexusOpts {
+            max_rebuild_jobs: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(NEXUS_NAME, NEXUS_SIZE, None, &[CHILD.into()])
+            .await
+            .unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+
+        let mut complete = Vec::new();
+        for destination in &DESTINATIONS {
+            nexus.add_child(destination, true).await.unwrap();
+            complete
+                .push(nexus.start_rebuild(destination, false).await.unwrap());
+            nexus
+                .set_rebuild_rate_limit(destination, RATE_LIMIT)
+                .unwrap();
+        }
+        Delay::new(Duration::from_millis(100)).await;
+        assert_eq!(states(nexus, 0).await, vec!["running", "queued", "queued"]);
+
+        // the jobs run one after the other, in the order they were started
+        let mut complete = complete.into_iter();
+        assert_eq!(
+            complete.next().unwrap().await.unwrap(),
+            RebuildState::Completed
+        );
+        Delay::new(Duration::from_millis(100)).await;
+        assert_eq!(states(nexus, 1).await, vec!["running", "queued"]);
+
+        assert_eq!(
+            complete.next().unwrap().await.unwrap(),
+            RebuildState::Completed
+        );
+        Delay::new(Duration::from_millis(100)).await;
+        assert_eq!(states(nexus, 2).await, vec!["running"]);
+
+        assert_eq!(
+            complete.next().unwrap().await.unwrap(),
+            RebuildState::Completed
+        );
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup},
+    core::MayastorCliArgs,
+    rebuild::RebuildState,
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "rate_limit_nexus";
+static CHILD_1: &str = "malloc:///rate_limit_child1?size_mb=64";
+static CHILD_2: &str = "malloc:///rate_limit_child2?size_mb=64";
+static CHILD_3: &str = "malloc:///rate_limit_child3?size_mb=64";
+
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+const RATE_LIMIT: u64 = 8 * 1024 * 1024;
+
+#[tokio::test]
+async fn rebuild_rate_limit() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(NEXUS_NAME, NEXUS_SIZE, None, &[CHILD_1.into()])
+            .await
+            .unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+        nexus.add_child(CHILD_2, true).await.unwrap();
+
+        let started = Instant::now();
+        let complete = nexus.start_rebuild(CHILD_2, false).await.unwrap();
+        nexus.set_rebuild_rate_limit(CHILD_2, RATE_LIMIT).unwrap();
+
+        Delay::new(Duration::from_secs(1)).await;
+        let state = nexus.get_rebuild_state(CHILD_2).await.unwrap();
+        assert_eq!(state.state, "running");
+        assert!(state.throughput > 0);
+        // the first segments are copied in a burst
+        assert!(
+            state.throughput < RATE_LIMIT * 3 / 2,
+            "throughput of {} bytes per second",
+            state.throughput
+        );
+
+        assert_eq!(complete.await.unwrap(), RebuildState::Completed);
+        // copying the whole nexus takes about two seconds at this rate
+        assert!(started.elapsed() >= Duration::from_millis(1500));
+
+        // a job that waits for the rate limit stops right away, the first
+        // segments put it a minute in debt at this rate
+        nexus.add_child(CHILD_3, true).await.unwrap();
+        let complete = nexus.start_rebuild(CHILD_3, false).await.unwrap();
+        nexus.set_rebuild_rate_limit(CHILD_3, 16 * 1024).unwrap();
+        Delay::new(Duration::from_millis(200)).await;
+
+        let stopped = Instant::now();
+        nexus.stop_rebuild(CHILD_3).await.unwrap();
+        assert_eq!(complete.await.unwrap(), RebuildState::Stopped);
+        assert!(stopped.elapsed() < Duration::from_secs(1));
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
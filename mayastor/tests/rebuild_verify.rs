@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use futures_timer::Delay;
+
+use common::MayastorTest;
+
+use mayastor::{
+    bdev::{nexus_create, nexus_lookup, ChildState, Reason},
+    core::{BdevHandle, MayastorCliArgs},
+    rebuild::{RebuildFailureReason, RebuildState},
+};
+
+pub mod common;
+
+static NEXUS_NAME: &str = "verify_nexus";
+static CHILD_1: &str = "malloc:///verify_child1?size_mb=64";
+static CHILD_2: &str = "malloc:///verify_child2?size_mb=64";
+static CHILD_3: &str = "malloc:///verify_child3?size_mb=64";
+
+const NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+// slow enough to corrupt the destination before the copy completes
+const RATE_LIMIT: u64 = 8 * 1024 * 1024;
+
+#[tokio::test]
+async fn rebuild_verify() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        nexus_create(NEXUS_NAME, NEXUS_SIZE, None, &[CHILD_1.into()])
+            .await
+            .unwrap();
+        let nexus = nexus_lookup(NEXUS_NAME).unwrap();
+
+        // an intact copy passes the verification
+        nexus.add_child(CHILD_2, true).await.unwrap();
+        let complete = nexus.start_rebuild(CHILD_2, true).await.unwrap();
+        assert_eq!(complete.await.unwrap(), RebuildState::Completed);
+
+        // data which changes behind the back of the nexus does not
+        nexus.add_child(CHILD_3, true).await.unwrap();
+        let complete = nexus.start_rebuild(CHILD_3, true).await.unwrap();
+        nexus.set_rebuild_rate_limit(CHILD_3, RATE_LIMIT).unwrap();
+        Delay::new(Duration::from_millis(200)).await;
+
+        let h = BdevHandle::open("verify_child3", true, false).unwrap();
+        let mut buf = h.dma_malloc(4096).unwrap();
+        buf.fill(0xff);
+        let offset = nexus.data_ent_offset * h.get_bdev().block_len() as u64;
+        h.write_at(offset, &buf).await.unwrap();
+        h.close();
+
+        match complete.await.unwrap() {
+            RebuildState::Failed {
+                reason:
+                    RebuildFailureReason::VerificationMismatch {
+                        lba,
+                        source_crc,
+                        destination_crc,
+                    },
+            } => {
+                assert_eq!(lba, nexus.data_ent_offset);
+                assert_ne!(source_crc, destination_crc);
+            }
+            state => panic!("unexpected rebuild state {:?}", state),
+        }
+
+        // the child is not brought online
+        Delay::new(Duration::from_millis(100)).await;
+        assert_eq!(
+            nexus.get_child_by_name(CHILD_3).unwrap().state(),
+            ChildState::Faulted(Reason::RebuildFailed)
+        );
+
+        nexus.destroy().await.unwrap();
+    })
+    .await;
+}
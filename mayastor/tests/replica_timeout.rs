@@ -5,7 +5,12 @@ use mayastor::{
     nexus_uri::bdev_get_name,
     subsys::{Config, NvmeBdevOpts},
 };
-use rpc::mayastor::{BdevShareRequest, BdevUri, Null, ShareProtocolNexus};
+use rpc::mayastor::{
+    BdevShareRequest,
+    BdevUri,
+    ListBdevsRequest,
+    ShareProtocolNexus,
+};
 use std::process::{Command, Stdio};
 use tokio::time::Duration;
 
@@ -40,7 +45,7 @@ async fn replica_stop_cont() {
 
     // create and share a bdev on each container
     for h in &mut hdls {
-        h.bdev.list(Null {}).await.unwrap();
+        h.bdev.list(ListBdevsRequest::default()).await.unwrap();
         h.bdev
             .create(BdevUri {
                 uri: "malloc:///disk0?size_mb=100".into(),
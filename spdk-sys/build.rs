@@ -77,11 +77,13 @@ fn main() {
         .whitelist_function("*.uring.*")
         .whitelist_function("^iscsi.*")
         .whitelist_function("^spdk.*")
+        .whitelist_function("create_delay_disk")
         .whitelist_function("create_malloc_disk")
         .whitelist_function("delete_malloc_disk")
         .whitelist_function("^bdev.*")
         .whitelist_function("^nbd_.*")
         .whitelist_function("^vbdev_.*")
+        .whitelist_function("^nvme_bdev_get_ns")
         .whitelist_function("^nvme_cmd_.*")
         .whitelist_function("^nvme_status_.*")
         .whitelist_function("^nvmf_subsystem_find_listener")
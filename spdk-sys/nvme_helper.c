@@ -2,6 +2,7 @@
 
 #include <spdk/bdev_module.h>
 #include <spdk/lib/bdev/bdev_internal.h>
+#include <spdk/module/bdev/nvme/bdev_nvme.h>
 #include <spdk/nvme_spec.h>
 #include <spdk/thread.h>
 
@@ -15,6 +16,11 @@ nvme_cmd_cdw11_get(struct spdk_nvme_cmd *cmd) {
        return &cmd->cdw11;
 }
 
+uint32_t *
+nvme_cmd_cdw12_get(struct spdk_nvme_cmd *cmd) {
+       return &cmd->cdw12;
+}
+
 uint32_t
 nvme_cmd_cdw10_get_val(const struct spdk_nvme_cmd *cmd) {
        return cmd->cdw10;
@@ -35,6 +41,19 @@ nvme_status_raw_get(struct spdk_nvme_cpl *cpl) {
 	return &cpl->status_raw;
 }
 
+/* Return the namespace backing an NVMe bdev or NULL for any other bdev. */
+struct spdk_nvme_ns *
+nvme_bdev_get_ns(struct spdk_bdev *bdev) {
+	struct nvme_bdev *nbdev;
+
+	if (bdev_nvme_get_ctrlr(bdev) == NULL) {
+		return NULL;
+	}
+
+	nbdev = SPDK_CONTAINEROF(bdev, struct nvme_bdev, disk);
+	return nbdev->nvme_ns->ns;
+}
+
 /* Based on spdk_bdev_nvme_admin_passthru with the check for desc->write
  * removed.
  * spdk_bdev_nvme_io_passthru has a comment on parsing the command to
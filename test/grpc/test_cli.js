@@ -177,6 +177,58 @@ describe('mayastor-client', function () {
             ]
           }
         },
+        {
+          method: 'StartRebuild',
+          input: {
+            uuid: UUID,
+            uri: 'child_a'
+          },
+          output: {}
+        },
+        {
+          method: 'GetRebuildStats',
+          input: {
+            uuid: UUID,
+            uri: 'child_a'
+          },
+          output: {
+            blocksTotal: 2048,
+            blocksRecovered: 2048,
+            progress: 100,
+            segmentSizeBlks: 128,
+            blockSize: 512,
+            tasksTotal: 16,
+            tasksActive: 0
+          }
+        },
+        {
+          method: 'GetNexusLabel',
+          input: {
+            uuid: UUID1
+          },
+          output: {
+            uri: 'child1',
+            diskGuid: UUID1,
+            blockSize: 512,
+            status: 'both',
+            partitions: [
+              {
+                name: 'MayaMeta',
+                typeGuid: '27663382-e5e6-11e9-81b4-ca5ca5ca5ca5',
+                startLba: 2048,
+                endLba: 10239,
+                sizeBytes: 4194304
+              },
+              {
+                name: 'MayaData',
+                typeGuid: '27663382-e5e6-11e9-81b4-ca5ca5ca5ca5',
+                startLba: 10240,
+                endLba: 20446,
+                sizeBytes: 5225984
+              }
+            ]
+          }
+        },
         {
           method: 'DestroyNexus',
           input: {
@@ -365,6 +417,78 @@ describe('mayastor-client', function () {
       });
     });
 
+    it('should show pool stats', function (done) {
+      const cmd = util.format('%s -ui -q pool stats', EGRESS_CMD);
+
+      exec(cmd, (err, stdout, stderr) => {
+        const pools = [];
+
+        if (err) {
+          return done(err);
+        }
+        assert.isEmpty(stderr);
+
+        stdout.split('\n').forEach((line) => {
+          const parts = line
+            .trim()
+            .split(' ')
+            .filter((s) => s.length !== 0);
+
+          if (parts.length <= 1) {
+            return;
+          }
+
+          pools.push({
+            name: parts[0],
+            capacity: parts[1],
+            capacity_unit: parts[2],
+            used: parts[3],
+            used_unit: parts[4],
+            available: parts[5],
+            available_unit: parts[6]
+          });
+        });
+
+        assert.lengthOf(pools, 2);
+
+        assert.equal(pools[0].name, POOL + '1');
+        assert.equal(pools[0].capacity, '100.00');
+        assert.equal(pools[0].capacity_unit, 'MiB');
+        assert.isAbove(parseFloat(pools[0].used), 0);
+        assert.equal(pools[0].used, '50.00');
+        assert.equal(pools[0].used_unit, 'MiB');
+        assert.equal(pools[0].available, '50.00');
+        assert.equal(pools[0].available_unit, 'MiB');
+
+        assert.equal(pools[1].name, POOL + '2');
+        assert.equal(pools[1].capacity, '1000.00');
+        assert.equal(pools[1].capacity_unit, 'MiB');
+        assert.isAbove(parseFloat(pools[1].used), 0);
+        assert.equal(pools[1].used, '99.00');
+        assert.equal(pools[1].used_unit, 'MiB');
+        assert.equal(pools[1].available, '901.00');
+        assert.equal(pools[1].available_unit, 'MiB');
+
+        done();
+      });
+    });
+
+    it('should show stats of a single pool', function (done) {
+      const cmd = util.format('%s -q pool stats %s', EGRESS_CMD, POOL + '2');
+
+      exec(cmd, (err, stdout, stderr) => {
+        if (err) {
+          return done(err);
+        }
+        assert.isEmpty(stderr);
+
+        const lines = stdout.split('\n').filter((l) => l.length !== 0);
+        assert.lengthOf(lines, 1);
+        assert.match(lines[0], /^tpool2 /);
+        done();
+      });
+    });
+
     it('should destroy a pool', function (done) {
       const cmd = util.format('%s pool destroy %s', EGRESS_CMD, POOL);
 
@@ -495,6 +619,37 @@ describe('mayastor-client', function () {
       });
     });
 
+    it('should list nexus as csv', function (done) {
+      const cmd = util.format('%s -o csv nexus list -c', EGRESS_CMD);
+
+      exec(cmd, (err, stdout, stderr) => {
+        if (err) { return done(err); }
+        assert.isEmpty(stderr);
+        const lines = stdout.split('\n').filter((l) => l.length !== 0);
+        assert.deepEqual(lines, [
+          'NAME,SIZE,STATE,REBUILDS,PATH,CHILDREN',
+          UUID1 + ',104857600,online,123,file:///dev/blah,"child1,child2"',
+          UUID2 + ',10485760,degraded,1,file:///dev/blah2,'
+        ]);
+        done();
+      });
+    });
+
+    it('should list nexus as yaml', function (done) {
+      const cmd = util.format('%s -o yaml nexus list -c', EGRESS_CMD);
+
+      exec(cmd, (err, stdout, stderr) => {
+        if (err) { return done(err); }
+        assert.isEmpty(stderr);
+        assert.lengthOf(stdout.match(/^- NAME: /gm), 2);
+        assert.match(stdout, new RegExp('^- NAME: ' + UUID1 + '$', 'm'));
+        assert.match(stdout, /^ {2}STATE: online$/m);
+        assert.match(stdout, /^ {2}STATE: degraded$/m);
+        assert.match(stdout, /^ {2}CHILDREN: "child1,child2"$/m);
+        done();
+      });
+    });
+
     it('should list nexus children', function (done) {
       const cmd = util.format('%s -q nexus children %s', EGRESS_CMD, UUID1);
 
@@ -523,6 +678,75 @@ describe('mayastor-client', function () {
       });
     });
 
+    it('should show the nexus label', function (done) {
+      const cmd = util.format('%s nexus label %s', EGRESS_CMD, UUID1);
+
+      exec(cmd, (err, stdout, stderr) => {
+        if (err) { return done(err); }
+        assert.isEmpty(stderr);
+        assert.match(stdout, /Child: child1/);
+        assert.match(stdout, /Status: both/);
+        assert.match(stdout, /Name: MayaMeta/);
+        assert.match(stdout, /LBA start: 10240/);
+        done();
+      });
+    });
+
+    it('should show the nexus label as json', function (done) {
+      const cmd = util.format('%s -o json nexus label %s', EGRESS_CMD, UUID1);
+
+      exec(cmd, (err, stdout, stderr) => {
+        if (err) { return done(err); }
+        assert.isEmpty(stderr);
+        const label = JSON.parse(stdout);
+        assert.equal(label.uri, 'child1');
+        assert.equal(label.status, 'both');
+        assert.lengthOf(label.partitions, 2);
+        assert.equal(label.partitions[1].name, 'MayaData');
+        assert.equal(label.partitions[1].size_bytes, 5225984);
+        done();
+      });
+    });
+
+    it('should start a rebuild and wait for it to complete', function (done) {
+      const startCmd = util.format(
+        '%s nexus rebuild start %s child_a',
+        EGRESS_CMD,
+        UUID
+      );
+      const statusCmd = util.format(
+        '%s nexus rebuild status %s child_a',
+        EGRESS_CMD,
+        UUID
+      );
+
+      exec(startCmd, (err, stdout, stderr) => {
+        if (err) {
+          return done(err);
+        }
+        assert.isEmpty(stderr);
+        assert.match(stdout, /child_a/);
+
+        let retries = 10;
+        const poll = () => {
+          exec(statusCmd, (err, stdout, stderr) => {
+            if (err) {
+              return done(err);
+            }
+            assert.isEmpty(stderr);
+            if (stdout.match(/progress_pct: 100\.0%/)) {
+              return done();
+            }
+            if (--retries === 0) {
+              return done(new Error('rebuild did not complete'));
+            }
+            setTimeout(poll, 100);
+          });
+        };
+        poll();
+      });
+    });
+
     it('should destroy a nexus', function (done) {
       const cmd = util.format('%s nexus destroy %s', EGRESS_CMD, UUID);
 
@@ -812,6 +1036,28 @@ describe('mayastor-client', function () {
       });
     });
 
+    it('should stop watching the pools in case of internal error', function (done) {
+      const cmd = util.format('%s -q --watch 1 pool list', EGRESS_CMD);
+
+      exec(cmd, (err, stdout, stderr) => {
+        assert(err, 'Expected the command "' + cmd + '" to exit with error');
+        assert.match(stderr, /Internal error/);
+        assert.isEmpty(stdout);
+        done();
+      });
+    });
+
+    it('should not watch with an invalid interval', function (done) {
+      const cmd = util.format('%s --watch 0 pool list', EGRESS_CMD);
+
+      exec(cmd, (err, stdout, stderr) => {
+        assert(err, 'Expected the command "' + cmd + '" to exit with error');
+        assert.match(stderr, /WatchIntervalError/);
+        assert.isEmpty(stdout);
+        done();
+      });
+    });
+
     it('should not destroy a pool if it does not exist', function (done) {
       const cmd = util.format('%s pool destroy %s', EGRESS_CMD, POOL);
 
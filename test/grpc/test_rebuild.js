@@ -222,7 +222,7 @@ describe('rebuild tests', function () {
     });
 
     it('check nexus state', async () => {
-      await checkState(ObjectType.NEXUS, 'NEXUS_DEGRADED');
+      await checkState(ObjectType.NEXUS, 'NEXUS_REBUILDING');
     });
 
     it('check source state', async () => {
@@ -304,7 +304,7 @@ describe('rebuild tests', function () {
     });
 
     it('check nexus state', async () => {
-      await checkState(ObjectType.NEXUS, 'NEXUS_DEGRADED');
+      await checkState(ObjectType.NEXUS, 'NEXUS_REBUILDING');
     });
 
     it('check source state', async () => {
@@ -345,7 +345,7 @@ describe('rebuild tests', function () {
     });
 
     it('check nexus state', async () => {
-      await checkState(ObjectType.NEXUS, 'NEXUS_DEGRADED');
+      await checkState(ObjectType.NEXUS, 'NEXUS_REBUILDING');
     });
 
     it('check source state', async () => {
@@ -378,7 +378,7 @@ describe('rebuild tests', function () {
     });
 
     it('check nexus state', async () => {
-      await checkState(ObjectType.NEXUS, 'NEXUS_DEGRADED');
+      await checkState(ObjectType.NEXUS, 'NEXUS_REBUILDING');
     });
 
     it('check source state', async () => {